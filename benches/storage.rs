@@ -0,0 +1,43 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use registry_testkit::bench::{run_workload, Workload};
+use registry_testkit::storage::create_storage;
+use registry_testkit::StorageBackend;
+
+fn storage_benches(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let backends = [
+        ("memory", StorageBackend::Memory),
+        ("disk", StorageBackend::TempDir),
+    ];
+    let workloads = [
+        Workload::StoreBlobs {
+            count: 32,
+            size: 64 * 1024,
+        },
+        Workload::GetBlobs {
+            count: 32,
+            size: 64 * 1024,
+        },
+        Workload::Manifests { count: 32 },
+        Workload::Concurrent {
+            tasks: 8,
+            ops_per_task: 8,
+            size: 16 * 1024,
+        },
+    ];
+
+    for workload in &workloads {
+        let mut group = c.benchmark_group(workload.to_string());
+        for (name, backend) in &backends {
+            let storage = runtime.block_on(create_storage(backend)).unwrap();
+            group.bench_with_input(BenchmarkId::from_parameter(name), workload, |b, w| {
+                b.to_async(&runtime)
+                    .iter(|| async { run_workload(storage.clone(), w).await.unwrap() });
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, storage_benches);
+criterion_main!(benches);
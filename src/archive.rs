@@ -0,0 +1,433 @@
+//! OCI image layout archives (`oci-archive`).
+//!
+//! Besides single images, the whole registry can be written as one layout
+//! whose `index.json` lists every stored manifest under its
+//! `io.containerd.image.name` (`app:v1`, or `app@sha256:...` for manifests
+//! stored by digest only), and read back into storage. A single
+//! repository can be written the same way, with only the blobs its
+//! manifests reference, and read back under another name.
+
+use crate::digest::sha256_digest;
+use crate::error::{RegistryError, Result};
+use crate::storage::{ManifestEntry, Storage};
+use crate::synthetic::SyntheticBlob;
+use crate::verify::descriptors;
+use futures_util::StreamExt;
+use std::collections::{HashMap, HashSet};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub(crate) const BLOCK: usize = 512;
+
+/// Annotation holding the full name a manifest is stored under.
+const IMAGE_NAME_ANNOTATION: &str = "io.containerd.image.name";
+const REF_NAME_ANNOTATION: &str = "org.opencontainers.image.ref.name";
+const OCI_INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+
+/// Builds a ustar header block for an entry of type `kind` (`b'0'` for a
+/// regular file), truncating `path` to the 99 bytes the name field holds.
+pub(crate) fn tar_header(path: &str, size: u64, kind: u8) -> [u8; BLOCK] {
+    fn octal(field: &mut [u8], value: u64) {
+        let digits = format!("{:0width$o}", value, width = field.len() - 1);
+        field[..digits.len()].copy_from_slice(digits.as_bytes());
+    }
+
+    let mut header = [0u8; BLOCK];
+    let name = &path.as_bytes()[..path.len().min(99)];
+    header[..name.len()].copy_from_slice(name);
+    octal(&mut header[100..108], 0o644);
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], size);
+    octal(&mut header[136..148], 0);
+    header[156] = kind;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // The checksum is computed with its own field filled with spaces.
+    header[148..156].fill(b' ');
+    let checksum: u64 = header.iter().map(|&b| u64::from(b)).sum();
+    octal(&mut header[148..155], checksum);
+    header
+}
+
+/// Writes a tar archive in the ustar format: regular files only, with PAX
+/// headers for paths that do not fit.
+struct TarWriter<W> {
+    inner: W,
+}
+
+impl<W: AsyncWrite + Unpin> TarWriter<W> {
+    async fn start(&mut self, path: &str, size: u64) -> Result<()> {
+        if path.len() >= 100 {
+            // Paths that do not fit the name field (`blobs/sha512/...`)
+            // are given in a PAX extended header.
+            let record = pax_record("path", path);
+            self.inner
+                .write_all(&tar_header("PaxHeader", record.len() as u64, b'x'))
+                .await?;
+            self.inner.write_all(record.as_bytes()).await?;
+            self.pad(record.len() as u64).await?;
+        }
+        self.inner.write_all(&tar_header(path, size, b'0')).await?;
+        Ok(())
+    }
+
+    async fn pad(&mut self, size: u64) -> Result<()> {
+        let remainder = (size % BLOCK as u64) as usize;
+        if remainder != 0 {
+            self.inner.write_all(&[0; BLOCK][remainder..]).await?;
+        }
+        Ok(())
+    }
+
+    async fn append(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        self.start(path, data.len() as u64).await?;
+        self.inner.write_all(data).await?;
+        self.pad(data.len() as u64).await
+    }
+
+    async fn append_synthetic(&mut self, path: &str, blob: SyntheticBlob) -> Result<()> {
+        self.start(path, blob.size).await?;
+        let mut chunks = blob.stream();
+        while let Some(Ok(chunk)) = chunks.next().await {
+            self.inner.write_all(&chunk).await?;
+        }
+        self.pad(blob.size).await
+    }
+
+    async fn finish(mut self) -> Result<()> {
+        self.inner.write_all(&[0; 2 * BLOCK]).await?;
+        self.inner.flush().await?;
+        Ok(())
+    }
+}
+
+/// Formats a PAX extended header record: `<length> <key>=<value>\n`,
+/// where the length counts the whole record, its own digits included.
+fn pax_record(key: &str, value: &str) -> String {
+    let rest = key.len() + value.len() + 3;
+    let mut length = rest + 1;
+    while length != rest + length.to_string().len() {
+        length = rest + length.to_string().len();
+    }
+    format!("{} {}={}\n", length, key, value)
+}
+
+fn blob_path(digest: &str) -> String {
+    format!("blobs/{}", digest.replacen(':', "/", 1))
+}
+
+/// Writes the image stored under `repository:reference`, with every
+/// manifest and blob it references, as an OCI image layout tar.
+pub(crate) async fn export<W: AsyncWrite + Unpin>(
+    storage: &dyn Storage,
+    synthetic: &HashMap<String, SyntheticBlob>,
+    repository: &str,
+    reference: &str,
+    writer: W,
+) -> Result<()> {
+    let key = format!("{}:{}", repository, reference);
+    let root = storage
+        .get_manifest(&key)
+        .await?
+        .ok_or(RegistryError::ManifestNotFound(key))?;
+    let root_digest = sha256_digest(&root.data);
+
+    let mut descriptor = serde_json::json!({
+        "mediaType": root.content_type,
+        "digest": root_digest,
+        "size": root.data.len(),
+    });
+    if !reference.contains(':') {
+        descriptor["annotations"] = serde_json::json!({ REF_NAME_ANNOTATION: reference });
+    }
+    let index = serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": OCI_INDEX_MEDIA_TYPE,
+        "manifests": [descriptor],
+    });
+
+    let mut tar = TarWriter { inner: writer };
+    tar.append("oci-layout", br#"{"imageLayoutVersion":"1.0.0"}"#)
+        .await?;
+    tar.append("index.json", &serde_json::to_vec(&index)?)
+        .await?;
+
+    let mut written = HashSet::new();
+    let mut queue = vec![(root_digest, root.data)];
+    while let Some((digest, data)) = queue.pop() {
+        if !written.insert(digest.clone()) {
+            continue;
+        }
+        tar.append(&blob_path(&digest), &data).await?;
+        let manifest: serde_json::Value = serde_json::from_slice(&data)?;
+
+        for child in descriptors(&manifest, "manifests") {
+            let key = format!("{}:{}", repository, child.digest);
+            let entry = storage
+                .get_manifest(&key)
+                .await?
+                .ok_or(RegistryError::ManifestNotFound(key))?;
+            queue.push((child.digest, entry.data));
+        }
+        for field in ["config", "layers", "blobs"] {
+            for blob in descriptors(&manifest, field) {
+                if !written.insert(blob.digest.clone()) {
+                    continue;
+                }
+                let path = blob_path(&blob.digest);
+                if let Some(generated) = synthetic.get(&blob.digest) {
+                    tar.append_synthetic(&path, *generated).await?;
+                    continue;
+                }
+                let data = storage
+                    .get_blob(&blob.digest)
+                    .await?
+                    .ok_or(RegistryError::BlobNotFound(blob.digest))?;
+                tar.append(&path, &data).await?;
+            }
+        }
+    }
+    tar.finish().await
+}
+
+/// Writes every manifest and blob in storage as one OCI image layout tar,
+/// for [`import_registry`] to load back.
+///
+/// With `repository`, only the manifests of that repository are written,
+/// with the stored blobs they reference.
+pub(crate) async fn export_registry<W: AsyncWrite + Unpin>(
+    storage: &dyn Storage,
+    synthetic: &HashMap<String, SyntheticBlob>,
+    repository: Option<&str>,
+    writer: W,
+) -> Result<()> {
+    let prefix = repository.map(|repository| format!("{}:", repository));
+    let mut keys = storage.list_manifests().await?;
+    keys.retain(|key| prefix.as_ref().is_none_or(|prefix| key.starts_with(prefix)));
+    keys.sort();
+    let mut listed = Vec::new();
+    let mut manifests = Vec::new();
+    for key in keys {
+        let Some((repository, reference)) = key.split_once(':') else {
+            continue;
+        };
+        let Some(entry) = storage.get_manifest(&key).await? else {
+            continue;
+        };
+        let digest = sha256_digest(&entry.data);
+        let mut annotations = serde_json::Map::new();
+        if reference.contains(':') {
+            annotations.insert(
+                IMAGE_NAME_ANNOTATION.to_string(),
+                format!("{}@{}", repository, reference).into(),
+            );
+        } else {
+            annotations.insert(IMAGE_NAME_ANNOTATION.to_string(), key.clone().into());
+            annotations.insert(REF_NAME_ANNOTATION.to_string(), reference.into());
+        }
+        listed.push(serde_json::json!({
+            "mediaType": entry.content_type,
+            "digest": digest,
+            "size": entry.data.len(),
+            "annotations": annotations,
+        }));
+        manifests.push((digest, entry.data));
+    }
+    let index = serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": OCI_INDEX_MEDIA_TYPE,
+        "manifests": listed,
+    });
+
+    let mut tar = TarWriter { inner: writer };
+    tar.append("oci-layout", br#"{"imageLayoutVersion":"1.0.0"}"#)
+        .await?;
+    tar.append("index.json", &serde_json::to_vec(&index)?)
+        .await?;
+
+    let mut blobs = match repository {
+        Some(_) => {
+            let mut referenced = HashSet::new();
+            for (_, data) in &manifests {
+                let Ok(manifest) = serde_json::from_slice::<serde_json::Value>(data) else {
+                    continue;
+                };
+                for field in ["config", "layers", "blobs"] {
+                    referenced.extend(descriptors(&manifest, field).into_iter().map(|d| d.digest));
+                }
+            }
+            referenced.into_iter().collect()
+        }
+        None => {
+            let mut blobs = storage.list_blobs().await?;
+            blobs.extend(synthetic.keys().cloned());
+            blobs
+        }
+    };
+    blobs.sort();
+
+    let mut written = HashSet::new();
+    for (digest, data) in manifests {
+        if written.insert(digest.clone()) {
+            tar.append(&blob_path(&digest), &data).await?;
+        }
+    }
+    for digest in blobs {
+        if !written.insert(digest.clone()) {
+            continue;
+        }
+        if let Some(data) = storage.get_blob(&digest).await? {
+            tar.append(&blob_path(&digest), &data).await?;
+        } else if let Some(generated) = synthetic.get(&digest) {
+            tar.append_synthetic(&blob_path(&digest), *generated)
+                .await?;
+        }
+    }
+    tar.finish().await
+}
+
+fn invalid(message: impl Into<String>) -> RegistryError {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.into()).into()
+}
+
+/// Reads the next regular file of a tar stream, skipping other entries.
+async fn next_file<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<(String, Vec<u8>)>> {
+    fn field(bytes: &[u8]) -> &str {
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        std::str::from_utf8(&bytes[..end]).unwrap_or_default()
+    }
+
+    let mut long_path = None;
+    loop {
+        let mut header = [0u8; BLOCK];
+        match reader.read_exact(&mut header).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        if header.iter().all(|&b| b == 0) {
+            return Ok(None);
+        }
+
+        let size = u64::from_str_radix(field(&header[124..136]).trim(), 8)
+            .map_err(|_| invalid("malformed tar header"))?;
+        let mut data = vec![0; size as usize];
+        reader.read_exact(&mut data).await?;
+        let padding = (BLOCK - size as usize % BLOCK) % BLOCK;
+        reader.read_exact(&mut [0; BLOCK][..padding]).await?;
+
+        match header[156] {
+            b'x' => {
+                long_path = String::from_utf8_lossy(&data)
+                    .lines()
+                    .filter_map(|record| record.split_once(' ')?.1.strip_prefix("path="))
+                    .map(str::to_string)
+                    .next_back();
+                continue;
+            }
+            b'0' | 0 => {}
+            _ => {
+                long_path = None;
+                continue;
+            }
+        }
+        let path = long_path.take().unwrap_or_else(|| {
+            match (field(&header[345..500]), field(&header[..100])) {
+                ("", name) => name.to_string(),
+                (prefix, name) => format!("{}/{}", prefix, name),
+            }
+        });
+        return Ok(Some((path.trim_start_matches("./").to_string(), data)));
+    }
+}
+
+/// Loads a layout written by [`export_registry`] into storage, returning
+/// the number of manifests stored.
+///
+/// Every file under `blobs/` that `index.json` does not list as a manifest
+/// is stored as a blob. `index.json` is expected before the blobs, as
+/// [`export_registry`] writes it; files seen earlier are held until it
+/// arrives. With `repository`, every manifest is stored in that repository
+/// rather than the one it was exported from.
+pub(crate) async fn import_registry<R: AsyncRead + Unpin>(
+    storage: &dyn Storage,
+    repository: Option<&str>,
+    mut reader: R,
+) -> Result<usize> {
+    // Manifest digest to the keys and content type it is stored under.
+    let mut manifests: Option<HashMap<String, (Vec<String>, String)>> = None;
+    let mut pending = Vec::new();
+    let mut stored = 0;
+
+    while let Some((path, data)) = next_file(&mut reader).await? {
+        if path == "index.json" {
+            let index: serde_json::Value = serde_json::from_slice(&data)?;
+            let mut listed: HashMap<String, (Vec<String>, String)> = HashMap::new();
+            for descriptor in index["manifests"].as_array().into_iter().flatten() {
+                let (Some(digest), Some(name)) = (
+                    descriptor["digest"].as_str(),
+                    descriptor["annotations"][IMAGE_NAME_ANNOTATION].as_str(),
+                ) else {
+                    continue;
+                };
+                let Some((name, reference)) =
+                    name.split_once('@').or_else(|| name.rsplit_once(':'))
+                else {
+                    continue;
+                };
+                let key = format!("{}:{}", repository.unwrap_or(name), reference);
+                let content_type = descriptor["mediaType"].as_str().unwrap_or_default();
+                listed
+                    .entry(digest.to_string())
+                    .or_insert_with(|| (Vec::new(), content_type.to_string()))
+                    .0
+                    .push(key);
+            }
+            manifests = Some(listed);
+            for (digest, data) in std::mem::take(&mut pending) {
+                stored += store(storage, manifests.as_ref(), digest, data).await?;
+            }
+            continue;
+        }
+        let Some(digest) = path
+            .strip_prefix("blobs/")
+            .and_then(|rest| rest.split_once('/'))
+            .map(|(algorithm, encoded)| format!("{}:{}", algorithm, encoded))
+        else {
+            continue;
+        };
+        if manifests.is_some() {
+            stored += store(storage, manifests.as_ref(), digest, data).await?;
+        } else {
+            pending.push((digest, data));
+        }
+    }
+
+    if manifests.is_none() {
+        return Err(invalid("registry snapshot has no index.json"));
+    }
+    Ok(stored)
+}
+
+/// Stores one file of a snapshot as the manifests `index` lists it as, or
+/// as a blob. Returns the number of manifests stored.
+async fn store(
+    storage: &dyn Storage,
+    index: Option<&HashMap<String, (Vec<String>, String)>>,
+    digest: String,
+    data: Vec<u8>,
+) -> Result<usize> {
+    let Some((keys, content_type)) = index.and_then(|index| index.get(&digest)) else {
+        storage.store_blob(digest, data).await?;
+        return Ok(0);
+    };
+    for key in keys {
+        let entry = ManifestEntry {
+            data: data.clone(),
+            content_type: content_type.clone(),
+        };
+        storage.store_manifest(key.clone(), entry).await?;
+    }
+    Ok(keys.len())
+}
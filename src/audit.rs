@@ -0,0 +1,111 @@
+//! Audit trail of authenticated actions.
+//!
+//! With authentication enabled, every request to a repository is recorded
+//! with the user it was made as, whether or not it was allowed, so policy
+//! and compliance tooling can be tested against
+//! [`RegistryServer::audit_log`](crate::RegistryServer::audit_log). With
+//! [`AuthConfig::with_audit_file`](crate::AuthConfig::with_audit_file) the
+//! entries are also appended to a file, one JSON object per line.
+
+use crate::history::rfc3339;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+/// One request to a repository, as the audit trail records it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// When the request was answered.
+    pub time: SystemTime,
+    /// Who made the request; `None` for anonymous requests.
+    pub user: Option<String>,
+    /// Repository the request was for.
+    pub repository: String,
+    /// What the request did: `pull`, `push` or `delete`.
+    pub action: String,
+    /// Request method.
+    pub method: String,
+    /// Request path.
+    pub path: String,
+    /// Response status; `401` and `403` mark denied requests.
+    pub status: u16,
+}
+
+impl AuditEntry {
+    /// Returns whether the request was refused for lack of access.
+    pub fn denied(&self) -> bool {
+        matches!(self.status, 401 | 403)
+    }
+}
+
+/// JSON form written to the audit file.
+#[derive(Serialize)]
+struct AuditLine<'a> {
+    time: String,
+    user: Option<&'a str>,
+    repository: &'a str,
+    action: &'a str,
+    method: &'a str,
+    path: &'a str,
+    status: u16,
+}
+
+impl<'a> From<&'a AuditEntry> for AuditLine<'a> {
+    fn from(entry: &'a AuditEntry) -> Self {
+        Self {
+            time: rfc3339(entry.time),
+            user: entry.user.as_deref(),
+            repository: &entry.repository,
+            action: &entry.action,
+            method: &entry.method,
+            path: &entry.path,
+            status: entry.status,
+        }
+    }
+}
+
+/// Keeps audit entries in memory and, optionally, in a file.
+pub(crate) struct AuditLog {
+    entries: Mutex<Vec<AuditEntry>>,
+    file: tokio::sync::Mutex<Option<fs::File>>,
+}
+
+impl AuditLog {
+    /// Creates a log, appending to `file` if given.
+    pub(crate) async fn new(file: Option<&Path>) -> std::io::Result<Self> {
+        let file = match file {
+            Some(path) => Some(
+                fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            entries: Mutex::default(),
+            file: tokio::sync::Mutex::new(file),
+        })
+    }
+
+    pub(crate) fn entries(&self) -> Vec<AuditEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    pub(crate) async fn record(&self, entry: AuditEntry) {
+        let mut file = self.file.lock().await;
+        if let Some(file) = file.as_mut() {
+            let mut line = serde_json::to_vec(&AuditLine::from(&entry)).unwrap_or_default();
+            line.push(b'\n');
+            if let Err(e) = file.write_all(&line).await {
+                warn!("Failed to write audit entry: {}", e);
+            }
+        }
+        self.entries.lock().unwrap().push(entry);
+    }
+}
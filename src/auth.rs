@@ -0,0 +1,692 @@
+//! Authentication: basic auth and a bearer token issuer.
+//!
+//! Bearer tokens are JWT-shaped (`header.claims.signature`, HMAC-SHA256) so
+//! clients that inspect `exp` or `access` claims see realistic values.
+
+use crate::audit::{AuditEntry, AuditLog};
+use crate::clock::{self, ClockSkew};
+use crate::profile::RegistryProfile;
+use crate::server::error_response;
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+    Form,
+};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// How clients authenticate against the registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthScheme {
+    /// HTTP basic auth on every request.
+    Basic,
+    /// Token auth: clients exchange credentials for a bearer token at `realm`.
+    Bearer {
+        /// Token endpoint URL; defaults to `<registry url>/token`.
+        realm: Option<String>,
+        /// Service name advertised in challenges and token audiences.
+        service: String,
+    },
+}
+
+/// Authentication configuration.
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    /// Authentication scheme.
+    pub scheme: AuthScheme,
+    /// Accepted username/password pairs.
+    pub users: HashMap<String, String>,
+    /// Whether unauthenticated clients may pull.
+    pub anonymous_pull: bool,
+    /// Lifetime of issued bearer tokens.
+    pub token_ttl: Duration,
+    /// Repositories hidden from clients that may not pull them.
+    pub private_repositories: HashSet<String>,
+    /// File the audit trail is appended to, besides memory.
+    pub audit_file: Option<PathBuf>,
+    /// Key bearer tokens are signed with; random per server if `None`.
+    pub signing_key: Option<String>,
+}
+
+impl AuthConfig {
+    fn new(scheme: AuthScheme) -> Self {
+        Self {
+            scheme,
+            users: HashMap::new(),
+            anonymous_pull: false,
+            token_ttl: Duration::from_secs(300),
+            private_repositories: HashSet::new(),
+            audit_file: None,
+            signing_key: None,
+        }
+    }
+
+    /// Requires HTTP basic auth.
+    pub fn basic() -> Self {
+        Self::new(AuthScheme::Basic)
+    }
+
+    /// Requires bearer tokens issued for `service` by the built-in token
+    /// endpoint.
+    pub fn bearer(service: impl Into<String>) -> Self {
+        Self::new(AuthScheme::Bearer {
+            realm: None,
+            service: service.into(),
+        })
+    }
+
+    /// Overrides the token realm advertised in bearer challenges.
+    pub fn with_realm(mut self, url: impl Into<String>) -> Self {
+        if let AuthScheme::Bearer { realm, .. } = &mut self.scheme {
+            *realm = Some(url.into());
+        }
+        self
+    }
+
+    /// Adds a user.
+    pub fn with_user(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.users.insert(username.into(), password.into());
+        self
+    }
+
+    /// Allows or forbids unauthenticated pulls.
+    pub fn with_anonymous_pull(mut self, allowed: bool) -> Self {
+        self.anonymous_pull = allowed;
+        self
+    }
+
+    /// Marks a repository private: anonymous clients may not pull it, and
+    /// under [`RegistryProfile::Ghcr`] only its namespace owner may.
+    pub fn with_private_repository(mut self, name: impl Into<String>) -> Self {
+        self.private_repositories.insert(name.into());
+        self
+    }
+
+    /// Sets the lifetime of issued bearer tokens.
+    pub fn with_token_ttl(mut self, ttl: Duration) -> Self {
+        self.token_ttl = ttl;
+        self
+    }
+
+    /// Signs bearer tokens with `key`, so that servers sharing it accept
+    /// each other's tokens, as a registry and a [`TokenServer`](crate::TokenServer) do.
+    pub fn with_signing_key(mut self, key: impl Into<String>) -> Self {
+        self.signing_key = Some(key.into());
+        self
+    }
+
+    /// Also appends the [audit trail](crate::audit) to `path`, as JSON
+    /// lines. The file is created if missing.
+    pub fn with_audit_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.audit_file = Some(path.into());
+        self
+    }
+}
+
+/// One `access` entry of a bearer token.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessEntry {
+    /// Resource type, normally `repository`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Resource name.
+    pub name: String,
+    /// Granted actions (`pull`, `push`, `delete`).
+    pub actions: Vec<String>,
+}
+
+/// Claims carried by issued bearer tokens.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenClaims {
+    /// Token issuer.
+    pub iss: String,
+    /// Authenticated user, empty for anonymous tokens.
+    pub sub: String,
+    /// Service the token is valid for.
+    pub aud: String,
+    /// Issue time (seconds since the Unix epoch).
+    pub iat: u64,
+    /// Expiry time (seconds since the Unix epoch).
+    pub exp: u64,
+    /// Granted access.
+    pub access: Vec<AccessEntry>,
+}
+
+impl TokenClaims {
+    /// Returns whether the claims grant `action` on repository `name`.
+    pub fn allows(&self, name: &str, action: &str) -> bool {
+        self.access.iter().any(|entry| {
+            entry.kind == "repository"
+                && (entry.name == name || entry.name == "*")
+                && entry.actions.iter().any(|a| a == action || a == "*")
+        })
+    }
+
+    /// Returns the granted access as scopes (`repository:app:pull,push`),
+    /// in the order the token lists them.
+    pub fn scopes(&self) -> Vec<String> {
+        self.access
+            .iter()
+            .map(|entry| format!("{}:{}:{}", entry.kind, entry.name, entry.actions.join(",")))
+            .collect()
+    }
+}
+
+/// What the registry knows about a token it issued, as reported by
+/// [`RegistryServer::introspect_token`](crate::RegistryServer::introspect_token)
+/// and `POST /token/introspect`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenIntrospection {
+    /// Whether the registry still accepts the token.
+    pub active: bool,
+    /// The token's claims.
+    pub claims: TokenClaims,
+}
+
+impl TokenIntrospection {
+    /// Returns when the token expires.
+    pub fn expires_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.claims.exp)
+    }
+}
+
+/// Actions a request needs on its repository.
+fn required_actions(method: &Method) -> &'static [&'static str] {
+    match *method {
+        Method::GET | Method::HEAD => &["pull"],
+        Method::DELETE => &["delete"],
+        _ => &["pull", "push"],
+    }
+}
+
+/// Parses a `repository:<name>:<actions>` scope.
+fn parse_scope(scope: &str) -> Option<AccessEntry> {
+    let (kind, rest) = scope.split_once(':')?;
+    let (name, actions) = rest.rsplit_once(':')?;
+    Some(AccessEntry {
+        kind: kind.to_string(),
+        name: name.to_string(),
+        actions: actions.split(',').map(str::to_string).collect(),
+    })
+}
+
+/// Credentials issued by [`RegistryServer::authorization_token`](crate::RegistryServer::authorization_token),
+/// shaped like ECR's `GetAuthorizationToken` response.
+#[derive(Debug, Clone)]
+pub struct AuthorizationToken {
+    /// Base64-encoded `AWS:<password>`.
+    pub authorization_token: String,
+    /// When the password stops being accepted.
+    pub expires_at: SystemTime,
+    /// Registry URL to log in to.
+    pub proxy_endpoint: String,
+}
+
+impl AuthorizationToken {
+    /// Decodes the username and password to log in with.
+    pub fn credentials(&self) -> Option<(String, String)> {
+        let decoded = String::from_utf8(STANDARD.decode(&self.authorization_token).ok()?).ok()?;
+        let (user, password) = decoded.split_once(':')?;
+        Some((user.to_string(), password.to_string()))
+    }
+}
+
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get("Authorization")?.to_str().ok()?;
+    let encoded = value.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (user, password) = decoded.split_once(':')?;
+    Some((user.to_string(), password.to_string()))
+}
+
+/// Validates credentials and issues tokens for one server.
+pub(crate) struct Authenticator {
+    config: AuthConfig,
+    profile: RegistryProfile,
+    registry_url: String,
+    realm: String,
+    secret: String,
+    clock: Option<ClockSkew>,
+    audit: AuditLog,
+}
+
+impl Authenticator {
+    pub(crate) fn new(
+        config: AuthConfig,
+        profile: RegistryProfile,
+        registry_url: &str,
+        clock: Option<ClockSkew>,
+        audit: AuditLog,
+    ) -> Self {
+        let realm = match &config.scheme {
+            AuthScheme::Bearer {
+                realm: Some(realm), ..
+            } => realm.clone(),
+            _ => format!("{}/token", registry_url),
+        };
+        let secret = config
+            .signing_key
+            .clone()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        Self {
+            config,
+            profile,
+            registry_url: registry_url.to_string(),
+            realm,
+            secret,
+            clock,
+            audit,
+        }
+    }
+
+    /// Current time on the registry's clock, in seconds since the epoch.
+    fn now(&self) -> u64 {
+        clock::unix_now(self.clock)
+    }
+
+    /// Returns the audit trail recorded so far.
+    pub(crate) fn audit_log(&self) -> Vec<AuditEntry> {
+        self.audit.entries()
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()).expect("HMAC accepts any key")
+    }
+
+    /// Signs `claims` into a token.
+    pub(crate) fn sign(&self, claims: &TokenClaims) -> String {
+        let header = URL_SAFE_NO_PAD.encode(br#"{"alg":"HS256","typ":"JWT"}"#);
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims).unwrap_or_default());
+        let signing_input = format!("{}.{}", header, payload);
+        let mut mac = self.mac();
+        mac.update(signing_input.as_bytes());
+        let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+        format!("{}.{}", signing_input, signature)
+    }
+
+    /// Verifies a token's signature and returns its claims, expired or not.
+    pub(crate) fn decode(&self, token: &str) -> Option<TokenClaims> {
+        let (signing_input, signature) = token.rsplit_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        let mut mac = self.mac();
+        mac.update(signing_input.as_bytes());
+        mac.verify_slice(&signature).ok()?;
+
+        let (_, payload) = signing_input.split_once('.')?;
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()
+    }
+
+    /// Decodes a token this registry signed and reports whether it is
+    /// still accepted.
+    pub(crate) fn introspect(&self, token: &str) -> Option<TokenIntrospection> {
+        let claims = self.decode(token.trim())?;
+        Some(TokenIntrospection {
+            active: claims.exp > self.now(),
+            claims,
+        })
+    }
+
+    /// Returns the username if the basic credentials are valid.
+    ///
+    /// Unknown users may log in with a password issued by
+    /// [`authorization_token`](Self::authorization_token) until it expires.
+    fn check_basic(&self, headers: &HeaderMap) -> Option<String> {
+        let (user, password) = basic_credentials(headers)?;
+        match self.config.users.get(&user) {
+            Some(expected) => (*expected == password).then_some(user),
+            None => self
+                .password_claims(&user, &password)
+                .filter(|claims| claims.exp > self.now())
+                .map(|_| user),
+        }
+    }
+
+    fn password_claims(&self, user: &str, password: &str) -> Option<TokenClaims> {
+        let claims = self.decode(password)?;
+        (claims.sub == user).then_some(claims)
+    }
+
+    fn has_expired_password(&self, headers: &HeaderMap) -> bool {
+        basic_credentials(headers)
+            .and_then(|(user, password)| self.password_claims(&user, &password))
+            .is_some_and(|claims| claims.exp <= self.now())
+    }
+
+    /// Issues an ECR-style login for user `AWS`, valid for the token TTL.
+    pub(crate) fn authorization_token(&self) -> AuthorizationToken {
+        let iat = self.now();
+        let claims = TokenClaims {
+            iss: "registry-testkit".to_string(),
+            sub: "AWS".to_string(),
+            aud: self.registry_url.clone(),
+            iat,
+            exp: iat + self.config.token_ttl.as_secs(),
+            access: vec![AccessEntry {
+                kind: "repository".to_string(),
+                name: "*".to_string(),
+                actions: vec!["*".to_string()],
+            }],
+        };
+        let password = self.sign(&claims);
+
+        AuthorizationToken {
+            authorization_token: STANDARD.encode(format!("AWS:{}", password)),
+            expires_at: UNIX_EPOCH + Duration::from_secs(claims.exp),
+            proxy_endpoint: self.registry_url.clone(),
+        }
+    }
+
+    fn bearer_claims(&self, headers: &HeaderMap) -> Option<TokenClaims> {
+        let value = headers.get("Authorization")?.to_str().ok()?;
+        let token = value.strip_prefix("Bearer ")?;
+        let claims = self.decode(token.trim())?;
+        (claims.exp > self.now()).then_some(claims)
+    }
+
+    /// Returns who a request is made as, if its credentials are valid.
+    fn subject(&self, headers: &HeaderMap) -> Option<String> {
+        self.check_basic(headers).or_else(|| {
+            self.bearer_claims(headers)
+                .map(|claims| claims.sub)
+                .filter(|sub| !sub.is_empty())
+        })
+    }
+
+    fn challenge(&self, scope: Option<(&str, &[&str])>) -> Response {
+        let header = match &self.config.scheme {
+            AuthScheme::Basic if self.profile == RegistryProfile::Ecr => format!(
+                "Basic realm=\"{}/\",service=\"ecr.amazonaws.com\"",
+                self.registry_url
+            ),
+            AuthScheme::Basic => "Basic realm=\"Registry Realm\"".to_string(),
+            AuthScheme::Bearer { service, .. } => {
+                let mut header = format!("Bearer realm=\"{}\",service=\"{}\"", self.realm, service);
+                if let Some((name, actions)) = scope {
+                    header.push_str(&format!(
+                        ",scope=\"repository:{}:{}\"",
+                        name,
+                        actions.join(",")
+                    ));
+                }
+                header
+            }
+        };
+        let mut response = error_response(
+            StatusCode::UNAUTHORIZED,
+            "UNAUTHORIZED",
+            "authentication required",
+        );
+        if let Ok(value) = header.parse() {
+            response.headers_mut().insert("WWW-Authenticate", value);
+        }
+        response
+    }
+
+    fn is_private(&self, name: &str) -> bool {
+        self.config.private_repositories.contains(name)
+    }
+
+    /// Restricts a requested access entry to what `subject` may be granted.
+    fn grant(&self, subject: Option<&str>, mut entry: AccessEntry) -> Option<AccessEntry> {
+        entry.name = self.profile.normalize_name(&entry.name);
+        match subject {
+            None => {
+                if !self.config.anonymous_pull || self.is_private(&entry.name) {
+                    return None;
+                }
+                entry.actions.retain(|action| action == "pull");
+            }
+            Some(user) if self.profile == RegistryProfile::Ghcr => {
+                // GHCR packages belong to the namespace owner; other users
+                // only see public packages.
+                let owner = entry.name.split('/').next().unwrap_or_default();
+                if !owner.eq_ignore_ascii_case(user) {
+                    if self.is_private(&entry.name) {
+                        return None;
+                    }
+                    entry.actions.retain(|action| action == "pull");
+                }
+            }
+            Some(user) => {
+                if let Some(namespace) = self.profile.robot_namespace(user) {
+                    let in_namespace = entry.name == namespace
+                        || entry
+                            .name
+                            .strip_prefix(namespace)
+                            .is_some_and(|rest| rest.starts_with('/'));
+                    if !in_namespace {
+                        return None;
+                    }
+                }
+            }
+        }
+        (!entry.actions.is_empty()).then_some(entry)
+    }
+
+    /// Checks a registry API request, returning a challenge if it is denied.
+    fn challenge_for(&self, request: &Request) -> Option<Response> {
+        let path = request.uri().path();
+        let scope = crate::server::split_repository_path(path).map(|(name, _)| {
+            (
+                self.profile.normalize_name(name),
+                required_actions(request.method()),
+            )
+        });
+        let scope_ref = scope
+            .as_ref()
+            .map(|(name, actions)| (name.as_str(), *actions));
+        let anonymous_pull = scope_ref.is_some_and(|(name, actions)| {
+            actions == ["pull"] && self.config.anonymous_pull && !self.is_private(name)
+        });
+
+        match &self.config.scheme {
+            AuthScheme::Basic => {
+                if self.check_basic(request.headers()).is_some() || anonymous_pull {
+                    return None;
+                }
+                if self.profile == RegistryProfile::Ecr
+                    && self.has_expired_password(request.headers())
+                {
+                    return Some(error_response(
+                        StatusCode::FORBIDDEN,
+                        "DENIED",
+                        "Your authorization token has expired. Reauthenticate and try again.",
+                    ));
+                }
+            }
+            AuthScheme::Bearer { .. } => {
+                let claims = self.bearer_claims(request.headers()).or_else(|| {
+                    // GHCR accepts a PAT as a basic password and treats it
+                    // like the token it would have issued for it.
+                    if self.profile != RegistryProfile::Ghcr {
+                        return None;
+                    }
+                    let user = self.check_basic(request.headers())?;
+                    let requested = scope.iter().map(|(name, actions)| AccessEntry {
+                        kind: "repository".to_string(),
+                        name: name.clone(),
+                        actions: actions.iter().map(|action| action.to_string()).collect(),
+                    });
+                    Some(self.claims_for(Some(user), String::new(), requested))
+                });
+                if let Some(claims) = claims {
+                    match scope_ref {
+                        None => return None,
+                        Some((name, actions)) => {
+                            if actions.iter().all(|action| claims.allows(name, action)) {
+                                return None;
+                            }
+                            if self.profile == RegistryProfile::Ghcr {
+                                debug!("Hiding {} from client without access", name);
+                                return Some(error_response(
+                                    StatusCode::NOT_FOUND,
+                                    "NAME_UNKNOWN",
+                                    "repository name not known to registry",
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        debug!("Rejecting unauthenticated request to {}", path);
+        Some(self.challenge(scope_ref))
+    }
+
+    /// Builds claims granting `subject` what it may have of `requested`.
+    fn claims_for(
+        &self,
+        subject: Option<String>,
+        audience: String,
+        requested: impl IntoIterator<Item = AccessEntry>,
+    ) -> TokenClaims {
+        let access = requested
+            .into_iter()
+            .filter_map(|entry| self.grant(subject.as_deref(), entry))
+            .collect();
+        let iat = self.now();
+        TokenClaims {
+            iss: "registry-testkit".to_string(),
+            sub: subject.unwrap_or_default(),
+            aud: audience,
+            iat,
+            exp: iat + self.config.token_ttl.as_secs(),
+            access,
+        }
+    }
+
+    /// Issues a token for the scopes requested in `query`.
+    fn issue(&self, headers: &HeaderMap, query: &str) -> Result<TokenResponse, StatusCode> {
+        let service = match &self.config.scheme {
+            AuthScheme::Bearer { service, .. } => service.clone(),
+            AuthScheme::Basic => return Err(StatusCode::NOT_FOUND),
+        };
+
+        let subject = if headers.contains_key("Authorization") {
+            match self.check_basic(headers) {
+                Some(user) => Some(user),
+                None => {
+                    warn!("Token request with invalid credentials");
+                    return Err(StatusCode::UNAUTHORIZED);
+                }
+            }
+        } else {
+            None
+        };
+
+        let requested = form_urlencoded::parse(query.as_bytes())
+            .filter(|(key, _)| key == "scope")
+            .filter_map(|(_, value)| parse_scope(&value));
+        let claims = self.claims_for(subject, service, requested);
+        let token = self.sign(&claims);
+
+        Ok(TokenResponse {
+            // GHCR's token endpoint only returns `token`.
+            access_token: (self.profile != RegistryProfile::Ghcr).then(|| token.clone()),
+            token,
+            expires_in: self.config.token_ttl.as_secs(),
+        })
+    }
+}
+
+/// Body of a token endpoint response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct TokenResponse {
+    pub(crate) token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) access_token: Option<String>,
+    pub(crate) expires_in: u64,
+}
+
+/// Form body of a token introspection request (RFC 7662).
+#[derive(Deserialize)]
+pub(crate) struct IntrospectionRequest {
+    token: String,
+}
+
+/// Body of a token introspection response: only `active` for tokens the
+/// registry did not issue, the claims and space-separated scopes otherwise.
+#[derive(Serialize)]
+struct IntrospectionResponse {
+    active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    claims: Option<TokenClaims>,
+}
+
+pub(crate) async fn require_auth(
+    State(auth): State<Arc<Authenticator>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let audited =
+        crate::server::split_repository_path(request.uri().path()).map(|(name, _)| AuditEntry {
+            time: SystemTime::now(),
+            user: auth.subject(request.headers()),
+            repository: auth.profile.normalize_name(name),
+            action: required_actions(request.method())
+                .last()
+                .map(|action| action.to_string())
+                .unwrap_or_default(),
+            method: request.method().to_string(),
+            path: request.uri().path().to_string(),
+            status: 0,
+        });
+
+    let response = match auth.challenge_for(&request) {
+        None => next.run(request).await,
+        Some(challenge) => challenge,
+    };
+
+    if let Some(mut entry) = audited {
+        entry.time = auth.clock.map_or_else(SystemTime::now, ClockSkew::now);
+        entry.status = response.status().as_u16();
+        auth.audit.record(entry).await;
+    }
+    response
+}
+
+pub(crate) async fn token_endpoint(
+    State(auth): State<Arc<Authenticator>>,
+    request: Request,
+) -> Response {
+    let query = request.uri().query().unwrap_or_default();
+    match auth.issue(request.headers(), query) {
+        Ok(token) => Json(token).into_response(),
+        Err(status) => status.into_response(),
+    }
+}
+
+pub(crate) async fn introspection_endpoint(
+    State(auth): State<Arc<Authenticator>>,
+    Form(request): Form<IntrospectionRequest>,
+) -> Response {
+    if auth.config.scheme == AuthScheme::Basic {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let response = match auth.introspect(&request.token) {
+        Some(introspection) => IntrospectionResponse {
+            active: introspection.active,
+            scope: Some(introspection.claims.scopes().join(" ")),
+            claims: Some(introspection.claims),
+        },
+        None => IntrospectionResponse {
+            active: false,
+            scope: None,
+            claims: None,
+        },
+    };
+    Json(response).into_response()
+}
@@ -0,0 +1,221 @@
+//! Storage backend benchmarking helpers.
+//!
+//! Runs the same workloads against any set of [`Storage`] implementations so
+//! backends can be compared side by side.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use registry_testkit::bench::{BenchSuite, Workload};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let results = BenchSuite::with_default_backends()
+//!     .await?
+//!     .with_workload(Workload::StoreBlobs { count: 100, size: 64 * 1024 })
+//!     .run()
+//!     .await?;
+//! for result in results {
+//!     println!("{} {}: {:.0} ops/s", result.backend, result.workload, result.ops_per_sec());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::config::StorageBackend;
+use crate::error::Result;
+use crate::storage::{create_storage, ManifestEntry, Storage};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+/// A storage workload to measure.
+#[derive(Debug, Clone)]
+pub enum Workload {
+    /// Store `count` blobs of `size` bytes.
+    StoreBlobs { count: usize, size: usize },
+    /// Store `count` blobs of `size` bytes, then time reading them back.
+    GetBlobs { count: usize, size: usize },
+    /// Store and read back `count` small manifests.
+    Manifests { count: usize },
+    /// `tasks` concurrent tasks each doing `ops_per_task` store+get pairs.
+    Concurrent {
+        tasks: usize,
+        ops_per_task: usize,
+        size: usize,
+    },
+}
+
+impl Workload {
+    /// Number of storage operations the workload performs in its timed phase.
+    pub fn operations(&self) -> usize {
+        match self {
+            Workload::StoreBlobs { count, .. } | Workload::GetBlobs { count, .. } => *count,
+            Workload::Manifests { count } => count * 2,
+            Workload::Concurrent {
+                tasks,
+                ops_per_task,
+                ..
+            } => tasks * ops_per_task * 2,
+        }
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Workload::StoreBlobs { count, size } => write!(f, "store_blobs/{}x{}", count, size),
+            Workload::GetBlobs { count, size } => write!(f, "get_blobs/{}x{}", count, size),
+            Workload::Manifests { count } => write!(f, "manifests/{}", count),
+            Workload::Concurrent {
+                tasks,
+                ops_per_task,
+                size,
+            } => write!(f, "concurrent/{}x{}x{}", tasks, ops_per_task, size),
+        }
+    }
+}
+
+/// Outcome of running one workload against one backend.
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    /// Name the backend was registered under.
+    pub backend: String,
+    /// Description of the workload.
+    pub workload: String,
+    /// Number of storage operations timed.
+    pub operations: usize,
+    /// Time spent in the timed phase.
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    /// Throughput in operations per second.
+    pub fn ops_per_sec(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return f64::INFINITY;
+        }
+        self.operations as f64 / secs
+    }
+}
+
+/// Runs a single workload against `storage` and returns the timed duration.
+pub async fn run_workload(storage: Arc<dyn Storage>, workload: &Workload) -> Result<Duration> {
+    match workload {
+        Workload::StoreBlobs { count, size } => {
+            let start = Instant::now();
+            for i in 0..*count {
+                storage
+                    .store_blob(bench_digest(0, i), vec![0xAB; *size])
+                    .await?;
+            }
+            Ok(start.elapsed())
+        }
+        Workload::GetBlobs { count, size } => {
+            for i in 0..*count {
+                storage
+                    .store_blob(bench_digest(0, i), vec![0xCD; *size])
+                    .await?;
+            }
+            let start = Instant::now();
+            for i in 0..*count {
+                storage.get_blob(&bench_digest(0, i)).await?;
+            }
+            Ok(start.elapsed())
+        }
+        Workload::Manifests { count } => {
+            let start = Instant::now();
+            for i in 0..*count {
+                let entry = ManifestEntry {
+                    data: format!("{{\"schemaVersion\":2,\"n\":{}}}", i).into_bytes(),
+                    content_type: "application/vnd.oci.image.manifest.v1+json".to_string(),
+                };
+                storage
+                    .store_manifest(format!("bench:tag{}", i), entry)
+                    .await?;
+                storage.get_manifest(&format!("bench:tag{}", i)).await?;
+            }
+            Ok(start.elapsed())
+        }
+        Workload::Concurrent {
+            tasks,
+            ops_per_task,
+            size,
+        } => {
+            let start = Instant::now();
+            let mut set = JoinSet::new();
+            for task in 0..*tasks {
+                let storage = storage.clone();
+                let ops = *ops_per_task;
+                let size = *size;
+                set.spawn(async move {
+                    for i in 0..ops {
+                        let digest = bench_digest(task + 1, i);
+                        storage.store_blob(digest.clone(), vec![0xEF; size]).await?;
+                        storage.get_blob(&digest).await?;
+                    }
+                    Ok::<_, crate::error::RegistryError>(())
+                });
+            }
+            while let Some(joined) = set.join_next().await {
+                joined.expect("benchmark task panicked")?;
+            }
+            Ok(start.elapsed())
+        }
+    }
+}
+
+fn bench_digest(task: usize, index: usize) -> String {
+    format!("sha256:{:032x}{:032x}", task, index)
+}
+
+/// A set of backends and workloads to compare.
+#[derive(Default)]
+pub struct BenchSuite {
+    backends: Vec<(String, Arc<dyn Storage>)>,
+    workloads: Vec<Workload>,
+}
+
+impl BenchSuite {
+    /// Creates an empty suite.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a suite with the built-in memory and temp-dir backends.
+    pub async fn with_default_backends() -> Result<Self> {
+        Ok(Self::new()
+            .with_backend("memory", create_storage(&StorageBackend::Memory).await?)
+            .with_backend("disk", create_storage(&StorageBackend::TempDir).await?))
+    }
+
+    /// Adds a backend under the given name.
+    pub fn with_backend(mut self, name: impl Into<String>, storage: Arc<dyn Storage>) -> Self {
+        self.backends.push((name.into(), storage));
+        self
+    }
+
+    /// Adds a workload to run against every backend.
+    pub fn with_workload(mut self, workload: Workload) -> Self {
+        self.workloads.push(workload);
+        self
+    }
+
+    /// Runs every workload against every backend, sequentially.
+    pub async fn run(&self) -> Result<Vec<BenchResult>> {
+        let mut results = Vec::new();
+        for workload in &self.workloads {
+            for (name, storage) in &self.backends {
+                let elapsed = run_workload(storage.clone(), workload).await?;
+                results.push(BenchResult {
+                    backend: name.clone(),
+                    workload: workload.to_string(),
+                    operations: workload.operations(),
+                    elapsed,
+                });
+            }
+        }
+        Ok(results)
+    }
+}
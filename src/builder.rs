@@ -0,0 +1,591 @@
+//! Builders for images with precise control over their manifests.
+
+use crate::client::{RegistryClient, OCI_CONFIG_MEDIA_TYPE, OCI_MANIFEST_MEDIA_TYPE};
+use crate::digest::sha256_digest;
+use crate::error::Result;
+use crate::foreign::DOCKER_FOREIGN_LAYER_MEDIA_TYPE;
+use crate::oci::manifest::{Descriptor, ImageIndex, ImageManifest, Platform, OCI_INDEX_MEDIA_TYPE};
+use std::collections::BTreeMap;
+
+/// Media type of gzip-compressed OCI layers (eStargz layers use it too).
+pub const OCI_LAYER_GZIP_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar+gzip";
+/// Media type of zstd-compressed OCI layers, including zstd:chunked.
+pub const OCI_LAYER_ZSTD_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar+zstd";
+
+/// Media type of the empty `{}` config that OCI 1.1 artifacts carry.
+pub const OCI_EMPTY_MEDIA_TYPE: &str = "application/vnd.oci.empty.v1+json";
+
+/// Annotation holding the digest of an eStargz layer's table of contents.
+pub const ESTARGZ_TOC_DIGEST_ANNOTATION: &str = "containerd.io/snapshot/stargz/toc.digest";
+/// Annotation holding the uncompressed size of an eStargz layer.
+pub const ESTARGZ_UNCOMPRESSED_SIZE_ANNOTATION: &str = "io.containers.estargz.uncompressed-size";
+/// Annotation holding the checksum of a zstd:chunked layer's manifest.
+pub const ZSTD_CHUNKED_MANIFEST_CHECKSUM_ANNOTATION: &str =
+    "io.github.containers.zstd-chunked.manifest-checksum";
+/// Annotation locating a zstd:chunked layer's manifest inside the blob.
+pub const ZSTD_CHUNKED_MANIFEST_POSITION_ANNOTATION: &str =
+    "io.github.containers.zstd-chunked.manifest-position";
+
+/// A layer blob with the media type and annotations its descriptor carries.
+///
+/// The content is used as given: the constructors only label it, so tests
+/// can push real compressed layers or opaque bytes alike.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layer {
+    /// Layer content.
+    pub data: Vec<u8>,
+    /// Media type of the descriptor.
+    pub media_type: String,
+    /// Annotations of the descriptor.
+    pub annotations: BTreeMap<String, String>,
+    /// URLs the layer can be downloaded from instead of the registry.
+    pub urls: Vec<String>,
+}
+
+impl Layer {
+    /// A gzip-compressed tar layer.
+    pub fn new(data: impl Into<Vec<u8>>) -> Self {
+        Self::with_media_type(data, OCI_LAYER_GZIP_MEDIA_TYPE)
+    }
+
+    /// A layer with an arbitrary media type.
+    pub fn with_media_type(data: impl Into<Vec<u8>>, media_type: impl Into<String>) -> Self {
+        Self {
+            data: data.into(),
+            media_type: media_type.into(),
+            annotations: BTreeMap::new(),
+            urls: Vec::new(),
+        }
+    }
+
+    /// A zstd-compressed tar layer.
+    pub fn zstd(data: impl Into<Vec<u8>>) -> Self {
+        Self::with_media_type(data, OCI_LAYER_ZSTD_MEDIA_TYPE)
+    }
+
+    /// An eStargz layer: gzip media type plus the annotations stargz
+    /// snapshotters look for to lazily pull it.
+    pub fn estargz(
+        data: impl Into<Vec<u8>>,
+        toc_digest: impl Into<String>,
+        uncompressed_size: u64,
+    ) -> Self {
+        Self::new(data)
+            .with_annotation(ESTARGZ_TOC_DIGEST_ANNOTATION, toc_digest)
+            .with_annotation(
+                ESTARGZ_UNCOMPRESSED_SIZE_ANNOTATION,
+                uncompressed_size.to_string(),
+            )
+    }
+
+    /// A zstd:chunked layer. `manifest_position` is the
+    /// `offset:length:uncompressedLength:type` locator of the chunk
+    /// manifest inside the blob.
+    pub fn zstd_chunked(
+        data: impl Into<Vec<u8>>,
+        manifest_checksum: impl Into<String>,
+        manifest_position: impl Into<String>,
+    ) -> Self {
+        Self::zstd(data)
+            .with_annotation(ZSTD_CHUNKED_MANIFEST_CHECKSUM_ANNOTATION, manifest_checksum)
+            .with_annotation(ZSTD_CHUNKED_MANIFEST_POSITION_ANNOTATION, manifest_position)
+    }
+
+    /// A Docker foreign layer, downloaded from `urls` rather than the
+    /// registry, like the base layers of Windows images.
+    pub fn foreign<S: Into<String>>(
+        data: impl Into<Vec<u8>>,
+        urls: impl IntoIterator<Item = S>,
+    ) -> Self {
+        let mut layer = Self::with_media_type(data, DOCKER_FOREIGN_LAYER_MEDIA_TYPE);
+        layer.urls = urls.into_iter().map(Into::into).collect();
+        layer
+    }
+
+    /// Adds an annotation to the layer's descriptor.
+    pub fn with_annotation(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.annotations.insert(key.into(), value.into());
+        self
+    }
+
+    /// Returns the descriptor manifests list the layer with.
+    pub fn descriptor(&self) -> Descriptor {
+        Descriptor {
+            media_type: self.media_type.clone(),
+            digest: sha256_digest(&self.data),
+            size: self.data.len() as u64,
+            urls: self.urls.clone(),
+            annotations: self.annotations.clone(),
+            ..Descriptor::default()
+        }
+    }
+}
+
+/// An image ready to push: its manifest, config and layer blobs.
+#[derive(Debug, Clone)]
+pub struct BuiltImage {
+    /// Digest of the manifest.
+    pub digest: String,
+    /// Serialized manifest.
+    pub manifest: Vec<u8>,
+    /// Media type of the manifest.
+    pub media_type: String,
+    /// Serialized config blob.
+    pub config: Vec<u8>,
+    /// Layers, bottom first.
+    pub layers: Vec<Layer>,
+    /// Platform from the config, as an index describes it.
+    pub platform: Platform,
+}
+
+impl BuiltImage {
+    /// Returns the descriptor an index or a referrer uses for this image.
+    pub fn descriptor(&self) -> Descriptor {
+        Descriptor {
+            media_type: self.media_type.clone(),
+            digest: self.digest.clone(),
+            size: self.manifest.len() as u64,
+            ..Descriptor::default()
+        }
+    }
+
+    /// Pushes the config, the layers and the manifest (as `reference`) to
+    /// `repository`, returning the manifest digest.
+    pub async fn push(
+        &self,
+        client: &RegistryClient,
+        repository: &str,
+        reference: &str,
+    ) -> Result<String> {
+        client.push_blob(repository, self.config.clone()).await?;
+        for layer in &self.layers {
+            client.push_blob(repository, layer.data.clone()).await?;
+        }
+        client
+            .push_manifest(
+                repository,
+                reference,
+                &self.media_type,
+                self.manifest.clone(),
+            )
+            .await
+    }
+
+    /// Pushes the config, the layers and the manifest of an artifact with
+    /// a `subject` to `repository` through
+    /// [`RegistryClient::push_referrer`], returning the manifest digest.
+    pub async fn attach(&self, client: &RegistryClient, repository: &str) -> Result<String> {
+        client.push_blob(repository, self.config.clone()).await?;
+        for layer in &self.layers {
+            client.push_blob(repository, layer.data.clone()).await?;
+        }
+        client
+            .push_referrer(repository, &self.media_type, self.manifest.clone())
+            .await
+    }
+}
+
+/// Builds OCI images layer by layer.
+///
+/// # Examples
+///
+/// ```no_run
+/// use registry_testkit::builder::{ImageBuilder, Layer};
+/// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+/// # let client = RegistryClient::new(server.url());
+/// let image = ImageBuilder::new()
+///     .platform("linux", "arm64")
+///     .env("MODE=prod")
+///     .layer(Layer::estargz(b"...".to_vec(), "sha256:...", 4096))
+///     .layer(Layer::zstd(b"...".to_vec()))
+///     .build();
+/// image.push(&client, "app", "v1").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ImageBuilder {
+    os: String,
+    architecture: String,
+    variant: Option<String>,
+    os_version: Option<String>,
+    env: Vec<String>,
+    entrypoint: Option<Vec<String>>,
+    cmd: Option<Vec<String>>,
+    labels: BTreeMap<String, String>,
+    annotations: BTreeMap<String, String>,
+    layers: Vec<Layer>,
+}
+
+impl ImageBuilder {
+    /// Starts a `linux/amd64` image with no layers.
+    pub fn new() -> Self {
+        Self {
+            os: "linux".to_string(),
+            architecture: "amd64".to_string(),
+            variant: None,
+            os_version: None,
+            env: Vec::new(),
+            entrypoint: None,
+            cmd: None,
+            labels: BTreeMap::new(),
+            annotations: BTreeMap::new(),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Sets the operating system and CPU architecture.
+    pub fn platform(mut self, os: impl Into<String>, architecture: impl Into<String>) -> Self {
+        self.os = os.into();
+        self.architecture = architecture.into();
+        self
+    }
+
+    /// Sets the CPU variant, such as `v8`.
+    pub fn variant(mut self, variant: impl Into<String>) -> Self {
+        self.variant = Some(variant.into());
+        self
+    }
+
+    /// Sets the operating system version, such as `10.0.20348.2113` for
+    /// Windows images.
+    pub fn os_version(mut self, version: impl Into<String>) -> Self {
+        self.os_version = Some(version.into());
+        self
+    }
+
+    /// Adds an environment variable, as `KEY=value`.
+    pub fn env(mut self, var: impl Into<String>) -> Self {
+        self.env.push(var.into());
+        self
+    }
+
+    /// Sets the entrypoint.
+    pub fn entrypoint<S: Into<String>>(mut self, args: impl IntoIterator<Item = S>) -> Self {
+        self.entrypoint = Some(args.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets the default command.
+    pub fn cmd<S: Into<String>>(mut self, args: impl IntoIterator<Item = S>) -> Self {
+        self.cmd = Some(args.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Adds a config label.
+    pub fn label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// Adds a manifest annotation.
+    pub fn annotation(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.annotations.insert(key.into(), value.into());
+        self
+    }
+
+    /// Adds a layer on top of the existing ones.
+    pub fn layer(mut self, layer: Layer) -> Self {
+        self.layers.push(layer);
+        self
+    }
+
+    /// Serializes the config and manifest.
+    pub fn build(self) -> BuiltImage {
+        let mut platform = serde_json::json!({
+            "architecture": self.architecture,
+            "os": self.os,
+        });
+        if let Some(variant) = &self.variant {
+            platform["variant"] = serde_json::json!(variant);
+        }
+        if let Some(version) = &self.os_version {
+            platform["os.version"] = serde_json::json!(version);
+        }
+        let mut config = platform;
+        config["config"] = serde_json::json!({
+            "Env": self.env,
+            "Entrypoint": self.entrypoint,
+            "Cmd": self.cmd,
+            "Labels": self.labels,
+        });
+        // Layer digests double as diff IDs; the testkit never unpacks them.
+        config["rootfs"] = serde_json::json!({
+            "type": "layers",
+            "diff_ids": self.layers.iter().map(|l| sha256_digest(&l.data)).collect::<Vec<_>>(),
+        });
+        let config = serde_json::to_vec(&config).unwrap_or_default();
+
+        let mut manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": OCI_MANIFEST_MEDIA_TYPE,
+            "config": {
+                "mediaType": OCI_CONFIG_MEDIA_TYPE,
+                "size": config.len(),
+                "digest": sha256_digest(&config),
+            },
+            "layers": self.layers.iter().map(Layer::descriptor).collect::<Vec<_>>(),
+        });
+        if !self.annotations.is_empty() {
+            manifest["annotations"] = serde_json::json!(self.annotations);
+        }
+        let manifest = serde_json::to_vec(&manifest).unwrap_or_default();
+
+        BuiltImage {
+            digest: sha256_digest(&manifest),
+            manifest,
+            media_type: OCI_MANIFEST_MEDIA_TYPE.to_string(),
+            config,
+            layers: self.layers,
+            platform: Platform {
+                architecture: self.architecture,
+                os: self.os,
+                os_version: self.os_version,
+                os_features: Vec::new(),
+                variant: self.variant,
+            },
+        }
+    }
+}
+
+impl Default for ImageBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A multi-platform index ready to push, with the images it lists.
+#[derive(Debug, Clone)]
+pub struct BuiltIndex {
+    /// Digest of the index.
+    pub digest: String,
+    /// Serialized index.
+    pub manifest: Vec<u8>,
+    /// Media type of the index.
+    pub media_type: String,
+    /// The per-platform images, in index order.
+    pub images: Vec<BuiltImage>,
+}
+
+impl BuiltIndex {
+    /// Returns the descriptor a referrer uses for this index.
+    pub fn descriptor(&self) -> Descriptor {
+        Descriptor {
+            media_type: self.media_type.clone(),
+            digest: self.digest.clone(),
+            size: self.manifest.len() as u64,
+            ..Descriptor::default()
+        }
+    }
+
+    /// Pushes every image by digest, then the index as `reference`,
+    /// returning the index digest.
+    pub async fn push(
+        &self,
+        client: &RegistryClient,
+        repository: &str,
+        reference: &str,
+    ) -> Result<String> {
+        for image in &self.images {
+            image.push(client, repository, &image.digest).await?;
+        }
+        client
+            .push_manifest(
+                repository,
+                reference,
+                &self.media_type,
+                self.manifest.clone(),
+            )
+            .await
+    }
+
+    /// Returns the image built for `os`/`architecture`, if any.
+    pub fn image(&self, os: &str, architecture: &str) -> Option<&BuiltImage> {
+        self.images
+            .iter()
+            .find(|image| image.platform.os == os && image.platform.architecture == architecture)
+    }
+}
+
+/// Assembles per-platform images into an OCI image index.
+///
+/// # Examples
+///
+/// ```no_run
+/// use registry_testkit::builder::{ImageBuilder, IndexBuilder, Layer};
+/// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+/// # let client = RegistryClient::new(server.url());
+/// let index = IndexBuilder::new()
+///     .image(ImageBuilder::new().layer(Layer::new(b"amd64".to_vec())))
+///     .image(
+///         ImageBuilder::new()
+///             .platform("linux", "arm64")
+///             .variant("v8")
+///             .layer(Layer::new(b"arm64".to_vec())),
+///     )
+///     .image(
+///         ImageBuilder::new()
+///             .platform("windows", "amd64")
+///             .os_version("10.0.20348.2113")
+///             .layer(Layer::foreign(b"base".to_vec(), ["https://mcr.test/base"])),
+///     )
+///     .build();
+/// index.push(&client, "app", "v1").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct IndexBuilder {
+    images: Vec<BuiltImage>,
+    annotations: BTreeMap<String, String>,
+}
+
+impl IndexBuilder {
+    /// Starts an empty index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds `image` and adds it to the index.
+    pub fn image(self, image: ImageBuilder) -> Self {
+        self.built_image(image.build())
+    }
+
+    /// Adds an already built image to the index.
+    pub fn built_image(mut self, image: BuiltImage) -> Self {
+        self.images.push(image);
+        self
+    }
+
+    /// Adds an index annotation.
+    pub fn annotation(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.annotations.insert(key.into(), value.into());
+        self
+    }
+
+    /// Serializes the index.
+    pub fn build(self) -> BuiltIndex {
+        let index = ImageIndex {
+            schema_version: 2,
+            media_type: Some(OCI_INDEX_MEDIA_TYPE.to_string()),
+            manifests: self
+                .images
+                .iter()
+                .map(|image| Descriptor {
+                    platform: Some(image.platform.clone()),
+                    ..image.descriptor()
+                })
+                .collect(),
+            annotations: self.annotations,
+            ..ImageIndex::default()
+        };
+        let manifest = serde_json::to_vec(&index).unwrap_or_default();
+
+        BuiltIndex {
+            digest: sha256_digest(&manifest),
+            manifest,
+            media_type: OCI_INDEX_MEDIA_TYPE.to_string(),
+            images: self.images,
+        }
+    }
+}
+
+/// Builds OCI 1.1 artifacts: arbitrary blobs under an `artifactType`,
+/// optionally attached to another manifest through `subject`.
+///
+/// Artifacts use the empty `{}` config, so pushing one is all it takes to
+/// add a node to a referrer graph.
+///
+/// # Examples
+///
+/// ```no_run
+/// use registry_testkit::builder::{ArtifactBuilder, ImageBuilder, Layer};
+/// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+/// # let client = RegistryClient::new(server.url());
+/// let image = ImageBuilder::new().layer(Layer::new(b"app".to_vec())).build();
+/// image.push(&client, "app", "v1").await?;
+///
+/// let sbom = ArtifactBuilder::new("application/spdx+json")
+///     .blob(Layer::with_media_type(b"{}".to_vec(), "application/spdx+json"))
+///     .annotation("org.opencontainers.image.created", "2024-01-01T00:00:00Z")
+///     .subject(image.descriptor())
+///     .build();
+/// sbom.push(&client, "app", &sbom.digest).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ArtifactBuilder {
+    artifact_type: String,
+    blobs: Vec<Layer>,
+    annotations: BTreeMap<String, String>,
+    subject: Option<Descriptor>,
+}
+
+impl ArtifactBuilder {
+    /// Starts an artifact of the given type with no blobs.
+    pub fn new(artifact_type: impl Into<String>) -> Self {
+        Self {
+            artifact_type: artifact_type.into(),
+            blobs: Vec::new(),
+            annotations: BTreeMap::new(),
+            subject: None,
+        }
+    }
+
+    /// Adds a blob; its media type is the layer's.
+    pub fn blob(mut self, blob: Layer) -> Self {
+        self.blobs.push(blob);
+        self
+    }
+
+    /// Adds a manifest annotation.
+    pub fn annotation(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.annotations.insert(key.into(), value.into());
+        self
+    }
+
+    /// Attaches the artifact to the manifest `subject` describes.
+    pub fn subject(mut self, subject: Descriptor) -> Self {
+        self.subject = Some(subject);
+        self
+    }
+
+    /// Serializes the manifest.
+    pub fn build(self) -> BuiltImage {
+        let config = b"{}".to_vec();
+        let manifest = ImageManifest {
+            schema_version: 2,
+            media_type: Some(OCI_MANIFEST_MEDIA_TYPE.to_string()),
+            artifact_type: Some(self.artifact_type),
+            config: Descriptor {
+                media_type: OCI_EMPTY_MEDIA_TYPE.to_string(),
+                digest: sha256_digest(&config),
+                size: config.len() as u64,
+                ..Descriptor::default()
+            },
+            layers: self.blobs.iter().map(Layer::descriptor).collect(),
+            subject: self.subject,
+            annotations: self.annotations,
+        };
+        let manifest = serde_json::to_vec(&manifest).unwrap_or_default();
+
+        BuiltImage {
+            digest: sha256_digest(&manifest),
+            manifest,
+            media_type: OCI_MANIFEST_MEDIA_TYPE.to_string(),
+            config,
+            layers: self.blobs,
+            platform: Platform::default(),
+        }
+    }
+}
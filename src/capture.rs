@@ -0,0 +1,373 @@
+//! Recording of HTTP exchanges for debugging and CI artifacts.
+
+use crate::history::rfc3339;
+use crate::server::split_repository_path;
+use crate::warnings::{matches_repository, WarningEndpoint};
+use axum::{
+    body::{to_bytes, Body, HttpBody},
+    extract::{Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use base64::Engine;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Value recorded in place of redacted headers.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Which exchanges are recorded and what is kept of them.
+///
+/// Credentials are redacted and large bodies dropped by default, so
+/// captures can be attached to CI runs as they are.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::capture::CaptureConfig;
+/// use registry_testkit::warnings::WarningEndpoint;
+///
+/// let capture = CaptureConfig::new()
+///     .for_repository("app*")
+///     .for_endpoint(WarningEndpoint::Manifests)
+///     .with_max_body_size(4096);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureConfig {
+    /// Repositories to record; a trailing `*` matches a prefix. Everything
+    /// is recorded if empty.
+    pub repositories: Vec<String>,
+    /// Endpoints to record; everything is recorded if empty.
+    pub endpoints: Vec<WarningEndpoint>,
+    /// Headers whose values are replaced by [`REDACTED`], lowercase.
+    pub redacted_headers: Vec<String>,
+    /// Bodies larger than this many bytes are recorded by size only.
+    pub max_body_size: usize,
+}
+
+impl CaptureConfig {
+    /// Records every exchange, redacting credentials and cookies and
+    /// keeping bodies up to 64 KiB.
+    pub fn new() -> Self {
+        Self {
+            repositories: Vec::new(),
+            endpoints: Vec::new(),
+            redacted_headers: [
+                "authorization",
+                "proxy-authorization",
+                "cookie",
+                "set-cookie",
+            ]
+            .map(String::from)
+            .to_vec(),
+            max_body_size: 64 * 1024,
+        }
+    }
+
+    /// Records requests for a repository, or a prefix ending in `*`.
+    /// Several calls record several repositories.
+    pub fn for_repository(mut self, repository: impl Into<String>) -> Self {
+        self.repositories.push(repository.into());
+        self
+    }
+
+    /// Records requests to an endpoint. Several calls record several
+    /// endpoints.
+    pub fn for_endpoint(mut self, endpoint: WarningEndpoint) -> Self {
+        self.endpoints.push(endpoint);
+        self
+    }
+
+    /// Redacts another header.
+    pub fn redact_header(mut self, name: impl Into<String>) -> Self {
+        self.redacted_headers.push(name.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Sets the largest body recorded in full.
+    pub fn with_max_body_size(mut self, bytes: usize) -> Self {
+        self.max_body_size = bytes;
+        self
+    }
+
+    fn records(&self, path: &str) -> bool {
+        if self.repositories.is_empty() && self.endpoints.is_empty() {
+            return true;
+        }
+        let Some((repository, rest)) = split_repository_path(path) else {
+            return false;
+        };
+        let repository_matches = self.repositories.is_empty()
+            || self
+                .repositories
+                .iter()
+                .any(|pattern| matches_repository(pattern, repository));
+        let endpoint = WarningEndpoint::of(rest);
+        let endpoint_matches =
+            self.endpoints.is_empty() || endpoint.is_some_and(|e| self.endpoints.contains(&e));
+        repository_matches && endpoint_matches
+    }
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A body as recorded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CapturedBody {
+    /// The complete body.
+    Full(Vec<u8>),
+    /// A body over the size threshold, or streamed with unknown length.
+    Omitted {
+        /// Size in bytes, when announced.
+        size: Option<u64>,
+    },
+}
+
+impl CapturedBody {
+    /// Size in bytes, when known.
+    pub fn size(&self) -> Option<u64> {
+        match self {
+            Self::Full(data) => Some(data.len() as u64),
+            Self::Omitted { size } => *size,
+        }
+    }
+}
+
+/// A recorded request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedRequest {
+    /// Request method.
+    pub method: String,
+    /// Path and query.
+    pub uri: String,
+    /// Headers in order, with redacted values replaced.
+    pub headers: Vec<(String, String)>,
+    /// Request body.
+    pub body: CapturedBody,
+}
+
+/// A recorded response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedResponse {
+    /// Status code.
+    pub status: u16,
+    /// Headers in order, with redacted values replaced.
+    pub headers: Vec<(String, String)>,
+    /// Response body.
+    pub body: CapturedBody,
+}
+
+/// One recorded request and its response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedExchange {
+    /// When the request arrived.
+    pub started: SystemTime,
+    /// Time until the response was complete.
+    pub duration: Duration,
+    /// The request.
+    pub request: CapturedRequest,
+    /// The response.
+    pub response: CapturedResponse,
+}
+
+impl CapturedExchange {
+    fn har_entry(&self, base_url: &str) -> serde_json::Value {
+        fn headers(headers: &[(String, String)]) -> serde_json::Value {
+            headers
+                .iter()
+                .map(|(name, value)| serde_json::json!({ "name": name, "value": value }))
+                .collect()
+        }
+        fn header<'a>(headers: &'a [(String, String)], name: &str) -> &'a str {
+            headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map_or("", |(_, v)| v.as_str())
+        }
+        fn body_size(body: &CapturedBody) -> i64 {
+            body.size().map_or(-1, |size| size as i64)
+        }
+
+        let mut request = serde_json::json!({
+            "method": self.request.method,
+            "url": format!("{}{}", base_url, self.request.uri),
+            "httpVersion": "HTTP/1.1",
+            "headers": headers(&self.request.headers),
+            "queryString": [],
+            "cookies": [],
+            "headersSize": -1,
+            "bodySize": body_size(&self.request.body),
+        });
+        if let CapturedBody::Full(data) = &self.request.body {
+            if !data.is_empty() {
+                let (text, _) = har_text(data);
+                request["postData"] = serde_json::json!({
+                    "mimeType": header(&self.request.headers, "content-type"),
+                    "text": text,
+                });
+            }
+        }
+
+        let mut content = serde_json::json!({
+            "size": self.response.body.size().unwrap_or(0),
+            "mimeType": header(&self.response.headers, "content-type"),
+        });
+        if let CapturedBody::Full(data) = &self.response.body {
+            let (text, encoding) = har_text(data);
+            content["text"] = serde_json::json!(text);
+            if let Some(encoding) = encoding {
+                content["encoding"] = serde_json::json!(encoding);
+            }
+        }
+
+        let millis = self.duration.as_secs_f64() * 1000.0;
+        serde_json::json!({
+            "startedDateTime": rfc3339(self.started),
+            "time": millis,
+            "request": request,
+            "response": {
+                "status": self.response.status,
+                "statusText": "",
+                "httpVersion": "HTTP/1.1",
+                "headers": headers(&self.response.headers),
+                "cookies": [],
+                "content": content,
+                "redirectURL": header(&self.response.headers, "location"),
+                "headersSize": -1,
+                "bodySize": body_size(&self.response.body),
+            },
+            "cache": {},
+            "timings": { "send": 0, "wait": millis, "receive": 0 },
+        })
+    }
+}
+
+/// HAR text for a body: UTF-8 as is, anything else base64-encoded.
+fn har_text(data: &[u8]) -> (String, Option<&'static str>) {
+    match std::str::from_utf8(data) {
+        Ok(text) => (text.to_string(), None),
+        Err(_) => (
+            base64::engine::general_purpose::STANDARD.encode(data),
+            Some("base64"),
+        ),
+    }
+}
+
+/// Renders exchanges as a HAR 1.2 log for `base_url`.
+pub(crate) fn har(exchanges: &[CapturedExchange], base_url: &str) -> serde_json::Value {
+    serde_json::json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": "registry-testkit", "version": env!("CARGO_PKG_VERSION") },
+            "entries": exchanges
+                .iter()
+                .map(|exchange| exchange.har_entry(base_url))
+                .collect::<Vec<_>>(),
+        }
+    })
+}
+
+/// Records exchanges matching a [`CaptureConfig`].
+pub(crate) struct Recorder {
+    config: CaptureConfig,
+    exchanges: Mutex<Vec<CapturedExchange>>,
+}
+
+impl Recorder {
+    pub(crate) fn new(config: CaptureConfig) -> Self {
+        Self {
+            config,
+            exchanges: Mutex::default(),
+        }
+    }
+
+    pub(crate) fn exchanges(&self) -> Vec<CapturedExchange> {
+        self.exchanges.lock().unwrap().clone()
+    }
+
+    pub(crate) fn clear(&self) {
+        self.exchanges.lock().unwrap().clear();
+    }
+
+    fn headers(&self, headers: &HeaderMap) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if self
+                    .config
+                    .redacted_headers
+                    .iter()
+                    .any(|h| h == name.as_str())
+                {
+                    REDACTED.to_string()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                (name.to_string(), value)
+            })
+            .collect()
+    }
+
+    /// Buffers `body` if its length is known and within the threshold.
+    async fn body(&self, headers: &HeaderMap, body: Body) -> (CapturedBody, Body) {
+        let length = body.size_hint().exact().or_else(|| {
+            headers
+                .get(header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+        });
+        match length {
+            Some(size) if size <= self.config.max_body_size as u64 => {
+                match to_bytes(body, self.config.max_body_size).await {
+                    Ok(bytes) => (CapturedBody::Full(bytes.to_vec()), Body::from(bytes)),
+                    Err(_) => (CapturedBody::Omitted { size: Some(size) }, Body::empty()),
+                }
+            }
+            size => (CapturedBody::Omitted { size }, body),
+        }
+    }
+}
+
+/// Records the exchange if the request matches the capture filters.
+pub(crate) async fn capture(
+    State(recorder): State<Arc<Recorder>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !recorder.config.records(request.uri().path()) {
+        return next.run(request).await;
+    }
+    let started = SystemTime::now();
+    let timer = Instant::now();
+
+    let (parts, body) = request.into_parts();
+    let (request_body, body) = recorder.body(&parts.headers, body).await;
+    let captured_request = CapturedRequest {
+        method: parts.method.to_string(),
+        uri: parts.uri.to_string(),
+        headers: recorder.headers(&parts.headers),
+        body: request_body,
+    };
+
+    let response = next.run(Request::from_parts(parts, body)).await;
+    let (parts, body) = response.into_parts();
+    let (response_body, body) = recorder.body(&parts.headers, body).await;
+    let captured_response = CapturedResponse {
+        status: parts.status.as_u16(),
+        headers: recorder.headers(&parts.headers),
+        body: response_body,
+    };
+
+    recorder.exchanges.lock().unwrap().push(CapturedExchange {
+        started,
+        duration: timer.elapsed(),
+        request: captured_request,
+        response: captured_response,
+    });
+    Response::from_parts(parts, body)
+}
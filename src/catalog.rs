@@ -0,0 +1,49 @@
+//! Repository catalog and its vendor query extensions.
+
+use serde::{Deserialize, Serialize};
+
+/// Body of `GET /v2/_catalog`.
+#[derive(Serialize)]
+pub(crate) struct Catalog {
+    pub repositories: Vec<String>,
+}
+
+/// Sort order of the catalog.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Non-standard catalog query parameters, as registries like Harbor and
+/// Artifactory offer them: `prefix`, `name` (substring) and `sort`.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct CatalogQuery {
+    prefix: Option<String>,
+    name: Option<String>,
+    #[serde(default)]
+    sort: SortOrder,
+}
+
+impl CatalogQuery {
+    /// Filters and orders sorted, deduplicated repository names.
+    pub(crate) fn apply(&self, mut repositories: Vec<String>) -> Vec<String> {
+        if let Some(prefix) = &self.prefix {
+            repositories.retain(|name| name.starts_with(prefix.as_str()));
+        }
+        if let Some(needle) = &self.name {
+            repositories.retain(|name| name.contains(needle.as_str()));
+        }
+        if self.sort == SortOrder::Desc {
+            repositories.reverse();
+        }
+        repositories
+    }
+
+    /// Returns whether the catalog is listed in reverse.
+    pub(crate) fn descending(&self) -> bool {
+        self.sort == SortOrder::Desc
+    }
+}
@@ -0,0 +1,704 @@
+//! Minimal registry client used by the load generator and test helpers.
+
+use crate::digest::{self, sha256_digest};
+use crate::error::{RegistryError, Result};
+use crate::oci::manifest::{subject_digest, ImageIndex, OCI_INDEX_MEDIA_TYPE};
+use crate::reference::Reference;
+use crate::referrers;
+use crate::storage::ManifestEntry;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Media type used for manifests pushed by [`RegistryClient::push_image`].
+pub const OCI_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+/// Media type used for image configs pushed by [`RegistryClient::push_image`].
+pub const OCI_CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.image.config.v1+json";
+/// Media type used for layers pushed by [`RegistryClient::push_image`].
+pub const OCI_LAYER_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar+gzip";
+
+/// An image pulled with [`RegistryClient::pull_image`].
+#[derive(Debug, Clone)]
+pub struct PulledImage {
+    /// Digest of the manifest.
+    pub digest: String,
+    /// The manifest as served by the registry.
+    pub manifest: ManifestEntry,
+    /// Raw config blob.
+    pub config: Vec<u8>,
+    /// Raw layer blobs in manifest order.
+    pub layers: Vec<Vec<u8>>,
+}
+
+/// How often [`RegistryClient::push_blob_from`] mounted blobs instead of
+/// uploading them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MountStats {
+    /// Mount requests sent.
+    pub attempted: u64,
+    /// Mounts the registry accepted with `201 Created`.
+    pub mounted: u64,
+    /// Mounts the registry answered with an upload session, after which
+    /// the blob was uploaded.
+    pub fell_back: u64,
+}
+
+#[derive(Debug, Default)]
+struct MountCounters {
+    attempted: AtomicU64,
+    mounted: AtomicU64,
+    fell_back: AtomicU64,
+}
+
+/// Direction of a blob transfer reported by a progress callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferDirection {
+    /// Blob uploaded by [`RegistryClient::push_image`].
+    Push,
+    /// Blob downloaded by [`RegistryClient::pull_image`].
+    Pull,
+}
+
+/// A finished blob transfer, passed to the callback given to
+/// [`RegistryClient::with_progress`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferProgress {
+    /// Whether the blob was pushed or pulled.
+    pub direction: TransferDirection,
+    /// Digest of the blob.
+    pub digest: String,
+    /// Size of the blob in bytes.
+    pub bytes: u64,
+    /// Blobs of the image transferred so far, including this one.
+    pub completed: usize,
+    /// Blobs of the image to transfer, config included.
+    pub total: usize,
+}
+
+type ProgressCallback = Arc<dyn Fn(&TransferProgress) + Send + Sync>;
+
+#[derive(Clone, Default)]
+struct ProgressHook(Option<ProgressCallback>);
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ProgressHook")
+            .field(&self.0.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+/// HTTP client speaking the registry API against any registry URL.
+///
+/// # Examples
+///
+/// ```no_run
+/// use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let server = RegistryServer::new(RegistryConfig::memory()).await?;
+/// let client = RegistryClient::new(server.url());
+/// let digest = client.push_image("app", "latest", &[b"layer".to_vec()]).await?;
+/// let image = client.pull_image("app", &digest).await?;
+/// assert_eq!(image.layers.len(), 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegistryClient {
+    http: reqwest::Client,
+    base_url: String,
+    credentials: Option<(String, String)>,
+    authorization: Arc<Mutex<Authorization>>,
+    mounts: Arc<MountCounters>,
+    concurrency: usize,
+    progress: ProgressHook,
+    verify_digests: bool,
+}
+
+/// Authorization the client attaches to requests after a challenge.
+#[derive(Debug, Default)]
+enum Authorization {
+    #[default]
+    None,
+    Basic,
+    Bearer(String),
+}
+
+/// Parses the `key="value"` parameters of a `WWW-Authenticate` challenge.
+fn challenge_params(challenge: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    let mut rest = challenge
+        .split_once(' ')
+        .map(|(_, p)| p)
+        .unwrap_or_default();
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_string();
+        let (value, remaining) = match after.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => after.split_once(',').unwrap_or((after, "")),
+        };
+        params.insert(key, value.to_string());
+        rest = remaining;
+    }
+    params
+}
+
+#[derive(Deserialize)]
+struct TokenBody {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+impl RegistryClient {
+    /// Creates a client for the registry at `base_url` (e.g. `http://127.0.0.1:5000`).
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            credentials: None,
+            authorization: Arc::default(),
+            mounts: Arc::default(),
+            concurrency: 1,
+            progress: ProgressHook::default(),
+            verify_digests: true,
+        }
+    }
+
+    /// Authenticates with the given credentials when the registry asks to,
+    /// using basic auth or exchanging them for a bearer token.
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Transfers up to `concurrency` blobs of an image at once in
+    /// [`push_image`](Self::push_image) and [`pull_image`](Self::pull_image).
+    /// Defaults to one, transferring blobs in sequence.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Calls `callback` whenever [`push_image`](Self::push_image) or
+    /// [`pull_image`](Self::pull_image) finishes transferring a blob.
+    ///
+    /// With concurrency above one, the callback runs from several
+    /// transfers in turn, in completion order.
+    pub fn with_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&TransferProgress) + Send + Sync + 'static,
+    {
+        self.progress = ProgressHook(Some(Arc::new(callback)));
+        self
+    }
+
+    /// Sets whether pulled blobs are checked against their digest, which
+    /// they are by default.
+    ///
+    /// With verification, a blob whose content does not hash to the
+    /// requested digest fails with [`RegistryError::DigestInvalid`],
+    /// surfacing corruption on the registry side. Without it, the client
+    /// behaves like a naive one and hands the content over as served.
+    pub fn with_digest_verification(mut self, verify: bool) -> Self {
+        self.verify_digests = verify;
+        self
+    }
+
+    fn report(
+        &self,
+        direction: TransferDirection,
+        digest: &str,
+        bytes: usize,
+        total: usize,
+        completed: &AtomicUsize,
+    ) {
+        let Some(callback) = &self.progress.0 else {
+            return;
+        };
+        callback(&TransferProgress {
+            direction,
+            digest: digest.to_string(),
+            bytes: bytes as u64,
+            completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+            total,
+        });
+    }
+
+    /// Returns the base URL this client talks to.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn resolve(&self, location: &str) -> String {
+        if location.starts_with('/') {
+            format!("{}{}", self.base_url, location)
+        } else {
+            location.to_string()
+        }
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &*self.authorization.lock().unwrap() {
+            Authorization::None => request,
+            Authorization::Basic => match &self.credentials {
+                Some((user, password)) => request.basic_auth(user, Some(password)),
+                None => request,
+            },
+            Authorization::Bearer(token) => request.bearer_auth(token),
+        }
+    }
+
+    /// Sends a request, answering one authentication challenge if needed.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let retry = request.try_clone();
+        let response = self.authorize(request).send().await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        let Some(retry) = retry else {
+            return Ok(response);
+        };
+        let challenge = response
+            .headers()
+            .get("WWW-Authenticate")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        if !self.answer_challenge(&challenge).await? {
+            return Ok(response);
+        }
+
+        Ok(self.authorize(retry).send().await?)
+    }
+
+    async fn answer_challenge(&self, challenge: &str) -> Result<bool> {
+        if challenge.starts_with("Basic") {
+            if self.credentials.is_none() {
+                return Ok(false);
+            }
+            *self.authorization.lock().unwrap() = Authorization::Basic;
+            return Ok(true);
+        }
+
+        if !challenge.starts_with("Bearer") {
+            return Ok(false);
+        }
+        let params = challenge_params(challenge);
+        let Some(realm) = params.get("realm") else {
+            return Ok(false);
+        };
+
+        let mut query = Vec::new();
+        for key in ["service", "scope"] {
+            if let Some(value) = params.get(key) {
+                query.push((key, value.as_str()));
+            }
+        }
+        let mut request = self.http.get(realm).query(&query);
+        if let Some((user, password)) = &self.credentials {
+            request = request.basic_auth(user, Some(password));
+        }
+
+        let response = request.send().await?;
+        Self::check(&response, &[StatusCode::OK])?;
+        let body: TokenBody = response.json().await?;
+        let token = body.token.or(body.access_token).unwrap_or_default();
+        *self.authorization.lock().unwrap() = Authorization::Bearer(token);
+        Ok(true)
+    }
+
+    fn check(response: &reqwest::Response, expected: &[StatusCode]) -> Result<()> {
+        if expected.contains(&response.status()) {
+            Ok(())
+        } else {
+            Err(RegistryError::UnexpectedStatus {
+                status: response.status().as_u16(),
+                url: response.url().to_string(),
+            })
+        }
+    }
+
+    /// Uploads a blob monolithically and returns its digest.
+    pub async fn push_blob(&self, repo: &str, data: Vec<u8>) -> Result<String> {
+        let digest = sha256_digest(&data);
+
+        let request = self
+            .http
+            .post(format!("{}/v2/{}/blobs/uploads/", self.base_url, repo));
+        let response = self.send(request).await?;
+        Self::check(&response, &[StatusCode::ACCEPTED])?;
+        self.upload_to_session(&response, &digest, data).await?;
+        Ok(digest)
+    }
+
+    /// Completes the upload session opened by `response` with `data` in a
+    /// single request.
+    async fn upload_to_session(
+        &self,
+        response: &reqwest::Response,
+        digest: &str,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        let location = response
+            .headers()
+            .get("Location")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let separator = if location.contains('?') { '&' } else { '?' };
+        let url = format!("{}{}digest={}", self.resolve(&location), separator, digest);
+
+        let response = self.send(self.http.put(url).body(data)).await?;
+        Self::check(&response, &[StatusCode::CREATED])?;
+        Ok(())
+    }
+
+    /// Asks the registry to mount the blob `digest` from repository `from`
+    /// into `repo`, and returns whether it did.
+    ///
+    /// A registry that declines opens an upload session instead; it is
+    /// left unused, so prefer [`push_blob_from`](Self::push_blob_from) when
+    /// the blob content is at hand.
+    pub async fn mount_blob(&self, repo: &str, digest: &str, from: &str) -> Result<bool> {
+        let response = self.request_mount(repo, digest, from).await?;
+        Ok(response.status() == StatusCode::CREATED)
+    }
+
+    /// Uploads a blob known to exist in repository `from`, mounting it when
+    /// the registry allows and uploading `data` otherwise. Returns the
+    /// digest.
+    ///
+    /// Outcomes are counted in [`mount_stats`](Self::mount_stats).
+    pub async fn push_blob_from(&self, repo: &str, from: &str, data: Vec<u8>) -> Result<String> {
+        let digest = sha256_digest(&data);
+        let response = self.request_mount(repo, &digest, from).await?;
+        if response.status() == StatusCode::ACCEPTED {
+            self.mounts.fell_back.fetch_add(1, Ordering::Relaxed);
+            self.upload_to_session(&response, &digest, data).await?;
+        }
+        Ok(digest)
+    }
+
+    async fn request_mount(
+        &self,
+        repo: &str,
+        digest: &str,
+        from: &str,
+    ) -> Result<reqwest::Response> {
+        let request = self
+            .http
+            .post(format!("{}/v2/{}/blobs/uploads/", self.base_url, repo))
+            .query(&[("mount", digest), ("from", from)]);
+        self.mounts.attempted.fetch_add(1, Ordering::Relaxed);
+        let response = self.send(request).await?;
+        Self::check(&response, &[StatusCode::CREATED, StatusCode::ACCEPTED])?;
+        if response.status() == StatusCode::CREATED {
+            self.mounts.mounted.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(response)
+    }
+
+    /// Returns how often mounts were attempted, accepted and fell back to
+    /// uploads, across this client and its clones.
+    pub fn mount_stats(&self) -> MountStats {
+        MountStats {
+            attempted: self.mounts.attempted.load(Ordering::Relaxed),
+            mounted: self.mounts.mounted.load(Ordering::Relaxed),
+            fell_back: self.mounts.fell_back.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns whether the registry has a blob with the given digest.
+    pub async fn blob_exists(&self, repo: &str, digest: &str) -> Result<bool> {
+        let request = self
+            .http
+            .head(format!("{}/v2/{}/blobs/{}", self.base_url, repo, digest));
+        let response = self.send(request).await?;
+        Self::check(&response, &[StatusCode::OK, StatusCode::NOT_FOUND])?;
+        Ok(response.status() == StatusCode::OK)
+    }
+
+    /// Returns whether the registry has each blob, in the order of
+    /// `digests`, with a single request.
+    ///
+    /// Uses the registry's `blobs/_exists` extension, which needs push
+    /// access to the repository.
+    pub async fn blobs_exist(&self, repo: &str, digests: &[String]) -> Result<Vec<bool>> {
+        #[derive(Deserialize)]
+        struct Body {
+            blobs: Vec<Blob>,
+        }
+        #[derive(Deserialize)]
+        struct Blob {
+            exists: bool,
+        }
+
+        let request = self
+            .http
+            .post(format!("{}/v2/{}/blobs/_exists", self.base_url, repo))
+            .json(&serde_json::json!({ "digests": digests }));
+        let response = self.send(request).await?;
+        Self::check(&response, &[StatusCode::OK])?;
+        let body: Body = response.json().await?;
+        Ok(body.blobs.into_iter().map(|blob| blob.exists).collect())
+    }
+
+    /// Downloads a blob, checking its content against `digest` unless
+    /// [verification](Self::with_digest_verification) is off.
+    pub async fn pull_blob(&self, repo: &str, digest: &str) -> Result<Vec<u8>> {
+        let request = self
+            .http
+            .get(format!("{}/v2/{}/blobs/{}", self.base_url, repo, digest));
+        let response = self.send(request).await?;
+        Self::check(&response, &[StatusCode::OK])?;
+        let blob = response.bytes().await?.to_vec();
+        if self.verify_digests {
+            digest::verify(digest, &blob).map_err(RegistryError::DigestInvalid)?;
+        }
+        Ok(blob)
+    }
+
+    /// Uploads a manifest under `reference` and returns its digest.
+    pub async fn push_manifest(
+        &self,
+        repo: &str,
+        reference: &str,
+        content_type: &str,
+        data: Vec<u8>,
+    ) -> Result<String> {
+        let digest = sha256_digest(&data);
+        let request = self
+            .http
+            .put(format!(
+                "{}/v2/{}/manifests/{}",
+                self.base_url, repo, reference
+            ))
+            .header("Content-Type", content_type)
+            .body(data);
+        let response = self.send(request).await?;
+        Self::check(&response, &[StatusCode::CREATED])?;
+        Ok(digest)
+    }
+
+    /// Pushes a manifest with a `subject` by digest and returns its digest.
+    ///
+    /// Registries implementing the referrers API acknowledge the subject
+    /// with an `OCI-Subject` header. Without it, the client adds the
+    /// manifest to the index under the subject's
+    /// [fallback tag](crate::referrers::fallback_tag), as OCI clients do.
+    pub async fn push_referrer(
+        &self,
+        repo: &str,
+        content_type: &str,
+        data: Vec<u8>,
+    ) -> Result<String> {
+        let subject = subject_digest(&data)
+            .ok_or_else(|| RegistryError::InvalidManifest("manifest has no subject".into()))?;
+        let descriptor = referrers::referrer_descriptor(content_type, &data)?;
+        let digest = descriptor.digest.clone();
+        let request = self
+            .http
+            .put(format!(
+                "{}/v2/{}/manifests/{}",
+                self.base_url, repo, digest
+            ))
+            .header("Content-Type", content_type)
+            .body(data);
+        let response = self.send(request).await?;
+        Self::check(&response, &[StatusCode::CREATED])?;
+        if response.headers().contains_key(referrers::SUBJECT_HEADER) {
+            return Ok(digest);
+        }
+
+        let tag = referrers::fallback_tag(&subject);
+        let mut index = match self.pull_manifest(repo, &tag).await {
+            Ok(entry) => serde_json::from_slice(&entry.data)?,
+            Err(RegistryError::UnexpectedStatus { status: 404, .. }) => referrers::empty_index(),
+            Err(e) => return Err(e),
+        };
+        index.manifests.retain(|d| d.digest != digest);
+        index.manifests.push(descriptor);
+        self.push_manifest(
+            repo,
+            &tag,
+            OCI_INDEX_MEDIA_TYPE,
+            serde_json::to_vec(&index)?,
+        )
+        .await?;
+        Ok(digest)
+    }
+
+    /// Lists the referrers of the manifest `digest`, from the referrers API
+    /// or, where the registry answers it with `404`, from the index under
+    /// the [fallback tag](crate::referrers::fallback_tag).
+    pub async fn referrers(&self, repo: &str, digest: &str) -> Result<ImageIndex> {
+        let request = self.http.get(format!(
+            "{}/v2/{}/referrers/{}",
+            self.base_url, repo, digest
+        ));
+        let response = self.send(request).await?;
+        if response.status() != StatusCode::NOT_FOUND {
+            Self::check(&response, &[StatusCode::OK])?;
+            return Ok(response.json().await?);
+        }
+        match self
+            .pull_manifest(repo, &referrers::fallback_tag(digest))
+            .await
+        {
+            Ok(entry) => Ok(serde_json::from_slice(&entry.data)?),
+            Err(RegistryError::UnexpectedStatus { status: 404, .. }) => {
+                Ok(referrers::empty_index())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Downloads a manifest by tag or digest.
+    pub async fn pull_manifest(&self, repo: &str, reference: &str) -> Result<ManifestEntry> {
+        let request = self.http.get(format!(
+            "{}/v2/{}/manifests/{}",
+            self.base_url, repo, reference
+        ));
+        let response = self.send(request).await?;
+        Self::check(&response, &[StatusCode::OK])?;
+
+        let content_type = response
+            .headers()
+            .get("Content-Type")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or(OCI_MANIFEST_MEDIA_TYPE)
+            .to_string();
+        let data = response.bytes().await?.to_vec();
+
+        Ok(ManifestEntry { data, content_type })
+    }
+
+    /// Pushes an image made of the given layers, tagged as `tag`, and
+    /// returns the manifest digest.
+    ///
+    /// The config and layers are uploaded up to
+    /// [`with_concurrency`](Self::with_concurrency) at a time.
+    pub async fn push_image(&self, repo: &str, tag: &str, layers: &[Vec<u8>]) -> Result<String> {
+        let config = serde_json::to_vec(&serde_json::json!({
+            "architecture": "amd64",
+            "os": "linux",
+            "rootfs": { "type": "layers", "diff_ids": [] },
+        }))?;
+        let config_size = config.len();
+
+        let total = layers.len() + 1;
+        let completed = &AtomicUsize::new(0);
+        let digests: Vec<String> =
+            stream::iter(std::iter::once(config).chain(layers.iter().cloned()))
+                .map(|blob| async move {
+                    let size = blob.len();
+                    let digest = self.push_blob(repo, blob).await?;
+                    self.report(TransferDirection::Push, &digest, size, total, completed);
+                    Ok::<_, RegistryError>(digest)
+                })
+                .buffered(self.concurrency)
+                .try_collect()
+                .await?;
+
+        let descriptors: Vec<serde_json::Value> = layers
+            .iter()
+            .zip(&digests[1..])
+            .map(|(layer, digest)| {
+                serde_json::json!({
+                    "mediaType": OCI_LAYER_MEDIA_TYPE,
+                    "size": layer.len(),
+                    "digest": digest,
+                })
+            })
+            .collect();
+
+        let manifest = serde_json::to_vec(&serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": OCI_MANIFEST_MEDIA_TYPE,
+            "config": {
+                "mediaType": OCI_CONFIG_MEDIA_TYPE,
+                "size": config_size,
+                "digest": digests[0],
+            },
+            "layers": descriptors,
+        }))?;
+
+        self.push_manifest(repo, tag, OCI_MANIFEST_MEDIA_TYPE, manifest)
+            .await
+    }
+
+    /// Pushes an image made of the given layers to the repository of
+    /// `reference`, under its tag or, if it has none, by digest. The
+    /// registry part of `reference` is ignored.
+    pub async fn push_reference(
+        &self,
+        reference: &Reference,
+        layers: &[Vec<u8>],
+    ) -> Result<String> {
+        self.push_image(reference.repository(), reference.reference(), layers)
+            .await
+    }
+
+    /// Pulls the image `reference` points at, by digest if it pins one.
+    /// The registry part of `reference` is ignored.
+    pub async fn pull_reference(&self, reference: &Reference) -> Result<PulledImage> {
+        self.pull_image(reference.repository(), reference.reference())
+            .await
+    }
+
+    /// Pulls an image manifest along with its config and layer blobs.
+    ///
+    /// Blobs are downloaded up to
+    /// [`with_concurrency`](Self::with_concurrency) at a time.
+    pub async fn pull_image(&self, repo: &str, reference: &str) -> Result<PulledImage> {
+        let manifest = self.pull_manifest(repo, reference).await?;
+        let digest = sha256_digest(&manifest.data);
+        let parsed: serde_json::Value = serde_json::from_slice(&manifest.data)?;
+
+        let digests: Vec<String> = std::iter::once(&parsed["config"])
+            .chain(parsed["layers"].as_array().into_iter().flatten())
+            .map(|descriptor| {
+                descriptor["digest"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string()
+            })
+            .collect();
+
+        let total = digests.len();
+        let completed = &AtomicUsize::new(0);
+        let mut blobs: Vec<Vec<u8>> = stream::iter(digests)
+            .map(|blob_digest| async move {
+                let blob = self.pull_blob(repo, &blob_digest).await?;
+                self.report(
+                    TransferDirection::Pull,
+                    &blob_digest,
+                    blob.len(),
+                    total,
+                    completed,
+                );
+                Ok::<_, RegistryError>(blob)
+            })
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+        let config = blobs.remove(0);
+
+        Ok(PulledImage {
+            digest,
+            manifest,
+            config,
+            layers: blobs,
+        })
+    }
+}
@@ -0,0 +1,97 @@
+//! Artificial clock skew between the registry and its clients.
+//!
+//! Clients compare the registry's `Date` headers and the `iat`/`exp`
+//! claims of its tokens with their own clock, and a few seconds of drift
+//! between hosts is enough to break naive ones. With a skew configured,
+//! the registry reports times as if its clock were off by that much, while
+//! staying consistent with itself: tokens it issues remain valid for their
+//! full lifetime on its own clock.
+
+use crate::history::civil_date;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Offset of the registry's clock from the real time.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::{ClockSkew, RegistryConfig};
+/// use std::time::Duration;
+///
+/// // A registry whose clock is five minutes fast.
+/// let config = RegistryConfig::memory().with_clock_skew(ClockSkew::Ahead(Duration::from_secs(300)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockSkew {
+    /// The registry's clock runs ahead of the real time.
+    Ahead(Duration),
+    /// The registry's clock runs behind the real time.
+    Behind(Duration),
+}
+
+impl ClockSkew {
+    /// Shifts `time` by the skew, saturating at the Unix epoch.
+    pub fn apply(self, time: SystemTime) -> SystemTime {
+        match self {
+            Self::Ahead(offset) => time + offset,
+            Self::Behind(offset) => time.checked_sub(offset).unwrap_or(UNIX_EPOCH),
+        }
+    }
+
+    /// Returns the current time on the registry's clock.
+    pub fn now(self) -> SystemTime {
+        self.apply(SystemTime::now())
+    }
+}
+
+/// Returns the current time on a clock with an optional skew, in seconds
+/// since the Unix epoch.
+pub(crate) fn unix_now(skew: Option<ClockSkew>) -> u64 {
+    skew.map_or_else(SystemTime::now, ClockSkew::now)
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Formats a time as an HTTP date, such as `Sun, 06 Nov 1994 08:49:37 GMT`.
+pub fn http_date(time: SystemTime) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_date(days);
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Sets the `Date` header of responses to the skewed time.
+pub(crate) async fn skew_date(
+    State(skew): State<ClockSkew>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    if let Ok(date) = HeaderValue::from_str(&http_date(skew.now())) {
+        response.headers_mut().insert(header::DATE, date);
+    }
+    response
+}
@@ -0,0 +1,89 @@
+//! Compressed manifest and token responses.
+//!
+//! Registries behind a CDN or reverse proxy often serve JSON with a
+//! `Content-Encoding`, and some clients forget to decode it. Enabling
+//! compression here makes the local registry behave the same way.
+
+use crate::gzip;
+use crate::server::split_repository_path;
+use axum::{
+    extract::Request,
+    http::{Extensions, HeaderMap, Method, StatusCode, Version},
+    middleware::Next,
+    response::Response,
+};
+use tower_http::compression::{predicate::Predicate, CompressionLayer};
+
+/// A `Content-Encoding` the registry can compress responses with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    /// `gzip`.
+    Gzip,
+    /// `zstd`.
+    Zstd,
+}
+
+impl ContentEncoding {
+    /// Token used in `Accept-Encoding` and `Content-Encoding`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// Encodes `data`.
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Gzip => gzip::compress(data),
+            // Encoding into a `Vec` cannot fail.
+            Self::Zstd => zstd::encode_all(data, 0).unwrap(),
+        }
+    }
+
+    /// Decodes a body compressed with this encoding.
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Gzip => gzip::decompress(data),
+            Self::Zstd => zstd::decode_all(data).map_err(|e| format!("invalid zstd frame: {}", e)),
+        }
+    }
+}
+
+/// Whether responses to this request are compressed: manifest `GET`s and
+/// token requests.
+fn compressible(request: &Request) -> bool {
+    if request.method() != Method::GET {
+        return false;
+    }
+    let path = request.uri().path();
+    path == "/token"
+        || split_repository_path(path).is_some_and(|(_, rest)| rest.starts_with("manifests/"))
+}
+
+/// Marks responses to compressible requests for [`compression`].
+#[derive(Debug, Clone, Copy)]
+struct Compressible;
+
+pub(crate) async fn mark_compressible(request: Request, next: Next) -> Response {
+    let compressible = compressible(&request);
+    let mut response = next.run(request).await;
+    if compressible {
+        response.extensions_mut().insert(Compressible);
+    }
+    response
+}
+
+/// Compresses successful responses marked by [`mark_compressible`] with
+/// the enabled `encodings`. Of those the client accepts, the one with the
+/// highest q-value wins, zstd on a tie.
+pub(crate) fn compression(encodings: &[ContentEncoding]) -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new()
+        .gzip(encodings.contains(&ContentEncoding::Gzip))
+        .zstd(encodings.contains(&ContentEncoding::Zstd))
+        .compress_when(
+            |status: StatusCode, _: Version, _: &HeaderMap, extensions: &Extensions| {
+                status.is_success() && extensions.get::<Compressible>().is_some()
+            },
+        )
+}
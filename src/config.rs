@@ -1,6 +1,40 @@
 //! Configuration types for the registry server.
 
+use crate::auth::AuthConfig;
+use crate::capture::CaptureConfig;
+use crate::clock::ClockSkew;
+use crate::compression::ContentEncoding;
+use crate::consistency::Visibility;
+use crate::digest::DigestPolicy;
+use crate::endpoints::ApiEndpoint;
+use crate::faults::FaultConfig;
+use crate::federation::{NamespaceRoute, NamespaceTarget};
+use crate::foreign::ForeignLayerPolicy;
+use crate::headers::HeaderQuirks;
+use crate::lifecycle::{LifecycleEvent, LifecycleHooks};
+use crate::location::LocationStyle;
+use crate::maintenance::MaintenanceConfig;
+use crate::oci::manifest::{ContentTypePolicy, ValidationLevel};
+use crate::oci::schema1::Schema1Mode;
+use crate::profile::RegistryProfile;
+use crate::proxy::ProxySimulation;
+use crate::quota::QuotaConfig;
+use crate::ratelimit::PullRateLimit;
+use crate::redirect::BlobRedirectConfig;
+use crate::referrers::ReferrerDeletePolicy;
+use crate::replication::ReplicationConfig;
+use crate::retention::RetentionPolicy;
+use crate::signing::SigningPolicy;
+use crate::socket::SocketOptions;
+use crate::storage::SharedTempDir;
+use crate::upstream::UpstreamConfig;
+use crate::warnings::RegistryWarning;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Largest request body the registry accepts.
+pub(crate) const MAX_BODY_SIZE: usize = 512 * 1024 * 1024;
 
 /// Storage backend for registry data.
 #[derive(Debug, Clone)]
@@ -11,6 +45,9 @@ pub enum StorageBackend {
     TempDir,
     /// Persistent directory storage at a specific path.
     Directory(PathBuf),
+    /// Temporary directory storage in a subdirectory of a directory shared
+    /// with other servers; see [`SharedTempDir`].
+    SharedTempDir(SharedTempDir),
 }
 
 /// Configuration for the registry server.
@@ -18,10 +55,99 @@ pub enum StorageBackend {
 pub struct RegistryConfig {
     /// Storage backend to use.
     pub storage: StorageBackend,
+    /// Registry snapshot loaded into storage at startup.
+    pub preload: Option<PathBuf>,
     /// Port to bind to (None for random port).
     pub port: Option<u16>,
+    /// Bind a random port when [`port`](Self::port) is taken.
+    pub port_fallback: bool,
     /// Host address to bind to.
     pub host: String,
+    /// When writes become visible to reads.
+    pub visibility: Visibility,
+    /// Faults injected into responses.
+    pub faults: FaultConfig,
+    /// Per-client request quota (unlimited if `None`).
+    pub quota: Option<QuotaConfig>,
+    /// Redirect blob downloads to a secondary blob server.
+    pub blob_redirect: Option<BlobRedirectConfig>,
+    /// Hosted registry whose behavior is emulated.
+    pub profile: RegistryProfile,
+    /// Authentication (open access if `None`).
+    pub auth: Option<AuthConfig>,
+    /// Reject pushes that would move an existing tag to a different manifest.
+    pub immutable_tags: bool,
+    /// Docker Hub style pull rate limit (unlimited if `None`).
+    pub pull_rate_limit: Option<PullRateLimit>,
+    /// Tag retention policies by repository name; `*` applies to every
+    /// repository without its own policy.
+    pub retention: HashMap<String, RetentionPolicy>,
+    /// Background maintenance schedule (none if `None`).
+    pub maintenance: Option<MaintenanceConfig>,
+    /// Keep deleted manifests as tombstones until garbage collection.
+    pub soft_delete: bool,
+    /// Signatures required of manifests pushed by tag (none if `None`).
+    pub signing_policy: Option<SigningPolicy>,
+    /// What happens to the referrers of a deleted manifest.
+    pub referrer_deletes: ReferrerDeletePolicy,
+    /// Apply `artifactType` filters to referrers responses and say so in
+    /// `OCI-Filters-Applied`.
+    pub referrers_filtering: bool,
+    /// Honor the `prefix`, `name` and `sort` catalog query parameters.
+    pub catalog_extensions: bool,
+    /// Registry every push is mirrored to (none if `None`).
+    pub replication: Option<ReplicationConfig>,
+    /// Registries consulted in order when a manifest or blob is missing.
+    pub upstreams: Vec<UpstreamConfig>,
+    /// Namespaces served by their own backend or upstream.
+    pub namespaces: Vec<NamespaceRoute>,
+    /// How blob requests for foreign layers are answered.
+    pub foreign_layers: ForeignLayerPolicy,
+    /// `Warning` headers added to matching responses.
+    pub warnings: Vec<RegistryWarning>,
+    /// Encodings manifest and token responses may be compressed with.
+    pub response_compression: Vec<ContentEncoding>,
+    /// Whether `Location` headers are paths or full URLs.
+    pub location_style: LocationStyle,
+    /// URL clients reach the registry at, when it differs from the bound
+    /// address (reverse proxies, port-forwards).
+    pub external_url: Option<String>,
+    /// Validation of digests in request paths and upload queries.
+    pub digest_policy: Option<DigestPolicy>,
+    /// How thoroughly pushed manifests are checked.
+    pub manifest_validation: ValidationLevel,
+    /// Largest manifest body accepted, in bytes.
+    pub max_manifest_size: usize,
+    /// Size of the chunks manifests are served in with chunked transfer
+    /// encoding (served whole with a `Content-Length` if `None`).
+    pub manifest_chunk_size: Option<usize>,
+    /// Whether manifest pushes are streamed to storage instead of being
+    /// read into memory first, where nothing needs the whole body.
+    pub stream_manifests: bool,
+    /// How the content type of pushed manifests is chosen.
+    pub manifest_content_type: ContentTypePolicy,
+    /// Handling of Docker schema 1 manifests (like any other body if `None`).
+    pub schema1: Option<Schema1Mode>,
+    /// Whether the registry API accepts `DELETE` requests.
+    pub deletes_enabled: bool,
+    /// Optional API endpoints that are switched off.
+    pub disabled_endpoints: Vec<ApiEndpoint>,
+    /// Recording of HTTP exchanges (off if `None`).
+    pub capture: Option<CaptureConfig>,
+    /// Log a line per request and response at debug level.
+    pub protocol_log: bool,
+    /// Offset of the registry's clock (none if `None`).
+    pub clock_skew: Option<ClockSkew>,
+    /// How many requests per repository have their headers kept.
+    pub received_requests: usize,
+    /// Reverse proxy the registry simulates sitting behind (none if `None`).
+    pub proxy: Option<ProxySimulation>,
+    /// Casing and repetition of response headers.
+    pub header_quirks: HeaderQuirks,
+    /// Callbacks for startup, shutdown and failures.
+    pub lifecycle: LifecycleHooks,
+    /// Options for the listening sockets.
+    pub socket: SocketOptions,
 }
 
 impl RegistryConfig {
@@ -29,8 +155,50 @@ impl RegistryConfig {
     pub fn new(storage: StorageBackend) -> Self {
         Self {
             storage,
+            preload: None,
             port: None,
+            port_fallback: false,
             host: "127.0.0.1".to_string(),
+            visibility: Visibility::Immediate,
+            faults: FaultConfig::default(),
+            quota: None,
+            blob_redirect: None,
+            profile: RegistryProfile::Generic,
+            auth: None,
+            immutable_tags: false,
+            soft_delete: false,
+            signing_policy: None,
+            referrer_deletes: ReferrerDeletePolicy::default(),
+            referrers_filtering: true,
+            catalog_extensions: false,
+            replication: None,
+            upstreams: Vec::new(),
+            namespaces: Vec::new(),
+            foreign_layers: ForeignLayerPolicy::default(),
+            warnings: Vec::new(),
+            response_compression: Vec::new(),
+            location_style: LocationStyle::default(),
+            external_url: None,
+            digest_policy: None,
+            manifest_validation: ValidationLevel::default(),
+            max_manifest_size: MAX_BODY_SIZE,
+            manifest_chunk_size: None,
+            stream_manifests: false,
+            manifest_content_type: ContentTypePolicy::default(),
+            schema1: None,
+            deletes_enabled: true,
+            disabled_endpoints: Vec::new(),
+            capture: None,
+            protocol_log: false,
+            clock_skew: None,
+            received_requests: 0,
+            proxy: None,
+            header_quirks: HeaderQuirks::default(),
+            lifecycle: LifecycleHooks::default(),
+            socket: SocketOptions::default(),
+            pull_rate_limit: None,
+            retention: HashMap::new(),
+            maintenance: None,
         }
     }
 
@@ -44,22 +212,501 @@ impl RegistryConfig {
         Self::new(StorageBackend::TempDir)
     }
 
+    /// Creates a configuration with temporary directory storage under
+    /// `shared`, alongside other servers using it.
+    pub fn shared_temp_dir(shared: &SharedTempDir) -> Self {
+        Self::new(StorageBackend::SharedTempDir(shared.clone()))
+    }
+
     /// Creates a configuration with directory storage at the specified path.
     pub fn directory(path: PathBuf) -> Self {
         Self::new(StorageBackend::Directory(path))
     }
 
+    /// Loads a registry snapshot written by
+    /// [`RegistryServer::export_registry_tar`](crate::RegistryServer::export_registry_tar)
+    /// into storage at startup, before the listener opens, so the server
+    /// starts with every image of the snapshot already present.
+    ///
+    /// Startup fails if the file cannot be read or is not a snapshot.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let server = RegistryServer::new(RegistryConfig::memory().preload_from_tar("fixtures.tar")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn preload_from_tar(mut self, path: impl Into<PathBuf>) -> Self {
+        self.preload = Some(path.into());
+        self
+    }
+
     /// Sets a specific port for the server to bind to.
     pub fn with_port(mut self, port: u16) -> Self {
         self.port = Some(port);
         self
     }
 
+    /// Binds a random free port instead of failing with
+    /// [`RegistryError::AddrInUse`](crate::RegistryError::AddrInUse) when
+    /// the port set with [`with_port`](Self::with_port) is taken, so
+    /// parallel runs sharing a configuration do not collide. Check
+    /// [`RegistryServer::url`](crate::RegistryServer::url) for the port
+    /// actually bound.
+    pub fn with_port_fallback(mut self, fallback: bool) -> Self {
+        self.port_fallback = fallback;
+        self
+    }
+
     /// Sets the host address for the server to bind to.
     pub fn with_host(mut self, host: impl Into<String>) -> Self {
         self.host = host.into();
         self
     }
+
+    /// Sets when pushed manifests and blobs become visible to reads.
+    ///
+    /// Use [`Visibility::Delayed`] or [`Visibility::Manual`] to emulate a
+    /// replicated registry with consistency lag.
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    /// Sets the faults the server injects.
+    pub fn with_faults(mut self, faults: FaultConfig) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    /// Limits how many requests each client may make, answering 429 beyond it.
+    pub fn with_quota(mut self, quota: QuotaConfig) -> Self {
+        self.quota = Some(quota);
+        self
+    }
+
+    /// Redirects blob downloads to signed URLs on a secondary blob server.
+    pub fn with_blob_redirects(mut self, redirect: BlobRedirectConfig) -> Self {
+        self.blob_redirect = Some(redirect);
+        self
+    }
+
+    /// Emulates the quirks of a hosted registry.
+    ///
+    /// Enables the profile's default authentication and pull rate limit
+    /// unless they were already configured; call [`with_auth`](Self::with_auth)
+    /// or [`with_pull_rate_limit`](Self::with_pull_rate_limit) afterwards to
+    /// override them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use registry_testkit::{RegistryConfig, RegistryProfile};
+    ///
+    /// let config = RegistryConfig::memory().emulate(RegistryProfile::DockerHub);
+    /// assert!(config.auth.is_some());
+    /// ```
+    pub fn emulate(mut self, profile: RegistryProfile) -> Self {
+        self.profile = profile;
+        if self.auth.is_none() {
+            self.auth = profile.default_auth();
+        }
+        if self.pull_rate_limit.is_none() {
+            self.pull_rate_limit = profile.default_pull_rate_limit();
+        }
+        self
+    }
+
+    /// Requires clients to authenticate.
+    pub fn with_auth(mut self, auth: AuthConfig) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Limits manifest pulls per client and reports the remaining budget in
+    /// Docker Hub's `ratelimit-*` headers.
+    pub fn with_pull_rate_limit(mut self, limit: PullRateLimit) -> Self {
+        self.pull_rate_limit = Some(limit);
+        self
+    }
+
+    /// Sets the retention policy for `repository`, or for every repository
+    /// without its own policy if `repository` is `*`.
+    ///
+    /// Policies are enforced by
+    /// [`RegistryServer::apply_retention`](crate::RegistryServer::apply_retention).
+    pub fn with_retention(
+        mut self,
+        repository: impl Into<String>,
+        policy: RetentionPolicy,
+    ) -> Self {
+        self.retention.insert(repository.into(), policy);
+        self
+    }
+
+    /// Runs maintenance tasks (retention, garbage collection, upload cleanup,
+    /// integrity scrub) in the background.
+    pub fn with_maintenance(mut self, maintenance: MaintenanceConfig) -> Self {
+        self.maintenance = Some(maintenance);
+        self
+    }
+
+    /// Makes tags immutable, like an ECR repository with tag immutability
+    /// enabled: re-pushing a tag is only accepted with the same manifest.
+    pub fn with_immutable_tags(mut self, immutable: bool) -> Self {
+        self.immutable_tags = immutable;
+        self
+    }
+
+    /// Sets whether the registry API accepts `DELETE` requests. Disabled,
+    /// deletes of manifests and blobs answer `405 UNSUPPORTED`, like a
+    /// distribution registry without `storage.delete.enabled`, while
+    /// cancelling an upload still works.
+    ///
+    /// Deletes are enabled by default.
+    pub fn with_deletes_enabled(mut self, enabled: bool) -> Self {
+        self.deletes_enabled = enabled;
+        self
+    }
+
+    /// Switches off an optional API endpoint, answering it the way
+    /// registries without it do; see [`ApiEndpoint`] for each response.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use registry_testkit::{ApiEndpoint, RegistryConfig};
+    ///
+    /// // A registry without the referrers API or cross-repository mounts.
+    /// let config = RegistryConfig::memory()
+    ///     .with_endpoint_disabled(ApiEndpoint::Referrers)
+    ///     .with_endpoint_disabled(ApiEndpoint::BlobMount);
+    /// assert!(!config.endpoint_enabled(ApiEndpoint::Referrers));
+    /// ```
+    pub fn with_endpoint_disabled(mut self, endpoint: ApiEndpoint) -> Self {
+        if !self.disabled_endpoints.contains(&endpoint) {
+            self.disabled_endpoints.push(endpoint);
+        }
+        self
+    }
+
+    /// Returns whether `endpoint` is served. Deletes also follow
+    /// [`with_deletes_enabled`](Self::with_deletes_enabled), and the
+    /// catalog the [profile](Self::emulate).
+    pub fn endpoint_enabled(&self, endpoint: ApiEndpoint) -> bool {
+        let supported = match endpoint {
+            ApiEndpoint::Deletes => self.deletes_enabled,
+            ApiEndpoint::Catalog => self.profile.supports_catalog(),
+            _ => true,
+        };
+        supported && !self.disabled_endpoints.contains(&endpoint)
+    }
+
+    /// Makes deletes leave tombstones that
+    /// [`RegistryServer::undelete`](crate::RegistryServer::undelete) can
+    /// restore until the next garbage collection purges them.
+    pub fn with_soft_delete(mut self, soft_delete: bool) -> Self {
+        self.soft_delete = soft_delete;
+        self
+    }
+
+    /// Refuses manifests pushed by tag with `403 DENIED` unless they meet
+    /// `policy`, so pipelines meant to sign before they tag can be tested
+    /// for pushing unsigned images.
+    pub fn with_signing_policy(mut self, policy: SigningPolicy) -> Self {
+        self.signing_policy = Some(policy);
+        self
+    }
+
+    /// Sets what happens to the referrers of a manifest deleted by digest:
+    /// left orphaned, or deleted with it. Either way, a deleted referrer is
+    /// removed from its subject's
+    /// [fallback index](crate::referrers::fallback_tag).
+    pub fn with_referrer_deletes(mut self, policy: ReferrerDeletePolicy) -> Self {
+        self.referrer_deletes = policy;
+        self
+    }
+
+    /// Sets whether `GET /v2/<name>/referrers/<digest>?artifactType=<type>`
+    /// filters the referrers it lists, on by default. Filtered responses
+    /// carry `OCI-Filters-Applied: artifactType`; with filtering off, every
+    /// referrer is listed and the header is left out, as registries that
+    /// do not support filters answer, and clients have to filter
+    /// themselves.
+    pub fn with_referrers_filtering(mut self, enabled: bool) -> Self {
+        self.referrers_filtering = enabled;
+        self
+    }
+
+    /// Enables vendor extensions to `GET /v2/_catalog`: `prefix` and `name`
+    /// (substring) filters and `sort=asc|desc`, as Harbor and Artifactory
+    /// offer them. Without this the parameters are ignored. The standard
+    /// `n`/`last` pagination applies to the filtered list, in its order.
+    pub fn with_catalog_extensions(mut self, enabled: bool) -> Self {
+        self.catalog_extensions = enabled;
+        self
+    }
+
+    /// Mirrors every successful push to another registry in the background.
+    pub fn with_replication(mut self, replication: ReplicationConfig) -> Self {
+        self.replication = Some(replication);
+        self
+    }
+
+    /// Adds an upstream to the read-through fallback chain. Upstreams are
+    /// consulted in the order they were added.
+    pub fn with_upstream(mut self, upstream: UpstreamConfig) -> Self {
+        self.upstreams.push(upstream);
+        self
+    }
+
+    /// Sets how blob requests for foreign (non-distributable) layers are
+    /// answered; see [`ForeignLayerPolicy`]. Under
+    /// [`Redirect`](ForeignLayerPolicy::Redirect), layers listed without
+    /// `urls` are answered with `404`.
+    pub fn with_foreign_layers(mut self, policy: ForeignLayerPolicy) -> Self {
+        self.foreign_layers = policy;
+        self
+    }
+
+    /// Adds a `Warning` header to responses matching `warning`'s repository
+    /// and endpoint. Several matching warnings produce several headers.
+    pub fn with_warning(mut self, warning: RegistryWarning) -> Self {
+        self.warnings.push(warning);
+        self
+    }
+
+    /// Compresses manifest and token responses with whichever of
+    /// `encodings` the client's `Accept-Encoding` ranks highest, zstd on a
+    /// tie. Clients that send no `Accept-Encoding` get uncompressed
+    /// responses.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use registry_testkit::{ContentEncoding, RegistryConfig};
+    ///
+    /// let config = RegistryConfig::memory()
+    ///     .with_response_compression([ContentEncoding::Zstd, ContentEncoding::Gzip]);
+    /// ```
+    pub fn with_response_compression(
+        mut self,
+        encodings: impl IntoIterator<Item = ContentEncoding>,
+    ) -> Self {
+        self.response_compression = encodings.into_iter().collect();
+        self
+    }
+
+    /// Sets whether `Location` headers are paths or full URLs.
+    pub fn with_location_style(mut self, style: LocationStyle) -> Self {
+        self.location_style = style;
+        self
+    }
+
+    /// Sets the URL clients reach the registry at, such as
+    /// `https://registry.test.local` behind a reverse proxy.
+    ///
+    /// Absolute `Location` headers and token realms use it as their base,
+    /// and a path in it (`https://proxy.local/registry`) is prepended to
+    /// relative `Location` headers. Pagination `Link` headers follow the
+    /// same rules. Blob redirects go to a separate server; see
+    /// [`BlobRedirectConfig::with_external_url`].
+    pub fn with_external_url(mut self, url: impl Into<String>) -> Self {
+        self.external_url = Some(url.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Validates digests in requests against `policy`, answering
+    /// `DIGEST_INVALID` for malformed or disallowed ones and storing the
+    /// rest in canonical form.
+    pub fn with_digest_policy(mut self, policy: DigestPolicy) -> Self {
+        self.digest_policy = Some(policy);
+        self
+    }
+
+    /// Sets how thoroughly pushed manifests are checked. Manifests are
+    /// stored as-is by default.
+    pub fn with_manifest_validation(mut self, level: ValidationLevel) -> Self {
+        self.manifest_validation = level;
+        self
+    }
+
+    /// Sets the largest manifest `PUT` accepts. Bodies are refused with
+    /// `413 SIZE_INVALID` as soon as they exceed `bytes`, before the rest is
+    /// read. Accepted manifests are held in memory whole for validation and
+    /// storage unless they are
+    /// [streamed](RegistryConfig::with_streamed_manifests).
+    ///
+    /// Defaults to the 512 MiB limit of every request body.
+    pub fn with_max_manifest_size(mut self, bytes: usize) -> Self {
+        self.max_manifest_size = bytes;
+        self
+    }
+
+    /// Serves manifests in chunks of `chunk_size` bytes with chunked
+    /// transfer encoding and no `Content-Length`, as registries streaming
+    /// from object storage do. `HEAD` responses keep their
+    /// `Content-Length`. Storage backends that read manifests piecewise,
+    /// like the disk one, never hold them in memory whole.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use registry_testkit::RegistryConfig;
+    ///
+    /// // A 64 MiB ceiling on manifests, served back in 8 KiB chunks.
+    /// let config = RegistryConfig::memory()
+    ///     .with_max_manifest_size(64 * 1024 * 1024)
+    ///     .with_chunked_manifests(8 * 1024);
+    /// ```
+    pub fn with_chunked_manifests(mut self, chunk_size: usize) -> Self {
+        self.manifest_chunk_size = Some(chunk_size.max(1));
+        self
+    }
+
+    /// Streams manifest pushes to storage, hashing them on the way, so that
+    /// memory use is bounded by the chunk size rather than by
+    /// [`with_max_manifest_size`](RegistryConfig::with_max_manifest_size).
+    /// A push that fails its digest check or exceeds the limit partway
+    /// through leaves the stored manifest untouched.
+    ///
+    /// Only pushes nothing needs to read whole are streamed: manifest
+    /// validation, schema 1 handling, signing policies, immutable tags,
+    /// replication and digest algorithms without an incremental
+    /// [`Digester::hasher`](crate::Digester::hasher) all fall back to
+    /// buffering. Streamed manifests are stored under the request's
+    /// `Content-Type`, or the Docker manifest type when it is missing or
+    /// generic, and their subjects and foreign layers are not recorded.
+    pub fn with_streamed_manifests(mut self, stream: bool) -> Self {
+        self.stream_manifests = stream;
+        self
+    }
+
+    /// Sets how the content type of pushed manifests is chosen. By default
+    /// a missing or generic `Content-Type` is replaced by the type detected
+    /// from the body, so OCI manifests are served back as OCI.
+    pub fn with_manifest_content_type(mut self, policy: ContentTypePolicy) -> Self {
+        self.manifest_content_type = policy;
+        self
+    }
+
+    /// Sets how Docker schema 1 manifests are handled: stored as pushed,
+    /// converted to schema 2, or rejected.
+    pub fn with_schema1(mut self, mode: Schema1Mode) -> Self {
+        self.schema1 = Some(mode);
+        self
+    }
+
+    /// Records HTTP exchanges matching `capture`, for
+    /// [`RegistryServer::captured`](crate::RegistryServer::captured) and
+    /// [`RegistryServer::captured_har`](crate::RegistryServer::captured_har).
+    pub fn with_capture(mut self, capture: CaptureConfig) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
+    /// Logs each request and response line with its key OCI headers at
+    /// debug level, under the [`wirelog::TARGET`](crate::wirelog::TARGET)
+    /// target. Nothing is kept in memory, unlike
+    /// [`with_capture`](Self::with_capture).
+    pub fn with_protocol_logging(mut self, enabled: bool) -> Self {
+        self.protocol_log = enabled;
+        self
+    }
+
+    /// Keeps the method, URI and headers of the last `limit` requests to
+    /// each repository, as received, for
+    /// [`RegistryServer::received_requests`](crate::RegistryServer::received_requests)
+    /// and `GET /admin/repositories/<name>/requests`. Credentials and
+    /// cookies are redacted.
+    pub fn with_received_requests(mut self, limit: usize) -> Self {
+        self.received_requests = limit;
+        self
+    }
+
+    /// Mangles requests and responses the way `proxy` does, as if the
+    /// registry sat behind it. It can be switched on and off while the
+    /// server runs with
+    /// [`RegistryServer::set_proxy_simulation`](crate::RegistryServer::set_proxy_simulation).
+    pub fn with_proxy_simulation(mut self, proxy: ProxySimulation) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Writes response headers with `quirks`: in another casing, or with
+    /// values duplicated or joined, to reproduce clients that mishandle
+    /// headers from some production registries.
+    pub fn with_header_quirks(mut self, quirks: HeaderQuirks) -> Self {
+        self.header_quirks = quirks;
+        self
+    }
+
+    /// Runs the registry's clock off by `skew`: `Date` headers and the
+    /// `iat` and `exp` claims of issued tokens use the skewed time, so
+    /// clients' tolerance of clock drift can be tested.
+    pub fn with_clock_skew(mut self, skew: ClockSkew) -> Self {
+        self.clock_skew = Some(skew);
+        self
+    }
+
+    /// Sets the options of the listening sockets.
+    pub fn with_socket_options(mut self, socket: SocketOptions) -> Self {
+        self.socket = socket;
+        self
+    }
+
+    /// Calls `callback` at every [`LifecycleEvent`]: storage set up, server
+    /// started or stopped, or startup failed. Callbacks run synchronously
+    /// on the task causing the event, in registration order.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use registry_testkit::{LifecycleEvent, RegistryConfig, RegistryServer};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = RegistryConfig::memory().on_event(|event| {
+    ///     if let LifecycleEvent::Started { url, .. } = event {
+    ///         println!("register {} with discovery", url);
+    ///     }
+    /// });
+    /// let server = RegistryServer::new(config).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_event<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&LifecycleEvent) + Send + Sync + 'static,
+    {
+        self.lifecycle.push(Arc::new(callback));
+        self
+    }
+
+    /// Serves repositories whose names start with `prefix` from `target`
+    /// instead of the main storage. The longest matching prefix wins.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use registry_testkit::{NamespaceTarget, RegistryConfig, StorageBackend, UpstreamConfig};
+    ///
+    /// let config = RegistryConfig::memory()
+    ///     .with_namespace("internal/", NamespaceTarget::Storage(StorageBackend::TempDir))
+    ///     .with_namespace(
+    ///         "docker.io/",
+    ///         NamespaceTarget::Upstream(UpstreamConfig::new("https://registry-1.docker.io").with_cache(true)),
+    ///     );
+    /// ```
+    pub fn with_namespace(mut self, prefix: impl Into<String>, target: NamespaceTarget) -> Self {
+        self.namespaces.push(NamespaceRoute {
+            prefix: prefix.into(),
+            target,
+        });
+        self
+    }
 }
 
 impl Default for RegistryConfig {
@@ -0,0 +1,173 @@
+//! Eventual-consistency simulation for replicated registries.
+
+use crate::error::Result;
+use crate::storage::{ManifestEntry, Storage};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// When newly written manifests and blobs become visible to reads.
+#[derive(Debug, Clone, Default)]
+pub enum Visibility {
+    /// Writes are visible immediately.
+    #[default]
+    Immediate,
+    /// Writes become visible after the given delay.
+    Delayed(Duration),
+    /// Writes stay invisible until [`RegistryServer::flush`](crate::RegistryServer::flush).
+    Manual,
+}
+
+struct Pending {
+    visible_at: Option<Instant>,
+    previous: Option<ManifestEntry>,
+}
+
+impl Pending {
+    fn is_visible(&self) -> bool {
+        self.visible_at.is_some_and(|at| Instant::now() >= at)
+    }
+}
+
+/// Storage decorator that hides recent writes from readers.
+///
+/// Overwritten manifests keep serving their previous content until the new
+/// write becomes visible, like a lagging read replica.
+pub struct LaggedStorage {
+    inner: Arc<dyn Storage>,
+    delay: Option<Duration>,
+    manifests: RwLock<HashMap<String, Pending>>,
+    blobs: RwLock<HashMap<String, Pending>>,
+}
+
+impl LaggedStorage {
+    /// Wraps `inner`, hiding writes for `delay` (or until flushed if `None`).
+    pub fn new(inner: Arc<dyn Storage>, delay: Option<Duration>) -> Self {
+        Self {
+            inner,
+            delay,
+            manifests: RwLock::default(),
+            blobs: RwLock::default(),
+        }
+    }
+
+    /// Makes every pending write visible immediately.
+    pub async fn flush(&self) {
+        self.manifests.write().await.clear();
+        self.blobs.write().await.clear();
+    }
+
+    fn pending(&self, previous: Option<ManifestEntry>) -> Pending {
+        Pending {
+            visible_at: self.delay.map(|delay| Instant::now() + delay),
+            previous,
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for LaggedStorage {
+    async fn store_manifest(&self, key: String, entry: ManifestEntry) -> Result<()> {
+        let mut manifests = self.manifests.write().await;
+        let previous = match manifests.remove(&key) {
+            Some(pending) if !pending.is_visible() => pending.previous,
+            _ => self.inner.get_manifest(&key).await?,
+        };
+        self.inner.store_manifest(key.clone(), entry).await?;
+        manifests.insert(key, self.pending(previous));
+        Ok(())
+    }
+
+    async fn get_manifest(&self, key: &str) -> Result<Option<ManifestEntry>> {
+        if let Some(pending) = self.manifests.read().await.get(key) {
+            if !pending.is_visible() {
+                return Ok(pending.previous.clone());
+            }
+        }
+        self.inner.get_manifest(key).await
+    }
+
+    async fn delete_manifest(&self, key: &str) -> Result<bool> {
+        let mut manifests = self.manifests.write().await;
+        manifests.remove(key);
+        self.inner.delete_manifest(key).await
+    }
+
+    async fn list_manifests(&self) -> Result<Vec<String>> {
+        let manifests = self.manifests.read().await;
+        let mut keys = self.inner.list_manifests().await?;
+        keys.retain(|key| {
+            manifests
+                .get(key)
+                .is_none_or(|pending| pending.is_visible() || pending.previous.is_some())
+        });
+        Ok(keys)
+    }
+
+    async fn store_blob(&self, digest: String, data: Vec<u8>) -> Result<()> {
+        let mut blobs = self.blobs.write().await;
+        let existed = self.inner.get_blob(&digest).await?.is_some();
+        self.inner.store_blob(digest.clone(), data).await?;
+        if !existed {
+            blobs.insert(digest, self.pending(None));
+        }
+        Ok(())
+    }
+
+    async fn get_blob(&self, digest: &str) -> Result<Option<Vec<u8>>> {
+        if let Some(pending) = self.blobs.read().await.get(digest) {
+            if !pending.is_visible() {
+                return Ok(None);
+            }
+        }
+        self.inner.get_blob(digest).await
+    }
+
+    async fn delete_blob(&self, digest: &str) -> Result<bool> {
+        let mut blobs = self.blobs.write().await;
+        blobs.remove(digest);
+        self.inner.delete_blob(digest).await
+    }
+
+    async fn list_blobs(&self) -> Result<Vec<String>> {
+        let blobs = self.blobs.read().await;
+        let mut digests = self.inner.list_blobs().await?;
+        digests.retain(|digest| blobs.get(digest).is_none_or(Pending::is_visible));
+        Ok(digests)
+    }
+
+    async fn blobs_exist(&self, digests: &[String]) -> Result<Vec<bool>> {
+        let blobs = self.blobs.read().await;
+        let mut exist = self.inner.blobs_exist(digests).await?;
+        for (digest, exists) in digests.iter().zip(&mut exist) {
+            *exists &= blobs.get(digest).is_none_or(Pending::is_visible);
+        }
+        Ok(exist)
+    }
+
+    async fn create_upload(&self, uuid: String) -> Result<()> {
+        self.inner.create_upload(uuid).await
+    }
+
+    async fn append_upload(&self, uuid: &str, data: &[u8]) -> Result<()> {
+        self.inner.append_upload(uuid, data).await
+    }
+
+    async fn upload_size(&self, uuid: &str) -> Result<Option<u64>> {
+        self.inner.upload_size(uuid).await
+    }
+
+    async fn finish_upload(&self, uuid: &str) -> Result<Option<Vec<u8>>> {
+        self.inner.finish_upload(uuid).await
+    }
+
+    async fn delete_upload(&self, uuid: &str) -> Result<bool> {
+        self.inner.delete_upload(uuid).await
+    }
+
+    async fn simulate_crash(&self) -> Result<()> {
+        self.inner.simulate_crash().await
+    }
+}
@@ -0,0 +1,308 @@
+//! Copying images between registries, optionally narrowed to platforms.
+
+use crate::client::RegistryClient;
+use crate::digest::sha256_digest;
+use crate::error::Result;
+use crate::oci::manifest::Platform;
+use crate::reference::Reference;
+use crate::verify::descriptors;
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use std::fmt;
+use std::str::FromStr;
+
+/// Annotation buildx puts on attestation manifests, naming the image
+/// manifest they describe.
+const ATTESTATION_SUBJECT: &str = "vnd.docker.reference.digest";
+
+/// Which platforms of a multi-platform image to keep.
+///
+/// Platforms are written like `docker --platform`: `os`, `os/arch` or
+/// `os/arch/variant`. Parts left out match anything.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::copy::PlatformFilter;
+/// use registry_testkit::oci::manifest::Platform;
+///
+/// let filter = PlatformFilter::only(["linux/amd64"]).unwrap();
+/// let amd64 = Platform {
+///     os: "linux".to_string(),
+///     architecture: "amd64".to_string(),
+///     ..Platform::default()
+/// };
+/// assert!(filter.matches(&amd64));
+/// assert!(PlatformFilter::all().matches(&amd64));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlatformFilter {
+    platforms: Vec<PlatformSpec>,
+}
+
+/// One `os[/arch[/variant]]` entry of a [`PlatformFilter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PlatformSpec {
+    os: String,
+    architecture: Option<String>,
+    variant: Option<String>,
+}
+
+impl FromStr for PlatformSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        let mut parts = s.split('/').map(str::to_string);
+        let os = parts.next().filter(|os| !os.is_empty());
+        let (architecture, variant) = (parts.next(), parts.next());
+        match os {
+            Some(os) if parts.next().is_none() => Ok(Self {
+                os,
+                architecture,
+                variant,
+            }),
+            _ => Err(format!("platform {:?} is not os[/arch[/variant]]", s)),
+        }
+    }
+}
+
+impl fmt::Display for PlatformSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.os)?;
+        for part in [&self.architecture, &self.variant].into_iter().flatten() {
+            write!(f, "/{}", part)?;
+        }
+        Ok(())
+    }
+}
+
+impl PlatformFilter {
+    /// Keeps every platform.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Keeps only the given platforms, such as `linux/amd64`, or says
+    /// which one is malformed.
+    pub fn only<I, S>(platforms: I) -> std::result::Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let platforms = platforms
+            .into_iter()
+            .map(|platform| platform.as_ref().parse())
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(Self { platforms })
+    }
+
+    /// Returns whether this filter keeps every platform.
+    pub fn is_all(&self) -> bool {
+        self.platforms.is_empty()
+    }
+
+    /// Returns whether `platform` is kept.
+    pub fn matches(&self, platform: &Platform) -> bool {
+        self.is_all()
+            || self.platforms.iter().any(|spec| {
+                spec.os == platform.os
+                    && spec
+                        .architecture
+                        .as_ref()
+                        .is_none_or(|arch| *arch == platform.architecture)
+                    && spec
+                        .variant
+                        .as_ref()
+                        .is_none_or(|variant| Some(variant) == platform.variant.as_ref())
+            })
+    }
+
+    /// Removes the entries of an index for platforms this filter drops,
+    /// along with attestations for them. Entries without a platform are
+    /// kept. Returns `None` if `index` is not an index or nothing was
+    /// removed; other fields are preserved as they are.
+    pub(crate) fn filter_index(&self, index: &[u8]) -> Option<Vec<u8>> {
+        if self.is_all() {
+            return None;
+        }
+        let mut value: serde_json::Value = serde_json::from_slice(index).ok()?;
+        let manifests = value.get_mut("manifests")?.as_array_mut()?;
+        let before = manifests.len();
+
+        let dropped: Vec<String> = manifests
+            .iter()
+            .filter(|entry| !self.keeps(entry))
+            .filter_map(|entry| entry["digest"].as_str().map(str::to_string))
+            .collect();
+        manifests.retain(|entry| {
+            let subject = entry["annotations"][ATTESTATION_SUBJECT].as_str();
+            self.keeps(entry) && !subject.is_some_and(|s| dropped.iter().any(|d| d == s))
+        });
+        if manifests.len() == before {
+            return None;
+        }
+        serde_json::to_vec(&value).ok()
+    }
+
+    fn keeps(&self, entry: &serde_json::Value) -> bool {
+        match entry.get("platform") {
+            Some(platform) => serde_json::from_value(platform.clone())
+                .map(|platform| self.matches(&platform))
+                .unwrap_or(true),
+            None => true,
+        }
+    }
+}
+
+impl fmt::Display for PlatformFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_all() {
+            return f.write_str("all");
+        }
+        let platforms: Vec<String> = self.platforms.iter().map(ToString::to_string).collect();
+        f.write_str(&platforms.join(","))
+    }
+}
+
+/// Copies the image `from` points at on `source` to `to` on `target`,
+/// keeping only the platforms `platforms` selects, and returns the digest
+/// of the copied manifest.
+///
+/// Indexes are copied with the children they keep, by digest, and pushed
+/// under the tag of `to` once rewritten; a rewritten index has a new digest,
+/// so a digest in `to` is ignored for it. Blobs outside the registry, such
+/// as foreign layers with `urls`, are not copied. Registry parts of the
+/// references are ignored; the clients decide where content comes from and
+/// goes to.
+///
+/// # Examples
+///
+/// ```no_run
+/// use registry_testkit::copy::{copy_image, PlatformFilter};
+/// use registry_testkit::{Reference, RegistryClient, RegistryConfig, RegistryServer};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let server = RegistryServer::new(RegistryConfig::memory()).await?;
+/// let upstream = RegistryClient::new("https://registry-1.docker.io");
+/// let local = RegistryClient::new(server.url());
+/// let busybox = Reference::parse("busybox:1.36")?;
+/// let filter = PlatformFilter::only(["linux/amd64"])?;
+/// copy_image(&upstream, &busybox, &local, &busybox, &filter).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn copy_image(
+    source: &RegistryClient,
+    from: &Reference,
+    target: &RegistryClient,
+    to: &Reference,
+    platforms: &PlatformFilter,
+) -> Result<String> {
+    let mut manifest = source
+        .pull_manifest(from.repository(), from.reference())
+        .await?;
+    let value: serde_json::Value = serde_json::from_slice(&manifest.data)?;
+
+    let Some(children) = value["manifests"].as_array() else {
+        copy_manifest(source, from.repository(), target, to.repository(), &value).await?;
+        return target
+            .push_manifest(
+                to.repository(),
+                to.reference(),
+                &manifest.content_type,
+                manifest.data,
+            )
+            .await;
+    };
+
+    let filtered = platforms.filter_index(&manifest.data);
+    let kept: Vec<String> = match &filtered {
+        Some(data) => serde_json::from_slice::<serde_json::Value>(data)?["manifests"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry["digest"].as_str().map(str::to_string))
+            .collect(),
+        None => children
+            .iter()
+            .filter_map(|entry| entry["digest"].as_str().map(str::to_string))
+            .collect(),
+    };
+    for digest in &kept {
+        copy_by_digest(source, from.repository(), target, to.repository(), digest).await?;
+    }
+
+    let reference = match filtered {
+        Some(data) => {
+            manifest.data = data;
+            to.tag()
+                .map(str::to_string)
+                .unwrap_or_else(|| sha256_digest(&manifest.data))
+        }
+        None => to.reference().to_string(),
+    };
+    target
+        .push_manifest(
+            to.repository(),
+            &reference,
+            &manifest.content_type,
+            manifest.data,
+        )
+        .await
+}
+
+/// Copies the manifest `digest` and everything it references.
+fn copy_by_digest<'a>(
+    source: &'a RegistryClient,
+    from: &'a str,
+    target: &'a RegistryClient,
+    to: &'a str,
+    digest: &'a str,
+) -> BoxFuture<'a, Result<()>> {
+    async move {
+        let manifest = source.pull_manifest(from, digest).await?;
+        let value: serde_json::Value = serde_json::from_slice(&manifest.data)?;
+        for child in descriptors(&value, "manifests") {
+            copy_by_digest(source, from, target, to, &child.digest).await?;
+        }
+        copy_manifest(source, from, target, to, &value).await?;
+        target
+            .push_manifest(to, digest, &manifest.content_type, manifest.data)
+            .await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+/// Copies the blobs an image manifest references.
+async fn copy_manifest(
+    source: &RegistryClient,
+    from: &str,
+    target: &RegistryClient,
+    to: &str,
+    manifest: &serde_json::Value,
+) -> Result<()> {
+    let foreign: Vec<&str> = manifest["layers"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|layer| {
+            layer["urls"]
+                .as_array()
+                .is_some_and(|urls| !urls.is_empty())
+        })
+        .filter_map(|layer| layer["digest"].as_str())
+        .collect();
+    for field in ["config", "layers", "blobs"] {
+        for blob in descriptors(manifest, field) {
+            if foreign.contains(&blob.digest.as_str())
+                || target.blob_exists(to, &blob.digest).await?
+            {
+                continue;
+            }
+            let data = source.pull_blob(from, &blob.digest).await?;
+            target.push_blob(to, data).await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Deduplication statistics: how many pushed blobs were already stored.
+//!
+//! A blob counts as a duplicate when its digest is already in storage as
+//! it is pushed, either because an upload finished with content the
+//! registry had or because a cross-repository mount succeeded. Build
+//! systems that skip layers the registry has show few duplicate uploads;
+//! ones that re-upload unchanged layers show many.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Blob pushes and how many of them were already stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DedupStats {
+    /// Blobs pushed: uploads that stored a blob plus successful mounts.
+    pub pushed: u64,
+    /// Pushed blobs whose digest was already stored.
+    pub duplicates: u64,
+    /// Bytes uploaded for blobs that were already stored; mounts transfer
+    /// nothing and add none.
+    pub duplicate_bytes: u64,
+}
+
+impl DedupStats {
+    /// Fraction of pushed blobs that were already stored, `0.0` when
+    /// nothing was pushed.
+    pub fn hit_ratio(&self) -> f64 {
+        if self.pushed == 0 {
+            0.0
+        } else {
+            self.duplicates as f64 / self.pushed as f64
+        }
+    }
+
+    fn add(&mut self, other: &DedupStats) {
+        self.pushed += other.pushed;
+        self.duplicates += other.duplicates;
+        self.duplicate_bytes += other.duplicate_bytes;
+    }
+}
+
+/// Deduplication statistics for the whole registry and each repository,
+/// as returned by [`RegistryServer::dedup_stats`](crate::RegistryServer::dedup_stats).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DedupReport {
+    /// All repositories together.
+    pub total: DedupStats,
+    /// Each repository blobs were pushed to.
+    pub repositories: BTreeMap<String, DedupStats>,
+}
+
+impl DedupReport {
+    /// Returns the statistics of `repository`, zero if nothing was pushed
+    /// to it.
+    pub fn repository(&self, repository: &str) -> DedupStats {
+        self.repositories
+            .get(repository)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Counts blob pushes per repository.
+#[derive(Debug, Default)]
+pub(crate) struct DedupTracker {
+    repositories: Mutex<BTreeMap<String, DedupStats>>,
+}
+
+impl DedupTracker {
+    /// Records a finished upload of `size` bytes.
+    pub(crate) fn record_upload(&self, repository: &str, duplicate: bool, size: u64) {
+        let mut repositories = self.repositories.lock().unwrap();
+        let stats = repositories.entry(repository.to_string()).or_default();
+        stats.pushed += 1;
+        if duplicate {
+            stats.duplicates += 1;
+            stats.duplicate_bytes += size;
+        }
+    }
+
+    /// Records a successful mount, which always reuses a stored blob.
+    pub(crate) fn record_mount(&self, repository: &str) {
+        let mut repositories = self.repositories.lock().unwrap();
+        let stats = repositories.entry(repository.to_string()).or_default();
+        stats.pushed += 1;
+        stats.duplicates += 1;
+    }
+
+    pub(crate) fn report(&self) -> DedupReport {
+        let repositories = self.repositories.lock().unwrap().clone();
+        let mut total = DedupStats::default();
+        for stats in repositories.values() {
+            total.add(stats);
+        }
+        DedupReport {
+            total,
+            repositories,
+        }
+    }
+}
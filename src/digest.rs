@@ -0,0 +1,407 @@
+//! Digest algorithms, validation and canonicalization.
+
+use crate::error::RegistryError;
+use crate::server::split_repository_path;
+use axum::{
+    extract::{Request, State},
+    http::Uri,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt;
+use std::sync::Arc;
+use tracing::debug;
+
+/// A digest algorithm, hashing content to the encoded part of its digests.
+///
+/// sha256 and sha512 are built in. Other algorithms, such as blake3 for
+/// experiments, are plugged in with [`DigestPolicy::with_digester`]. A
+/// digester registered under a built-in name replaces it, which lets
+/// negative tests make the registry compute wrong digests.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::digest::Digester;
+/// use registry_testkit::DigestPolicy;
+///
+/// /// Claims every blob hashes to zeros.
+/// #[derive(Debug)]
+/// struct Zeros;
+///
+/// impl Digester for Zeros {
+///     fn algorithm(&self) -> &str {
+///         "sha256"
+///     }
+///
+///     fn encode(&self, _data: &[u8]) -> String {
+///         "0".repeat(64)
+///     }
+/// }
+///
+/// let policy = DigestPolicy::new().with_digester(Zeros);
+/// let digest = policy.compute("sha256", b"data").unwrap();
+/// assert_eq!(digest, format!("sha256:{}", "0".repeat(64)));
+/// ```
+pub trait Digester: fmt::Debug + Send + Sync {
+    /// Name of the algorithm, the part of a digest before the colon.
+    fn algorithm(&self) -> &str;
+
+    /// Hashes `data` to the encoded part of its digest.
+    fn encode(&self, data: &[u8]) -> String;
+
+    /// Length of the encoded part if it is fixed-length lowercase hex, as
+    /// for the registered algorithms. Such digests are canonicalized to
+    /// lowercase and rejected at any other length.
+    fn encoded_length(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the digest of `data`, `<algorithm>:<encoded>`.
+    fn digest(&self, data: &[u8]) -> String {
+        format!("{}:{}", self.algorithm(), self.encode(data))
+    }
+
+    /// Starts hashing content fed in pieces, if the algorithm can.
+    /// [Streamed manifests](crate::RegistryConfig::with_streamed_manifests)
+    /// need one; without it they are buffered and hashed whole.
+    fn hasher(&self) -> Option<Box<dyn DigestHasher>> {
+        None
+    }
+}
+
+/// Incremental form of a [`Digester`].
+pub trait DigestHasher: Send {
+    /// Hashes the next piece of content.
+    fn update(&mut self, data: &[u8]);
+
+    /// Returns the encoded part of the digest of everything fed so far.
+    fn finish(self: Box<Self>) -> String;
+}
+
+/// Incremental hashing with a `sha2` algorithm.
+struct Sha2Hasher<D>(D);
+
+impl<D: Digest + Send> DigestHasher for Sha2Hasher<D> {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finish(self: Box<Self>) -> String {
+        hex::encode(self.0.finalize())
+    }
+}
+
+/// The built-in sha256 digester.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256Digester;
+
+impl Digester for Sha256Digester {
+    fn algorithm(&self) -> &str {
+        "sha256"
+    }
+
+    fn encode(&self, data: &[u8]) -> String {
+        hex::encode(Sha256::digest(data))
+    }
+
+    fn encoded_length(&self) -> Option<usize> {
+        Some(64)
+    }
+
+    fn hasher(&self) -> Option<Box<dyn DigestHasher>> {
+        Some(Box::new(Sha2Hasher(Sha256::new())))
+    }
+}
+
+/// The built-in sha512 digester.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha512Digester;
+
+impl Digester for Sha512Digester {
+    fn algorithm(&self) -> &str {
+        "sha512"
+    }
+
+    fn encode(&self, data: &[u8]) -> String {
+        hex::encode(Sha512::digest(data))
+    }
+
+    fn encoded_length(&self) -> Option<usize> {
+        Some(128)
+    }
+
+    fn hasher(&self) -> Option<Box<dyn DigestHasher>> {
+        Some(Box::new(Sha2Hasher(Sha512::new())))
+    }
+}
+
+/// Which digests the registry accepts and how it treats nonstandard forms.
+///
+/// Digests appear in blob URLs, manifest references and the `digest` query
+/// parameter that completes uploads. By default the policy accepts any
+/// well-formed algorithm and rewrites denormalized digests (uppercase hex,
+/// surrounding whitespace) to their canonical form before they reach
+/// storage. In strict mode they are rejected with `DIGEST_INVALID` instead.
+///
+/// Pushed content is verified against its digest with the policy's
+/// [`Digester`]s, sha256 and sha512 unless others are registered.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::DigestPolicy;
+///
+/// let policy = DigestPolicy::new().with_algorithms(["sha256"]).strict(true);
+/// assert!(policy.canonicalize(&format!("sha256:{}", "a".repeat(64))).is_ok());
+/// assert!(policy.canonicalize(&format!("sha256:{}", "A".repeat(64))).is_err());
+/// assert!(policy.canonicalize(&format!("sha512:{}", "a".repeat(128))).is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DigestPolicy {
+    /// Accepted algorithms; empty accepts any well-formed algorithm.
+    pub algorithms: Vec<String>,
+    /// Rejects denormalized digests instead of canonicalizing them.
+    pub strict: bool,
+    /// Digesters added to, or replacing, the built-in ones.
+    pub digesters: Vec<Arc<dyn Digester>>,
+}
+
+/// Policies are equal when they accept the same digests and register
+/// digesters for the same algorithms.
+impl PartialEq for DigestPolicy {
+    fn eq(&self, other: &Self) -> bool {
+        let algorithms = |policy: &Self| {
+            policy
+                .digesters
+                .iter()
+                .map(|digester| digester.algorithm().to_string())
+                .collect::<Vec<_>>()
+        };
+        self.algorithms == other.algorithms
+            && self.strict == other.strict
+            && algorithms(self) == algorithms(other)
+    }
+}
+
+impl Eq for DigestPolicy {}
+
+impl DigestPolicy {
+    /// Creates a policy accepting any algorithm and canonicalizing digests.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts accepted digests to the given algorithms.
+    pub fn with_algorithms<I, S>(mut self, algorithms: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.algorithms = algorithms.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets whether denormalized digests are rejected rather than rewritten.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Registers a digester, replacing any registered for its algorithm.
+    pub fn with_digester(mut self, digester: impl Digester + 'static) -> Self {
+        self.digesters
+            .retain(|registered| registered.algorithm() != digester.algorithm());
+        self.digesters.push(Arc::new(digester));
+        self
+    }
+
+    /// Returns the digester for `algorithm`: a registered one, else a
+    /// built-in one.
+    pub fn digester(&self, algorithm: &str) -> Option<&dyn Digester> {
+        self.digesters
+            .iter()
+            .find(|digester| digester.algorithm() == algorithm)
+            .map(|digester| digester.as_ref())
+            .or_else(|| builtin(algorithm))
+    }
+
+    /// Computes the digest of `data` with `algorithm`, if there is a
+    /// digester for it.
+    pub fn compute(&self, algorithm: &str, data: &[u8]) -> Option<String> {
+        self.digester(algorithm)
+            .map(|digester| digester.digest(data))
+    }
+
+    /// Checks that `data` hashes to `digest`.
+    pub fn verify(&self, digest: &str, data: &[u8]) -> Result<(), String> {
+        let (algorithm, _) = digest
+            .split_once(':')
+            .ok_or_else(|| format!("digest {:?} has no algorithm", digest))?;
+        match self.compute(algorithm, data) {
+            Some(computed) if computed == digest => Ok(()),
+            Some(computed) => Err(format!(
+                "content digest {} does not match reference {}",
+                computed, digest
+            )),
+            None => Err(format!(
+                "digest algorithm {:?} cannot be verified by this registry",
+                algorithm
+            )),
+        }
+    }
+
+    /// Returns the canonical form of `digest`, or why it is not accepted.
+    pub fn canonicalize(&self, digest: &str) -> Result<String, String> {
+        let trimmed = digest.trim();
+        let (algorithm, encoded) = trimmed
+            .split_once(':')
+            .ok_or_else(|| format!("digest {:?} has no algorithm", digest))?;
+        let algorithm = algorithm.to_ascii_lowercase();
+        let length = self
+            .digester(&algorithm)
+            .and_then(|digester| digester.encoded_length());
+        let encoded = match length {
+            Some(_) => encoded.to_ascii_lowercase(),
+            None => encoded.to_string(),
+        };
+
+        if !valid_algorithm(&algorithm) || !valid_encoded(length, &encoded) {
+            return Err(format!("digest {:?} is malformed", digest));
+        }
+        if !self.algorithms.is_empty() && !self.algorithms.contains(&algorithm) {
+            return Err(format!(
+                "digest algorithm {:?} is not accepted by this registry",
+                algorithm
+            ));
+        }
+
+        let canonical = format!("{}:{}", algorithm, encoded);
+        if self.strict && canonical != digest {
+            return Err(format!(
+                "digest {:?} is not in canonical form {:?}",
+                digest, canonical
+            ));
+        }
+        Ok(canonical)
+    }
+}
+
+/// Checks that `data` hashes to `digest` with the built-in digesters.
+pub(crate) fn verify(digest: &str, data: &[u8]) -> Result<(), String> {
+    DigestPolicy::new().verify(digest, data)
+}
+
+/// Returns the sha256 digest of `data`.
+pub(crate) fn sha256_digest(data: &[u8]) -> String {
+    Sha256Digester.digest(data)
+}
+
+/// The built-in digester for `algorithm`.
+fn builtin(algorithm: &str) -> Option<&'static dyn Digester> {
+    match algorithm {
+        "sha256" => Some(&Sha256Digester),
+        "sha512" => Some(&Sha512Digester),
+        _ => None,
+    }
+}
+
+/// `[a-z0-9]+([+._-][a-z0-9]+)*`
+fn valid_algorithm(algorithm: &str) -> bool {
+    algorithm.split(['+', '.', '_', '-']).all(|component| {
+        !component.is_empty()
+            && component
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+    })
+}
+
+fn valid_encoded(length: Option<usize>, encoded: &str) -> bool {
+    match length {
+        Some(length) => {
+            encoded.len() == length
+                && encoded
+                    .bytes()
+                    .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+        }
+        None => {
+            !encoded.is_empty()
+                && encoded
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b"=_-".contains(&b))
+        }
+    }
+}
+
+/// Validates the digests in a request and rewrites them to canonical form.
+pub(crate) async fn check_digests(
+    State(policy): State<Arc<DigestPolicy>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match canonical_uri(&policy, request.uri()) {
+        Ok(Some(uri)) => *request.uri_mut() = uri,
+        Ok(None) => {}
+        Err(message) => {
+            debug!("Rejecting request for {}: {}", request.uri(), message);
+            return RegistryError::DigestInvalid(message).into_response();
+        }
+    }
+    next.run(request).await
+}
+
+/// Returns the request URI with canonical digests, if any digest changed.
+fn canonical_uri(policy: &DigestPolicy, uri: &Uri) -> Result<Option<Uri>, String> {
+    let mut changed = false;
+
+    let mut path = uri.path().to_string();
+    if let Some((name, rest)) = split_repository_path(uri.path()) {
+        let digest = match rest.split_once('/') {
+            Some(("blobs", digest)) if !digest.starts_with("uploads") && digest != "_exists" => {
+                Some(digest)
+            }
+            Some(("manifests", reference)) if reference.contains(':') => Some(reference),
+            _ => None,
+        };
+        if let Some(digest) = digest {
+            let canonical = policy.canonicalize(digest)?;
+            if canonical != digest {
+                let endpoint = rest.split_once('/').map(|(e, _)| e).unwrap_or_default();
+                path = format!("/v2/{}/{}/{}", name, endpoint, canonical);
+                changed = true;
+            }
+        }
+    }
+
+    let mut query = uri.query().map(str::to_string);
+    if let Some(raw) = uri.query() {
+        let mut pairs = Vec::new();
+        for (key, value) in form_urlencoded::parse(raw.as_bytes()) {
+            let value = if key == "digest" {
+                let canonical = policy.canonicalize(&value)?;
+                changed |= canonical != value;
+                canonical
+            } else {
+                value.into_owned()
+            };
+            pairs.push((key.into_owned(), value));
+        }
+        if changed {
+            query = Some(
+                form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(pairs)
+                    .finish(),
+            );
+        }
+    }
+
+    if !changed {
+        return Ok(None);
+    }
+    let path_and_query = match query {
+        Some(query) => format!("{}?{}", path, query),
+        None => path,
+    };
+    Ok(path_and_query.parse().ok())
+}
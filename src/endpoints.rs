@@ -0,0 +1,92 @@
+//! Optional registry API endpoints that can be switched off.
+//!
+//! Minimal registries leave out parts of the distribution API, and clients
+//! probe for them: a missing referrers API means falling back to tag
+//! schemes, an ignored mount means uploading the blob. Disabling endpoints
+//! here reproduces those registries.
+
+use crate::server::{error_response, split_repository_path};
+use axum::{
+    extract::{Request, State},
+    http::{Method, StatusCode, Uri},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+/// An optional part of the registry API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApiEndpoint {
+    /// `GET /v2/_catalog`; disabled, it answers `405 UNSUPPORTED`.
+    Catalog,
+    /// `GET /v2/<name>/tags/list`; disabled, it answers `404`.
+    TagList,
+    /// `GET /v2/<name>/referrers/<digest>`; disabled, it answers `404`,
+    /// which OCI clients take as the cue to use the referrers tag schema.
+    Referrers,
+    /// `DELETE` on manifests and blobs; disabled, it answers
+    /// `405 UNSUPPORTED`.
+    Deletes,
+    /// `mount`/`from` on `POST /v2/<name>/blobs/uploads/`; disabled, the
+    /// parameters are ignored and a regular upload session is opened, as
+    /// the distribution spec allows.
+    BlobMount,
+}
+
+/// Applies the disabled endpoints handled per request: tag list, referrers
+/// and blob mount. Catalog and deletes are switched where routes are built.
+pub(crate) async fn disable_endpoints(
+    State(disabled): State<Arc<Vec<ApiEndpoint>>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some((_, rest)) = split_repository_path(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let endpoint = if rest == "tags/list" {
+        Some(ApiEndpoint::TagList)
+    } else if rest.starts_with("referrers/") {
+        Some(ApiEndpoint::Referrers)
+    } else if rest == "blobs/uploads/" && request.method() == Method::POST {
+        Some(ApiEndpoint::BlobMount)
+    } else {
+        None
+    };
+    match endpoint {
+        Some(endpoint) if disabled.contains(&endpoint) => {}
+        _ => return next.run(request).await,
+    }
+
+    if endpoint == Some(ApiEndpoint::BlobMount) {
+        *request.uri_mut() = without_mount(request.uri());
+        return next.run(request).await;
+    }
+    error_response(
+        StatusCode::NOT_FOUND,
+        "UNSUPPORTED",
+        "the operation is unsupported",
+    )
+}
+
+/// Drops the `mount` and `from` parameters from an upload URI.
+fn without_mount(uri: &Uri) -> Uri {
+    let Some(query) = uri.query() else {
+        return uri.clone();
+    };
+    let kept: Vec<(String, String)> = form_urlencoded::parse(query.as_bytes())
+        .filter(|(key, _)| key != "mount" && key != "from")
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    let mut path_and_query = uri.path().to_string();
+    if !kept.is_empty() {
+        path_and_query.push('?');
+        path_and_query.push_str(
+            &form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(kept)
+                .finish(),
+        );
+    }
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = path_and_query.parse().ok();
+    Uri::from_parts(parts).unwrap_or_else(|_| uri.clone())
+}
@@ -1,5 +1,8 @@
 //! Error types for the registry.
 
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// Result type alias for registry operations.
@@ -13,4 +16,181 @@ pub enum RegistryError {
 
     #[error("Upload not found: {0}")]
     UploadNotFound(String),
+
+    #[error("Manifest not found: {0}")]
+    ManifestNotFound(String),
+
+    #[error("Repository not found: {0}")]
+    RepositoryNotFound(String),
+
+    #[error("Blob not found: {0}")]
+    BlobNotFound(String),
+
+    #[error("Invalid manifest: {0}")]
+    InvalidManifest(String),
+
+    #[error("Manifest references unknown blob: {0}")]
+    ManifestBlobUnknown(String),
+
+    #[error("Invalid digest: {0}")]
+    DigestInvalid(String),
+
+    #[error("Invalid repository name: {0}")]
+    NameInvalid(String),
+
+    #[error("Access denied: {0}")]
+    Denied(String),
+
+    #[error("Authentication required: {0}")]
+    Unauthorized(String),
+
+    #[error("Content too large: {0}")]
+    TooLarge(String),
+
+    #[error("Storage backend error: {0}")]
+    StorageBackend(String),
+
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Unexpected status {status} from {url}")]
+    UnexpectedStatus { status: u16, url: String },
+
+    #[error("Port {port} is already in use")]
+    AddrInUse { port: u16 },
+}
+
+impl RegistryError {
+    /// Returns the HTTP status a registry answers this error with.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::UploadNotFound(_)
+            | Self::ManifestNotFound(_)
+            | Self::RepositoryNotFound(_)
+            | Self::BlobNotFound(_) => StatusCode::NOT_FOUND,
+            Self::InvalidManifest(_)
+            | Self::ManifestBlobUnknown(_)
+            | Self::DigestInvalid(_)
+            | Self::NameInvalid(_) => StatusCode::BAD_REQUEST,
+            Self::Denied(_) => StatusCode::FORBIDDEN,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Io(_)
+            | Self::Http(_)
+            | Self::Json(_)
+            | Self::UnexpectedStatus { .. }
+            | Self::AddrInUse { .. }
+            | Self::StorageBackend(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Returns the distribution-spec error code, such as `BLOB_UNKNOWN`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UploadNotFound(_) => "BLOB_UPLOAD_UNKNOWN",
+            Self::ManifestNotFound(_) => "MANIFEST_UNKNOWN",
+            Self::RepositoryNotFound(_) => "NAME_UNKNOWN",
+            Self::BlobNotFound(_) => "BLOB_UNKNOWN",
+            Self::InvalidManifest(_) => "MANIFEST_INVALID",
+            Self::ManifestBlobUnknown(_) => "MANIFEST_BLOB_UNKNOWN",
+            Self::DigestInvalid(_) => "DIGEST_INVALID",
+            Self::NameInvalid(_) => "NAME_INVALID",
+            Self::Denied(_) => "DENIED",
+            Self::Unauthorized(_) => "UNAUTHORIZED",
+            Self::TooLarge(_) => "SIZE_INVALID",
+            Self::Io(_)
+            | Self::Http(_)
+            | Self::Json(_)
+            | Self::UnexpectedStatus { .. }
+            | Self::AddrInUse { .. }
+            | Self::StorageBackend(_) => "UNKNOWN",
+        }
+    }
+}
+
+/// Answers with the error's status and a distribution-spec error body.
+impl IntoResponse for RegistryError {
+    fn into_response(self) -> Response {
+        ErrorResponse::new(self.status_code(), self.code(), self.to_string()).into_response()
+    }
+}
+
+/// A distribution-spec error response: a status and a body of the form
+/// `{"errors":[{"code":..,"message":..,"detail":..}]}`.
+///
+/// Every error the registry answers with goes through this type, so
+/// clients parsing `errors[]` see the same shape from every endpoint.
+///
+/// # Examples
+///
+/// ```
+/// use axum::http::StatusCode;
+/// use registry_testkit::error::ErrorResponse;
+///
+/// let response = ErrorResponse::new(StatusCode::NOT_FOUND, "BLOB_UNKNOWN", "blob unknown")
+///     .with_detail(serde_json::json!({ "digest": "sha256:abc" }));
+/// assert_eq!(response.body.errors[0].code, "BLOB_UNKNOWN");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ErrorResponse {
+    /// HTTP status of the response.
+    pub status: StatusCode,
+    /// JSON body of the response.
+    pub body: ErrorBody,
+}
+
+/// Body of an error response, as clients parse it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub errors: Vec<ErrorInfo>,
+}
+
+/// One entry of an error body's `errors` array.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorInfo {
+    /// Distribution-spec error code, such as `MANIFEST_UNKNOWN`.
+    pub code: String,
+    /// Human-readable description.
+    pub message: String,
+    /// Unstructured context, left out of the body when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<serde_json::Value>,
+}
+
+impl ErrorResponse {
+    /// An error response with a single error entry.
+    pub fn new(status: StatusCode, code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: ErrorBody {
+                errors: vec![ErrorInfo {
+                    code: code.into(),
+                    message: message.into(),
+                    detail: None,
+                }],
+            },
+        }
+    }
+
+    /// Sets the detail of the error entries.
+    pub fn with_detail(mut self, detail: serde_json::Value) -> Self {
+        for error in &mut self.body.errors {
+            error.detail = Some(detail.clone());
+        }
+        self
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            [("Content-Type", "application/json; charset=utf-8")],
+            serde_json::to_string(&self.body).unwrap_or_default(),
+        )
+            .into_response()
+    }
 }
@@ -0,0 +1,40 @@
+//! Events emitted by the registry.
+
+use crate::maintenance::MaintenanceReport;
+
+/// Something the registry did on its own, delivered to
+/// [`RegistryServer::subscribe`](crate::RegistryServer::subscribe) receivers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RegistryEvent {
+    /// A retention rule deleted a tag.
+    TagExpired {
+        /// Repository the tag belonged to.
+        repository: String,
+        /// The deleted tag.
+        tag: String,
+    },
+    /// A retention run finished.
+    RetentionApplied {
+        /// Number of tags deleted by the run.
+        tags_deleted: usize,
+    },
+    /// A scheduled maintenance run finished.
+    MaintenanceCompleted(MaintenanceReport),
+    /// A push was copied to the replication target.
+    Replicated {
+        /// Repository of the push.
+        repository: String,
+        /// Tag or digest the manifest was pushed under.
+        reference: String,
+    },
+    /// A push could not be copied to the replication target.
+    ReplicationFailed {
+        /// Repository of the push.
+        repository: String,
+        /// Tag or digest the manifest was pushed under.
+        reference: String,
+        /// What went wrong.
+        error: String,
+    },
+}
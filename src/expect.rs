@@ -0,0 +1,50 @@
+//! `Expect` request header handling.
+//!
+//! hyper answers `Expect: 100-continue` with `100 Continue` as soon as a
+//! handler starts reading the body. Requests that are rejected without
+//! reading it (authentication, quotas, unknown upload sessions) therefore
+//! get their final status instead, so clients never upload a body that is
+//! going to be discarded. This layer covers the cases that can be decided
+//! from the headers alone.
+
+use crate::config::MAX_BODY_SIZE;
+use crate::server::error_response;
+use axum::{
+    extract::Request,
+    http::{header, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use tracing::debug;
+
+/// Rejects unsupported expectations with `417` and oversized bodies
+/// announced with `100-continue` with `413`, before any body is read.
+pub(crate) async fn check_expectation(request: Request, next: Next) -> Response {
+    let Some(expect) = request.headers().get(header::EXPECT) else {
+        return next.run(request).await;
+    };
+    if !expect.as_bytes().eq_ignore_ascii_case(b"100-continue") {
+        debug!("Unsupported expectation: {:?}", expect);
+        return error_response(
+            StatusCode::EXPECTATION_FAILED,
+            "UNSUPPORTED",
+            "only 100-continue expectations are supported",
+        );
+    }
+
+    let announced = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if announced.is_some_and(|length| length > MAX_BODY_SIZE as u64) {
+        debug!("Rejecting announced body of {:?} bytes", announced);
+        return error_response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "SIZE_INVALID",
+            "announced content is too large",
+        );
+    }
+
+    next.run(request).await
+}
@@ -0,0 +1,510 @@
+//! Fault injection for exercising client error handling.
+
+use crate::error::{RegistryError, Result};
+use crate::storage::{is_tag_key, ManifestEntry, Storage};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Faults the server injects into its responses.
+#[derive(Debug, Clone, Default)]
+pub struct FaultConfig {
+    /// Window during which an overwritten tag keeps serving its previous
+    /// manifest, like a CDN cache in front of the registry.
+    pub stale_reads: Option<Duration>,
+    /// Failures, delays and corruption injected below the HTTP layer, into
+    /// calls the server makes to its storage backend.
+    pub storage: Vec<StorageFault>,
+}
+
+impl FaultConfig {
+    /// Creates a configuration with no faults enabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves the previous manifest of an overwritten tag for `window`.
+    pub fn with_stale_reads(mut self, window: Duration) -> Self {
+        self.stale_reads = Some(window);
+        self
+    }
+
+    /// Injects `fault` into the storage backend, see [`FlakyStorage`].
+    pub fn with_storage_fault(mut self, fault: StorageFault) -> Self {
+        self.storage.push(fault);
+        self
+    }
+}
+
+struct StaleEntry {
+    previous: ManifestEntry,
+    until: Instant,
+}
+
+/// Storage decorator serving the previous version of overwritten tags.
+///
+/// Lookups by digest are never stale, so clients that pin digests see the
+/// new content while clients that resolve tags may not.
+pub struct StaleReadStorage {
+    inner: Arc<dyn Storage>,
+    window: Duration,
+    stale: RwLock<HashMap<String, StaleEntry>>,
+}
+
+impl StaleReadStorage {
+    /// Wraps `inner`, serving stale tags for `window` after an overwrite.
+    pub fn new(inner: Arc<dyn Storage>, window: Duration) -> Self {
+        Self {
+            inner,
+            window,
+            stale: RwLock::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for StaleReadStorage {
+    async fn store_manifest(&self, key: String, entry: ManifestEntry) -> Result<()> {
+        if is_tag_key(&key) {
+            if let Some(previous) = self.inner.get_manifest(&key).await? {
+                if previous.data != entry.data {
+                    let stale = StaleEntry {
+                        previous,
+                        until: Instant::now() + self.window,
+                    };
+                    self.stale.write().await.insert(key.clone(), stale);
+                }
+            }
+        }
+        self.inner.store_manifest(key, entry).await
+    }
+
+    async fn get_manifest(&self, key: &str) -> Result<Option<ManifestEntry>> {
+        if let Some(stale) = self.stale.read().await.get(key) {
+            if Instant::now() < stale.until {
+                return Ok(Some(stale.previous.clone()));
+            }
+        }
+        self.inner.get_manifest(key).await
+    }
+
+    async fn delete_manifest(&self, key: &str) -> Result<bool> {
+        self.stale.write().await.remove(key);
+        self.inner.delete_manifest(key).await
+    }
+
+    async fn list_manifests(&self) -> Result<Vec<String>> {
+        self.inner.list_manifests().await
+    }
+
+    async fn list_repositories(&self) -> Result<Vec<String>> {
+        self.inner.list_repositories().await
+    }
+
+    async fn list_tags(&self, repository: &str) -> Result<Vec<String>> {
+        self.inner.list_tags(repository).await
+    }
+
+    async fn list_referrers(&self, repository: &str, subject: &str) -> Result<Vec<String>> {
+        self.inner.list_referrers(repository, subject).await
+    }
+
+    async fn store_blob(&self, digest: String, data: Vec<u8>) -> Result<()> {
+        self.inner.store_blob(digest, data).await
+    }
+
+    async fn get_blob(&self, digest: &str) -> Result<Option<Vec<u8>>> {
+        self.inner.get_blob(digest).await
+    }
+
+    async fn delete_blob(&self, digest: &str) -> Result<bool> {
+        self.inner.delete_blob(digest).await
+    }
+
+    async fn list_blobs(&self) -> Result<Vec<String>> {
+        self.inner.list_blobs().await
+    }
+
+    async fn blobs_exist(&self, digests: &[String]) -> Result<Vec<bool>> {
+        self.inner.blobs_exist(digests).await
+    }
+
+    async fn create_upload(&self, uuid: String) -> Result<()> {
+        self.inner.create_upload(uuid).await
+    }
+
+    async fn append_upload(&self, uuid: &str, data: &[u8]) -> Result<()> {
+        self.inner.append_upload(uuid, data).await
+    }
+
+    async fn upload_size(&self, uuid: &str) -> Result<Option<u64>> {
+        self.inner.upload_size(uuid).await
+    }
+
+    async fn finish_upload(&self, uuid: &str) -> Result<Option<Vec<u8>>> {
+        self.inner.finish_upload(uuid).await
+    }
+
+    async fn delete_upload(&self, uuid: &str) -> Result<bool> {
+        self.inner.delete_upload(uuid).await
+    }
+
+    async fn simulate_crash(&self) -> Result<()> {
+        self.inner.simulate_crash().await
+    }
+}
+
+/// A method of the [`Storage`] trait.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StorageMethod {
+    /// [`Storage::store_manifest`].
+    StoreManifest,
+    /// [`Storage::get_manifest`].
+    GetManifest,
+    /// [`Storage::delete_manifest`].
+    DeleteManifest,
+    /// [`Storage::list_manifests`].
+    ListManifests,
+    /// [`Storage::list_repositories`].
+    ListRepositories,
+    /// [`Storage::list_tags`].
+    ListTags,
+    /// [`Storage::list_referrers`].
+    ListReferrers,
+    /// [`Storage::store_blob`].
+    StoreBlob,
+    /// [`Storage::get_blob`].
+    GetBlob,
+    /// [`Storage::delete_blob`].
+    DeleteBlob,
+    /// [`Storage::list_blobs`].
+    ListBlobs,
+    /// [`Storage::blobs_exist`].
+    BlobsExist,
+    /// [`Storage::create_upload`].
+    CreateUpload,
+    /// [`Storage::append_upload`].
+    AppendUpload,
+    /// [`Storage::upload_size`].
+    UploadSize,
+    /// [`Storage::finish_upload`].
+    FinishUpload,
+    /// [`Storage::delete_upload`].
+    DeleteUpload,
+}
+
+impl fmt::Display for StorageMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::StoreManifest => "store_manifest",
+            Self::GetManifest => "get_manifest",
+            Self::DeleteManifest => "delete_manifest",
+            Self::ListManifests => "list_manifests",
+            Self::ListRepositories => "list_repositories",
+            Self::ListTags => "list_tags",
+            Self::ListReferrers => "list_referrers",
+            Self::StoreBlob => "store_blob",
+            Self::GetBlob => "get_blob",
+            Self::DeleteBlob => "delete_blob",
+            Self::ListBlobs => "list_blobs",
+            Self::BlobsExist => "blobs_exist",
+            Self::CreateUpload => "create_upload",
+            Self::AppendUpload => "append_upload",
+            Self::UploadSize => "upload_size",
+            Self::FinishUpload => "finish_upload",
+            Self::DeleteUpload => "delete_upload",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A failure, delay or corruption injected into calls to one storage
+/// method.
+///
+/// Calls are counted per method from the start of the server. By default a
+/// fault applies to every call; [`after`](Self::after) and
+/// [`times`](Self::times) narrow it to a window of calls.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::faults::{StorageFault, StorageMethod};
+/// use registry_testkit::{FaultConfig, RegistryConfig};
+/// use std::time::Duration;
+///
+/// // The third blob read fails; every manifest write takes 200ms.
+/// let faults = FaultConfig::new()
+///     .with_storage_fault(StorageFault::fail(StorageMethod::GetBlob).after(2).times(1))
+///     .with_storage_fault(StorageFault::delay(
+///         StorageMethod::StoreManifest,
+///         Duration::from_millis(200),
+///     ));
+/// let config = RegistryConfig::memory().with_faults(faults);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageFault {
+    /// Method the fault applies to.
+    pub method: StorageMethod,
+    /// Error message returned instead of calling the backend, or `None` to
+    /// only delay.
+    pub error: Option<String>,
+    /// Delay before the call proceeds or fails.
+    pub delay: Option<Duration>,
+    /// Flips a byte of the content written, letting the call succeed.
+    pub corrupt: bool,
+    /// Number of calls that pass before the fault applies.
+    pub skip: u64,
+    /// Number of calls the fault applies to, or `None` for all of them.
+    pub times: Option<u64>,
+}
+
+impl StorageFault {
+    /// Fails every call to `method` with a storage backend error.
+    pub fn fail(method: StorageMethod) -> Self {
+        Self {
+            method,
+            error: Some(format!("injected {} failure", method)),
+            delay: None,
+            corrupt: false,
+            skip: 0,
+            times: None,
+        }
+    }
+
+    /// Delays every call to `method` by `delay`.
+    pub fn delay(method: StorageMethod, delay: Duration) -> Self {
+        Self {
+            method,
+            error: None,
+            delay: Some(delay),
+            corrupt: false,
+            skip: 0,
+            times: None,
+        }
+    }
+
+    /// Corrupts the content of every call to `method` while reporting
+    /// success, so the content served back no longer matches its digest.
+    ///
+    /// Applies to [`StorageMethod::StoreBlob`], which stores finished
+    /// uploads, and [`StorageMethod::StoreManifest`]; other methods write
+    /// no content.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use registry_testkit::faults::{StorageFault, StorageMethod};
+    /// use registry_testkit::{FaultConfig, RegistryConfig};
+    ///
+    /// // The first pushed blob is stored corrupted.
+    /// let faults = FaultConfig::new()
+    ///     .with_storage_fault(StorageFault::corrupt(StorageMethod::StoreBlob).times(1));
+    /// let config = RegistryConfig::memory().with_faults(faults);
+    /// ```
+    pub fn corrupt(method: StorageMethod) -> Self {
+        Self {
+            method,
+            error: None,
+            delay: None,
+            corrupt: true,
+            skip: 0,
+            times: None,
+        }
+    }
+
+    /// Sets the error message of a failure.
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.error = Some(message.into());
+        self
+    }
+
+    /// Also delays the call by `delay` before it fails.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Lets the first `calls` calls through untouched.
+    pub fn after(mut self, calls: u64) -> Self {
+        self.skip = calls;
+        self
+    }
+
+    /// Applies the fault to `calls` calls only.
+    pub fn times(mut self, calls: u64) -> Self {
+        self.times = Some(calls);
+        self
+    }
+
+    fn applies_to(&self, call: u64) -> bool {
+        call >= self.skip && self.times.is_none_or(|times| call - self.skip < times)
+    }
+}
+
+/// Storage decorator injecting [`StorageFault`]s.
+///
+/// Failures surface as [`RegistryError::StorageBackend`], exactly like an
+/// error from a real backend, so tests see what clients get when the
+/// registry's storage is down rather than a simulated HTTP response.
+pub struct FlakyStorage {
+    inner: Arc<dyn Storage>,
+    faults: Vec<StorageFault>,
+    calls: Mutex<HashMap<StorageMethod, u64>>,
+}
+
+impl FlakyStorage {
+    /// Wraps `inner`, injecting `faults`.
+    pub fn new(inner: Arc<dyn Storage>, faults: Vec<StorageFault>) -> Self {
+        Self {
+            inner,
+            faults,
+            calls: Mutex::default(),
+        }
+    }
+
+    /// Returns how many times `method` has been called.
+    pub fn calls(&self, method: StorageMethod) -> u64 {
+        self.calls
+            .lock()
+            .unwrap()
+            .get(&method)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Counts a call to `method` and applies the faults matching it.
+    /// Returns whether the content the call writes is to be corrupted.
+    async fn inject(&self, method: StorageMethod) -> Result<bool> {
+        let call = {
+            let mut calls = self.calls.lock().unwrap();
+            let count = calls.entry(method).or_insert(0);
+            *count += 1;
+            *count - 1
+        };
+        let mut error = None;
+        let mut corrupt = false;
+        for fault in &self.faults {
+            if fault.method != method || !fault.applies_to(call) {
+                continue;
+            }
+            if let Some(delay) = fault.delay {
+                tokio::time::sleep(delay).await;
+            }
+            if error.is_none() {
+                error = fault.error.clone();
+            }
+            corrupt |= fault.corrupt;
+        }
+        match error {
+            Some(message) => Err(RegistryError::StorageBackend(message)),
+            None => Ok(corrupt),
+        }
+    }
+}
+
+/// Flips the bits of the middle byte of `data`, or adds one if it is empty.
+fn corrupt(data: &mut Vec<u8>) {
+    match data.len() {
+        0 => data.push(0xff),
+        len => data[len / 2] ^= 0xff,
+    }
+}
+
+#[async_trait]
+impl Storage for FlakyStorage {
+    async fn store_manifest(&self, key: String, mut entry: ManifestEntry) -> Result<()> {
+        if self.inject(StorageMethod::StoreManifest).await? {
+            corrupt(&mut entry.data);
+        }
+        self.inner.store_manifest(key, entry).await
+    }
+
+    async fn get_manifest(&self, key: &str) -> Result<Option<ManifestEntry>> {
+        self.inject(StorageMethod::GetManifest).await?;
+        self.inner.get_manifest(key).await
+    }
+
+    async fn delete_manifest(&self, key: &str) -> Result<bool> {
+        self.inject(StorageMethod::DeleteManifest).await?;
+        self.inner.delete_manifest(key).await
+    }
+
+    async fn list_manifests(&self) -> Result<Vec<String>> {
+        self.inject(StorageMethod::ListManifests).await?;
+        self.inner.list_manifests().await
+    }
+
+    async fn list_repositories(&self) -> Result<Vec<String>> {
+        self.inject(StorageMethod::ListRepositories).await?;
+        self.inner.list_repositories().await
+    }
+
+    async fn list_tags(&self, repository: &str) -> Result<Vec<String>> {
+        self.inject(StorageMethod::ListTags).await?;
+        self.inner.list_tags(repository).await
+    }
+
+    async fn list_referrers(&self, repository: &str, subject: &str) -> Result<Vec<String>> {
+        self.inject(StorageMethod::ListReferrers).await?;
+        self.inner.list_referrers(repository, subject).await
+    }
+
+    async fn store_blob(&self, digest: String, mut data: Vec<u8>) -> Result<()> {
+        if self.inject(StorageMethod::StoreBlob).await? {
+            corrupt(&mut data);
+        }
+        self.inner.store_blob(digest, data).await
+    }
+
+    async fn get_blob(&self, digest: &str) -> Result<Option<Vec<u8>>> {
+        self.inject(StorageMethod::GetBlob).await?;
+        self.inner.get_blob(digest).await
+    }
+
+    async fn delete_blob(&self, digest: &str) -> Result<bool> {
+        self.inject(StorageMethod::DeleteBlob).await?;
+        self.inner.delete_blob(digest).await
+    }
+
+    async fn list_blobs(&self) -> Result<Vec<String>> {
+        self.inject(StorageMethod::ListBlobs).await?;
+        self.inner.list_blobs().await
+    }
+
+    async fn blobs_exist(&self, digests: &[String]) -> Result<Vec<bool>> {
+        self.inject(StorageMethod::BlobsExist).await?;
+        self.inner.blobs_exist(digests).await
+    }
+
+    async fn create_upload(&self, uuid: String) -> Result<()> {
+        self.inject(StorageMethod::CreateUpload).await?;
+        self.inner.create_upload(uuid).await
+    }
+
+    async fn append_upload(&self, uuid: &str, data: &[u8]) -> Result<()> {
+        self.inject(StorageMethod::AppendUpload).await?;
+        self.inner.append_upload(uuid, data).await
+    }
+
+    async fn upload_size(&self, uuid: &str) -> Result<Option<u64>> {
+        self.inject(StorageMethod::UploadSize).await?;
+        self.inner.upload_size(uuid).await
+    }
+
+    async fn finish_upload(&self, uuid: &str) -> Result<Option<Vec<u8>>> {
+        self.inject(StorageMethod::FinishUpload).await?;
+        self.inner.finish_upload(uuid).await
+    }
+
+    async fn delete_upload(&self, uuid: &str) -> Result<bool> {
+        self.inject(StorageMethod::DeleteUpload).await?;
+        self.inner.delete_upload(uuid).await
+    }
+
+    async fn simulate_crash(&self) -> Result<()> {
+        self.inner.simulate_crash().await
+    }
+}
@@ -0,0 +1,108 @@
+//! Namespace-based routing to separate backends and upstream registries.
+
+use crate::config::StorageBackend;
+use crate::server::split_repository_path;
+use crate::upstream::UpstreamConfig;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, Extensions, HeaderValue, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Router,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+/// Where repositories under a namespace prefix are served from.
+#[derive(Debug, Clone)]
+pub enum NamespaceTarget {
+    /// A storage backend of their own; repository names are kept as is.
+    Storage(StorageBackend),
+    /// A pull-through proxy of an upstream registry. The prefix is stripped
+    /// before asking the upstream, so `docker.io/library/alpine` is fetched
+    /// as `library/alpine`. Content is kept in memory when the upstream
+    /// [caches](UpstreamConfig::with_cache).
+    Upstream(UpstreamConfig),
+}
+
+/// Repositories whose names start with `prefix` are served by `target`.
+#[derive(Debug, Clone)]
+pub struct NamespaceRoute {
+    /// Name prefix, such as `internal/` or `docker.io/`.
+    pub prefix: String,
+    /// Backend serving the namespace.
+    pub target: NamespaceTarget,
+}
+
+/// A namespace with the routes serving it.
+pub(crate) struct Namespace {
+    pub prefix: String,
+    /// Whether the prefix is stripped before routing.
+    pub strip: bool,
+    pub router: Router,
+}
+
+/// Dispatches `/v2/` requests to the namespace with the longest matching
+/// prefix, or to the default routes when none matches.
+pub(crate) async fn route_namespace(
+    State(namespaces): State<Arc<Vec<Namespace>>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    let Some((name, rest)) = split_repository_path(&path) else {
+        return next.run(request).await;
+    };
+    let Some(namespace) = namespaces
+        .iter()
+        .filter(|namespace| name.starts_with(&namespace.prefix))
+        .max_by_key(|namespace| namespace.prefix.len())
+    else {
+        return next.run(request).await;
+    };
+
+    if !namespace.strip {
+        return forward(&namespace.router, request).await;
+    }
+
+    let inner = &name[namespace.prefix.len()..];
+    let mut target = format!("/v2/{}/{}", inner, rest);
+    if let Some(query) = request.uri().query() {
+        target = format!("{}?{}", target, query);
+    }
+    match target.parse::<Uri>() {
+        Ok(uri) => *request.uri_mut() = uri,
+        Err(_) => return next.run(request).await,
+    }
+
+    let mut response = forward(&namespace.router, request).await;
+    // Keep follow-up requests (upload sessions) inside the namespace.
+    let location = response
+        .headers()
+        .get(header::LOCATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix(&format!("/v2/{}/", inner)))
+        .map(|tail| format!("/v2/{}/{}", name, tail));
+    if let Some(value) = location.and_then(|l| HeaderValue::from_str(&l).ok()) {
+        response.headers_mut().insert(header::LOCATION, value);
+    }
+    response
+}
+
+/// Runs `request` through a namespace's routes. Extensions are reset so the
+/// outer router's path parameters don't leak into the inner match.
+async fn forward(router: &Router, request: Request) -> Response {
+    let (mut parts, body) = request.into_parts();
+    let connect_info = parts.extensions.get::<ConnectInfo<SocketAddr>>().copied();
+    parts.extensions = Extensions::new();
+    if let Some(connect_info) = connect_info {
+        parts.extensions.insert(connect_info);
+    }
+    let request = Request::from_parts(parts, body);
+    match router.clone().oneshot(request).await {
+        Ok(response) => response,
+        Err(infallible) => match infallible {},
+    }
+    .into_response()
+}
@@ -0,0 +1,53 @@
+//! Non-distributable ("foreign") layers.
+
+/// Media type of Docker foreign layers, used by Windows base images.
+pub const DOCKER_FOREIGN_LAYER_MEDIA_TYPE: &str =
+    "application/vnd.docker.image.rootfs.foreign.diff.tar.gzip";
+/// Media type of OCI non-distributable layers.
+pub const OCI_NONDISTRIBUTABLE_LAYER_MEDIA_TYPE: &str =
+    "application/vnd.oci.image.layer.nondistributable.v1.tar+gzip";
+
+/// How blob requests for foreign layers are answered.
+///
+/// A layer is foreign when a pushed manifest lists it with a foreign or
+/// non-distributable media type; its descriptor's `urls` say where clients
+/// should download it from instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ForeignLayerPolicy {
+    /// Serve the blob like any other if it was pushed.
+    #[default]
+    Serve,
+    /// Answer `404 BLOB_UNKNOWN` even if the blob was pushed, as registries
+    /// that refuse to host non-distributable content do.
+    NotFound,
+    /// Answer `307 Temporary Redirect` to the first URL of the descriptor.
+    Redirect,
+}
+
+/// Returns whether `media_type` marks a layer as non-distributable.
+pub(crate) fn is_foreign(media_type: &str) -> bool {
+    media_type.contains(".foreign.") || media_type.contains(".nondistributable.")
+}
+
+/// Foreign layers of a manifest, as `(digest, urls)` pairs.
+pub(crate) fn foreign_layers(manifest: &[u8]) -> Vec<(String, Vec<String>)> {
+    let Ok(manifest) = serde_json::from_slice::<serde_json::Value>(manifest) else {
+        return Vec::new();
+    };
+    manifest["layers"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|layer| layer["mediaType"].as_str().is_some_and(is_foreign))
+        .filter_map(|layer| {
+            let digest = layer["digest"].as_str()?.to_string();
+            let urls = layer["urls"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|url| url.as_str().map(str::to_string))
+                .collect();
+            Some((digest, urls))
+        })
+        .collect()
+}
@@ -0,0 +1,150 @@
+//! Garbage collection planning.
+//!
+//! A collection keeps every manifest reachable from a tag (directly, as a
+//! child of a tagged index, or as a referrer of a kept manifest) and every
+//! blob those manifests reference. Everything else is garbage.
+
+use crate::digest::sha256_digest;
+use crate::error::Result;
+use crate::storage::{is_tag_key, Storage};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// What a garbage collection would remove.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GcReport {
+    /// Unreachable manifests, as `repository@digest`.
+    pub manifests: BTreeSet<String>,
+    /// Unreferenced blob digests.
+    pub blobs: BTreeSet<String>,
+    /// Tombstones of soft-deleted manifests, as `repository:reference`.
+    pub tombstones: BTreeSet<String>,
+    /// Total size of the listed manifests and blobs.
+    pub bytes_reclaimed: u64,
+}
+
+impl GcReport {
+    /// Returns whether the collection would remove nothing.
+    pub fn is_empty(&self) -> bool {
+        self.manifests.is_empty() && self.blobs.is_empty() && self.tombstones.is_empty()
+    }
+}
+
+/// Digests a manifest references: child manifests, referenced blobs, and
+/// the subject it refers to.
+#[derive(Default)]
+struct References {
+    manifests: Vec<String>,
+    blobs: Vec<String>,
+    subject: Option<String>,
+}
+
+fn references(data: &[u8]) -> References {
+    let Ok(manifest) = serde_json::from_slice::<serde_json::Value>(data) else {
+        return References::default();
+    };
+    let digests = |field: &str| -> Vec<String> {
+        manifest[field]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|descriptor| descriptor["digest"].as_str())
+            .map(str::to_string)
+            .collect()
+    };
+
+    let mut blobs = digests("layers");
+    blobs.extend(digests("blobs"));
+    if let Some(config) = manifest["config"]["digest"].as_str() {
+        blobs.push(config.to_string());
+    }
+
+    References {
+        manifests: digests("manifests"),
+        blobs,
+        subject: manifest["subject"]["digest"].as_str().map(str::to_string),
+    }
+}
+
+/// Returns the digests of the blobs a manifest references.
+pub(crate) fn blob_references(data: &[u8]) -> Vec<String> {
+    references(data).blobs
+}
+
+/// Computes what a garbage collection of `storage` would remove.
+pub(crate) async fn plan(storage: &dyn Storage) -> Result<GcReport> {
+    // Manifests per repository, keyed by digest, plus the digests of tags.
+    let mut repositories: HashMap<String, HashMap<String, Vec<u8>>> = HashMap::new();
+    let mut roots: Vec<(String, String)> = Vec::new();
+    for key in storage.list_manifests().await? {
+        let Some((repository, reference)) = key.split_once(':') else {
+            continue;
+        };
+        let Some(entry) = storage.get_manifest(&key).await? else {
+            continue;
+        };
+        if is_tag_key(&key) {
+            roots.push((repository.to_string(), sha256_digest(&entry.data)));
+        } else {
+            repositories
+                .entry(repository.to_string())
+                .or_default()
+                .insert(reference.to_string(), entry.data);
+        }
+    }
+
+    let mut kept: HashSet<(String, String)> = HashSet::new();
+    let mut kept_blobs: HashSet<String> = HashSet::new();
+    let mut queue = roots;
+    loop {
+        while let Some((repository, digest)) = queue.pop() {
+            if !kept.insert((repository.clone(), digest.clone())) {
+                continue;
+            }
+            let Some(data) = repositories.get(&repository).and_then(|m| m.get(&digest)) else {
+                continue;
+            };
+            let refs = references(data);
+            kept_blobs.extend(refs.blobs);
+            queue.extend(refs.manifests.into_iter().map(|d| (repository.clone(), d)));
+        }
+
+        // Referrers of kept manifests are kept with them.
+        for (repository, manifests) in &repositories {
+            for (digest, data) in manifests {
+                let key = (repository.clone(), digest.clone());
+                if kept.contains(&key) {
+                    continue;
+                }
+                let subject = references(data).subject;
+                if subject.is_some_and(|s| kept.contains(&(repository.clone(), s))) {
+                    queue.push(key);
+                }
+            }
+        }
+        if queue.is_empty() {
+            break;
+        }
+    }
+
+    let mut report = GcReport::default();
+    for (repository, manifests) in &repositories {
+        for (digest, data) in manifests {
+            if !kept.contains(&(repository.clone(), digest.clone())) {
+                report
+                    .manifests
+                    .insert(format!("{}@{}", repository, digest));
+                report.bytes_reclaimed += data.len() as u64;
+            }
+        }
+    }
+    for digest in storage.list_blobs().await? {
+        if kept_blobs.contains(&digest) {
+            continue;
+        }
+        if let Some(blob) = storage.get_blob(&digest).await? {
+            report.bytes_reclaimed += blob.len() as u64;
+        }
+        report.blobs.insert(digest);
+    }
+    Ok(report)
+}
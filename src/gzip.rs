@@ -0,0 +1,30 @@
+//! Gzip helpers over `flate2`.
+//!
+//! Whole blobs are held in memory like everywhere else in storage.
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+/// Returns whether `data` starts with the gzip magic bytes.
+pub(crate) fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&[0x1f, 0x8b])
+}
+
+/// Decompresses every member of a gzip stream, checking their CRCs.
+pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    MultiGzDecoder::new(data)
+        .read_to_end(&mut out)
+        .map_err(|e| format!("invalid gzip stream: {}", e))?;
+    Ok(out)
+}
+
+/// Compresses `data` into a single gzip member.
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::with_capacity(data.len() / 2), Compression::default());
+    // Writing to a `Vec` cannot fail.
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
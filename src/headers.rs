@@ -0,0 +1,121 @@
+//! Response header quirks: casing and duplicated or joined values.
+//!
+//! HTTP header names are case-insensitive and a header sent twice means
+//! the same as one with comma-separated values, but clients that match
+//! names byte for byte or keep only the first value exist, and production
+//! registries differ in what they send. These quirks reproduce that.
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+/// Casing of response header names on HTTP/1.1 connections. HTTP/2 always
+/// sends lowercase names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeaderCasing {
+    /// `docker-content-digest`, as the server sends by default.
+    #[default]
+    Lowercase,
+    /// `Docker-Content-Digest`, as distribution and most proxies send.
+    TitleCase,
+}
+
+/// How response headers are written.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::{HeaderCasing, HeaderQuirks, RegistryConfig};
+///
+/// let config = RegistryConfig::memory().with_header_quirks(
+///     HeaderQuirks::new()
+///         .with_casing(HeaderCasing::TitleCase)
+///         .duplicate("docker-content-digest")
+///         .join("www-authenticate"),
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderQuirks {
+    pub(crate) casing: HeaderCasing,
+    duplicated: Vec<HeaderName>,
+    joined: Vec<HeaderName>,
+}
+
+impl HeaderQuirks {
+    /// Creates quirks that change nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the casing of header names.
+    pub fn with_casing(mut self, casing: HeaderCasing) -> Self {
+        self.casing = casing;
+        self
+    }
+
+    /// Sends every value of header `name` twice, as separate lines.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is not a valid header name.
+    pub fn duplicate(mut self, name: &str) -> Self {
+        self.duplicated.push(header_name(name));
+        self
+    }
+
+    /// Sends the values of header `name` as one comma-separated line
+    /// rather than one line each.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is not a valid header name.
+    pub fn join(mut self, name: &str) -> Self {
+        self.joined.push(header_name(name));
+        self
+    }
+
+    /// Returns whether the quirks change response headers, not just their
+    /// casing.
+    pub(crate) fn rewrites_headers(&self) -> bool {
+        !self.duplicated.is_empty() || !self.joined.is_empty()
+    }
+}
+
+fn header_name(name: &str) -> HeaderName {
+    HeaderName::from_bytes(name.as_bytes()).expect("invalid header name")
+}
+
+/// Duplicates and joins response headers as configured.
+pub(crate) async fn apply_header_quirks(
+    State(quirks): State<Arc<HeaderQuirks>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    for name in &quirks.joined {
+        let values: Vec<_> = headers
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .map(str::to_string)
+            .collect();
+        if values.len() < 2 {
+            continue;
+        }
+        if let Ok(value) = HeaderValue::from_str(&values.join(", ")) {
+            headers.insert(name.clone(), value);
+        }
+    }
+    for name in &quirks.duplicated {
+        let values: Vec<HeaderValue> = headers.get_all(name).iter().cloned().collect();
+        for value in values {
+            headers.append(name.clone(), value);
+        }
+    }
+    response
+}
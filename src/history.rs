@@ -0,0 +1,63 @@
+//! Tag history: which manifests a tag has pointed to over time.
+
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One manifest a tag pointed to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagRevision {
+    /// Digest of the manifest.
+    pub digest: String,
+    /// When the tag was moved to this manifest.
+    pub pushed_at: SystemTime,
+}
+
+/// JSON form served by the admin API.
+#[derive(Serialize)]
+pub(crate) struct RevisionBody {
+    digest: String,
+    #[serde(rename = "pushedAt")]
+    pushed_at: String,
+}
+
+impl From<&TagRevision> for RevisionBody {
+    fn from(revision: &TagRevision) -> Self {
+        Self {
+            digest: revision.digest.clone(),
+            pushed_at: rfc3339(revision.pushed_at),
+        }
+    }
+}
+
+/// Returns the `(year, month, day)` of a day counted from the Unix epoch
+/// (Howard Hinnant's civil-from-days algorithm).
+pub(crate) fn civil_date(days: u64) -> (i64, i64, i64) {
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp with millisecond precision.
+pub(crate) fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_date(days);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
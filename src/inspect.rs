@@ -0,0 +1,295 @@
+//! Structured views of stored images.
+
+use crate::error::{RegistryError, Result};
+use crate::oci::manifest::{ImageConfig, ImageManifest, Manifest};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// An image manifest and its config, parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInspect {
+    /// Digest of the manifest.
+    pub digest: String,
+    /// Media type of the manifest.
+    pub media_type: String,
+    /// Digest of the config blob.
+    pub config_digest: String,
+    /// Layers, bottom first.
+    pub layers: Vec<LayerInfo>,
+    /// Platform the image was built for.
+    pub platform: Platform,
+    /// Environment variables, as `KEY=value`.
+    pub env: Vec<String>,
+    /// Entrypoint (empty if unset).
+    pub entrypoint: Vec<String>,
+    /// Default command (empty if unset).
+    pub cmd: Vec<String>,
+    /// Image labels.
+    pub labels: BTreeMap<String, String>,
+}
+
+/// One layer of an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerInfo {
+    /// Digest of the layer blob.
+    pub digest: String,
+    /// Size of the layer blob in bytes.
+    pub size: u64,
+    /// Media type of the layer.
+    pub media_type: String,
+    /// Annotations of the layer descriptor, such as eStargz TOC digests.
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// Operating system and CPU architecture of an image.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Platform {
+    /// Operating system, such as `linux`.
+    pub os: String,
+    /// CPU architecture, such as `amd64`.
+    pub architecture: String,
+    /// CPU variant, such as `v8`.
+    pub variant: Option<String>,
+}
+
+impl ImageInspect {
+    /// Compares this image with `other`, taking this one as the base.
+    pub fn diff(&self, other: &ImageInspect) -> ImageDiff {
+        let mut diff = ImageDiff::default();
+        let shared = self.layers.len().min(other.layers.len());
+        for (index, (from, to)) in self.layers.iter().zip(&other.layers).enumerate() {
+            if from.digest != to.digest {
+                diff.changed.push(LayerChange {
+                    index,
+                    from: from.clone(),
+                    to: to.clone(),
+                });
+            }
+        }
+        diff.removed = self.layers[shared..].to_vec();
+        diff.added = other.layers[shared..].to_vec();
+
+        let mut compare = |field: String, from: Option<String>, to: Option<String>| {
+            if from != to {
+                diff.config.push(ConfigChange { field, from, to });
+            }
+        };
+        compare(
+            "os".into(),
+            Some(self.platform.os.clone()),
+            Some(other.platform.os.clone()),
+        );
+        compare(
+            "architecture".into(),
+            Some(self.platform.architecture.clone()),
+            Some(other.platform.architecture.clone()),
+        );
+        compare(
+            "variant".into(),
+            self.platform.variant.clone(),
+            other.platform.variant.clone(),
+        );
+        compare(
+            "entrypoint".into(),
+            command(&self.entrypoint),
+            command(&other.entrypoint),
+        );
+        compare("cmd".into(), command(&self.cmd), command(&other.cmd));
+
+        let from_env = env_map(&self.env);
+        let to_env = env_map(&other.env);
+        for name in from_env
+            .keys()
+            .chain(to_env.keys())
+            .collect::<BTreeSet<_>>()
+        {
+            compare(
+                format!("env.{}", name),
+                from_env.get(name).map(|v| v.to_string()),
+                to_env.get(name).map(|v| v.to_string()),
+            );
+        }
+        let labels: BTreeSet<_> = self.labels.keys().chain(other.labels.keys()).collect();
+        for name in labels {
+            compare(
+                format!("labels.{}", name),
+                self.labels.get(name).cloned(),
+                other.labels.get(name).cloned(),
+            );
+        }
+        diff
+    }
+}
+
+/// Annotations of a stored manifest or index and of its descriptors.
+///
+/// Served as JSON with camelCase keys.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestAnnotations {
+    /// Digest of the manifest.
+    pub digest: String,
+    /// Media type of the manifest.
+    pub media_type: String,
+    /// Artifact type, for artifact manifests and indexes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact_type: Option<String>,
+    /// Annotations of the manifest itself.
+    pub annotations: BTreeMap<String, String>,
+    /// Descriptors in manifest order: config and layers, or the child
+    /// manifests of an index, followed by the subject.
+    pub descriptors: Vec<DescriptorAnnotations>,
+}
+
+/// Annotations of one descriptor in a manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DescriptorAnnotations {
+    /// Digest of the referenced content.
+    pub digest: String,
+    /// Media type of the referenced content.
+    pub media_type: String,
+    /// Annotations of the descriptor.
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// Differences between two images, from a base image to a newer one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageDiff {
+    /// Layers the newer image has on top of the base's.
+    pub added: Vec<LayerInfo>,
+    /// Top layers of the base that the newer image lacks.
+    pub removed: Vec<LayerInfo>,
+    /// Layers that differ at the same position.
+    pub changed: Vec<LayerChange>,
+    /// Config fields that differ, in field order.
+    pub config: Vec<ConfigChange>,
+}
+
+impl ImageDiff {
+    /// Returns whether the images have the same layers and config.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.config.is_empty()
+    }
+}
+
+/// A layer replaced at the same position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerChange {
+    /// Position of the layer, bottom first.
+    pub index: usize,
+    /// Layer in the base image.
+    pub from: LayerInfo,
+    /// Layer in the newer image.
+    pub to: LayerInfo,
+}
+
+/// A config field that differs between two images.
+///
+/// Fields are named `os`, `architecture`, `variant`, `entrypoint`, `cmd`,
+/// `env.<NAME>` and `labels.<key>`. Commands are rendered as JSON arrays;
+/// `None` means the field is unset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigChange {
+    /// Name of the field.
+    pub field: String,
+    /// Value in the base image.
+    pub from: Option<String>,
+    /// Value in the newer image.
+    pub to: Option<String>,
+}
+
+fn command(args: &[String]) -> Option<String> {
+    (!args.is_empty()).then(|| serde_json::to_string(args).unwrap_or_default())
+}
+
+fn env_map(env: &[String]) -> BTreeMap<&str, &str> {
+    env.iter()
+        .map(|entry| entry.split_once('=').unwrap_or((entry, "")))
+        .collect()
+}
+
+fn image_manifest(key: &str, content_type: &str, manifest: &[u8]) -> Result<ImageManifest> {
+    match Manifest::parse(content_type, manifest)? {
+        Manifest::Image(manifest) => Ok(*manifest),
+        Manifest::Index(_) => Err(RegistryError::InvalidManifest(format!(
+            "{} is not an image manifest",
+            key
+        ))),
+    }
+}
+
+/// Collects the annotations of a manifest or index.
+pub(crate) fn annotations(
+    digest: String,
+    content_type: &str,
+    manifest: &[u8],
+) -> Result<ManifestAnnotations> {
+    let parsed = Manifest::parse(content_type, manifest)?;
+    let (artifact_type, annotations) = match &parsed {
+        Manifest::Image(manifest) => (&manifest.artifact_type, &manifest.annotations),
+        Manifest::Index(index) => (&index.artifact_type, &index.annotations),
+    };
+    Ok(ManifestAnnotations {
+        digest,
+        media_type: parsed.media_type().unwrap_or(content_type).to_string(),
+        artifact_type: artifact_type.clone(),
+        annotations: annotations.clone(),
+        descriptors: parsed
+            .descriptors()
+            .into_iter()
+            .map(|descriptor| DescriptorAnnotations {
+                digest: descriptor.digest.clone(),
+                media_type: descriptor.media_type.clone(),
+                annotations: descriptor.annotations.clone(),
+            })
+            .collect(),
+    })
+}
+
+/// Parses an image manifest, returning the config digest to fetch.
+pub(crate) fn config_digest(key: &str, content_type: &str, manifest: &[u8]) -> Result<String> {
+    Ok(image_manifest(key, content_type, manifest)?.config.digest)
+}
+
+/// Builds the inspection of a manifest whose config blob is `config`.
+pub(crate) fn inspect(
+    digest: String,
+    content_type: &str,
+    manifest: &[u8],
+    config: &[u8],
+) -> Result<ImageInspect> {
+    let manifest = image_manifest(&digest, content_type, manifest)?;
+    let parsed: ImageConfig = serde_json::from_slice(config)?;
+    let runtime = parsed.config.unwrap_or_default();
+
+    Ok(ImageInspect {
+        digest,
+        media_type: manifest
+            .media_type
+            .unwrap_or_else(|| content_type.to_string()),
+        config_digest: manifest.config.digest,
+        layers: manifest
+            .layers
+            .into_iter()
+            .map(|layer| LayerInfo {
+                digest: layer.digest,
+                size: layer.size,
+                media_type: layer.media_type,
+                annotations: layer.annotations,
+            })
+            .collect(),
+        platform: Platform {
+            os: parsed.os,
+            architecture: parsed.architecture,
+            variant: parsed.variant,
+        },
+        env: runtime.env.unwrap_or_default(),
+        entrypoint: runtime.entrypoint.unwrap_or_default(),
+        cmd: runtime.cmd.unwrap_or_default(),
+        labels: runtime.labels.unwrap_or_default(),
+    })
+}
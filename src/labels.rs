@@ -0,0 +1,160 @@
+//! Label index: which stored images carry which labels.
+//!
+//! The labels of an image are the `Labels` of its config blob together with
+//! the annotations of its manifest, an annotation winning over a config label
+//! of the same key. Both are where `org.opencontainers.image.*` keys such as
+//! `org.opencontainers.image.source` end up, depending on the build tool.
+//! Labels are extracted once per manifest digest, so repeated searches only
+//! list and fetch manifests instead of parsing every config again.
+
+use crate::digest::sha256_digest;
+use crate::error::Result;
+use crate::oci::manifest::{ImageConfig, Manifest};
+use crate::storage::{is_tag_key, ManifestEntry, Storage};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// A stored manifest and its labels, as returned by
+/// [`RegistryServer::find_images_with_label`](crate::RegistryServer::find_images_with_label).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LabeledImage {
+    /// Repository the manifest is stored in.
+    pub repository: String,
+    /// Digest of the manifest.
+    pub digest: String,
+    /// Tags pointing at the manifest, sorted; empty for manifests only
+    /// stored by digest, such as the children of an index.
+    pub tags: Vec<String>,
+    /// Config labels and manifest annotations.
+    pub labels: BTreeMap<String, String>,
+}
+
+impl LabeledImage {
+    /// Returns the value of the label `key`.
+    pub fn label(&self, key: &str) -> Option<&str> {
+        self.labels.get(key).map(String::as_str)
+    }
+}
+
+/// Query parameters of `GET /v2/_testkit/images`: images with the label
+/// `label`, set to `value` if given, or with `missing=true` images without it.
+#[derive(Debug, Deserialize)]
+pub(crate) struct LabelQuery {
+    pub(crate) label: String,
+    pub(crate) value: Option<String>,
+    #[serde(default)]
+    pub(crate) missing: bool,
+}
+
+impl LabelQuery {
+    pub(crate) fn matches(&self, image: &LabeledImage) -> bool {
+        let found = match (image.label(&self.label), &self.value) {
+            (Some(actual), Some(expected)) => actual == expected,
+            (found, None) => found.is_some(),
+            (None, Some(_)) => false,
+        };
+        found != self.missing
+    }
+}
+
+/// Search results served by `GET /v2/_testkit/images`.
+#[derive(Serialize)]
+pub(crate) struct LabeledImages {
+    pub(crate) images: Vec<LabeledImage>,
+}
+
+/// Labels of manifests, by manifest digest.
+#[derive(Debug, Default)]
+pub(crate) struct LabelIndex {
+    labels: RwLock<HashMap<String, Arc<BTreeMap<String, String>>>>,
+}
+
+impl LabelIndex {
+    /// Lists every stored manifest that parses, with its labels, sorted by
+    /// repository and digest.
+    pub(crate) async fn images(&self, storage: &dyn Storage) -> Result<Vec<LabeledImage>> {
+        let mut images: BTreeMap<(String, String), LabeledImage> = BTreeMap::new();
+        for key in storage.list_manifests().await? {
+            let Some((repository, reference)) = key.split_once(':') else {
+                continue;
+            };
+            let Some(entry) = storage.get_manifest(&key).await? else {
+                continue;
+            };
+            let digest = if is_tag_key(&key) {
+                sha256_digest(&entry.data)
+            } else {
+                reference.to_string()
+            };
+            let Some(labels) = self.labels(storage, &digest, &entry).await? else {
+                continue;
+            };
+            let image = images
+                .entry((repository.to_string(), digest.clone()))
+                .or_insert_with(|| LabeledImage {
+                    repository: repository.to_string(),
+                    digest,
+                    tags: Vec::new(),
+                    labels: (*labels).clone(),
+                });
+            if is_tag_key(&key) {
+                image.tags.push(reference.to_string());
+            }
+        }
+        let mut images: Vec<LabeledImage> = images.into_values().collect();
+        for image in &mut images {
+            image.tags.sort();
+        }
+        Ok(images)
+    }
+
+    /// Returns the labels of the manifest `digest`, extracting and indexing
+    /// them on first use. `None` if the manifest does not parse.
+    async fn labels(
+        &self,
+        storage: &dyn Storage,
+        digest: &str,
+        entry: &ManifestEntry,
+    ) -> Result<Option<Arc<BTreeMap<String, String>>>> {
+        if let Some(labels) = self.labels.read().await.get(digest) {
+            return Ok(Some(labels.clone()));
+        }
+        let Ok(manifest) = Manifest::parse(&entry.content_type, &entry.data) else {
+            return Ok(None);
+        };
+        let mut labels = BTreeMap::new();
+        // A config pushed after its manifest would be missed; index it then.
+        let mut complete = true;
+        let annotations = match manifest {
+            Manifest::Image(manifest) => {
+                match storage.get_blob(&manifest.config.digest).await? {
+                    Some(config) => {
+                        if let Ok(config) = serde_json::from_slice::<ImageConfig>(&config) {
+                            labels.extend(
+                                config
+                                    .config
+                                    .and_then(|config| config.labels)
+                                    .unwrap_or_default(),
+                            );
+                        }
+                    }
+                    None => complete = false,
+                }
+                manifest.annotations
+            }
+            Manifest::Index(index) => index.annotations,
+        };
+        labels.extend(annotations);
+
+        let labels = Arc::new(labels);
+        if complete {
+            self.labels
+                .write()
+                .await
+                .insert(digest.to_string(), labels.clone());
+        }
+        Ok(Some(labels))
+    }
+}
@@ -17,11 +17,120 @@
 //! }
 //! ```
 
+mod archive;
+pub mod audit;
+pub mod auth;
+pub mod bench;
+pub mod builder;
+pub mod capture;
+mod catalog;
+pub mod client;
+pub mod clock;
+pub mod compression;
 pub mod config;
+pub mod consistency;
+pub mod copy;
+pub mod dedup;
+pub mod digest;
+pub mod endpoints;
 pub mod error;
+pub mod events;
+mod expect;
+pub mod faults;
+pub mod federation;
+pub mod foreign;
+pub mod gc;
+mod gzip;
+pub mod headers;
+pub mod history;
+pub mod inspect;
+pub mod labels;
+pub mod lifecycle;
+pub mod loadgen;
+pub mod location;
+pub mod maintenance;
+pub mod metrics;
+mod names;
+pub mod oci;
+mod pagination;
+pub mod profile;
+pub mod proxy;
+mod quirks;
+pub mod quota;
+pub mod ratelimit;
+pub mod received;
+pub mod redirect;
+pub mod reference;
+pub mod referrers;
+pub mod replication;
+pub mod retention;
+pub mod retries;
+mod rng;
 pub mod server;
+pub mod signing;
+pub mod socket;
 pub mod storage;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+pub mod stress;
+pub mod sync;
+pub mod synthetic;
+mod tags;
+pub mod testdata;
+pub mod token_server;
+pub mod transport;
+pub mod uploads;
+pub mod upstream;
+pub mod verify;
+pub mod warnings;
+pub mod wirelog;
 
+pub use audit::AuditEntry;
+pub use auth::{AuthConfig, AuthScheme, AuthorizationToken, TokenIntrospection};
+pub use builder::{ArtifactBuilder, ImageBuilder, IndexBuilder, Layer};
+pub use capture::{CaptureConfig, CapturedExchange};
+pub use client::{MountStats, RegistryClient, TransferDirection, TransferProgress};
+pub use clock::ClockSkew;
+pub use compression::ContentEncoding;
 pub use config::{RegistryConfig, StorageBackend};
-pub use error::{RegistryError, Result};
-pub use server::RegistryServer;
+pub use consistency::Visibility;
+pub use dedup::{DedupReport, DedupStats};
+pub use digest::{DigestHasher, DigestPolicy, Digester};
+pub use endpoints::ApiEndpoint;
+pub use error::{ErrorBody, ErrorInfo, ErrorResponse, RegistryError, Result};
+pub use events::RegistryEvent;
+pub use faults::{FaultConfig, FlakyStorage, StorageFault, StorageMethod};
+pub use federation::{NamespaceRoute, NamespaceTarget};
+pub use foreign::ForeignLayerPolicy;
+pub use gc::GcReport;
+pub use headers::{HeaderCasing, HeaderQuirks};
+pub use history::TagRevision;
+pub use inspect::{
+    DescriptorAnnotations, ImageDiff, ImageInspect, LayerInfo, ManifestAnnotations, Platform,
+};
+pub use labels::LabeledImage;
+pub use lifecycle::LifecycleEvent;
+pub use location::LocationStyle;
+pub use maintenance::{MaintenanceConfig, MaintenanceReport};
+pub use metrics::{ConnectionStats, RegistryMetrics, RouteMetrics};
+pub use profile::RegistryProfile;
+pub use proxy::ProxySimulation;
+pub use quota::{QuotaConfig, QuotaKey};
+pub use ratelimit::PullRateLimit;
+pub use received::ReceivedRequest;
+pub use redirect::BlobRedirectConfig;
+pub use reference::Reference;
+pub use referrers::ReferrerDeletePolicy;
+pub use replication::{ReplicationConfig, ReplicationStatus};
+pub use retention::RetentionPolicy;
+pub use server::{RegistryServer, RepositoryMetadata, ServeFuture};
+pub use signing::SigningPolicy;
+pub use socket::SocketOptions;
+pub use storage::SharedTempDir;
+pub use sync::{verify_sync, SyncDifference, SyncReport};
+pub use testdata::LayerContent;
+pub use token_server::TokenServer;
+pub use uploads::{OpenUpload, UploadStats};
+pub use upstream::UpstreamConfig;
+pub use verify::{VerifyProblem, VerifyReport};
+pub use warnings::RegistryWarning;
@@ -0,0 +1,66 @@
+//! Callbacks at the edges of a server's life.
+
+use crate::config::StorageBackend;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// A point in a server's life, passed to
+/// [`RegistryConfig::on_event`](crate::RegistryConfig::on_event) callbacks.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum LifecycleEvent {
+    /// The storage backend is ready; nothing is listening yet.
+    StorageInitialized {
+        /// The backend that was set up.
+        backend: StorageBackend,
+    },
+    /// The server accepts connections, after startup or a restart.
+    Started {
+        /// Address the server is bound to.
+        addr: SocketAddr,
+        /// URL clients should use.
+        url: String,
+    },
+    /// The server stopped accepting connections, through a simulated
+    /// crash, a restart or being dropped.
+    Stopped {
+        /// Address the server was bound to.
+        addr: SocketAddr,
+    },
+    /// The server could not start.
+    FatalError {
+        /// What went wrong.
+        error: String,
+    },
+}
+
+type Callback = Arc<dyn Fn(&LifecycleEvent) + Send + Sync>;
+
+/// Callbacks registered with
+/// [`RegistryConfig::on_event`](crate::RegistryConfig::on_event).
+#[derive(Clone, Default)]
+pub struct LifecycleHooks {
+    callbacks: Vec<Callback>,
+}
+
+impl LifecycleHooks {
+    pub(crate) fn push(&mut self, callback: Callback) {
+        self.callbacks.push(callback);
+    }
+
+    /// Calls every callback, in registration order.
+    pub(crate) fn emit(&self, event: LifecycleEvent) {
+        for callback in &self.callbacks {
+            callback(&event);
+        }
+    }
+}
+
+impl fmt::Debug for LifecycleHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LifecycleHooks")
+            .field("callbacks", &self.callbacks.len())
+            .finish()
+    }
+}
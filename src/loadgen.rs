@@ -0,0 +1,322 @@
+//! Concurrent push/pull load generation against any registry URL.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use registry_testkit::loadgen::{self, LoadConfig, SizeDistribution};
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let config = LoadConfig::new()
+//!     .with_clients(8)
+//!     .with_duration(Duration::from_secs(10))
+//!     .with_sizes(SizeDistribution::Uniform { min: 1024, max: 1024 * 1024 });
+//! let report = loadgen::run("http://127.0.0.1:5000", config).await?;
+//! println!("push p99: {:?}", report.pushes.p99());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::RegistryClient;
+use crate::error::Result;
+use crate::rng::SplitMix64;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+/// Distribution of layer sizes (in bytes) used for generated images.
+#[derive(Debug, Clone)]
+pub enum SizeDistribution {
+    /// Every layer has exactly this size.
+    Fixed(usize),
+    /// Layer sizes are drawn uniformly from `min..=max`.
+    Uniform { min: usize, max: usize },
+    /// Layer sizes are drawn from `(size, weight)` pairs.
+    Weighted(Vec<(usize, u32)>),
+}
+
+impl SizeDistribution {
+    fn sample(&self, rng: &mut SplitMix64) -> usize {
+        match self {
+            SizeDistribution::Fixed(size) => *size,
+            SizeDistribution::Uniform { min, max } => rng.range(*min as u64, *max as u64) as usize,
+            SizeDistribution::Weighted(choices) => {
+                let total: u64 = choices.iter().map(|(_, w)| *w as u64).sum();
+                if total == 0 {
+                    return 0;
+                }
+                let mut pick = rng.range(0, total - 1);
+                for (size, weight) in choices {
+                    if pick < *weight as u64 {
+                        return *size;
+                    }
+                    pick -= *weight as u64;
+                }
+                0
+            }
+        }
+    }
+}
+
+/// Configuration for a load-generation run.
+#[derive(Debug, Clone)]
+pub struct LoadConfig {
+    /// Number of concurrent clients.
+    pub clients: usize,
+    /// How long to generate load for.
+    pub duration: Duration,
+    /// Layer size distribution for pushed images.
+    pub sizes: SizeDistribution,
+    /// Number of layers in each pushed image.
+    pub layers_per_image: usize,
+    /// Layers each client transfers at once within an image.
+    pub layer_concurrency: usize,
+    /// Fraction of operations (0.0 to 1.0) that are pulls once images exist.
+    pub pull_ratio: f64,
+    /// Repository that images are pushed to.
+    pub repository: String,
+    /// Seed for generated content and operation mix.
+    pub seed: u64,
+}
+
+impl LoadConfig {
+    /// Creates a configuration with 4 clients running for 5 seconds.
+    pub fn new() -> Self {
+        Self {
+            clients: 4,
+            duration: Duration::from_secs(5),
+            sizes: SizeDistribution::Fixed(64 * 1024),
+            layers_per_image: 1,
+            layer_concurrency: 1,
+            pull_ratio: 0.5,
+            repository: "loadgen".to_string(),
+            seed: 0,
+        }
+    }
+
+    /// Sets the number of concurrent clients.
+    pub fn with_clients(mut self, clients: usize) -> Self {
+        self.clients = clients;
+        self
+    }
+
+    /// Sets how long load is generated for.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Sets the layer size distribution.
+    pub fn with_sizes(mut self, sizes: SizeDistribution) -> Self {
+        self.sizes = sizes;
+        self
+    }
+
+    /// Sets the number of layers per pushed image.
+    pub fn with_layers_per_image(mut self, layers: usize) -> Self {
+        self.layers_per_image = layers;
+        self
+    }
+
+    /// Sets how many layers of an image each client transfers at once.
+    pub fn with_layer_concurrency(mut self, concurrency: usize) -> Self {
+        self.layer_concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Sets the fraction of operations that are pulls.
+    pub fn with_pull_ratio(mut self, ratio: f64) -> Self {
+        self.pull_ratio = ratio.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the repository images are pushed to.
+    pub fn with_repository(mut self, repository: impl Into<String>) -> Self {
+        self.repository = repository.into();
+        self
+    }
+
+    /// Sets the seed for generated content.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+impl Default for LoadConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Latency samples for one kind of operation.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyStats {
+    samples: Vec<Duration>,
+}
+
+impl LatencyStats {
+    pub(crate) fn from_samples(mut samples: Vec<Duration>) -> Self {
+        samples.sort();
+        Self { samples }
+    }
+
+    /// Number of successful operations.
+    pub fn count(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns the latency at percentile `p` (0.0 to 100.0).
+    pub fn percentile(&self, p: f64) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = (p.clamp(0.0, 100.0) / 100.0 * (self.samples.len() - 1) as f64).round();
+        self.samples[rank as usize]
+    }
+
+    /// Median latency.
+    pub fn p50(&self) -> Duration {
+        self.percentile(50.0)
+    }
+
+    /// 95th percentile latency.
+    pub fn p95(&self) -> Duration {
+        self.percentile(95.0)
+    }
+
+    /// 99th percentile latency.
+    pub fn p99(&self) -> Duration {
+        self.percentile(99.0)
+    }
+
+    /// Slowest observed latency.
+    pub fn max(&self) -> Duration {
+        self.samples.last().copied().unwrap_or_default()
+    }
+}
+
+/// Results of a load-generation run.
+#[derive(Debug, Clone, Default)]
+pub struct LoadReport {
+    /// Latencies of complete image pushes.
+    pub pushes: LatencyStats,
+    /// Latencies of complete image pulls.
+    pub pulls: LatencyStats,
+    /// Number of failed operations.
+    pub errors: usize,
+    /// Total layer bytes pushed.
+    pub bytes_pushed: u64,
+    /// Total layer bytes pulled.
+    pub bytes_pulled: u64,
+    /// Wall-clock duration of the run.
+    pub elapsed: Duration,
+}
+
+#[derive(Default)]
+struct ClientResult {
+    pushes: Vec<Duration>,
+    pulls: Vec<Duration>,
+    errors: usize,
+    bytes_pushed: u64,
+    bytes_pulled: u64,
+}
+
+/// Drives the configured workload against the registry at `url`.
+///
+/// Individual operation failures are counted in [`LoadReport::errors`]
+/// rather than aborting the run.
+pub async fn run(url: &str, config: LoadConfig) -> Result<LoadReport> {
+    let started = Instant::now();
+    let deadline = started + config.duration;
+    let pushed_tags: Arc<Mutex<Vec<String>>> = Arc::default();
+    let config = Arc::new(config);
+
+    let mut tasks = JoinSet::new();
+    for client_id in 0..config.clients {
+        let client = RegistryClient::new(url).with_concurrency(config.layer_concurrency);
+        let config = config.clone();
+        let pushed_tags = pushed_tags.clone();
+        tasks.spawn(
+            async move { run_client(client_id, client, &config, pushed_tags, deadline).await },
+        );
+    }
+
+    let mut pushes = Vec::new();
+    let mut pulls = Vec::new();
+    let mut report = LoadReport::default();
+    while let Some(result) = tasks.join_next().await {
+        let Ok(result) = result else {
+            report.errors += 1;
+            continue;
+        };
+        pushes.extend(result.pushes);
+        pulls.extend(result.pulls);
+        report.errors += result.errors;
+        report.bytes_pushed += result.bytes_pushed;
+        report.bytes_pulled += result.bytes_pulled;
+    }
+
+    report.pushes = LatencyStats::from_samples(pushes);
+    report.pulls = LatencyStats::from_samples(pulls);
+    report.elapsed = started.elapsed();
+    Ok(report)
+}
+
+async fn run_client(
+    client_id: usize,
+    client: RegistryClient,
+    config: &LoadConfig,
+    pushed_tags: Arc<Mutex<Vec<String>>>,
+    deadline: Instant,
+) -> ClientResult {
+    let mut rng = SplitMix64::new(config.seed ^ (client_id as u64).wrapping_mul(0x9E37));
+    let mut result = ClientResult::default();
+    let mut iteration = 0u64;
+
+    while Instant::now() < deadline {
+        let pull_target = {
+            let tags = pushed_tags.lock().unwrap();
+            if !tags.is_empty() && rng.next_f64() < config.pull_ratio {
+                Some(tags[rng.range(0, tags.len() as u64 - 1) as usize].clone())
+            } else {
+                None
+            }
+        };
+
+        if let Some(tag) = pull_target {
+            let start = Instant::now();
+            match client.pull_image(&config.repository, &tag).await {
+                Ok(image) => {
+                    result.pulls.push(start.elapsed());
+                    result.bytes_pulled += image.layers.iter().map(|l| l.len() as u64).sum::<u64>();
+                }
+                Err(_) => result.errors += 1,
+            }
+            continue;
+        }
+
+        let layers: Vec<Vec<u8>> = (0..config.layers_per_image)
+            .map(|_| {
+                let mut layer = vec![0u8; config.sizes.sample(&mut rng)];
+                rng.fill_bytes(&mut layer);
+                layer
+            })
+            .collect();
+        let bytes: u64 = layers.iter().map(|l| l.len() as u64).sum();
+        let tag = format!("client{}-{}", client_id, iteration);
+        iteration += 1;
+
+        let start = Instant::now();
+        match client.push_image(&config.repository, &tag, &layers).await {
+            Ok(_) => {
+                result.pushes.push(start.elapsed());
+                result.bytes_pushed += bytes;
+                pushed_tags.lock().unwrap().push(tag);
+            }
+            Err(_) => result.errors += 1,
+        }
+    }
+
+    result
+}
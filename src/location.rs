@@ -0,0 +1,102 @@
+//! Form of the `Location` and pagination `Link` headers the registry sends.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+/// Whether `Location` headers are paths or full URLs.
+///
+/// Handlers emit paths such as `/v2/<name>/blobs/uploads/<uuid>`; this
+/// decides how they reach clients. The targets of pagination `Link`
+/// headers take the same form.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LocationStyle {
+    /// Paths, as distribution sends them. With an external URL that has a
+    /// path, the path is prepended.
+    #[default]
+    Relative,
+    /// Full URLs on the external URL if one is configured, otherwise on
+    /// the `Host` the request was sent to.
+    Absolute,
+}
+
+/// How `Location` paths are rewritten.
+pub(crate) struct LocationRewrite {
+    pub style: LocationStyle,
+    /// External URL without a trailing slash.
+    pub external_url: Option<String>,
+}
+
+impl LocationRewrite {
+    /// Returns whether rewriting changes anything.
+    pub(crate) fn is_identity(&self) -> bool {
+        self.style == LocationStyle::Relative
+            && self
+                .external_url
+                .as_deref()
+                .is_none_or(|url| external_path(url).is_empty())
+    }
+
+    fn rewrite(&self, path: &str, host: Option<&str>) -> Option<String> {
+        match (self.style, self.external_url.as_deref()) {
+            (LocationStyle::Relative, Some(url)) => Some(format!("{}{}", external_path(url), path)),
+            (LocationStyle::Relative, None) => None,
+            (LocationStyle::Absolute, Some(url)) => Some(format!("{}{}", url, path)),
+            (LocationStyle::Absolute, None) => Some(format!("http://{}{}", host?, path)),
+        }
+    }
+}
+
+/// The path component of `url`, without a trailing slash.
+fn external_path(url: &str) -> &str {
+    let after_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    after_scheme
+        .find('/')
+        .map_or("", |index| &after_scheme[index..])
+        .trim_end_matches('/')
+}
+
+/// Rewrites path-only `Location` headers and `Link` targets according to
+/// the configuration.
+pub(crate) async fn rewrite_locations(
+    State(rewrite): State<Arc<LocationRewrite>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let mut response = next.run(request).await;
+
+    let location = response
+        .headers()
+        .get(header::LOCATION)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| v.starts_with('/'))
+        .and_then(|path| rewrite.rewrite(path, host.as_deref()));
+    if let Some(value) = location.and_then(|l| HeaderValue::from_str(&l).ok()) {
+        response.headers_mut().insert(header::LOCATION, value);
+    }
+
+    let link = response
+        .headers()
+        .get(header::LINK)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix('<'))
+        .and_then(|v| v.split_once('>'))
+        .filter(|(target, _)| target.starts_with('/'))
+        .and_then(|(target, params)| {
+            let target = rewrite.rewrite(target, host.as_deref())?;
+            Some(format!("<{}>{}", target, params))
+        });
+    if let Some(value) = link.and_then(|l| HeaderValue::from_str(&l).ok()) {
+        response.headers_mut().insert(header::LINK, value);
+    }
+    response
+}
@@ -0,0 +1,88 @@
+//! Scheduled background maintenance.
+
+use crate::gc::GcReport;
+use std::time::Duration;
+
+/// Which maintenance tasks the server runs in the background, and how often.
+///
+/// Every run emits a
+/// [`RegistryEvent::MaintenanceCompleted`](crate::RegistryEvent::MaintenanceCompleted)
+/// summary. Tasks run in this order: retention, upload-session cleanup,
+/// garbage collection, integrity scrub.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::{MaintenanceConfig, RegistryConfig};
+/// use std::time::Duration;
+///
+/// let config = RegistryConfig::memory().with_maintenance(
+///     MaintenanceConfig::new(Duration::from_secs(60))
+///         .with_retention(true)
+///         .with_gc(true)
+///         .with_upload_ttl(Duration::from_secs(3600)),
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceConfig {
+    /// Time between runs.
+    pub interval: Duration,
+    /// Apply the configured retention policies.
+    pub retention: bool,
+    /// Delete unreachable manifests and unreferenced blobs.
+    pub gc: bool,
+    /// Discard upload sessions older than this.
+    pub upload_ttl: Option<Duration>,
+    /// Verify that stored blobs match their digests.
+    pub scrub: bool,
+}
+
+impl MaintenanceConfig {
+    /// Runs every `interval`, with every task disabled.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            retention: false,
+            gc: false,
+            upload_ttl: None,
+            scrub: false,
+        }
+    }
+
+    /// Enables or disables applying retention policies.
+    pub fn with_retention(mut self, enabled: bool) -> Self {
+        self.retention = enabled;
+        self
+    }
+
+    /// Enables or disables garbage collection.
+    pub fn with_gc(mut self, enabled: bool) -> Self {
+        self.gc = enabled;
+        self
+    }
+
+    /// Discards upload sessions that have been open longer than `ttl`.
+    pub fn with_upload_ttl(mut self, ttl: Duration) -> Self {
+        self.upload_ttl = Some(ttl);
+        self
+    }
+
+    /// Enables or disables the blob integrity scrub.
+    pub fn with_scrub(mut self, enabled: bool) -> Self {
+        self.scrub = enabled;
+        self
+    }
+}
+
+/// What one maintenance run did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    /// Tags deleted by retention, as `(repository, tag)` pairs.
+    pub tags_deleted: Vec<(String, String)>,
+    /// Number of upload sessions discarded.
+    pub uploads_expired: usize,
+    /// What garbage collection removed, if it ran.
+    pub garbage: Option<GcReport>,
+    /// Blobs whose content no longer matches their digest.
+    pub corrupt_blobs: Vec<String>,
+}
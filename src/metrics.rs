@@ -0,0 +1,237 @@
+//! Per-route latency and throughput of the registry itself.
+
+use crate::loadgen::LatencyStats;
+use axum::{
+    body::{Body, Bytes},
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use hyper::body::{Body as HttpBody, Frame, SizeHint};
+use std::collections::{BTreeMap, HashMap};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Metrics of one route, such as `GET /v2/{name}/manifests/{reference}`.
+#[derive(Debug, Clone, Default)]
+pub struct RouteMetrics {
+    /// Time from receiving each request to sending its response headers.
+    pub latency: LatencyStats,
+    /// Request body bytes read by the route.
+    pub bytes_received: u64,
+    /// Response body bytes sent by the route.
+    pub bytes_sent: u64,
+}
+
+/// How clients connected, from [`RegistryMetrics::connections`].
+///
+/// A connection is counted when it carries its first request, so clients
+/// that connect and send nothing do not show up. Requests made over TCP and
+/// through the [in-process connector](crate::RegistryServer::connector) both
+/// count; the server only speaks plain HTTP, so no TLS handshakes take place.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    /// Connections that carried at least one request.
+    pub opened: u64,
+    /// Requests handled.
+    pub requests: u64,
+    /// Requests sent on a connection that had already carried one, by
+    /// keep-alive or HTTP/2 multiplexing.
+    pub reused: u64,
+}
+
+impl ConnectionStats {
+    /// Average number of requests per connection, `0.0` before any request.
+    pub fn requests_per_connection(&self) -> f64 {
+        if self.opened == 0 {
+            0.0
+        } else {
+            self.requests as f64 / self.opened as f64
+        }
+    }
+}
+
+/// The metrics generation one connection last served a request in (`0`
+/// before its first), attached to each of its requests as an extension.
+#[derive(Clone, Default)]
+pub(crate) struct ConnectionRequests(Arc<AtomicU64>);
+
+/// Snapshot of what the server has handled, from
+/// [`RegistryServer::metrics`](crate::RegistryServer::metrics).
+///
+/// Comparing these latencies with the client-side ones of a test shows
+/// whether time is spent in the client or in the testkit.
+#[derive(Debug, Clone, Default)]
+pub struct RegistryMetrics {
+    /// Metrics by route, keyed by method and route pattern.
+    pub routes: BTreeMap<String, RouteMetrics>,
+    /// Connections clients opened and how often they were reused.
+    pub connections: ConnectionStats,
+    /// Time covered by the snapshot.
+    pub elapsed: Duration,
+}
+
+impl RegistryMetrics {
+    /// Request body bytes received on all routes.
+    pub fn bytes_received(&self) -> u64 {
+        self.routes.values().map(|route| route.bytes_received).sum()
+    }
+
+    /// Response body bytes sent on all routes.
+    pub fn bytes_sent(&self) -> u64 {
+        self.routes.values().map(|route| route.bytes_sent).sum()
+    }
+
+    /// Average bytes received per second.
+    pub fn receive_rate(&self) -> f64 {
+        self.bytes_received() as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Average bytes sent per second.
+    pub fn send_rate(&self) -> f64 {
+        self.bytes_sent() as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+#[derive(Default)]
+struct RouteCounters {
+    samples: Vec<Duration>,
+    received: Arc<AtomicU64>,
+    sent: Arc<AtomicU64>,
+}
+
+/// Collects [`RegistryMetrics`] for one server.
+pub(crate) struct Metrics {
+    since: Mutex<Instant>,
+    routes: Mutex<HashMap<String, RouteCounters>>,
+    connections: Mutex<ConnectionStats>,
+    /// Bumped by every reset, so connections opened before it count as
+    /// opened again on their next request.
+    generation: AtomicU64,
+}
+
+impl Metrics {
+    pub(crate) fn new() -> Self {
+        Self {
+            since: Mutex::new(Instant::now()),
+            routes: Mutex::default(),
+            connections: Mutex::default(),
+            generation: AtomicU64::new(1),
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> RegistryMetrics {
+        let routes = self.routes.lock().unwrap();
+        RegistryMetrics {
+            routes: routes
+                .iter()
+                .map(|(route, counters)| {
+                    let metrics = RouteMetrics {
+                        latency: LatencyStats::from_samples(counters.samples.clone()),
+                        bytes_received: counters.received.load(Ordering::Relaxed),
+                        bytes_sent: counters.sent.load(Ordering::Relaxed),
+                    };
+                    (route.clone(), metrics)
+                })
+                .collect(),
+            connections: *self.connections.lock().unwrap(),
+            elapsed: self.since.lock().unwrap().elapsed(),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        self.routes.lock().unwrap().clear();
+        let mut connections = self.connections.lock().unwrap();
+        *connections = ConnectionStats::default();
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        drop(connections);
+        *self.since.lock().unwrap() = Instant::now();
+    }
+
+    fn counters(&self, route: &str) -> (Arc<AtomicU64>, Arc<AtomicU64>) {
+        let mut routes = self.routes.lock().unwrap();
+        let counters = routes.entry(route.to_string()).or_default();
+        (counters.received.clone(), counters.sent.clone())
+    }
+
+    fn record_request(&self, connection: Option<&ConnectionRequests>) {
+        let mut connections = self.connections.lock().unwrap();
+        connections.requests += 1;
+        let generation = self.generation.load(Ordering::Relaxed);
+        match connection.map(|requests| requests.0.swap(generation, Ordering::Relaxed)) {
+            Some(seen) if seen == generation => connections.reused += 1,
+            _ => connections.opened += 1,
+        }
+    }
+
+    fn record_latency(&self, route: &str, latency: Duration) {
+        let mut routes = self.routes.lock().unwrap();
+        routes
+            .entry(route.to_string())
+            .or_default()
+            .samples
+            .push(latency);
+    }
+}
+
+/// A body adding the length of every data frame to a counter. Size hints
+/// pass through, so `Content-Length` is unaffected.
+struct Counted {
+    inner: Body,
+    counter: Arc<AtomicU64>,
+}
+
+impl HttpBody for Counted {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+        let frame = ready!(Pin::new(&mut self.inner).poll_frame(cx));
+        if let Some(data) = frame
+            .as_ref()
+            .and_then(|frame| frame.as_ref().ok())
+            .and_then(Frame::data_ref)
+        {
+            self.counter.fetch_add(data.len() as u64, Ordering::Relaxed);
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+fn counted(inner: Body, counter: Arc<AtomicU64>) -> Body {
+    Body::new(Counted { inner, counter })
+}
+
+/// Times the request and counts the body bytes in both directions.
+pub(crate) async fn record_metrics(
+    State(metrics): State<Arc<Metrics>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    metrics.record_request(request.extensions().get::<ConnectionRequests>());
+    let Some(path) = request.extensions().get::<MatchedPath>() else {
+        return next.run(request).await;
+    };
+    let route = format!("{} {}", request.method(), path.as_str());
+    let (received, sent) = metrics.counters(&route);
+
+    let started = Instant::now();
+    let request = request.map(|body| counted(body, received));
+    let response = next.run(request).await;
+    metrics.record_latency(&route, started.elapsed());
+    response.map(|body| counted(body, sent))
+}
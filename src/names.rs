@@ -0,0 +1,90 @@
+//! Repository names spanning several path segments.
+//!
+//! Routes capture the repository name as one path segment, while names
+//! such as `library/nginx` span several. Before routing, the slashes inside
+//! the name are percent-encoded so the whole name matches `{name}`, which
+//! the `Path` extractor decodes again. Once routed, the request gets back
+//! the URI the client sent, so middleware and handlers never see the
+//! encoded form. This covers both the `/v2/` API and the repository routes
+//! of the admin API under `/admin/repositories/`.
+
+use crate::server::split_repository_path;
+use axum::{
+    extract::Request,
+    http::Uri,
+    middleware::{self, Next},
+    response::Response,
+    Router,
+};
+
+/// Prefix of the admin API routes scoped to a repository.
+const ADMIN_PREFIX: &str = "/admin/repositories/";
+
+/// The URI a request was sent with, before its name was encoded.
+#[derive(Clone)]
+struct ClientUri(Uri);
+
+/// Routes `app` by repository names of any number of segments.
+pub(crate) fn route_nested_names(app: Router) -> Router {
+    Router::new()
+        .fallback_service(app.layer(middleware::from_fn(restore_uri)))
+        .layer(middleware::from_fn(encode_name))
+}
+
+/// Percent-encodes the slashes of a multi-segment repository name.
+async fn encode_name(mut request: Request, next: Next) -> Response {
+    if let Some(uri) = encoded_uri(request.uri()) {
+        let client = std::mem::replace(request.uri_mut(), uri);
+        request.extensions_mut().insert(ClientUri(client));
+    }
+    next.run(request).await
+}
+
+/// Puts back the URI the client sent, once the request is routed.
+async fn restore_uri(mut request: Request, next: Next) -> Response {
+    if let Some(ClientUri(uri)) = request.extensions_mut().remove::<ClientUri>() {
+        *request.uri_mut() = uri;
+    }
+    next.run(request).await
+}
+
+/// Returns `uri` with the name's slashes encoded, if it has any.
+fn encoded_uri(uri: &Uri) -> Option<Uri> {
+    let (prefix, (name, rest)) = split_repository_path(uri.path())
+        .map(|parts| ("/v2/", parts))
+        .or_else(|| split_admin_path(uri.path()).map(|parts| (ADMIN_PREFIX, parts)))?;
+    if !name.contains('/') {
+        return None;
+    }
+    let mut target = format!("{}{}/{}", prefix, name.replace('/', "%2F"), rest);
+    if let Some(query) = uri.query() {
+        target = format!("{}?{}", target, query);
+    }
+    target.parse().ok()
+}
+
+/// Splits an admin API path into the repository name and the rest of the
+/// path after it, like [`split_repository_path`] does for `/v2/`. The rest
+/// must have the shape of a whole admin route, so names may contain
+/// segments such as `metadata`.
+fn split_admin_path(path: &str) -> Option<(&str, &str)> {
+    let rest = path.strip_prefix(ADMIN_PREFIX)?;
+    rest.match_indices('/')
+        .map(|(index, _)| index)
+        .find(|&index| is_admin_endpoint(&rest[index + 1..]))
+        .map(|index| (&rest[..index], &rest[index + 1..]))
+}
+
+/// Returns whether `path` is an admin endpoint under a repository.
+fn is_admin_endpoint(path: &str) -> bool {
+    let segments: Vec<&str> = path.split('/').collect();
+    matches!(
+        segments.as_slice(),
+        ["tags", _, "history"]
+            | ["manifests", _, "annotations"]
+            | ["metadata"]
+            | ["metadata", _]
+            | ["archive"]
+            | ["requests"]
+    )
+}
@@ -0,0 +1,445 @@
+//! Image manifests, indexes and configs.
+//!
+//! The structs deserialize leniently so that anything a registry might be
+//! handed can be looked at; [`Manifest::validate`] applies the schema rules
+//! that [`ValidationLevel::Schema`] enforces on push.
+
+use crate::client::OCI_MANIFEST_MEDIA_TYPE;
+use crate::digest::DigestPolicy;
+use crate::error::{RegistryError, Result};
+use crate::oci::schema1::{DOCKER_SCHEMA1_MEDIA_TYPE, DOCKER_SCHEMA1_SIGNED_MEDIA_TYPE};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Media type of OCI image indexes.
+pub const OCI_INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+/// Media type of Docker schema 2 image manifests.
+pub const DOCKER_MANIFEST_MEDIA_TYPE: &str = "application/vnd.docker.distribution.manifest.v2+json";
+/// Media type of Docker manifest lists.
+pub const DOCKER_MANIFEST_LIST_MEDIA_TYPE: &str =
+    "application/vnd.docker.distribution.manifest.list.v2+json";
+/// Media type of Docker image configs.
+pub const DOCKER_CONFIG_MEDIA_TYPE: &str = "application/vnd.docker.container.image.v1+json";
+
+/// How thoroughly `PUT /v2/<name>/manifests/<reference>` checks bodies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ValidationLevel {
+    /// Stores any bytes, like a content-addressed blob.
+    #[default]
+    None,
+    /// Requires a well-formed schema 2 manifest or index, answering
+    /// `MANIFEST_INVALID` otherwise.
+    Schema,
+    /// Additionally requires the config, layers and child manifests to be
+    /// present, answering `MANIFEST_BLOB_UNKNOWN` otherwise.
+    Full,
+}
+
+/// Reference to content by digest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Descriptor {
+    /// Media type of the referenced content.
+    #[serde(default)]
+    pub media_type: String,
+    /// Digest of the referenced content.
+    #[serde(default)]
+    pub digest: String,
+    /// Size of the referenced content in bytes.
+    #[serde(default)]
+    pub size: u64,
+    /// Locations the content can be downloaded from instead of the registry.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<String>,
+    /// Arbitrary metadata.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+    /// Platform of a manifest referenced from an index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<Platform>,
+    /// Artifact type of a manifest referenced from an index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_type: Option<String>,
+}
+
+/// Platform of a manifest in an index.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Platform {
+    /// CPU architecture, such as `amd64`.
+    #[serde(default)]
+    pub architecture: String,
+    /// Operating system, such as `linux`.
+    #[serde(default)]
+    pub os: String,
+    /// Operating system version, such as `10.0.17763.1040` on Windows.
+    #[serde(
+        rename = "os.version",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub os_version: Option<String>,
+    /// Required operating system features.
+    #[serde(rename = "os.features", default, skip_serializing_if = "Vec::is_empty")]
+    pub os_features: Vec<String>,
+    /// CPU variant, such as `v8`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+}
+
+/// An OCI image manifest or Docker schema 2 manifest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageManifest {
+    /// Always 2 for valid manifests.
+    #[serde(default)]
+    pub schema_version: u32,
+    /// Media type, which OCI manifests may omit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    /// Type of artifact, when the manifest is not a container image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_type: Option<String>,
+    /// The config blob.
+    pub config: Descriptor,
+    /// Layers, bottom first.
+    #[serde(default)]
+    pub layers: Vec<Descriptor>,
+    /// Manifest this one refers to, for referrers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Descriptor>,
+    /// Arbitrary metadata.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// An OCI image index or Docker manifest list.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageIndex {
+    /// Always 2 for valid indexes.
+    #[serde(default)]
+    pub schema_version: u32,
+    /// Media type, which OCI indexes may omit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    /// Type of artifact, when the index is not a multi-platform image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_type: Option<String>,
+    /// The manifests in the index.
+    pub manifests: Vec<Descriptor>,
+    /// Manifest this one refers to, for referrers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Descriptor>,
+    /// Arbitrary metadata.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// An image config blob.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageConfig {
+    /// CPU architecture, such as `amd64`.
+    #[serde(default)]
+    pub architecture: String,
+    /// Operating system, such as `linux`.
+    #[serde(default)]
+    pub os: String,
+    /// CPU variant, such as `v8`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    /// Creation time as an RFC 3339 timestamp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    /// Execution parameters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<ContainerConfig>,
+    /// Uncompressed layer digests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rootfs: Option<RootFs>,
+}
+
+/// Execution parameters of an image; Docker writes `null` for unset fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerConfig {
+    /// Environment variables, as `KEY=value`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<Vec<String>>,
+    /// Entrypoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entrypoint: Option<Vec<String>>,
+    /// Default command.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cmd: Option<Vec<String>>,
+    /// Image labels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub labels: Option<BTreeMap<String, String>>,
+    /// Working directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    /// User the process runs as.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// Uncompressed layer digests of an image config.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootFs {
+    /// Always `layers`.
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    /// Digests of the uncompressed layers, bottom first.
+    #[serde(default)]
+    pub diff_ids: Vec<String>,
+}
+
+/// A parsed manifest of either kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Manifest {
+    /// A single-platform image or artifact.
+    Image(Box<ImageManifest>),
+    /// A list of manifests.
+    Index(Box<ImageIndex>),
+}
+
+impl Manifest {
+    /// Parses a manifest body sent or served with `content_type`.
+    ///
+    /// The kind is taken from the body's `mediaType` when present, then
+    /// from `content_type`, then from whether the body lists `manifests`.
+    pub fn parse(content_type: &str, data: &[u8]) -> Result<Self> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Probe {
+            media_type: Option<String>,
+            manifests: Option<serde_json::Value>,
+        }
+
+        let invalid = |e: serde_json::Error| RegistryError::InvalidManifest(e.to_string());
+        let probe: Probe = serde_json::from_slice(data).map_err(invalid)?;
+        let media_type = probe.media_type.as_deref().unwrap_or(content_type);
+        let is_index = match media_type {
+            OCI_INDEX_MEDIA_TYPE | DOCKER_MANIFEST_LIST_MEDIA_TYPE => true,
+            OCI_MANIFEST_MEDIA_TYPE | DOCKER_MANIFEST_MEDIA_TYPE => false,
+            _ => probe.manifests.is_some(),
+        };
+        if is_index {
+            serde_json::from_slice(data)
+                .map(Self::Index)
+                .map_err(invalid)
+        } else {
+            serde_json::from_slice(data)
+                .map(Self::Image)
+                .map_err(invalid)
+        }
+    }
+
+    /// Returns the media type declared in the body, if any.
+    pub fn media_type(&self) -> Option<&str> {
+        match self {
+            Self::Image(manifest) => manifest.media_type.as_deref(),
+            Self::Index(index) => index.media_type.as_deref(),
+        }
+    }
+
+    /// Returns the schema version declared in the body.
+    pub fn schema_version(&self) -> u32 {
+        match self {
+            Self::Image(manifest) => manifest.schema_version,
+            Self::Index(index) => index.schema_version,
+        }
+    }
+
+    /// Returns every descriptor in the manifest: config and layers, or the
+    /// child manifests, followed by the subject.
+    pub fn descriptors(&self) -> Vec<&Descriptor> {
+        let (mut descriptors, subject): (Vec<&Descriptor>, _) = match self {
+            Self::Image(manifest) => (
+                std::iter::once(&manifest.config)
+                    .chain(&manifest.layers)
+                    .collect(),
+                &manifest.subject,
+            ),
+            Self::Index(index) => (index.manifests.iter().collect(), &index.subject),
+        };
+        descriptors.extend(subject);
+        descriptors
+    }
+
+    /// Checks the schema rules: version 2, a `mediaType` agreeing with
+    /// `content_type`, and well-formed descriptors.
+    pub fn validate(&self, content_type: &str) -> Result<()> {
+        let invalid = |message: String| Err(RegistryError::InvalidManifest(message));
+        if self.schema_version() != 2 {
+            return invalid(format!(
+                "unsupported schemaVersion {}",
+                self.schema_version()
+            ));
+        }
+        if let Some(media_type) = self.media_type() {
+            if media_type != content_type {
+                return invalid(format!(
+                    "mediaType {:?} does not match Content-Type {:?}",
+                    media_type, content_type
+                ));
+            }
+        }
+        let digests = DigestPolicy::new().strict(true);
+        for descriptor in self.descriptors() {
+            if descriptor.media_type.is_empty() {
+                return invalid(format!("descriptor {} has no mediaType", descriptor.digest));
+            }
+            if let Err(message) = digests.canonicalize(&descriptor.digest) {
+                return invalid(message);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How the content type of a pushed manifest is chosen.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::oci::manifest::{ContentTypePolicy, DOCKER_MANIFEST_MEDIA_TYPE};
+/// use registry_testkit::client::OCI_MANIFEST_MEDIA_TYPE;
+///
+/// let body = br#"{"schemaVersion":2,"mediaType":"application/vnd.oci.image.manifest.v1+json"}"#;
+/// let detected = ContentTypePolicy::Detect.resolve(None, body).unwrap();
+/// assert_eq!(detected, OCI_MANIFEST_MEDIA_TYPE);
+///
+/// let legacy = ContentTypePolicy::Fallback(DOCKER_MANIFEST_MEDIA_TYPE.to_string());
+/// assert_eq!(legacy.resolve(None, body).unwrap(), DOCKER_MANIFEST_MEDIA_TYPE);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ContentTypePolicy {
+    /// Keeps the request's `Content-Type` unless it is missing or generic
+    /// (`application/json`, `application/octet-stream`), in which case the
+    /// type is detected from the body's `mediaType` or shape. A manifest
+    /// `Content-Type` contradicting the body's `mediaType` is rejected.
+    #[default]
+    Detect,
+    /// Keeps the request's `Content-Type` as sent, using this type when it
+    /// is missing, without looking at the body.
+    Fallback(String),
+}
+
+impl ContentTypePolicy {
+    /// Returns the content type to store a manifest under, given the
+    /// request's `Content-Type`.
+    pub fn resolve(&self, content_type: Option<&str>, data: &[u8]) -> Result<String> {
+        let fallback = match self {
+            Self::Fallback(media_type) => {
+                return Ok(content_type.unwrap_or(media_type).to_string());
+            }
+            Self::Detect => DOCKER_MANIFEST_MEDIA_TYPE,
+        };
+
+        let essence = content_type.map(|c| c.split(';').next().unwrap_or_default().trim());
+        let generic = match essence {
+            None | Some("" | "application/json" | "application/octet-stream") => true,
+            Some(_) => false,
+        };
+        if generic {
+            return Ok(detect_media_type(data).unwrap_or_else(|| fallback.to_string()));
+        }
+
+        let content_type = content_type.unwrap_or_default();
+        if is_manifest_media_type(content_type) {
+            if let Some(declared) = declared_media_type(data) {
+                if is_manifest_media_type(&declared) && declared != content_type {
+                    return Err(RegistryError::InvalidManifest(format!(
+                        "mediaType {:?} does not match Content-Type {:?}",
+                        declared, content_type
+                    )));
+                }
+            }
+        }
+        Ok(content_type.to_string())
+    }
+}
+
+fn is_manifest_media_type(media_type: &str) -> bool {
+    matches!(
+        media_type,
+        OCI_MANIFEST_MEDIA_TYPE
+            | OCI_INDEX_MEDIA_TYPE
+            | DOCKER_MANIFEST_MEDIA_TYPE
+            | DOCKER_MANIFEST_LIST_MEDIA_TYPE
+    )
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MediaTypeProbe {
+    #[serde(default)]
+    media_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ShapeProbe {
+    schema_version: Option<u32>,
+    signatures: Option<serde_json::Value>,
+    #[serde(default)]
+    media_type: Option<String>,
+    config: Option<MediaTypeProbe>,
+    manifests: Option<Vec<MediaTypeProbe>>,
+}
+
+fn declared_media_type(data: &[u8]) -> Option<String> {
+    serde_json::from_slice::<MediaTypeProbe>(data)
+        .ok()?
+        .media_type
+        .filter(|media_type| !media_type.is_empty())
+}
+
+/// Detects the media type of a manifest body: its declared `mediaType`,
+/// else a Docker or OCI type inferred from the config or child manifests,
+/// or a schema 1 type from its `schemaVersion`.
+pub fn detect_media_type(data: &[u8]) -> Option<String> {
+    let probe: ShapeProbe = serde_json::from_slice(data).ok()?;
+    if let Some(media_type) = probe.media_type.filter(|m| !m.is_empty()) {
+        return Some(media_type);
+    }
+    if probe.schema_version == Some(1) {
+        return Some(
+            if probe.signatures.is_some() {
+                DOCKER_SCHEMA1_SIGNED_MEDIA_TYPE
+            } else {
+                DOCKER_SCHEMA1_MEDIA_TYPE
+            }
+            .to_string(),
+        );
+    }
+    if let Some(manifests) = probe.manifests {
+        let docker = manifests
+            .iter()
+            .any(|m| m.media_type.as_deref() == Some(DOCKER_MANIFEST_MEDIA_TYPE));
+        return Some(
+            if docker {
+                DOCKER_MANIFEST_LIST_MEDIA_TYPE
+            } else {
+                OCI_INDEX_MEDIA_TYPE
+            }
+            .to_string(),
+        );
+    }
+    let config = probe.config?;
+    let docker = config.media_type.as_deref() == Some(DOCKER_CONFIG_MEDIA_TYPE);
+    Some(
+        if docker {
+            DOCKER_MANIFEST_MEDIA_TYPE
+        } else {
+            OCI_MANIFEST_MEDIA_TYPE
+        }
+        .to_string(),
+    )
+}
+
+/// Returns the digest of the subject a manifest refers to, if any.
+pub(crate) fn subject_digest(data: &[u8]) -> Option<String> {
+    let manifest: serde_json::Value = serde_json::from_slice(data).ok()?;
+    manifest["subject"]["digest"].as_str().map(str::to_string)
+}
@@ -0,0 +1,4 @@
+//! Typed OCI and Docker data structures.
+
+pub mod manifest;
+pub mod schema1;
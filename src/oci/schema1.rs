@@ -0,0 +1,192 @@
+//! Legacy Docker image manifest schema 1.
+//!
+//! Schema 1 manifests list layers top first as `fsLayers`, each paired with
+//! a `history` entry whose `v1Compatibility` string holds the image config
+//! of that layer. There is no config blob; the registry builds one when
+//! converting them to schema 2.
+
+use crate::digest::sha256_digest;
+use crate::error::{RegistryError, Result};
+use crate::gzip;
+use crate::oci::manifest::{DOCKER_CONFIG_MEDIA_TYPE, DOCKER_MANIFEST_MEDIA_TYPE};
+use serde::{Deserialize, Serialize};
+
+/// Media type of unsigned schema 1 manifests.
+pub const DOCKER_SCHEMA1_MEDIA_TYPE: &str = "application/vnd.docker.distribution.manifest.v1+json";
+/// Media type of signed schema 1 manifests.
+pub const DOCKER_SCHEMA1_SIGNED_MEDIA_TYPE: &str =
+    "application/vnd.docker.distribution.manifest.v1+prettyjws";
+/// Media type of gzip-compressed Docker layers.
+pub const DOCKER_LAYER_MEDIA_TYPE: &str = "application/vnd.docker.image.rootfs.diff.tar.gzip";
+/// Media type of uncompressed Docker layers.
+pub const DOCKER_LAYER_TAR_MEDIA_TYPE: &str = "application/vnd.docker.image.rootfs.diff.tar";
+
+/// What `PUT /v2/<name>/manifests/<reference>` does with schema 1 manifests.
+///
+/// Without a mode they are handled like any other body, which means
+/// [`ValidationLevel::Schema`](crate::oci::manifest::ValidationLevel::Schema)
+/// and above reject them for their `schemaVersion`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schema1Mode {
+    /// Stores them as pushed, exempt from manifest validation.
+    Accept,
+    /// Stores a schema 2 manifest and config built from them instead. The
+    /// layers must already be pushed.
+    Convert,
+    /// Rejects them with `MANIFEST_INVALID`.
+    Reject,
+}
+
+/// A schema 1 image manifest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schema1Manifest {
+    /// Always 1.
+    pub schema_version: u32,
+    /// Repository name.
+    #[serde(default)]
+    pub name: String,
+    /// Tag.
+    #[serde(default)]
+    pub tag: String,
+    /// CPU architecture.
+    #[serde(default)]
+    pub architecture: String,
+    /// Layers, top first.
+    #[serde(default)]
+    pub fs_layers: Vec<FsLayer>,
+    /// Per-layer configs, top first.
+    #[serde(default)]
+    pub history: Vec<V1History>,
+}
+
+/// A layer of a schema 1 manifest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FsLayer {
+    /// Digest of the layer blob.
+    pub blob_sum: String,
+}
+
+/// A history entry of a schema 1 manifest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct V1History {
+    /// JSON image config of the matching layer, as a string.
+    pub v1_compatibility: String,
+}
+
+/// Returns whether a manifest sent with `content_type` is schema 1.
+pub fn is_schema1(content_type: &str, data: &[u8]) -> bool {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Probe {
+        schema_version: Option<u32>,
+    }
+
+    matches!(
+        content_type,
+        DOCKER_SCHEMA1_MEDIA_TYPE | DOCKER_SCHEMA1_SIGNED_MEDIA_TYPE
+    ) || serde_json::from_slice::<Probe>(data).is_ok_and(|p| p.schema_version == Some(1))
+}
+
+/// A schema 2 manifest converted from schema 1, with its config blob.
+pub(crate) struct Converted {
+    pub(crate) manifest: Vec<u8>,
+    pub(crate) config: Vec<u8>,
+}
+
+/// Converts a schema 1 manifest to schema 2, reading layer blobs through
+/// `blob` to size them and compute their diff IDs.
+///
+/// Layers marked `throwaway` are left out and recorded as empty layers in
+/// the config history.
+pub(crate) async fn convert<F, Fut>(data: &[u8], blob: F) -> Result<Converted>
+where
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = Result<Option<Vec<u8>>>>,
+{
+    let invalid = |message: String| RegistryError::InvalidManifest(message);
+    let manifest: Schema1Manifest =
+        serde_json::from_slice(data).map_err(|e| invalid(e.to_string()))?;
+    if manifest.fs_layers.len() != manifest.history.len() || manifest.history.is_empty() {
+        return Err(invalid(
+            "fsLayers and history must be non-empty and of equal length".to_string(),
+        ));
+    }
+
+    let mut layers = Vec::new();
+    let mut diff_ids = Vec::new();
+    let mut history = Vec::new();
+    for (layer, entry) in manifest.fs_layers.iter().zip(&manifest.history).rev() {
+        let v1: serde_json::Value = serde_json::from_str(&entry.v1_compatibility)
+            .map_err(|e| invalid(format!("invalid v1Compatibility: {}", e)))?;
+        let throwaway = v1["throwaway"].as_bool().unwrap_or(false);
+        let created_by = v1["container_config"]["Cmd"]
+            .as_array()
+            .map(|cmd| {
+                cmd.iter()
+                    .filter_map(|arg| arg.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default();
+        let mut item = serde_json::json!({ "created_by": created_by });
+        if let Some(created) = v1.get("created") {
+            item["created"] = created.clone();
+        }
+        if throwaway {
+            item["empty_layer"] = serde_json::json!(true);
+            history.push(item);
+            continue;
+        }
+        history.push(item);
+
+        let data = blob(layer.blob_sum.clone())
+            .await?
+            .ok_or_else(|| RegistryError::ManifestBlobUnknown(layer.blob_sum.clone()))?;
+        let (media_type, diff_id) = if gzip::is_gzip(&data) {
+            let tar = gzip::decompress(&data)
+                .map_err(|e| invalid(format!("layer {}: {}", layer.blob_sum, e)))?;
+            (DOCKER_LAYER_MEDIA_TYPE, sha256_digest(&tar))
+        } else {
+            (DOCKER_LAYER_TAR_MEDIA_TYPE, sha256_digest(&data))
+        };
+        layers.push(serde_json::json!({
+            "mediaType": media_type,
+            "size": data.len(),
+            "digest": layer.blob_sum,
+        }));
+        diff_ids.push(diff_id);
+    }
+
+    let mut config: serde_json::Value = serde_json::from_str(&manifest.history[0].v1_compatibility)
+        .map_err(|e| invalid(format!("invalid v1Compatibility: {}", e)))?;
+    let object = config
+        .as_object_mut()
+        .ok_or_else(|| invalid("v1Compatibility is not an object".to_string()))?;
+    for key in ["id", "parent", "Size", "parent_id", "layer_id", "throwaway"] {
+        object.remove(key);
+    }
+    object.insert(
+        "rootfs".to_string(),
+        serde_json::json!({ "type": "layers", "diff_ids": diff_ids }),
+    );
+    object.insert("history".to_string(), serde_json::json!(history));
+    let config = serde_json::to_vec(&config)?;
+
+    let converted = serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": DOCKER_MANIFEST_MEDIA_TYPE,
+        "config": {
+            "mediaType": DOCKER_CONFIG_MEDIA_TYPE,
+            "size": config.len(),
+            "digest": sha256_digest(&config),
+        },
+        "layers": layers,
+    });
+    Ok(Converted {
+        manifest: serde_json::to_vec(&converted)?,
+        config,
+    })
+}
@@ -0,0 +1,42 @@
+//! `n`/`last` pagination of the catalog and tag lists.
+
+use serde::Deserialize;
+
+/// Pagination query as the distribution spec defines it: at most `n`
+/// entries, starting after the entry `last`.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct Pagination {
+    n: Option<usize>,
+    last: Option<String>,
+}
+
+impl Pagination {
+    /// Returns the page of `entries` the query asks for, and the query of
+    /// the next page if entries remain after it. `entries` are sorted,
+    /// in reverse if `descending`.
+    pub(crate) fn page(
+        &self,
+        mut entries: Vec<String>,
+        descending: bool,
+    ) -> (Vec<String>, Option<String>) {
+        if let Some(last) = &self.last {
+            entries.retain(|entry| {
+                if descending {
+                    entry < last
+                } else {
+                    entry > last
+                }
+            });
+        }
+        let Some(n) = self.n else {
+            return (entries, None);
+        };
+        if entries.len() <= n {
+            return (entries, None);
+        }
+        entries.truncate(n);
+        // Tags and repository names need no escaping in a query string.
+        let next = entries.last().map(|last| format!("n={}&last={}", n, last));
+        (entries, next)
+    }
+}
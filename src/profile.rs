@@ -0,0 +1,100 @@
+//! Emulation profiles for well-known hosted registries.
+
+use crate::auth::AuthConfig;
+use crate::ratelimit::PullRateLimit;
+use std::time::Duration;
+
+/// A hosted registry whose quirks the server can emulate.
+///
+/// Select one with [`RegistryConfig::emulate`](crate::RegistryConfig::emulate).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RegistryProfile {
+    /// Plain distribution-spec behavior.
+    #[default]
+    Generic,
+    /// Docker Hub: single-component names live under `library/`, token auth
+    /// through an `auth.docker.io`-style realm with anonymous pulls, and pull
+    /// rate-limit headers on manifest GETs.
+    DockerHub,
+    /// Amazon ECR: basic auth with `GetAuthorizationToken`-style tokens
+    /// (see [`RegistryServer::authorization_token`](crate::RegistryServer::authorization_token))
+    /// that expire after 12 hours, no `_catalog` endpoint, and ECR's JSON
+    /// error payloads.
+    Ecr,
+    /// GitHub Container Registry: PATs are exchanged for (or used directly
+    /// as basic passwords in place of) `ghcr.io` bearer tokens, users may
+    /// only push to their own namespace, and private packages answer `404`
+    /// to clients without access.
+    Ghcr,
+    /// Quay: token auth for `quay.io`, `X-Registry-Supports-Signatures` on
+    /// every response, Quay error payloads, and `org+name` robot accounts
+    /// limited to their organization.
+    Quay,
+    /// Harbor: token auth for `harbor-registry`, Harbor error codes
+    /// (`NOT_FOUND`, `UNAUTHORIZED`), and `robot$project+name` robot
+    /// accounts limited to their project.
+    Harbor,
+}
+
+impl RegistryProfile {
+    /// Returns the canonical repository name for `name` under this profile.
+    ///
+    /// Docker Hub stores official images under `library/`, so `busybox`
+    /// becomes `library/busybox`.
+    pub fn normalize_name(&self, name: &str) -> String {
+        match self {
+            RegistryProfile::DockerHub if !name.contains('/') => format!("library/{}", name),
+            _ => name.to_string(),
+        }
+    }
+
+    /// Authentication the profile enables unless configured otherwise.
+    pub fn default_auth(&self) -> Option<AuthConfig> {
+        match self {
+            RegistryProfile::Generic => None,
+            RegistryProfile::DockerHub => {
+                Some(AuthConfig::bearer("registry.docker.io").with_anonymous_pull(true))
+            }
+            RegistryProfile::Ghcr => Some(AuthConfig::bearer("ghcr.io").with_anonymous_pull(true)),
+            RegistryProfile::Ecr => {
+                Some(AuthConfig::basic().with_token_ttl(Duration::from_secs(12 * 60 * 60)))
+            }
+            RegistryProfile::Quay => Some(AuthConfig::bearer("quay.io").with_anonymous_pull(true)),
+            RegistryProfile::Harbor => {
+                Some(AuthConfig::bearer("harbor-registry").with_anonymous_pull(true))
+            }
+        }
+    }
+
+    /// Returns the namespace a robot account is limited to, or `None` if
+    /// `user` is not a robot account under this profile.
+    ///
+    /// ```
+    /// use registry_testkit::RegistryProfile;
+    ///
+    /// assert_eq!(RegistryProfile::Quay.robot_namespace("acme+ci"), Some("acme"));
+    /// assert_eq!(RegistryProfile::Harbor.robot_namespace("robot$acme+ci"), Some("acme"));
+    /// assert_eq!(RegistryProfile::Harbor.robot_namespace("alice"), None);
+    /// ```
+    pub fn robot_namespace<'a>(&self, user: &'a str) -> Option<&'a str> {
+        let account = match self {
+            RegistryProfile::Quay => user,
+            RegistryProfile::Harbor => user.strip_prefix("robot$")?,
+            _ => return None,
+        };
+        account.split_once('+').map(|(namespace, _)| namespace)
+    }
+
+    /// Whether the registry serves `GET /v2/_catalog`.
+    pub fn supports_catalog(&self) -> bool {
+        !matches!(self, RegistryProfile::Ecr)
+    }
+
+    /// Pull rate limit the profile enables unless configured otherwise.
+    pub fn default_pull_rate_limit(&self) -> Option<PullRateLimit> {
+        match self {
+            RegistryProfile::DockerHub => Some(PullRateLimit::docker_hub()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Simulation of a reverse proxy in front of the registry.
+//!
+//! Registries usually sit behind a load balancer or ingress that adds
+//! `X-Forwarded-*` headers, rewrites `Host`, drops headers it does not
+//! know and sometimes talks HTTP/1.0 to its backend. Clients that build
+//! URLs from the wrong header or rely on chunked responses break there but
+//! not against a bare registry. With a [`ProxySimulation`] the registry
+//! mangles every request and response that way before handling it, so such
+//! bugs reproduce locally.
+
+use crate::config::MAX_BODY_SIZE;
+use crate::server::error_response;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode, Version},
+    middleware::Next,
+    response::Response,
+};
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+
+/// How the simulated proxy mangles requests and responses.
+///
+/// Every request gets `X-Forwarded-For`, `X-Forwarded-Host` and
+/// `X-Forwarded-Proto` headers; everything else is opt-in.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::{ProxySimulation, RegistryConfig};
+///
+/// let config = RegistryConfig::memory().with_proxy_simulation(
+///     ProxySimulation::new()
+///         .with_host("registry.internal:5000")
+///         .strip_header("docker-content-digest")
+///         .with_http10(true),
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxySimulation {
+    host: Option<String>,
+    forwarded_proto: String,
+    strip_headers: Vec<HeaderName>,
+    http10: bool,
+}
+
+impl Default for ProxySimulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProxySimulation {
+    /// Creates a proxy that only adds `X-Forwarded-*` headers, claiming
+    /// clients connected over HTTPS.
+    pub fn new() -> Self {
+        Self {
+            host: None,
+            forwarded_proto: "https".to_string(),
+            strip_headers: Vec::new(),
+            http10: false,
+        }
+    }
+
+    /// Replaces the `Host` header with `host`, as proxies forwarding to a
+    /// backend by its internal name do. The original host is still sent
+    /// as `X-Forwarded-Host`.
+    pub fn with_host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// Sets the `X-Forwarded-Proto` the proxy sends, `https` by default.
+    pub fn with_forwarded_proto(mut self, proto: impl Into<String>) -> Self {
+        self.forwarded_proto = proto.into();
+        self
+    }
+
+    /// Removes request header `name` before the registry sees it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is not a valid header name.
+    pub fn strip_header(mut self, name: &str) -> Self {
+        self.strip_headers
+            .push(HeaderName::from_bytes(name.as_bytes()).expect("invalid header name"));
+        self
+    }
+
+    /// Talks HTTP/1.0 to the registry: `Expect` is dropped, request and
+    /// response bodies are buffered and sent with `Content-Length` instead
+    /// of chunked, and every response closes its connection.
+    pub fn with_http10(mut self, enabled: bool) -> Self {
+        self.http10 = enabled;
+        self
+    }
+
+    async fn forward(&self, mut request: Request, next: Next) -> Response {
+        let remote = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(remote)| remote.ip().to_string());
+        let headers = request.headers_mut();
+        for name in &self.strip_headers {
+            headers.remove(name);
+        }
+        if let Some(remote) = remote {
+            // Proxies append to the chain a client or earlier proxy sent.
+            let chain = match headers.get(&X_FORWARDED_FOR).map(HeaderValue::to_str) {
+                Some(Ok(chain)) => format!("{}, {}", chain, remote),
+                _ => remote,
+            };
+            insert(headers, X_FORWARDED_FOR, &chain);
+        }
+        if let Some(host) = headers.get(header::HOST).cloned() {
+            headers.insert(X_FORWARDED_HOST, host);
+        }
+        insert(headers, X_FORWARDED_PROTO, &self.forwarded_proto);
+        if let Some(host) = &self.host {
+            insert(headers, header::HOST, host);
+        }
+
+        if !self.http10 {
+            return next.run(request).await;
+        }
+        let (mut parts, body) = request.into_parts();
+        let body = match to_bytes(body, MAX_BODY_SIZE).await {
+            Ok(body) => body,
+            Err(e) => {
+                return error_response(StatusCode::BAD_GATEWAY, "UNKNOWN", &e.to_string());
+            }
+        };
+        parts.version = Version::HTTP_10;
+        parts.headers.remove(header::EXPECT);
+        parts.headers.remove(header::TRANSFER_ENCODING);
+        parts
+            .headers
+            .insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+        let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+        let (mut parts, body) = response.into_parts();
+        let body = match to_bytes(body, MAX_BODY_SIZE).await {
+            Ok(body) => body,
+            Err(e) => {
+                return error_response(StatusCode::BAD_GATEWAY, "UNKNOWN", &e.to_string());
+            }
+        };
+        parts.headers.remove(header::TRANSFER_ENCODING);
+        parts
+            .headers
+            .insert(header::CONNECTION, HeaderValue::from_static("close"));
+        // HEAD responses keep the length of the body they describe.
+        if !body.is_empty() || !parts.headers.contains_key(header::CONTENT_LENGTH) {
+            parts
+                .headers
+                .insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+        }
+        Response::from_parts(parts, Body::from(body))
+    }
+}
+
+fn insert(headers: &mut HeaderMap, name: HeaderName, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(name, value);
+    }
+}
+
+/// The proxy simulation in effect, switchable while the server runs.
+pub(crate) type ProxySwitch = Arc<RwLock<Option<Arc<ProxySimulation>>>>;
+
+/// Passes requests through the proxy simulation, if one is switched on.
+pub(crate) async fn simulate_proxy(
+    State(proxy): State<ProxySwitch>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let proxy = proxy.read().unwrap().clone();
+    match proxy {
+        Some(proxy) => proxy.forward(request, next).await,
+        None => next.run(request).await,
+    }
+}
@@ -0,0 +1,127 @@
+//! Per-profile response quirks: error payloads and extra headers.
+
+use crate::profile::RegistryProfile;
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+
+/// An error as a hosted registry reports it.
+struct ProfileError {
+    code: &'static str,
+    message: String,
+}
+
+impl ProfileError {
+    fn new(code: &'static str, message: impl Into<String>) -> Option<Self> {
+        Some(Self {
+            code,
+            message: message.into(),
+        })
+    }
+}
+
+/// The error `profile` sends for a failed request to `path`.
+fn error_for(profile: RegistryProfile, path: &str, status: StatusCode) -> Option<ProfileError> {
+    let repository = crate::server::split_repository_path(path);
+    if status == StatusCode::UNAUTHORIZED {
+        return match profile {
+            RegistryProfile::Ecr => ProfileError::new("DENIED", "Not Authorized"),
+            RegistryProfile::Quay => ProfileError::new(
+                "UNAUTHORIZED",
+                "access to the requested resource is not authorized",
+            ),
+            RegistryProfile::Harbor => {
+                let name = repository.map(|(name, _)| name).unwrap_or_default();
+                ProfileError::new(
+                    "UNAUTHORIZED",
+                    format!("unauthorized to access repository: {}", name),
+                )
+            }
+            _ => None,
+        };
+    }
+    if status != StatusCode::NOT_FOUND {
+        return None;
+    }
+
+    let (name, rest) = repository?;
+    let (endpoint, reference) = rest.split_once('/')?;
+    let upload = reference.starts_with("uploads/");
+    match (profile, endpoint) {
+        (RegistryProfile::Ecr, "manifests") => {
+            ProfileError::new("MANIFEST_UNKNOWN", "Requested image not found")
+        }
+        (RegistryProfile::Ecr, "blobs") if upload => ProfileError::new(
+            "BLOB_UPLOAD_UNKNOWN",
+            format!("Upload not found in the repository with name '{}'", name),
+        ),
+        (RegistryProfile::Ecr, "blobs") => ProfileError::new(
+            "BLOB_UNKNOWN",
+            format!("Layer '{}' not found in repository '{}'", reference, name),
+        ),
+        (RegistryProfile::Quay, "manifests") => {
+            ProfileError::new("MANIFEST_UNKNOWN", "manifest unknown")
+        }
+        (RegistryProfile::Quay, "blobs") if upload => {
+            ProfileError::new("BLOB_UPLOAD_UNKNOWN", "blob upload unknown to registry")
+        }
+        (RegistryProfile::Quay, "blobs") => ProfileError::new("BLOB_UNKNOWN", "blob unknown"),
+        (RegistryProfile::Harbor, "manifests") => ProfileError::new(
+            "NOT_FOUND",
+            format!("artifact {}:{} not found", name, reference),
+        ),
+        (RegistryProfile::Harbor, "blobs") if upload => {
+            ProfileError::new("BLOB_UPLOAD_UNKNOWN", "blob upload unknown to registry")
+        }
+        (RegistryProfile::Harbor, "blobs") => {
+            ProfileError::new("NOT_FOUND", format!("blob {} not found", reference))
+        }
+        _ => None,
+    }
+}
+
+fn payload(profile: RegistryProfile, error: ProfileError) -> String {
+    let mut entry = serde_json::json!({ "code": error.code, "message": error.message });
+    if profile == RegistryProfile::Quay {
+        entry["detail"] = serde_json::json!({});
+    }
+    serde_json::json!({ "errors": [entry] }).to_string()
+}
+
+/// Adds the profile's headers and replaces the bodies of error responses
+/// with its JSON payloads.
+pub(crate) async fn profile_quirks(
+    State(profile): State<RegistryProfile>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    let is_head = request.method() == Method::HEAD;
+    let mut response = next.run(request).await;
+
+    if profile == RegistryProfile::Quay {
+        response.headers_mut().insert(
+            "X-Registry-Supports-Signatures",
+            HeaderValue::from_static("1"),
+        );
+    }
+
+    if is_head {
+        return response;
+    }
+
+    let Some(error) = error_for(profile, &path, response.status()) else {
+        return response;
+    };
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json; charset=utf-8"),
+    );
+    Response::from_parts(parts, Body::from(payload(profile, error)))
+}
@@ -0,0 +1,151 @@
+//! Per-client request quotas.
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// How clients are told apart for quota accounting.
+#[derive(Debug, Clone, Default)]
+pub enum QuotaKey {
+    /// The peer IP address of the connection.
+    #[default]
+    ClientAddr,
+    /// The first value of a request header such as `X-Forwarded-For`,
+    /// falling back to the peer address when absent.
+    Header(String),
+}
+
+/// Request quota applied per client within a fixed window.
+#[derive(Debug, Clone)]
+pub struct QuotaConfig {
+    /// Requests allowed per client in each window.
+    pub limit: u64,
+    /// Length of the accounting window.
+    pub window: Duration,
+    /// How clients are identified.
+    pub key: QuotaKey,
+    /// Per-client limits overriding `limit`, keyed by client identity.
+    pub overrides: HashMap<String, u64>,
+}
+
+impl QuotaConfig {
+    /// Allows `limit` requests per client every `window`.
+    pub fn new(limit: u64, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            key: QuotaKey::ClientAddr,
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Identifies clients by the given key.
+    pub fn with_key(mut self, key: QuotaKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Identifies clients by the `X-Forwarded-For` header, as behind a proxy.
+    pub fn behind_proxy(self) -> Self {
+        self.with_key(QuotaKey::Header("X-Forwarded-For".to_string()))
+    }
+
+    /// Sets a distinct limit for one client (an IP address or header value).
+    pub fn with_client_limit(mut self, client: impl Into<String>, limit: u64) -> Self {
+        self.overrides.insert(client.into(), limit);
+        self
+    }
+}
+
+struct Usage {
+    window_start: Instant,
+    count: u64,
+}
+
+/// Tracks request counts per client.
+pub(crate) struct QuotaTracker {
+    config: QuotaConfig,
+    usage: Mutex<HashMap<String, Usage>>,
+}
+
+impl QuotaTracker {
+    pub(crate) fn new(config: QuotaConfig) -> Self {
+        Self {
+            config,
+            usage: Mutex::default(),
+        }
+    }
+
+    fn client_key(&self, request: &Request) -> String {
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string())
+            .unwrap_or_default();
+
+        match &self.config.key {
+            QuotaKey::ClientAddr => peer,
+            QuotaKey::Header(name) => request
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+                .map(|v| v.trim().to_string())
+                .unwrap_or(peer),
+        }
+    }
+
+    /// Counts a request and returns the seconds until reset if over quota.
+    async fn check(&self, client: &str) -> Option<u64> {
+        let limit = self
+            .config
+            .overrides
+            .get(client)
+            .copied()
+            .unwrap_or(self.config.limit);
+
+        let mut usage = self.usage.lock().await;
+        let now = Instant::now();
+        let entry = usage.entry(client.to_string()).or_insert(Usage {
+            window_start: now,
+            count: 0,
+        });
+        if now.duration_since(entry.window_start) >= self.config.window {
+            entry.window_start = now;
+            entry.count = 0;
+        }
+
+        if entry.count >= limit {
+            let reset = self.config.window - now.duration_since(entry.window_start);
+            return Some(reset.as_secs().max(1));
+        }
+        entry.count += 1;
+        None
+    }
+}
+
+pub(crate) async fn enforce_quota(
+    State(tracker): State<Arc<QuotaTracker>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client = tracker.client_key(&request);
+    if let Some(retry_after) = tracker.check(&client).await {
+        warn!("Quota exceeded for client {}", client);
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [("Retry-After", retry_after.to_string())],
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
@@ -0,0 +1,171 @@
+//! Docker Hub style pull rate limiting with `ratelimit-*` headers.
+
+use crate::server::error_response;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Configuration for Docker Hub style pull rate limiting.
+///
+/// Every manifest `GET` consumes one pull from the client's budget and
+/// responses carry `ratelimit-limit`, `ratelimit-remaining` and
+/// `docker-ratelimit-source` headers; `HEAD` requests report the budget
+/// without consuming it. Once the budget is exhausted pulls get `429 Too
+/// Many Requests` until the window resets.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::{PullRateLimit, RegistryConfig};
+/// use std::time::Duration;
+///
+/// let config = RegistryConfig::memory()
+///     .with_pull_rate_limit(PullRateLimit::new(10, Duration::from_secs(60)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct PullRateLimit {
+    /// Pulls allowed per window.
+    pub limit: u64,
+    /// Window after which the budget resets.
+    pub window: Duration,
+    /// Value of `docker-ratelimit-source`; the client IP if `None`.
+    pub source: Option<String>,
+}
+
+impl PullRateLimit {
+    /// Allows `limit` pulls per `window` for each client.
+    pub fn new(limit: u64, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            source: None,
+        }
+    }
+
+    /// Docker Hub's anonymous limit: 100 pulls per 6 hours.
+    pub fn docker_hub() -> Self {
+        Self::new(100, Duration::from_secs(6 * 60 * 60))
+    }
+
+    /// Reports a fixed `docker-ratelimit-source` instead of the client IP.
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+}
+
+impl Default for PullRateLimit {
+    fn default() -> Self {
+        Self::docker_hub()
+    }
+}
+
+struct Budget {
+    window_start: Instant,
+    used: u64,
+}
+
+/// Tracks the per-client manifest pull budget.
+pub(crate) struct PullRateLimiter {
+    config: PullRateLimit,
+    budgets: Mutex<HashMap<String, Budget>>,
+}
+
+impl PullRateLimiter {
+    pub(crate) fn new(config: PullRateLimit) -> Self {
+        Self {
+            config,
+            budgets: Mutex::default(),
+        }
+    }
+
+    /// Consumes one pull if `consume` is set and returns the remaining budget,
+    /// or `None` if the budget was already exhausted.
+    async fn take(&self, client: &str, consume: bool) -> Option<u64> {
+        let mut budgets = self.budgets.lock().await;
+        let now = Instant::now();
+        let budget = budgets.entry(client.to_string()).or_insert(Budget {
+            window_start: now,
+            used: 0,
+        });
+        if now.duration_since(budget.window_start) >= self.config.window {
+            budget.window_start = now;
+            budget.used = 0;
+        }
+        if consume {
+            if budget.used >= self.config.limit {
+                return None;
+            }
+            budget.used += 1;
+        }
+        Some(self.config.limit.saturating_sub(budget.used))
+    }
+
+    fn headers(&self, response: &mut Response, remaining: u64, source: &str) {
+        let window = self.config.window.as_secs();
+        let source = self.config.source.as_deref().unwrap_or(source);
+        let headers = response.headers_mut();
+        let values = [
+            (
+                "ratelimit-limit",
+                format!("{};w={}", self.config.limit, window),
+            ),
+            ("ratelimit-remaining", format!("{};w={}", remaining, window)),
+            ("docker-ratelimit-source", source.to_string()),
+        ];
+        for (name, value) in values {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                headers.insert(name, value);
+            }
+        }
+    }
+}
+
+fn is_manifest_path(path: &str) -> bool {
+    crate::server::split_repository_path(path)
+        .is_some_and(|(_, rest)| rest.starts_with("manifests/"))
+}
+
+/// Counts manifest GETs against the client's budget (HEADs only report it).
+pub(crate) async fn pull_rate_limit(
+    State(limiter): State<Arc<PullRateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    if !is_manifest_path(request.uri().path()) || !(method == Method::GET || method == Method::HEAD)
+    {
+        return next.run(request).await;
+    }
+
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_default();
+
+    match limiter.take(&client, method == Method::GET).await {
+        Some(remaining) => {
+            let mut response = next.run(request).await;
+            limiter.headers(&mut response, remaining, &client);
+            response
+        }
+        None => {
+            let mut response = error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                "TOOMANYREQUESTS",
+                "too many requests",
+            );
+            limiter.headers(&mut response, 0, &client);
+            response
+        }
+    }
+}
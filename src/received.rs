@@ -0,0 +1,154 @@
+//! The headers of the last requests received for each repository.
+//!
+//! Proxies, ingress controllers and sidecars in a test network may add,
+//! drop or rewrite headers before requests reach the registry. Keeping
+//! what actually arrived lets tests assert on it, over the admin API or
+//! with [`RegistryServer::received_requests`](crate::RegistryServer::received_requests).
+
+use crate::capture::REDACTED;
+use crate::history::rfc3339;
+use crate::profile::RegistryProfile;
+use crate::server::split_repository_path;
+use axum::{
+    extract::{Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Headers whose values are replaced by [`REDACTED`].
+const REDACTED_HEADERS: &[&str] = &["authorization", "proxy-authorization", "cookie"];
+
+/// A request as the registry received it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedRequest {
+    /// When the request arrived.
+    pub received: SystemTime,
+    /// Request method.
+    pub method: String,
+    /// Path and query.
+    pub uri: String,
+    /// Headers in the order received, with credentials redacted.
+    pub headers: Vec<(String, String)>,
+}
+
+impl ReceivedRequest {
+    /// Returns the values of header `name`, in the order received.
+    pub fn header(&self, name: &str) -> Vec<&str> {
+        self.headers
+            .iter()
+            .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+}
+
+/// JSON form served by the admin API.
+#[derive(Serialize)]
+pub(crate) struct ReceivedRequestBody {
+    received: String,
+    method: String,
+    uri: String,
+    headers: Vec<HeaderBody>,
+}
+
+#[derive(Serialize)]
+struct HeaderBody {
+    name: String,
+    value: String,
+}
+
+impl From<&ReceivedRequest> for ReceivedRequestBody {
+    fn from(request: &ReceivedRequest) -> Self {
+        Self {
+            received: rfc3339(request.received),
+            method: request.method.clone(),
+            uri: request.uri.clone(),
+            headers: request
+                .headers
+                .iter()
+                .map(|(name, value)| HeaderBody {
+                    name: name.clone(),
+                    value: value.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Keeps the last requests of each repository.
+pub(crate) struct ReceivedLog {
+    limit: usize,
+    profile: RegistryProfile,
+    requests: Mutex<HashMap<String, VecDeque<ReceivedRequest>>>,
+}
+
+impl ReceivedLog {
+    pub(crate) fn new(limit: usize, profile: RegistryProfile) -> Self {
+        Self {
+            limit,
+            profile,
+            requests: Mutex::default(),
+        }
+    }
+
+    /// Returns the requests kept for `repository`, oldest first.
+    pub(crate) fn requests(&self, repository: &str) -> Vec<ReceivedRequest> {
+        self.requests
+            .lock()
+            .unwrap()
+            .get(repository)
+            .map(|requests| requests.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn record(&self, repository: &str, request: ReceivedRequest) {
+        let repository = self.profile.normalize_name(repository);
+        let mut requests = self.requests.lock().unwrap();
+        let kept = requests.entry(repository).or_default();
+        if kept.len() == self.limit {
+            kept.pop_front();
+        }
+        kept.push_back(request);
+    }
+}
+
+fn headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if REDACTED_HEADERS.contains(&name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+/// Keeps the headers of requests to a repository.
+pub(crate) async fn record_received(
+    State(log): State<Arc<ReceivedLog>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if log.limit > 0 {
+        if let Some((repository, _)) = split_repository_path(request.uri().path()) {
+            log.record(
+                repository,
+                ReceivedRequest {
+                    received: SystemTime::now(),
+                    method: request.method().to_string(),
+                    uri: request.uri().to_string(),
+                    headers: headers(request.headers()),
+                },
+            );
+        }
+    }
+    next.run(request).await
+}
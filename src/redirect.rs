@@ -0,0 +1,105 @@
+//! Blob download redirects to a secondary "object storage" server.
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Configuration for redirecting blob downloads.
+///
+/// When enabled, `GET /v2/<name>/blobs/<digest>` answers `307 Temporary
+/// Redirect` to a signed, expiring URL on a second server hosted by the
+/// testkit, the way ECR and GCR hand layer downloads off to object storage.
+#[derive(Debug, Clone)]
+pub struct BlobRedirectConfig {
+    /// How long signed URLs stay valid.
+    pub url_ttl: Duration,
+    /// URL the blob server is reached at, when it is fronted by a proxy.
+    pub external_url: Option<String>,
+}
+
+impl BlobRedirectConfig {
+    /// Creates a redirect configuration with 15-minute signed URLs.
+    pub fn new() -> Self {
+        Self {
+            url_ttl: Duration::from_secs(15 * 60),
+            external_url: None,
+        }
+    }
+
+    /// Sets how long signed URLs stay valid.
+    pub fn with_url_ttl(mut self, ttl: Duration) -> Self {
+        self.url_ttl = ttl;
+        self
+    }
+
+    /// Sets the URL signed blob URLs point at instead of the blob server's
+    /// bound address.
+    pub fn with_external_url(mut self, url: impl Into<String>) -> Self {
+        self.external_url = Some(url.into().trim_end_matches('/').to_string());
+        self
+    }
+}
+
+impl Default for BlobRedirectConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Query parameters carried by signed blob URLs.
+#[derive(Deserialize)]
+pub(crate) struct SignedParams {
+    pub(crate) expires: u64,
+    pub(crate) signature: String,
+}
+
+/// Issues and verifies signed blob URLs for the blob server.
+pub(crate) struct BlobRedirector {
+    base_url: String,
+    secret: String,
+    ttl: Duration,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl BlobRedirector {
+    pub(crate) fn new(base_url: String, config: &BlobRedirectConfig) -> Self {
+        Self {
+            base_url,
+            secret: uuid::Uuid::new_v4().to_string(),
+            ttl: config.url_ttl,
+        }
+    }
+
+    pub(crate) fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn sign(&self, digest: &str, expires: u64) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.secret.as_bytes());
+        hasher.update(digest.as_bytes());
+        hasher.update(expires.to_be_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    pub(crate) fn signed_url(&self, digest: &str) -> String {
+        let expires = unix_now() + self.ttl.as_secs();
+        format!(
+            "{}/blobs/{}?expires={}&signature={}",
+            self.base_url,
+            digest,
+            expires,
+            self.sign(digest, expires)
+        )
+    }
+
+    pub(crate) fn verify(&self, digest: &str, params: &SignedParams) -> bool {
+        params.expires >= unix_now() && params.signature == self.sign(digest, params.expires)
+    }
+}
@@ -0,0 +1,267 @@
+//! Image references such as `ghcr.io/org/app:v1@sha256:...`.
+
+use crate::digest::DigestPolicy;
+use crate::error::{RegistryError, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// Registry that references without a registry part point at.
+pub const DEFAULT_REGISTRY: &str = "docker.io";
+/// Tag that references without a tag or digest point at.
+pub const DEFAULT_TAG: &str = "latest";
+
+/// A parsed image reference: registry, repository, and tag and/or digest.
+///
+/// Parsing follows the rules of the docker CLI: a first path component
+/// containing `.` or `:`, or equal to `localhost`, is a registry host;
+/// otherwise the reference is on Docker Hub, where single-component names
+/// live under `library/`. Familiar forms are expanded, so `busybox` parses
+/// to `docker.io/library/busybox:latest`, and
+/// [`familiar`](Self::familiar) shortens them again.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::Reference;
+///
+/// let reference: Reference = "busybox".parse().unwrap();
+/// assert_eq!(reference.registry(), "docker.io");
+/// assert_eq!(reference.repository(), "library/busybox");
+/// assert_eq!(reference.to_string(), "docker.io/library/busybox:latest");
+/// assert_eq!(reference.familiar(), "busybox:latest");
+///
+/// let reference = Reference::parse("127.0.0.1:5000/team/app:v1").unwrap();
+/// assert_eq!(reference.registry(), "127.0.0.1:5000");
+/// assert_eq!(reference.repository(), "team/app");
+/// assert_eq!(reference.reference(), "v1");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Reference {
+    registry: String,
+    repository: String,
+    tag: Option<String>,
+    digest: Option<String>,
+}
+
+impl Reference {
+    /// Parses and normalizes a reference.
+    ///
+    /// Fails with [`RegistryError::NameInvalid`] for malformed names and
+    /// tags and [`RegistryError::DigestInvalid`] for malformed digests.
+    pub fn parse(reference: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            RegistryError::NameInvalid(format!("reference {:?} {}", reference, reason))
+        };
+
+        let (name, digest) = match reference.split_once('@') {
+            Some((name, digest)) => {
+                let digest = DigestPolicy::new()
+                    .strict(true)
+                    .canonicalize(digest)
+                    .map_err(RegistryError::DigestInvalid)?;
+                (name, Some(digest))
+            }
+            None => (reference, None),
+        };
+        // A colon after the last slash separates the tag; earlier ones
+        // belong to a registry port.
+        let (name, tag) = match name.rsplit_once(':') {
+            Some((name, tag)) if !tag.contains('/') => (name, Some(tag.to_string())),
+            _ => (name, None),
+        };
+        if let Some(tag) = &tag {
+            if !valid_tag(tag) {
+                return Err(invalid("has an invalid tag"));
+            }
+        }
+
+        let (registry, repository) = match name.split_once('/') {
+            Some((host, path)) if is_registry_host(host) => (host.to_string(), path.to_string()),
+            _ => (DEFAULT_REGISTRY.to_string(), name.to_string()),
+        };
+        let registry = match registry.as_str() {
+            "index.docker.io" | "registry-1.docker.io" => DEFAULT_REGISTRY.to_string(),
+            _ => registry,
+        };
+        if !valid_repository(&repository) {
+            return Err(invalid("has an invalid repository name"));
+        }
+        let repository = if registry == DEFAULT_REGISTRY && !repository.contains('/') {
+            format!("library/{}", repository)
+        } else {
+            repository
+        };
+
+        let tag = match (tag, &digest) {
+            (None, None) => Some(DEFAULT_TAG.to_string()),
+            (tag, _) => tag,
+        };
+        Ok(Self {
+            registry,
+            repository,
+            tag,
+            digest,
+        })
+    }
+
+    /// Creates a reference to `tag` in `repository` on `registry`, without
+    /// familiar-name expansion.
+    pub fn new(
+        registry: impl Into<String>,
+        repository: impl Into<String>,
+        tag: impl Into<String>,
+    ) -> Self {
+        Self {
+            registry: registry.into(),
+            repository: repository.into(),
+            tag: Some(tag.into()),
+            digest: None,
+        }
+    }
+
+    /// Registry host, with port if any, such as `docker.io` or
+    /// `127.0.0.1:5000`.
+    pub fn registry(&self) -> &str {
+        &self.registry
+    }
+
+    /// Repository name within the registry, such as `library/busybox`.
+    pub fn repository(&self) -> &str {
+        &self.repository
+    }
+
+    /// Tag, if the reference has one.
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    /// Digest, if the reference pins one.
+    pub fn digest(&self) -> Option<&str> {
+        self.digest.as_deref()
+    }
+
+    /// What to put in `/v2/<name>/manifests/<reference>`: the digest if
+    /// pinned, the tag otherwise.
+    pub fn reference(&self) -> &str {
+        self.digest
+            .as_deref()
+            .or(self.tag.as_deref())
+            .unwrap_or(DEFAULT_TAG)
+    }
+
+    /// Returns the reference on another registry, such as a test server's
+    /// [`public_url`](crate::RegistryServer::public_url) host.
+    pub fn with_registry(mut self, registry: impl Into<String>) -> Self {
+        let registry = registry.into();
+        let registry = registry
+            .strip_prefix("http://")
+            .or_else(|| registry.strip_prefix("https://"))
+            .unwrap_or(&registry);
+        self.registry = registry.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Returns the reference with `tag` instead of its current tag.
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    /// Returns the reference pinned to `digest`, keeping its tag.
+    pub fn with_digest(mut self, digest: impl Into<String>) -> Self {
+        self.digest = Some(digest.into());
+        self
+    }
+
+    /// Shortest form that parses back to this reference, as the docker CLI
+    /// prints it: Docker Hub and `library/` are left out.
+    pub fn familiar(&self) -> String {
+        let name = if self.registry == DEFAULT_REGISTRY {
+            self.repository
+                .strip_prefix("library/")
+                .filter(|rest| !rest.contains('/'))
+                .unwrap_or(&self.repository)
+                .to_string()
+        } else {
+            format!("{}/{}", self.registry, self.repository)
+        };
+        self.with_suffix(name)
+    }
+
+    fn with_suffix(&self, mut name: String) -> String {
+        if let Some(tag) = &self.tag {
+            name.push(':');
+            name.push_str(tag);
+        }
+        if let Some(digest) = &self.digest {
+            name.push('@');
+            name.push_str(digest);
+        }
+        name
+    }
+}
+
+/// The fully qualified form, such as `docker.io/library/busybox:latest`.
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = format!("{}/{}", self.registry, self.repository);
+        f.write_str(&self.with_suffix(name))
+    }
+}
+
+impl FromStr for Reference {
+    type Err = RegistryError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+/// Whether the first component of a name is a registry host.
+pub(crate) fn is_registry_host(component: &str) -> bool {
+    component.contains('.')
+        || component.contains(':')
+        || component == "localhost"
+        || component.chars().any(|c| c.is_ascii_uppercase())
+}
+
+/// `[a-z0-9]+((\.|_|__|-+)[a-z0-9]+)*(/[a-z0-9]+((\.|_|__|-+)[a-z0-9]+)*)*`
+pub(crate) fn valid_repository(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 255 && name.split('/').all(valid_component)
+}
+
+fn valid_component(component: &str) -> bool {
+    let bytes = component.as_bytes();
+    let alnum = |b: &u8| b.is_ascii_lowercase() || b.is_ascii_digit();
+    if !bytes.first().is_some_and(alnum) || !bytes.last().is_some_and(alnum) {
+        return false;
+    }
+    let mut separator = String::new();
+    for &b in bytes {
+        if alnum(&b) {
+            let allowed = matches!(separator.as_str(), "." | "_" | "__")
+                || separator.bytes().all(|s| s == b'-');
+            if !allowed {
+                return false;
+            }
+            separator.clear();
+        } else if matches!(b, b'.' | b'_' | b'-') {
+            separator.push(b as char);
+        } else {
+            return false;
+        }
+    }
+    true
+}
+
+/// `[A-Za-z0-9_][A-Za-z0-9_.-]{0,127}`
+pub(crate) fn valid_tag(tag: &str) -> bool {
+    let bytes = tag.as_bytes();
+    bytes.len() <= 128
+        && bytes
+            .first()
+            .is_some_and(|b| b.is_ascii_alphanumeric() || *b == b'_')
+        && bytes
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'-'))
+}
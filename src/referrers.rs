@@ -0,0 +1,110 @@
+//! Referrers of a manifest, through the referrers API and the tag schema
+//! clients fall back to.
+//!
+//! Registries without the OCI 1.1 referrers API leave it to clients to
+//! keep an index of a manifest's referrers under the tag `sha256-<hex>`.
+//! cosign predates that schema and tags signatures, attestations and SBOMs
+//! directly, as `sha256-<hex>.sig`, `.att` and `.sbom`. Disabling
+//! [`ApiEndpoint::Referrers`](crate::ApiEndpoint::Referrers) makes clients
+//! take these paths.
+
+use crate::digest::sha256_digest;
+use crate::error::Result;
+use crate::oci::manifest::{Descriptor, ImageIndex, OCI_INDEX_MEDIA_TYPE};
+use serde::Deserialize;
+
+/// Suffix of cosign signature tags.
+pub const SIGNATURE_TAG_SUFFIX: &str = ".sig";
+/// Suffix of cosign attestation tags.
+pub const ATTESTATION_TAG_SUFFIX: &str = ".att";
+/// Suffix of cosign SBOM tags.
+pub const SBOM_TAG_SUFFIX: &str = ".sbom";
+
+/// Returns the tag the referrers tag schema indexes the referrers of
+/// `digest` under, `sha256-<hex>` for `sha256:<hex>`.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::referrers::{fallback_tag, SIGNATURE_TAG_SUFFIX};
+///
+/// assert_eq!(fallback_tag("sha256:abc"), "sha256-abc");
+/// let cosign = format!("{}{}", fallback_tag("sha256:abc"), SIGNATURE_TAG_SUFFIX);
+/// assert_eq!(cosign, "sha256-abc.sig");
+/// ```
+pub fn fallback_tag(digest: &str) -> String {
+    digest.replacen(':', "-", 1)
+}
+
+/// Returns whether `tag` is the fallback tag of `digest` or a cosign tag
+/// derived from it, such as `sha256-<hex>.sig`.
+pub fn is_fallback_tag_of(tag: &str, digest: &str) -> bool {
+    let fallback = fallback_tag(digest);
+    tag.strip_prefix(&fallback)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// Returns the descriptor a referrers index lists a manifest with: its
+/// artifact type (or config media type) and annotations.
+pub(crate) fn referrer_descriptor(content_type: &str, data: &[u8]) -> Result<Descriptor> {
+    let manifest: serde_json::Value = serde_json::from_slice(data)?;
+    let artifact_type = manifest["artifactType"]
+        .as_str()
+        .or_else(|| manifest["config"]["mediaType"].as_str())
+        .map(str::to_string);
+    let annotations = manifest
+        .get("annotations")
+        .cloned()
+        .map(serde_json::from_value)
+        .transpose()?
+        .unwrap_or_default();
+    Ok(Descriptor {
+        media_type: manifest["mediaType"]
+            .as_str()
+            .unwrap_or(content_type)
+            .to_string(),
+        digest: sha256_digest(data),
+        size: data.len() as u64,
+        annotations,
+        artifact_type,
+        ..Descriptor::default()
+    })
+}
+
+/// Response header acknowledging the `subject` of a pushed manifest.
+///
+/// Clients that get it know the registry indexes referrers itself; without
+/// it they maintain the index under the fallback tag.
+pub const SUBJECT_HEADER: &str = "OCI-Subject";
+
+/// Response header naming the filters a referrers response applied.
+pub const FILTERS_APPLIED_HEADER: &str = "OCI-Filters-Applied";
+
+/// Query parameters of `GET /v2/<name>/referrers/<digest>`.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct ReferrersQuery {
+    #[serde(rename = "artifactType")]
+    pub(crate) artifact_type: Option<String>,
+}
+
+/// Returns an empty referrers index.
+pub(crate) fn empty_index() -> ImageIndex {
+    ImageIndex {
+        schema_version: 2,
+        media_type: Some(OCI_INDEX_MEDIA_TYPE.to_string()),
+        ..Default::default()
+    }
+}
+
+/// What happens to the referrers of a manifest when it is deleted by
+/// digest. Deleting a tag leaves the manifest, and its referrers, in place.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReferrerDeletePolicy {
+    /// Referrers stay, pointing at a subject that no longer exists, as on
+    /// most registries.
+    #[default]
+    Orphan,
+    /// Referrers are deleted with their subject, recursively, along with
+    /// the subject's fallback and cosign tags.
+    Cascade,
+}
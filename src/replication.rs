@@ -0,0 +1,233 @@
+//! Write-through replication of pushes to a remote registry.
+
+use crate::client::RegistryClient;
+use crate::error::{RegistryError, Result};
+use crate::events::RegistryEvent;
+use crate::storage::{ManifestEntry, Storage};
+use crate::synthetic::SyntheticBlob;
+use crate::verify::descriptors;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, watch, RwLock};
+use tracing::{info, warn};
+
+/// Configuration for mirroring pushes to another registry.
+///
+/// Every successful manifest push is copied, with the blobs and child
+/// manifests it references, to the same repository and reference on the
+/// remote registry. Copies run in the background, one at a time and in push
+/// order, so the remote ends up with the same tags as the testkit.
+#[derive(Debug, Clone)]
+pub struct ReplicationConfig {
+    /// Base URL of the remote registry, e.g. `https://staging.example.com`.
+    pub url: String,
+    /// Username and password for the remote registry.
+    pub credentials: Option<(String, String)>,
+}
+
+impl ReplicationConfig {
+    /// Replicates to the registry at `url` without credentials.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            credentials: None,
+        }
+    }
+
+    /// Logs in to the remote registry with the given credentials.
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+}
+
+/// Progress of replication, as returned by
+/// [`RegistryServer::replication_status`](crate::RegistryServer::replication_status).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplicationStatus {
+    /// Pushes queued or being copied.
+    pub pending: usize,
+    /// Pushes copied successfully.
+    pub replicated: usize,
+    /// Pushes that could not be copied, oldest first.
+    pub failures: Vec<ReplicationFailure>,
+}
+
+/// A push that could not be copied to the remote registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicationFailure {
+    /// Repository of the push.
+    pub repository: String,
+    /// Tag or digest the manifest was pushed under.
+    pub reference: String,
+    /// What went wrong.
+    pub error: String,
+}
+
+struct Job {
+    repository: String,
+    reference: String,
+    manifest: ManifestEntry,
+}
+
+/// Queues pushes and copies them to the remote registry in order.
+pub(crate) struct Replicator {
+    queue: mpsc::UnboundedSender<Job>,
+    status: watch::Sender<ReplicationStatus>,
+}
+
+/// What the background worker copies from.
+struct Worker {
+    client: RegistryClient,
+    storage: Arc<dyn Storage>,
+    synthetic: Arc<RwLock<HashMap<String, SyntheticBlob>>>,
+    status: watch::Sender<ReplicationStatus>,
+    events: broadcast::Sender<RegistryEvent>,
+}
+
+impl Replicator {
+    /// Starts the worker copying from `storage` to the configured registry.
+    pub(crate) fn start(
+        config: &ReplicationConfig,
+        storage: Arc<dyn Storage>,
+        synthetic: Arc<RwLock<HashMap<String, SyntheticBlob>>>,
+        events: broadcast::Sender<RegistryEvent>,
+    ) -> Self {
+        let mut client = RegistryClient::new(config.url.clone());
+        if let Some((username, password)) = &config.credentials {
+            client = client.with_credentials(username.clone(), password.clone());
+        }
+        let (queue, jobs) = mpsc::unbounded_channel();
+        let status = watch::Sender::new(ReplicationStatus::default());
+        let worker = Worker {
+            client,
+            storage,
+            synthetic,
+            status: status.clone(),
+            events,
+        };
+        tokio::spawn(worker.run(jobs));
+        Self { queue, status }
+    }
+
+    /// Queues a copy of `manifest`, just pushed as `repository:reference`.
+    pub(crate) fn enqueue(&self, repository: String, reference: String, manifest: ManifestEntry) {
+        self.status.send_modify(|status| status.pending += 1);
+        let job = Job {
+            repository,
+            reference,
+            manifest,
+        };
+        if self.queue.send(job).is_err() {
+            self.status.send_modify(|status| status.pending -= 1);
+        }
+    }
+
+    pub(crate) fn status(&self) -> ReplicationStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Waits until every queued copy has finished, successfully or not.
+    pub(crate) async fn idle(&self) {
+        let mut status = self.status.subscribe();
+        status.wait_for(|status| status.pending == 0).await.ok();
+    }
+}
+
+impl Worker {
+    async fn run(self, mut jobs: mpsc::UnboundedReceiver<Job>) {
+        while let Some(job) = jobs.recv().await {
+            let result = self
+                .copy_manifest(&job.repository, &job.reference, &job.manifest)
+                .await;
+            let event = match result {
+                Ok(()) => {
+                    info!("Replicated {}:{}", job.repository, job.reference);
+                    self.status.send_modify(|status| {
+                        status.pending -= 1;
+                        status.replicated += 1;
+                    });
+                    RegistryEvent::Replicated {
+                        repository: job.repository,
+                        reference: job.reference,
+                    }
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to replicate {}:{}: {}",
+                        job.repository, job.reference, e
+                    );
+                    let failure = ReplicationFailure {
+                        repository: job.repository.clone(),
+                        reference: job.reference.clone(),
+                        error: e.to_string(),
+                    };
+                    self.status.send_modify(|status| {
+                        status.pending -= 1;
+                        status.failures.push(failure);
+                    });
+                    RegistryEvent::ReplicationFailed {
+                        repository: job.repository,
+                        reference: job.reference,
+                        error: e.to_string(),
+                    }
+                }
+            };
+            self.events.send(event).ok();
+        }
+    }
+
+    /// Copies a manifest after everything it references.
+    async fn copy_manifest(
+        &self,
+        repository: &str,
+        reference: &str,
+        manifest: &ManifestEntry,
+    ) -> Result<()> {
+        let parsed: serde_json::Value = serde_json::from_slice(&manifest.data)?;
+        for child in descriptors(&parsed, "manifests") {
+            let key = format!("{}:{}", repository, child.digest);
+            let entry = self
+                .storage
+                .get_manifest(&key)
+                .await?
+                .ok_or(RegistryError::ManifestNotFound(key))?;
+            Box::pin(self.copy_manifest(repository, &child.digest, &entry)).await?;
+        }
+        for field in ["config", "layers", "blobs"] {
+            for blob in descriptors(&parsed, field) {
+                self.copy_blob(repository, &blob.digest).await?;
+            }
+        }
+        self.client
+            .push_manifest(
+                repository,
+                reference,
+                &manifest.content_type,
+                manifest.data.clone(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn copy_blob(&self, repository: &str, digest: &str) -> Result<()> {
+        if self.client.blob_exists(repository, digest).await? {
+            return Ok(());
+        }
+        let generated = self.synthetic.read().await.get(digest).copied();
+        let data = match generated {
+            Some(blob) => blob.to_vec(),
+            None => self
+                .storage
+                .get_blob(digest)
+                .await?
+                .ok_or_else(|| RegistryError::BlobNotFound(digest.to_string()))?,
+        };
+        self.client.push_blob(repository, data).await?;
+        Ok(())
+    }
+}
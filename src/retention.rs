@@ -0,0 +1,73 @@
+//! Tag retention policies.
+
+use std::time::{Duration, SystemTime};
+
+/// Which tags of a repository to keep when retention is applied.
+///
+/// Tags are ordered by when they were last pushed. A tag is deleted if it
+/// falls outside the newest [`keep_last`](Self::keep_last) or is older than
+/// [`max_age`](Self::max_age); manifests stay until garbage collected.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::{RegistryConfig, RetentionPolicy};
+/// use std::time::Duration;
+///
+/// let config = RegistryConfig::memory().with_retention(
+///     "nightly",
+///     RetentionPolicy::new()
+///         .keep_last(5)
+///         .max_age(Duration::from_secs(7 * 24 * 60 * 60)),
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Number of most recently pushed tags to keep.
+    pub keep_last: Option<usize>,
+    /// Age beyond which tags are deleted.
+    pub max_age: Option<Duration>,
+}
+
+impl RetentionPolicy {
+    /// Creates a policy that keeps every tag.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps only the `count` most recently pushed tags.
+    pub fn keep_last(mut self, count: usize) -> Self {
+        self.keep_last = Some(count);
+        self
+    }
+
+    /// Deletes tags pushed more than `age` ago.
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+
+    /// Returns the tags this policy deletes, given each tag's push time.
+    pub(crate) fn expired(
+        &self,
+        mut tags: Vec<(String, SystemTime)>,
+        now: SystemTime,
+    ) -> Vec<String> {
+        // Newest first; ties broken by name so runs are deterministic.
+        tags.sort_by(|(a_tag, a_time), (b_tag, b_time)| {
+            b_time.cmp(a_time).then_with(|| a_tag.cmp(b_tag))
+        });
+
+        tags.into_iter()
+            .enumerate()
+            .filter(|(rank, (_, pushed))| {
+                let beyond_count = self.keep_last.is_some_and(|keep| *rank >= keep);
+                let too_old = self.max_age.is_some_and(|max_age| {
+                    now.duration_since(*pushed).unwrap_or_default() > max_age
+                });
+                beyond_count || too_old
+            })
+            .map(|(_, (tag, _))| tag)
+            .collect()
+    }
+}
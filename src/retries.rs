@@ -0,0 +1,225 @@
+//! Assertions on how clients retry, built on captured exchanges.
+//!
+//! Pair [capture](crate::RegistryConfig::with_capture) with faults that make
+//! requests fail, such as [`StorageFault`](crate::StorageFault), then check
+//! that the client retried and how long it waited between attempts.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use registry_testkit::retries::Endpoint;
+//! use registry_testkit::{
+//!     CaptureConfig, FaultConfig, RegistryConfig, RegistryServer, StorageFault, StorageMethod,
+//! };
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let faults = FaultConfig::new()
+//!     .with_storage_fault(StorageFault::fail(StorageMethod::GetBlob).times(3));
+//! let config = RegistryConfig::memory()
+//!     .with_faults(faults)
+//!     .with_capture(CaptureConfig::new());
+//! let server = RegistryServer::new(config).await?;
+//! // ... run the client under test against server.url() ...
+//! let series = server.assert_retried(Endpoint::GetBlob, 3);
+//! series.assert_min_delay(Duration::from_millis(100));
+//! series.assert_backoff(1.5);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::capture::CapturedExchange;
+use crate::server::split_repository_path;
+use std::time::{Duration, SystemTime};
+
+/// A registry API operation, as identified by method and path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    /// `GET /v2/`.
+    ApiVersion,
+    /// `GET /token`.
+    Token,
+    /// `GET /v2/<name>/manifests/<reference>`.
+    GetManifest,
+    /// `HEAD /v2/<name>/manifests/<reference>`.
+    HeadManifest,
+    /// `PUT /v2/<name>/manifests/<reference>`.
+    PutManifest,
+    /// `DELETE /v2/<name>/manifests/<reference>`.
+    DeleteManifest,
+    /// `GET /v2/<name>/blobs/<digest>`.
+    GetBlob,
+    /// `HEAD /v2/<name>/blobs/<digest>`.
+    HeadBlob,
+    /// `DELETE /v2/<name>/blobs/<digest>`.
+    DeleteBlob,
+    /// `POST /v2/<name>/blobs/uploads/`.
+    StartUpload,
+    /// `PATCH /v2/<name>/blobs/uploads/<uuid>`.
+    PatchUpload,
+    /// `PUT /v2/<name>/blobs/uploads/<uuid>`.
+    FinishUpload,
+    /// `GET /v2/<name>/blobs/uploads/<uuid>`.
+    UploadStatus,
+    /// `GET /v2/<name>/tags/list`.
+    ListTags,
+}
+
+impl Endpoint {
+    /// Classifies a request.
+    pub fn of(method: &str, path: &str) -> Option<Self> {
+        match (method, path) {
+            ("GET", "/v2/") => return Some(Self::ApiVersion),
+            ("GET", "/token") => return Some(Self::Token),
+            _ => {}
+        }
+        let (_, rest) = split_repository_path(path)?;
+        let endpoint = if let Some(upload) = rest.strip_prefix("blobs/uploads/") {
+            match (method, upload.is_empty()) {
+                ("POST", true) => Self::StartUpload,
+                ("PATCH", false) => Self::PatchUpload,
+                ("PUT", false) => Self::FinishUpload,
+                ("GET", false) => Self::UploadStatus,
+                _ => return None,
+            }
+        } else if rest.starts_with("blobs/") {
+            match method {
+                "GET" => Self::GetBlob,
+                "HEAD" => Self::HeadBlob,
+                "DELETE" => Self::DeleteBlob,
+                _ => return None,
+            }
+        } else if rest.starts_with("manifests/") {
+            match method {
+                "GET" => Self::GetManifest,
+                "HEAD" => Self::HeadManifest,
+                "PUT" => Self::PutManifest,
+                "DELETE" => Self::DeleteManifest,
+                _ => return None,
+            }
+        } else if rest == "tags/list" && method == "GET" {
+            Self::ListTags
+        } else {
+            return None;
+        };
+        Some(endpoint)
+    }
+}
+
+/// One request of a [`RetrySeries`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attempt {
+    /// When the request arrived.
+    pub started: SystemTime,
+    /// Status it was answered with.
+    pub status: u16,
+}
+
+/// Requests for one endpoint and path, in arrival order: the first attempt
+/// and every retry of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetrySeries {
+    /// Endpoint requested.
+    pub endpoint: Endpoint,
+    /// Request path, without the query.
+    pub path: String,
+    /// Every request to the path.
+    pub attempts: Vec<Attempt>,
+}
+
+impl RetrySeries {
+    /// Groups the `exchanges` for `endpoint` by path, in order of first
+    /// request.
+    pub fn collect(exchanges: &[CapturedExchange], endpoint: Endpoint) -> Vec<Self> {
+        let mut series: Vec<Self> = Vec::new();
+        for exchange in exchanges {
+            let path = exchange
+                .request
+                .uri
+                .split_once('?')
+                .map_or(exchange.request.uri.as_str(), |(path, _)| path);
+            if Endpoint::of(&exchange.request.method, path) != Some(endpoint) {
+                continue;
+            }
+            let attempt = Attempt {
+                started: exchange.started,
+                status: exchange.response.status,
+            };
+            match series.iter_mut().find(|s| s.path == path) {
+                Some(existing) => existing.attempts.push(attempt),
+                None => series.push(Self {
+                    endpoint,
+                    path: path.to_string(),
+                    attempts: vec![attempt],
+                }),
+            }
+        }
+        series
+    }
+
+    /// Number of requests after the first.
+    pub fn retries(&self) -> usize {
+        self.attempts.len().saturating_sub(1)
+    }
+
+    /// Time between the arrival of each request and the next.
+    pub fn delays(&self) -> Vec<Duration> {
+        self.attempts
+            .windows(2)
+            .map(|pair| {
+                pair[1]
+                    .started
+                    .duration_since(pair[0].started)
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    /// Panics unless the client waited at least `min` before every retry.
+    #[track_caller]
+    pub fn assert_min_delay(&self, min: Duration) {
+        let delays = self.delays();
+        assert!(
+            delays.iter().all(|delay| *delay >= min),
+            "{:?} {} was retried after less than {:?}: {:?}",
+            self.endpoint,
+            self.path,
+            min,
+            delays
+        );
+    }
+
+    /// Panics unless each delay between retries is at least `factor` times
+    /// the one before, as exponential backoff produces.
+    ///
+    /// Use a factor below the client's multiplier to leave room for jitter
+    /// and scheduling, such as `1.5` for a client that doubles its delay.
+    #[track_caller]
+    pub fn assert_backoff(&self, factor: f64) {
+        let delays = self.delays();
+        assert!(
+            delays
+                .windows(2)
+                .all(|pair| pair[1].as_secs_f64() >= pair[0].as_secs_f64() * factor),
+            "{:?} {} delays do not grow by {}x: {:?}",
+            self.endpoint,
+            self.path,
+            factor,
+            delays
+        );
+    }
+
+    /// Panics unless the last attempt succeeded, so the client kept
+    /// retrying until the fault cleared.
+    #[track_caller]
+    pub fn assert_succeeded(&self) {
+        let statuses: Vec<u16> = self.attempts.iter().map(|a| a.status).collect();
+        assert!(
+            statuses.last().is_some_and(|status| *status < 400),
+            "{:?} {} never succeeded: {:?}",
+            self.endpoint,
+            self.path,
+            statuses
+        );
+    }
+}
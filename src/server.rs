@@ -1,21 +1,94 @@
 //! OCI-compliant registry server implementation.
 
-use crate::config::RegistryConfig;
-use crate::error::Result;
-use crate::storage::{create_storage, ManifestEntry, Storage};
+use crate::archive;
+use crate::audit::{AuditEntry, AuditLog};
+use crate::auth::{
+    introspection_endpoint, require_auth, token_endpoint, Authenticator, AuthorizationToken,
+    TokenIntrospection,
+};
+use crate::capture::{capture, har, CapturedExchange, Recorder};
+use crate::catalog::{Catalog, CatalogQuery};
+use crate::clock::skew_date;
+use crate::compression::{compression, mark_compressible};
+use crate::config::{RegistryConfig, StorageBackend, MAX_BODY_SIZE};
+use crate::consistency::{LaggedStorage, Visibility};
+use crate::dedup::{DedupReport, DedupTracker};
+use crate::digest::{self, check_digests, sha256_digest, DigestHasher, DigestPolicy};
+use crate::endpoints::{disable_endpoints, ApiEndpoint};
+use crate::error::{ErrorResponse, RegistryError, Result};
+use crate::events::RegistryEvent;
+use crate::expect::check_expectation;
+use crate::faults::{FlakyStorage, StaleReadStorage};
+use crate::federation::{route_namespace, Namespace, NamespaceTarget};
+use crate::foreign::{foreign_layers, is_foreign, ForeignLayerPolicy};
+use crate::gc::{self, GcReport};
+use crate::headers::{apply_header_quirks, HeaderCasing};
+use crate::history::{RevisionBody, TagRevision};
+use crate::inspect::{self, ImageDiff, ImageInspect, ManifestAnnotations};
+use crate::labels::{LabelIndex, LabelQuery, LabeledImage, LabeledImages};
+use crate::lifecycle::LifecycleEvent;
+use crate::location::{rewrite_locations, LocationRewrite};
+use crate::maintenance::{MaintenanceConfig, MaintenanceReport};
+use crate::metrics::{record_metrics, ConnectionRequests, Metrics, RegistryMetrics};
+use crate::names::route_nested_names;
+use crate::oci::manifest::{
+    subject_digest, ImageIndex, Manifest, ValidationLevel, DOCKER_MANIFEST_MEDIA_TYPE,
+    OCI_INDEX_MEDIA_TYPE,
+};
+use crate::oci::schema1::{self, Schema1Mode};
+use crate::pagination::Pagination;
+use crate::profile::RegistryProfile;
+use crate::proxy::{simulate_proxy, ProxySimulation, ProxySwitch};
+use crate::quirks::profile_quirks;
+use crate::quota::{enforce_quota, QuotaTracker};
+use crate::ratelimit::{pull_rate_limit, PullRateLimiter};
+use crate::received::{record_received, ReceivedLog, ReceivedRequest, ReceivedRequestBody};
+use crate::redirect::{BlobRedirector, SignedParams};
+use crate::reference::{is_registry_host, Reference};
+use crate::referrers::{
+    self, fallback_tag, is_fallback_tag_of, ReferrerDeletePolicy, ReferrersQuery,
+    FILTERS_APPLIED_HEADER, SUBJECT_HEADER,
+};
+use crate::replication::{ReplicationStatus, Replicator};
+use crate::retries::{Endpoint, RetrySeries};
+use crate::storage::{create_storage, is_tag_key, ManifestEntry, ManifestStream, Storage};
+use crate::sync::Snapshot;
+use crate::synthetic::SyntheticBlob;
+use crate::tags::TagList;
+use crate::transport::InProcessConnector;
+use crate::uploads::{OpenUpload, UploadCounters, UploadSession, UploadStats};
+use crate::upstream::Upstreams;
+use crate::verify::{self, VerifyReport};
+use crate::warnings::add_warnings;
+use crate::wirelog::log_protocol;
 use axum::{
-    body::Bytes,
-    extract::{Path, Query, State},
-    http::{HeaderMap, StatusCode},
-    response::{IntoResponse, Json},
-    routing::{get, head, patch, post, put},
+    body::{Body, Bytes},
+    extract::{rejection::QueryRejection, ConnectInfo, FromRequest, Path, Query, State},
+    http::{HeaderMap, HeaderValue, Method, Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get, head, patch, post, put},
     Router,
 };
+use futures_util::future::{self, BoxFuture, FutureExt};
+use futures_util::stream::{self, StreamExt};
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::service::TowerToHyperService;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
 use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpListener;
+use tokio::sync::{broadcast, oneshot, RwLock};
+use tokio::task::{JoinHandle, JoinSet};
+use tower::ServiceExt;
 use tower_http::trace::TraceLayer;
 use tracing::{debug, info, warn};
 
@@ -23,11 +96,680 @@ fn strip_leading_slash(s: &str) -> &str {
     s.strip_prefix('/').unwrap_or(s)
 }
 
+/// Splits a `/v2/<name>/<endpoint>...` path into the repository name and the
+/// remainder starting at the endpoint segment. The remainder must have the
+/// shape of a whole route, so names may contain segments such as `tags`.
+pub(crate) fn split_repository_path(path: &str) -> Option<(&str, &str)> {
+    let rest = path.strip_prefix("/v2/")?;
+    rest.match_indices('/')
+        .map(|(index, _)| index)
+        .find(|&index| is_repository_endpoint(&rest[index + 1..]))
+        .map(|index| (&rest[..index], &rest[index + 1..]))
+}
+
+/// Returns whether `path` is a `/v2/` endpoint under a repository.
+fn is_repository_endpoint(path: &str) -> bool {
+    let segments: Vec<&str> = path.split('/').collect();
+    matches!(
+        segments.as_slice(),
+        ["blobs", _]
+            | ["blobs", "uploads", _]
+            | ["manifests", _]
+            | ["tags", "list"]
+            | ["referrers", _]
+    )
+}
+
+/// Builds a distribution-spec error response with a single entry; see
+/// [`ErrorResponse`].
+pub(crate) fn error_response(status: StatusCode, code: &str, message: &str) -> Response {
+    ErrorResponse::new(status, code, message).into_response()
+}
+
 type SharedStorage = Arc<dyn Storage>;
 
+/// Key/value metadata attached to a repository.
+pub type RepositoryMetadata = BTreeMap<String, String>;
+
 #[derive(Clone)]
 struct AppState {
     storage: SharedStorage,
+    config: Arc<RegistryConfig>,
+    synthetic: Arc<RwLock<HashMap<String, SyntheticBlob>>>,
+    redirector: Option<Arc<BlobRedirector>>,
+    authenticator: Option<Arc<Authenticator>>,
+    replicator: Option<Arc<Replicator>>,
+    upstreams: Option<Arc<Upstreams>>,
+    recorder: Option<Arc<Recorder>>,
+    received: Option<Arc<ReceivedLog>>,
+    metrics: Arc<Metrics>,
+    pulls: Arc<RwLock<HashMap<String, u64>>>,
+    tag_history: Arc<RwLock<HashMap<String, Vec<TagRevision>>>>,
+    tombstones: Arc<RwLock<HashMap<String, ManifestEntry>>>,
+    metadata: Arc<RwLock<HashMap<String, RepositoryMetadata>>>,
+    /// URLs of foreign layers seen in pushed manifests, by digest.
+    foreign_layers: Arc<RwLock<HashMap<String, Vec<String>>>>,
+    uploads_started: Arc<RwLock<HashMap<String, UploadSession>>>,
+    upload_counters: Arc<UploadCounters>,
+    dedup: Arc<DedupTracker>,
+    labels: Arc<LabelIndex>,
+    /// Upload sessions a request is currently writing to.
+    upload_writers: Arc<std::sync::Mutex<HashSet<String>>>,
+    /// `Retry-After` of refused writes while in maintenance mode.
+    maintenance_mode: Arc<std::sync::RwLock<Option<Duration>>>,
+    /// Reverse proxy simulated in front of the registry.
+    proxy: ProxySwitch,
+    started: SystemTime,
+    events: broadcast::Sender<RegistryEvent>,
+}
+
+impl AppState {
+    async fn apply_retention(&self) -> Result<Vec<(String, String)>> {
+        let policies = &self.config.retention;
+        let mut tags: HashMap<String, Vec<(String, SystemTime)>> = HashMap::new();
+        {
+            let tag_history = self.tag_history.read().await;
+            for key in self.storage.list_manifests().await? {
+                if !is_tag_key(&key) {
+                    continue;
+                }
+                if let Some((repository, tag)) = key.split_once(':') {
+                    let pushed = tag_history
+                        .get(&key)
+                        .and_then(|revisions| revisions.last())
+                        .map_or(self.started, |revision| revision.pushed_at);
+                    tags.entry(repository.to_string())
+                        .or_default()
+                        .push((tag.to_string(), pushed));
+                }
+            }
+        }
+
+        let now = SystemTime::now();
+        let mut deleted = Vec::new();
+        for (repository, repository_tags) in tags {
+            let Some(policy) = policies.get(&repository).or_else(|| policies.get("*")) else {
+                continue;
+            };
+            for tag in policy.expired(repository_tags, now) {
+                let key = format!("{}:{}", repository, tag);
+                self.delete_manifest(&key).await?;
+                info!("Retention deleted {}", key);
+                self.events
+                    .send(RegistryEvent::TagExpired {
+                        repository: repository.clone(),
+                        tag: tag.clone(),
+                    })
+                    .ok();
+                deleted.push((repository.clone(), tag));
+            }
+        }
+        deleted.sort();
+
+        self.events
+            .send(RegistryEvent::RetentionApplied {
+                tags_deleted: deleted.len(),
+            })
+            .ok();
+        Ok(deleted)
+    }
+
+    /// Deletes the manifest stored under `key`, leaving a tombstone when
+    /// soft deletes are enabled, and updates the referrers it is part of.
+    async fn delete_manifest(&self, key: &str) -> Result<bool> {
+        let Some(entry) = self.storage.get_manifest(key).await? else {
+            return Ok(false);
+        };
+        if self.config.soft_delete {
+            self.tombstones
+                .write()
+                .await
+                .insert(key.to_string(), entry.clone());
+        }
+        if !self.storage.delete_manifest(key).await? {
+            return Ok(false);
+        }
+        if !is_tag_key(key) {
+            if let Some((repository, digest)) = key.split_once(':') {
+                self.propagate_delete(repository, digest, &entry.data)
+                    .await?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Deletes the blob `digest` through `repository`, stored or synthetic.
+    ///
+    /// Blobs are shared by every repository, so the delete is refused while
+    /// a manifest of another repository, or a soft-deleted manifest that
+    /// [`undelete`](RegistryServer::undelete) could restore, still
+    /// references the blob. Manifests of `repository` itself do not hold
+    /// it back, as with a registry that stores blobs per repository.
+    async fn delete_blob(&self, repository: &str, digest: &str) -> Result<bool> {
+        for key in self.storage.list_manifests().await? {
+            let Some((owner, _)) = key.split_once(':') else {
+                continue;
+            };
+            if owner == repository || is_tag_key(&key) {
+                continue;
+            }
+            let Some(entry) = self.storage.get_manifest(&key).await? else {
+                continue;
+            };
+            if gc::blob_references(&entry.data).iter().any(|d| d == digest) {
+                return Err(RegistryError::Denied(format!(
+                    "blob {} is referenced by {}",
+                    digest, key
+                )));
+            }
+        }
+        for (key, entry) in self.tombstones.read().await.iter() {
+            if gc::blob_references(&entry.data).iter().any(|d| d == digest) {
+                return Err(RegistryError::Denied(format!(
+                    "blob {} is referenced by deleted manifest {}",
+                    digest, key
+                )));
+            }
+        }
+
+        let synthetic = self.synthetic.write().await.remove(digest).is_some();
+        Ok(self.storage.delete_blob(digest).await? || synthetic)
+    }
+
+    /// Drops the manifest `digest` from the fallback index of its subject
+    /// and, under [`ReferrerDeletePolicy::Cascade`], deletes its referrers.
+    async fn propagate_delete(&self, repository: &str, digest: &str, data: &[u8]) -> Result<()> {
+        if let Some(subject) = subject_digest(data) {
+            self.remove_from_fallback_index(repository, &subject, digest)
+                .await?;
+        }
+        if self.config.referrer_deletes != ReferrerDeletePolicy::Cascade {
+            return Ok(());
+        }
+
+        let prefix = format!("{}:", repository);
+        for key in self.storage.list_manifests().await? {
+            let Some(tag) = key.strip_prefix(&prefix) else {
+                continue;
+            };
+            let Ok(Some(entry)) = self.storage.get_manifest(&key).await else {
+                continue;
+            };
+            if is_tag_key(&key) {
+                if !is_fallback_tag_of(tag, digest) {
+                    continue;
+                }
+                info!("Cascading delete to {}", key);
+                self.storage.delete_manifest(&key).await?;
+                let target = format!("{}{}", prefix, self.compute_digest(&entry.data));
+                Box::pin(self.delete_manifest(&target)).await?;
+            } else if subject_digest(&entry.data).as_deref() == Some(digest) {
+                info!("Cascading delete to {}", key);
+                Box::pin(self.delete_manifest(&key)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Lists the manifests in `repository` whose subject is `digest`, sorted
+    /// by digest, keeping only those of `artifact_type` if given.
+    async fn referrers(
+        &self,
+        repository: &str,
+        digest: &str,
+        artifact_type: Option<&str>,
+    ) -> Result<ImageIndex> {
+        let mut index = referrers::empty_index();
+        for referrer in self.storage.list_referrers(repository, digest).await? {
+            let key = format!("{}:{}", repository, referrer);
+            let Some(entry) = self.storage.get_manifest(&key).await? else {
+                continue;
+            };
+            let descriptor = referrers::referrer_descriptor(&entry.content_type, &entry.data)?;
+            if artifact_type
+                .is_none_or(|wanted| descriptor.artifact_type.as_deref() == Some(wanted))
+            {
+                index.manifests.push(descriptor);
+            }
+        }
+        Ok(index)
+    }
+
+    /// Removes `digest` from the index under the fallback tag of `subject`,
+    /// deleting the tag once the index is empty.
+    async fn remove_from_fallback_index(
+        &self,
+        repository: &str,
+        subject: &str,
+        digest: &str,
+    ) -> Result<()> {
+        let key = format!("{}:{}", repository, fallback_tag(subject));
+        let Some(entry) = self.storage.get_manifest(&key).await? else {
+            return Ok(());
+        };
+        let Ok(mut index) = serde_json::from_slice::<ImageIndex>(&entry.data) else {
+            return Ok(());
+        };
+        let listed = index.manifests.len();
+        index
+            .manifests
+            .retain(|descriptor| descriptor.digest != digest);
+        if index.manifests.len() == listed {
+            return Ok(());
+        }
+        if index.manifests.is_empty() {
+            debug!("Removing empty fallback index {}", key);
+            self.storage.delete_manifest(&key).await?;
+            return Ok(());
+        }
+
+        let entry = ManifestEntry {
+            data: serde_json::to_vec(&index)?,
+            content_type: entry.content_type,
+        };
+        let index_digest = self.compute_digest(&entry.data);
+        self.storage
+            .store_manifest(format!("{}:{}", repository, index_digest), entry.clone())
+            .await?;
+        self.storage.store_manifest(key.clone(), entry).await?;
+        self.record_tag(key, index_digest).await;
+        Ok(())
+    }
+
+    async fn gc_plan(&self) -> Result<GcReport> {
+        let mut report = gc::plan(self.storage.as_ref()).await?;
+        report.tombstones = self.tombstones.read().await.keys().cloned().collect();
+        Ok(report)
+    }
+
+    async fn collect_garbage(&self) -> Result<GcReport> {
+        let report = self.gc_plan().await?;
+        self.tombstones.write().await.clear();
+        for reference in &report.manifests {
+            if let Some((repository, digest)) = reference.split_once('@') {
+                self.storage
+                    .delete_manifest(&format!("{}:{}", repository, digest))
+                    .await?;
+            }
+        }
+        for digest in &report.blobs {
+            self.storage.delete_blob(digest).await?;
+        }
+        info!(
+            "Garbage collection removed {} manifests, {} blobs and {} tombstones ({} bytes)",
+            report.manifests.len(),
+            report.blobs.len(),
+            report.tombstones.len(),
+            report.bytes_reclaimed
+        );
+        Ok(report)
+    }
+
+    async fn scrub(&self) -> Result<Vec<String>> {
+        let policy = self.config.digest_policy.clone().unwrap_or_default();
+        let mut corrupt = Vec::new();
+        for digest in self.storage.list_blobs().await? {
+            let verifiable = digest
+                .split_once(':')
+                .is_some_and(|(algorithm, _)| policy.digester(algorithm).is_some());
+            if !verifiable {
+                continue;
+            }
+            if let Some(data) = self.storage.get_blob(&digest).await? {
+                if policy.verify(&digest, &data).is_err() {
+                    warn!("Blob {} does not match its digest", digest);
+                    corrupt.push(digest);
+                }
+            }
+        }
+        corrupt.sort();
+        Ok(corrupt)
+    }
+
+    /// Claims an upload session for one request, or returns `None` if
+    /// another request is writing to it.
+    fn claim_upload(&self, uuid: &str) -> Option<UploadWriter> {
+        let mut writers = self.upload_writers.lock().unwrap();
+        writers.insert(uuid.to_string()).then(|| UploadWriter {
+            writers: self.upload_writers.clone(),
+            uuid: uuid.to_string(),
+        })
+    }
+
+    /// Discards upload sessions opened more than `ttl` ago.
+    async fn expire_uploads(&self, ttl: Duration) -> Result<usize> {
+        let expired: Vec<String> = self
+            .uploads_started
+            .read()
+            .await
+            .iter()
+            .filter(|(_, session)| session.started.elapsed() > ttl)
+            .map(|(uuid, _)| uuid.clone())
+            .collect();
+
+        for uuid in &expired {
+            self.storage.delete_upload(uuid).await?;
+            self.uploads_started.write().await.remove(uuid);
+            self.upload_counters.expired.fetch_add(1, Ordering::Relaxed);
+            debug!("Expired upload session {}", uuid);
+        }
+        Ok(expired.len())
+    }
+
+    async fn run_maintenance(&self, maintenance: &MaintenanceConfig) -> Result<MaintenanceReport> {
+        let mut report = MaintenanceReport::default();
+        if maintenance.retention {
+            report.tags_deleted = self.apply_retention().await?;
+        }
+        if let Some(ttl) = maintenance.upload_ttl {
+            report.uploads_expired = self.expire_uploads(ttl).await?;
+        }
+        if maintenance.gc {
+            report.garbage = Some(self.collect_garbage().await?);
+        }
+        if maintenance.scrub {
+            report.corrupt_blobs = self.scrub().await?;
+        }
+        self.events
+            .send(RegistryEvent::MaintenanceCompleted(report.clone()))
+            .ok();
+        Ok(report)
+    }
+
+    /// Records that the tag stored under `key` now points at `digest`.
+    async fn record_tag(&self, key: String, digest: String) {
+        if !is_tag_key(&key) {
+            return;
+        }
+        let mut history = self.tag_history.write().await;
+        let revisions = history.entry(key).or_default();
+        if revisions.last().is_none_or(|last| last.digest != digest) {
+            revisions.push(TagRevision {
+                digest,
+                pushed_at: SystemTime::now(),
+            });
+        }
+    }
+
+    async fn manifest_annotations(
+        &self,
+        repository: &str,
+        reference: &str,
+    ) -> Result<ManifestAnnotations> {
+        let key = format!("{}:{}", self.repository(repository), reference);
+        let entry = self
+            .storage
+            .get_manifest(&key)
+            .await?
+            .ok_or(RegistryError::ManifestNotFound(key))?;
+        inspect::annotations(
+            self.compute_digest(&entry.data),
+            &entry.content_type,
+            &entry.data,
+        )
+    }
+
+    /// Lists the tags of `repository`, failing with
+    /// [`RegistryError::RepositoryNotFound`] if it holds no manifests.
+    async fn list_tags(&self, repository: &str) -> Result<Vec<String>> {
+        let tags = self.storage.list_tags(repository).await?;
+        if tags.is_empty() {
+            let prefix = format!("{}:", repository);
+            let keys = self.storage.list_manifests().await?;
+            if !keys.iter().any(|key| key.starts_with(&prefix)) {
+                return Err(RegistryError::RepositoryNotFound(repository.to_string()));
+            }
+        }
+        Ok(tags)
+    }
+
+    async fn find_labeled_images(&self, query: &LabelQuery) -> Result<Vec<LabeledImage>> {
+        let mut images = self.labels.images(self.storage.as_ref()).await?;
+        images.retain(|image| query.matches(image));
+        Ok(images)
+    }
+
+    /// Writes the manifests of `repository` and the blobs they reference as
+    /// a registry snapshot.
+    async fn export_repository<W: AsyncWrite + Unpin>(
+        &self,
+        repository: &str,
+        writer: W,
+    ) -> Result<()> {
+        let repository = self.repository(repository);
+        let prefix = format!("{}:", repository);
+        let keys = self.storage.list_manifests().await?;
+        if !keys.iter().any(|key| key.starts_with(&prefix)) {
+            return Err(RegistryError::RepositoryNotFound(repository));
+        }
+        let synthetic = self.synthetic.read().await.clone();
+        archive::export_registry(self.storage.as_ref(), &synthetic, Some(&repository), writer).await
+    }
+
+    /// Loads a registry snapshot into `repository`, returning the number of
+    /// manifests stored.
+    async fn import_repository<R: AsyncRead + Unpin>(
+        &self,
+        repository: &str,
+        reader: R,
+    ) -> Result<usize> {
+        let repository = self.repository(repository);
+        archive::import_registry(self.storage.as_ref(), Some(&repository), reader).await
+    }
+
+    /// Converts a schema 1 manifest pushed to `name`, storing the config
+    /// blob it gains and returning the schema 2 manifest.
+    async fn convert_schema1(&self, name: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let converted = schema1::convert(data, |digest| async move {
+            self.find_blob(Some(name), &digest).await
+        })
+        .await?;
+        self.storage
+            .store_blob(self.compute_digest(&converted.config), converted.config)
+            .await
+            .map_err(|e| RegistryError::StorageBackend(e.to_string()))?;
+        Ok(converted.manifest)
+    }
+
+    async fn remove_metadata(&self, repository: &str, key: &str) -> Option<String> {
+        let mut metadata = self.metadata.write().await;
+        let entries = metadata.get_mut(repository)?;
+        let removed = entries.remove(key);
+        if entries.is_empty() {
+            metadata.remove(repository);
+        }
+        removed
+    }
+
+    /// Answers a blob request according to the foreign layer policy, or
+    /// returns `None` to serve it normally.
+    async fn foreign_layer_response(&self, digest: &str) -> Option<Response> {
+        let policy = self.config.foreign_layers;
+        if policy == ForeignLayerPolicy::Serve {
+            return None;
+        }
+        let urls = self.foreign_layers.read().await.get(digest)?.clone();
+        match (policy, urls.first()) {
+            (ForeignLayerPolicy::Redirect, Some(url)) => {
+                Some((StatusCode::TEMPORARY_REDIRECT, [("Location", url.clone())]).into_response())
+            }
+            _ => Some(error_response(
+                StatusCode::NOT_FOUND,
+                "BLOB_UNKNOWN",
+                "blob unknown to registry",
+            )),
+        }
+    }
+
+    /// Checks a pushed manifest at the configured validation level,
+    /// returning the error response if it is rejected.
+    async fn manifest_rejection(
+        &self,
+        name: &str,
+        content_type: &str,
+        data: &[u8],
+    ) -> Option<Response> {
+        let level = self.config.manifest_validation;
+        if level == ValidationLevel::None {
+            return None;
+        }
+        let manifest = match Manifest::parse(content_type, data)
+            .and_then(|manifest| manifest.validate(content_type).map(|()| manifest))
+        {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                warn!("Rejected invalid manifest for {}: {}", name, e);
+                return Some(e.into_response());
+            }
+        };
+        if level < ValidationLevel::Full {
+            return None;
+        }
+
+        let missing = match &manifest {
+            Manifest::Image(image) => {
+                let mut missing = None;
+                for blob in std::iter::once(&image.config).chain(&image.layers) {
+                    if !is_foreign(&blob.media_type) && !self.has_blob(&blob.digest).await {
+                        missing = Some(&blob.digest);
+                        break;
+                    }
+                }
+                missing
+            }
+            Manifest::Index(index) => {
+                let mut missing = None;
+                for child in &index.manifests {
+                    let key = format!("{}:{}", name, child.digest);
+                    if !matches!(self.storage.get_manifest(&key).await, Ok(Some(_))) {
+                        missing = Some(&child.digest);
+                        break;
+                    }
+                }
+                missing
+            }
+        };
+        missing.map(|digest| {
+            warn!(
+                "Rejected manifest for {} referencing unknown {}",
+                name, digest
+            );
+            error_response(
+                StatusCode::BAD_REQUEST,
+                "MANIFEST_BLOB_UNKNOWN",
+                &format!("manifest references unknown content {}", digest),
+            )
+        })
+    }
+
+    /// Returns whether a blob is stored or registered as synthetic.
+    async fn has_blob(&self, digest: &str) -> bool {
+        self.synthetic.read().await.contains_key(digest)
+            || matches!(self.storage.get_blob(digest).await, Ok(Some(_)))
+    }
+
+    /// Looks up a manifest, falling back to the upstreams on a miss.
+    async fn find_manifest(&self, name: &str, reference: &str) -> Option<ManifestEntry> {
+        let key = format!("{}:{}", name, reference);
+        if let Ok(Some(entry)) = self.storage.get_manifest(&key).await {
+            return Some(entry);
+        }
+        let fetched = self.upstreams.as_ref()?.manifest(name, reference).await?;
+        if fetched.cache {
+            let digest_key = format!("{}:{}", name, self.compute_digest(&fetched.content.data));
+            for key in [key, digest_key] {
+                if let Err(e) = self
+                    .storage
+                    .store_manifest(key, fetched.content.clone())
+                    .await
+                {
+                    warn!("Failed to cache upstream manifest: {}", e);
+                }
+            }
+        }
+        Some(fetched.content)
+    }
+
+    /// Looks up a stored blob, falling back to the upstreams on a miss.
+    async fn find_blob(&self, name: Option<&str>, digest: &str) -> Result<Option<Vec<u8>>> {
+        if let Some(blob) = self.storage.get_blob(digest).await? {
+            return Ok(Some(blob));
+        }
+        let (Some(upstreams), Some(name)) = (&self.upstreams, name) else {
+            return Ok(None);
+        };
+        let Some(fetched) = upstreams.blob(name, digest).await else {
+            return Ok(None);
+        };
+        if fetched.cache {
+            if let Err(e) = self
+                .storage
+                .store_blob(digest.to_string(), fetched.content.clone())
+                .await
+            {
+                warn!("Failed to cache upstream blob: {}", e);
+            }
+        }
+        Ok(Some(fetched.content))
+    }
+
+    /// Canonical repository name under the configured profile.
+    fn repository(&self, name: &str) -> String {
+        self.config
+            .profile
+            .normalize_name(strip_leading_slash(name))
+    }
+
+    /// Returns the sha256 digest of `data` with the digester of the
+    /// configured policy, or the built-in one.
+    fn compute_digest(&self, data: &[u8]) -> String {
+        self.config
+            .digest_policy
+            .as_ref()
+            .and_then(|policy| policy.compute("sha256", data))
+            .unwrap_or_else(|| sha256_digest(data))
+    }
+
+    /// Incremental hashers for a streamed push of `reference`: sha256 for
+    /// the digest the manifest is stored under, and the reference's own
+    /// algorithm when it is a digest. `None` if the push has to be read
+    /// whole instead.
+    fn manifest_hashers(&self, reference: &str) -> Option<Vec<(String, Box<dyn DigestHasher>)>> {
+        let config = &self.config;
+        if !config.stream_manifests
+            || config.manifest_validation != ValidationLevel::None
+            || config.schema1.is_some()
+            || config.signing_policy.is_some()
+            || config.immutable_tags
+            || self.replicator.is_some()
+        {
+            return None;
+        }
+        let builtin = DigestPolicy::new();
+        let policy = config.digest_policy.as_ref().unwrap_or(&builtin);
+        let mut algorithms = vec!["sha256"];
+        if let Some((algorithm, _)) = reference.split_once(':') {
+            if algorithm != "sha256" {
+                algorithms.push(algorithm);
+            }
+        }
+        algorithms
+            .into_iter()
+            .map(|algorithm| {
+                let hasher = policy.digester(algorithm)?.hasher()?;
+                Some((algorithm.to_string(), hasher))
+            })
+            .collect()
+    }
+
+    /// Checks that `data` hashes to `digest` with the digesters of the
+    /// configured policy, or the built-in ones.
+    fn verify_digest(&self, digest: &str, data: &[u8]) -> std::result::Result<(), String> {
+        match &self.config.digest_policy {
+            Some(policy) => policy.verify(digest, data),
+            None => digest::verify(digest, data),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -40,13 +782,41 @@ struct UploadParams {
     digest: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct MountParams {
+    mount: Option<String>,
+    from: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BlobsExistRequest {
+    digests: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct BlobsExistBody {
+    blobs: Vec<BlobExistence>,
+}
+
+#[derive(Serialize)]
+struct BlobExistence {
+    digest: String,
+    exists: bool,
+}
+
 /// The main registry server.
 ///
 /// Implements an OCI-compliant container registry that can be used for
 /// testing Docker/container workflows.
 pub struct RegistryServer {
     addr: SocketAddr,
-    _handle: tokio::task::JoinHandle<()>,
+    state: AppState,
+    app: Router,
+    handle: Option<JoinHandle<()>>,
+    shutdown: Option<oneshot::Sender<()>>,
+    _blob_server: Option<JoinHandle<()>>,
+    maintenance: Option<JoinHandle<()>>,
+    lagged: Option<Arc<LaggedStorage>>,
 }
 
 impl RegistryServer {
@@ -64,46 +834,222 @@ impl RegistryServer {
     /// # }
     /// ```
     pub async fn new(config: RegistryConfig) -> Result<Self> {
-        let storage = create_storage(&config.storage).await?;
-
-        let state = AppState { storage };
-
-        let app = Router::new()
-            .route("/v2/", get(api_version))
-            .route("/v2/{name}/blobs/{digest}", head(check_blob))
-            .route("/v2/{name}/blobs/{digest}", get(get_blob))
-            .route("/v2/{name}/blobs/uploads/", post(start_upload))
-            .route("/v2/{name}/blobs/uploads/{uuid}", patch(upload_chunk))
-            .route("/v2/{name}/blobs/uploads/{uuid}", put(finish_upload))
-            .route("/v2/{name}/manifests/{reference}", put(put_manifest))
-            .route("/v2/{name}/manifests/{reference}", get(get_manifest))
-            .route("/v2/{name}/manifests/{reference}", head(check_manifest))
-            .layer(
-                tower::ServiceBuilder::new()
-                    .layer(axum::extract::DefaultBodyLimit::max(512 * 1024 * 1024))
-                    .layer(TraceLayer::new_for_http()),
-            )
-            .with_state(state);
+        let (mut server, tasks) = Self::prepare(config).await?;
+        server.handle = Some(tokio::spawn(tasks.serve));
+        server._blob_server = tasks.blob_server.map(tokio::spawn);
+        server.maintenance = tasks.maintenance.map(tokio::spawn);
+        server.emit_started();
+        Ok(server)
+    }
 
-        let bind_addr = if let Some(port) = config.port {
-            format!("{}:{}", config.host, port)
-        } else {
-            format!("{}:0", config.host)
+    /// Creates a registry server without spawning it, returning the future
+    /// that serves requests.
+    ///
+    /// The server accepts connections only while the future is polled, so
+    /// it can run on a `current_thread` runtime, inside a `LocalSet` or
+    /// under `select!` with the test body. The future completes once the
+    /// server is stopped or dropped. Connections are still served on tasks
+    /// of the current tokio runtime, and [`restart`](Self::restart) and
+    /// [`rebind_same_port`](Self::rebind_same_port) spawn the listener
+    /// they rebind.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    ///
+    /// let runtime = tokio::runtime::Builder::new_current_thread()
+    ///     .enable_all()
+    ///     .build()?;
+    /// runtime.block_on(async {
+    ///     let (server, serve) = RegistryServer::bind(RegistryConfig::memory()).await?;
+    ///     let client = RegistryClient::new(server.url());
+    ///     let layers = [b"layer".to_vec()];
+    ///     tokio::select! {
+    ///         _ = serve => unreachable!("the server is still alive"),
+    ///         pushed = client.push_image("app", "v1", &layers) => pushed?,
+    ///     };
+    ///     Ok::<_, Box<dyn std::error::Error>>(())
+    /// })?;
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    pub async fn bind(config: RegistryConfig) -> Result<(Self, ServeFuture)> {
+        let (mut server, tasks) = Self::prepare(config).await?;
+        let (shutdown, stopped) = oneshot::channel();
+        server.shutdown = Some(shutdown);
+        let serving = future::join3(
+            tasks.serve,
+            future::join_all(tasks.blob_server),
+            future::join_all(tasks.maintenance),
+        );
+        let serve = async move {
+            tokio::select! {
+                _ = serving => {}
+                _ = stopped => {}
+            }
+        };
+        server.emit_started();
+        Ok((server, serve.boxed()))
+    }
+
+    async fn prepare(config: RegistryConfig) -> Result<(Self, Tasks)> {
+        let lifecycle = config.lifecycle.clone();
+        Self::build(config).await.inspect_err(|e| {
+            lifecycle.emit(LifecycleEvent::FatalError {
+                error: e.to_string(),
+            })
+        })
+    }
+
+    async fn build(config: RegistryConfig) -> Result<(Self, Tasks)> {
+        let mut storage = create_storage(&config.storage).await?;
+        if let Some(path) = &config.preload {
+            let file = tokio::fs::File::open(path).await?;
+            let reader = tokio::io::BufReader::new(file);
+            let manifests = archive::import_registry(storage.as_ref(), None, reader).await?;
+            info!("Preloaded {} manifests from {}", manifests, path.display());
+        }
+
+        if !config.faults.storage.is_empty() {
+            storage = Arc::new(FlakyStorage::new(storage, config.faults.storage.clone()));
+        }
+        if let Some(window) = config.faults.stale_reads {
+            storage = Arc::new(StaleReadStorage::new(storage, window));
+        }
+
+        let lagged = match config.visibility {
+            Visibility::Immediate => None,
+            Visibility::Delayed(delay) => {
+                Some(Arc::new(LaggedStorage::new(storage.clone(), Some(delay))))
+            }
+            Visibility::Manual => Some(Arc::new(LaggedStorage::new(storage.clone(), None))),
         };
+        if let Some(lagged) = &lagged {
+            storage = lagged.clone();
+        }
+        config.lifecycle.emit(LifecycleEvent::StorageInitialized {
+            backend: config.storage.clone(),
+        });
 
-        let listener = TcpListener::bind(&bind_addr).await?;
+        let listener = match config.port {
+            Some(port) => match config
+                .socket
+                .bind(&format!("{}:{}", config.host, port))
+                .await
+            {
+                Err(e) if e.kind() == io::ErrorKind::AddrInUse && config.port_fallback => {
+                    warn!("Port {} is in use, binding a random port", port);
+                    config.socket.bind(&format!("{}:0", config.host)).await?
+                }
+                result => result.map_err(|e| addr_in_use(e, port))?,
+            },
+            None => config.socket.bind(&format!("{}:0", config.host)).await?,
+        };
         let addr = listener.local_addr()?;
 
+        let public_url = config
+            .external_url
+            .clone()
+            .unwrap_or_else(|| format!("http://{}", addr));
+        let authenticator = match config.auth.clone() {
+            Some(auth) => {
+                let audit = AuditLog::new(auth.audit_file.as_deref()).await?;
+                Some(Arc::new(Authenticator::new(
+                    auth,
+                    config.profile,
+                    &public_url,
+                    config.clock_skew,
+                    audit,
+                )))
+            }
+            None => None,
+        };
+
+        let mut state = AppState {
+            storage,
+            config: Arc::new(config.clone()),
+            synthetic: Arc::default(),
+            redirector: None,
+            authenticator,
+            replicator: None,
+            upstreams: (!config.upstreams.is_empty())
+                .then(|| Arc::new(Upstreams::new(&config.upstreams))),
+            recorder: config
+                .capture
+                .clone()
+                .map(|capture| Arc::new(Recorder::new(capture))),
+            received: (config.received_requests > 0)
+                .then(|| Arc::new(ReceivedLog::new(config.received_requests, config.profile))),
+            metrics: Arc::new(Metrics::new()),
+            pulls: Arc::default(),
+            tag_history: Arc::default(),
+            tombstones: Arc::default(),
+            metadata: Arc::default(),
+            foreign_layers: Arc::default(),
+            uploads_started: Arc::default(),
+            upload_counters: Arc::default(),
+            dedup: Arc::default(),
+            labels: Arc::default(),
+            upload_writers: Arc::default(),
+            maintenance_mode: Arc::default(),
+            proxy: Arc::new(std::sync::RwLock::new(config.proxy.clone().map(Arc::new))),
+            started: SystemTime::now(),
+            events: broadcast::channel(1024).0,
+        };
+
+        if let Some(replication) = &config.replication {
+            state.replicator = Some(Arc::new(Replicator::start(
+                replication,
+                state.storage.clone(),
+                state.synthetic.clone(),
+                state.events.clone(),
+            )));
+        }
+
+        let casing = state.config.header_quirks.casing;
+        let blob_server = match &config.blob_redirect {
+            Some(redirect) => {
+                let listener = config.socket.bind(&format!("{}:0", config.host)).await?;
+                let bound_url = format!("http://{}", listener.local_addr()?);
+                info!("Blob server listening on {}", bound_url);
+                let base_url = redirect.external_url.clone().unwrap_or(bound_url);
+                state.redirector = Some(Arc::new(BlobRedirector::new(base_url, redirect)));
+                Some(serve(listener, blob_server_router(state.clone()), casing).boxed())
+            }
+            None => None,
+        };
+
+        let app = router(state.clone(), namespaces(&state).await?);
+
         info!("Registry listening on {}", addr);
 
-        let handle = tokio::spawn(async move {
-            axum::serve(listener, app).await.ok();
-        });
+        let tasks = Tasks {
+            serve: serve(listener, app.clone(), casing).boxed(),
+            blob_server,
+            maintenance: config
+                .maintenance
+                .clone()
+                .map(|maintenance| maintain(state.clone(), maintenance).boxed()),
+        };
 
-        Ok(Self {
+        let server = Self {
             addr,
-            _handle: handle,
-        })
+            state,
+            app,
+            handle: None,
+            shutdown: None,
+            _blob_server: None,
+            maintenance: None,
+            lagged,
+        };
+        Ok((server, tasks))
+    }
+
+    fn emit_started(&self) {
+        self.state.config.lifecycle.emit(LifecycleEvent::Started {
+            addr: self.addr,
+            url: self.public_url(),
+        });
     }
 
     /// Returns the socket address the server is bound to.
@@ -127,110 +1073,2440 @@ impl RegistryServer {
         format!("http://{}", self.addr)
     }
 
+    /// Returns the URL clients are told to use: the configured external
+    /// URL, or [`url`](Self::url) when there is none.
+    pub fn public_url(&self) -> String {
+        self.state
+            .config
+            .external_url
+            .clone()
+            .unwrap_or_else(|| self.url())
+    }
+
+    /// Returns the storage the server reads and writes, for inspecting or
+    /// changing stored content behind the registry API's back.
+    ///
+    /// This is the storage as the server sees it: configured
+    /// [storage faults](RegistryConfig::with_faults) and
+    /// [visibility delays](RegistryConfig::with_visibility) apply to calls
+    /// made through it too.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// let digest = client.push_blob("app", b"layer".to_vec()).await?;
+    /// // Corrupt the blob to check that clients verify what they pull.
+    /// server
+    ///     .storage()
+    ///     .store_blob(digest.clone(), b"tampered".to_vec())
+    ///     .await?;
+    /// assert!(client.pull_blob("app", &digest).await.is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn storage(&self) -> Arc<dyn Storage> {
+        self.state.storage.clone()
+    }
+
+    /// Parses `name` as an image reference on this registry, for handing
+    /// to tools such as `docker pull`.
+    ///
+    /// The registry part of `name`, if any, is replaced with the host of
+    /// [`public_url`](Self::public_url), so `app:v1` becomes
+    /// `127.0.0.1:<port>/app:v1`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryServer, RegistryConfig};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// let reference = server.reference("app:v1")?;
+    /// println!("docker pull {}", reference);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reference(&self, name: &str) -> Result<Reference> {
+        let url = self.public_url();
+        let host = url
+            .split_once("://")
+            .map_or(url.as_str(), |(_, host)| host)
+            .trim_end_matches('/');
+        let qualified = match name.split_once('/') {
+            Some((first, rest)) if is_registry_host(first) => format!("{}/{}", host, rest),
+            _ => format!("{}/{}", host, name),
+        };
+        Reference::parse(&qualified)
+    }
+
     /// Returns the port number the server is listening on.
     pub fn port(&self) -> u16 {
         self.addr.port()
     }
-}
 
-async fn api_version() -> Json<ApiVersion> {
-    Json(ApiVersion {
-        version: "registry/2.0".to_string(),
-    })
+    /// Returns a connector that serves requests in-process, without TCP.
+    ///
+    /// See [`InProcessConnector`] for how to use it with a hyper client.
+    pub fn connector(&self) -> InProcessConnector {
+        InProcessConnector::new(self.app.clone(), self.state.config.header_quirks.casing)
+    }
+
+    /// Returns the URL of the secondary blob server when blob redirects are
+    /// enabled.
+    pub fn blob_server_url(&self) -> Option<String> {
+        self.state
+            .redirector
+            .as_ref()
+            .map(|redirector| redirector.base_url().to_string())
+    }
+
+    /// Returns how many times `repository` was pulled.
+    ///
+    /// Follows Docker Hub semantics: only successful manifest GETs count;
+    /// HEAD requests and blob downloads do not.
+    pub async fn pull_count(&self, repository: &str) -> u64 {
+        let repository = self.state.repository(repository);
+        self.state
+            .pulls
+            .read()
+            .await
+            .get(&repository)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns the metadata attached to `repository`.
+    ///
+    /// The same map is served as a JSON object at
+    /// `GET /admin/repositories/<name>/metadata`; `PUT` on that path merges
+    /// a JSON object of strings into it and
+    /// `DELETE /admin/repositories/<name>/metadata/<key>` removes one key.
+    pub async fn repository_metadata(&self, repository: &str) -> RepositoryMetadata {
+        let repository = self.state.repository(repository);
+        self.state
+            .metadata
+            .read()
+            .await
+            .get(&repository)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Sets `key` to `value` in the metadata of `repository`.
+    ///
+    /// Metadata is independent of content: it can be attached before the
+    /// first push and survives deleting every tag.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// server.set_repository_metadata("app", "owner", "team-web").await;
+    /// assert_eq!(server.repository_metadata("app").await["owner"], "team-web");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_repository_metadata(
+        &self,
+        repository: &str,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) {
+        let repository = self.state.repository(repository);
+        self.state
+            .metadata
+            .write()
+            .await
+            .entry(repository)
+            .or_default()
+            .insert(key.into(), value.into());
+    }
+
+    /// Removes `key` from the metadata of `repository`, returning its value.
+    pub async fn remove_repository_metadata(&self, repository: &str, key: &str) -> Option<String> {
+        let repository = self.state.repository(repository);
+        self.state.remove_metadata(&repository, key).await
+    }
+
+    /// Issues ECR `GetAuthorizationToken`-style credentials.
+    ///
+    /// Log in as the decoded user (`AWS`) with the decoded password; the
+    /// password is accepted until `expires_at`, which is the configured
+    /// [`token_ttl`](crate::AuthConfig::token_ttl) away (12 hours under
+    /// [`RegistryProfile::Ecr`](crate::RegistryProfile::Ecr)). Returns `None`
+    /// when authentication is disabled.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryProfile, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = RegistryConfig::memory().emulate(RegistryProfile::Ecr);
+    /// let server = RegistryServer::new(config).await?;
+    /// let (user, password) = server.authorization_token().unwrap().credentials().unwrap();
+    /// let client = RegistryClient::new(server.url()).with_credentials(user, password);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn authorization_token(&self) -> Option<AuthorizationToken> {
+        self.state
+            .authenticator
+            .as_ref()
+            .map(|authenticator| authenticator.authorization_token())
+    }
+
+    /// Returns the audit trail: every request to a repository since the
+    /// server started, with the user it was made as, oldest first. Empty
+    /// when authentication is disabled.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{AuthConfig, RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = RegistryConfig::memory().with_auth(AuthConfig::basic().with_user("ci", "secret"));
+    /// let server = RegistryServer::new(config).await?;
+    /// let client = RegistryClient::new(server.url()).with_credentials("ci", "secret");
+    /// client.push_image("app", "v1", &[b"layer".to_vec()]).await?;
+    /// assert!(server
+    ///     .audit_log()
+    ///     .iter()
+    ///     .any(|entry| entry.user.as_deref() == Some("ci") && entry.action == "push"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn audit_log(&self) -> Vec<AuditEntry> {
+        self.state
+            .authenticator
+            .as_ref()
+            .map(|authenticator| authenticator.audit_log())
+            .unwrap_or_default()
+    }
+
+    /// Decodes a token this registry issued, so tests can check which
+    /// scopes a client asked for and when its token expires. Returns
+    /// `None` when authentication is disabled or the token was not signed
+    /// by this registry.
+    ///
+    /// The same report is served at `POST /token/introspect` (a form with
+    /// a `token` field, as in RFC 7662) under bearer auth.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{AuthConfig, RegistryConfig, RegistryServer};
+    /// # async fn example(token: &str) -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = RegistryConfig::memory().with_auth(AuthConfig::bearer("registry.test"));
+    /// let server = RegistryServer::new(config).await?;
+    /// let introspection = server.introspect_token(token).unwrap();
+    /// assert_eq!(introspection.claims.scopes(), ["repository:app:pull"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn introspect_token(&self, token: &str) -> Option<TokenIntrospection> {
+        self.state.authenticator.as_ref()?.introspect(token)
+    }
+
+    /// Subscribes to events the registry emits, such as tags deleted by
+    /// retention.
+    ///
+    /// Events are only delivered to receivers that exist when they are
+    /// emitted.
+    pub fn subscribe(&self) -> broadcast::Receiver<RegistryEvent> {
+        self.state.events.subscribe()
+    }
+
+    /// Puts the registry in maintenance mode: pulls keep working, but every
+    /// write to the registry API is refused with `503 Service Unavailable`
+    /// and a `Retry-After` of `retry_after` (rounded up to whole seconds).
+    ///
+    /// The same switch is available over HTTP: `PUT /admin/maintenance-mode`
+    /// with an optional `{"retryAfter": <seconds>}` body enters it,
+    /// `DELETE` leaves it and `GET` reports
+    /// `{"enabled": <bool>, "retryAfter": <seconds>}`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryConfig, RegistryServer};
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// server.enter_maintenance_mode(Duration::from_secs(30));
+    /// // Pushes now fail with 503; the pipeline should defer and retry.
+    /// server.exit_maintenance_mode();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn enter_maintenance_mode(&self, retry_after: Duration) {
+        warn!("Registry on {} entering maintenance mode", self.addr);
+        *self.state.maintenance_mode.write().unwrap() = Some(retry_after);
+    }
+
+    /// Leaves maintenance mode, accepting writes again.
+    pub fn exit_maintenance_mode(&self) {
+        info!("Registry on {} leaving maintenance mode", self.addr);
+        *self.state.maintenance_mode.write().unwrap() = None;
+    }
+
+    /// Returns the `Retry-After` writes are refused with, or `None` outside
+    /// maintenance mode.
+    pub fn maintenance_mode(&self) -> Option<Duration> {
+        *self.state.maintenance_mode.read().unwrap()
+    }
+
+    /// Switches the [reverse proxy simulation](ProxySimulation) on, or off
+    /// with `None`, for the requests that follow.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{ProxySimulation, RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// server.set_proxy_simulation(Some(ProxySimulation::new().with_http10(true)));
+    /// client.push_image("app", "v1", &[b"layer".to_vec()]).await?;
+    /// server.set_proxy_simulation(None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_proxy_simulation(&self, proxy: Option<ProxySimulation>) {
+        *self.state.proxy.write().unwrap() = proxy.map(Arc::new);
+    }
+
+    /// Returns whether the server is currently accepting connections.
+    pub fn is_running(&self) -> bool {
+        self.handle.is_some() || self.shutdown.is_some()
+    }
+
+    /// Simulates a registry crash.
+    ///
+    /// Closes the listener, drops every open connection mid-flight and
+    /// discards state a real process would lose (in-memory upload sessions).
+    /// Content on disk is kept, so a [`restart`](Self::restart) with a disk
+    /// backend lets clients resume.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryServer, RegistryConfig};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut server = RegistryServer::new(RegistryConfig::temp_dir()).await?;
+    /// server.simulate_crash().await?;
+    /// // Clients now see connection errors.
+    /// server.restart().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn simulate_crash(&mut self) -> Result<()> {
+        self.stop().await;
+        warn!("Simulated crash of registry on {}", self.addr);
+        self.state.storage.simulate_crash().await
+    }
+
+    /// Starts serving again on the same address.
+    ///
+    /// If the server is still running it is stopped first, dropping open
+    /// connections.
+    pub async fn restart(&mut self) -> Result<()> {
+        self.stop().await;
+
+        let listener = self
+            .state
+            .config
+            .socket
+            .bind_addr(self.addr)
+            .map_err(|e| addr_in_use(e, self.addr.port()))?;
+        info!("Registry restarted on {}", self.addr);
+        self.handle = Some(tokio::spawn(serve(
+            listener,
+            self.app.clone(),
+            self.state.config.header_quirks.casing,
+        )));
+        self.emit_started();
+        Ok(())
+    }
+
+    /// Stops the server and binds its port again, retrying for up to five
+    /// seconds while the port is still held, with `SO_REUSEADDR` on.
+    ///
+    /// Use it instead of [`restart`](Self::restart) in stop/start cycles
+    /// that would otherwise fail with `AddrInUse` on slow machines.
+    pub async fn rebind_same_port(&mut self) -> Result<()> {
+        self.stop().await;
+
+        let listener = self
+            .state
+            .config
+            .socket
+            .with_reuse_address(true)
+            .rebind(self.addr, Duration::from_secs(5))
+            .await
+            .map_err(|e| addr_in_use(e, self.addr.port()))?;
+        info!("Registry rebound on {}", self.addr);
+        self.handle = Some(tokio::spawn(serve(
+            listener,
+            self.app.clone(),
+            self.state.config.header_quirks.casing,
+        )));
+        self.emit_started();
+        Ok(())
+    }
+
+    /// Registers a synthetic blob served from generated content.
+    ///
+    /// The digest is computed up front by streaming the content once, which
+    /// takes time proportional to `size` but never allocates the blob. The
+    /// blob is served under every repository name, like stored blobs.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryServer, RegistryConfig};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// let digest = server
+    ///     .register_synthetic_blob(10 * 1024 * 1024 * 1024, 42)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn register_synthetic_blob(&self, size: u64, seed: u64) -> Result<String> {
+        let blob = SyntheticBlob::new(size, seed);
+        let digest = tokio::task::spawn_blocking(move || blob.digest())
+            .await
+            .map_err(std::io::Error::other)?;
+        self.state
+            .synthetic
+            .write()
+            .await
+            .insert(digest.clone(), blob);
+        Ok(digest)
+    }
+
+    /// Points `new_tag` at the manifest `tag` currently refers to.
+    ///
+    /// Writes storage directly, so the push is not visible to request
+    /// middleware (authentication, quotas, immutable tags). `tag` keeps
+    /// pointing at the manifest, which makes promotion scenarios
+    /// (`staging` → `prod`) a single call.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// client.push_image("app", "rc1", &[b"layer".to_vec()]).await?;
+    /// server.retag("app", "rc1", "stable").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn retag(&self, repository: &str, tag: &str, new_tag: &str) -> Result<()> {
+        let repository = self.state.repository(repository);
+        let key = format!("{}:{}", repository, tag);
+        let entry = self
+            .state
+            .storage
+            .get_manifest(&key)
+            .await?
+            .ok_or(RegistryError::ManifestNotFound(key))?;
+
+        info!("Retagging {}:{} as {}", repository, tag, new_tag);
+        let new_key = format!("{}:{}", repository, new_tag);
+        let digest = self.state.compute_digest(&entry.data);
+        self.state
+            .storage
+            .store_manifest(new_key.clone(), entry)
+            .await?;
+        self.state.record_tag(new_key, digest).await;
+        Ok(())
+    }
+
+    /// Parses the manifest `reference` (a tag or digest) of `repository`
+    /// and its config.
+    ///
+    /// Fails with [`RegistryError::ManifestNotFound`] if nothing is stored
+    /// there, [`RegistryError::InvalidManifest`] for an image index or other
+    /// non-image manifest, and [`RegistryError::BlobNotFound`] if the config
+    /// blob is missing.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// client.push_image("app", "v1", &[b"layer".to_vec()]).await?;
+    /// let image = server.inspect_image("app", "v1").await?;
+    /// assert_eq!(image.layers.len(), 1);
+    /// assert_eq!(image.platform.architecture, "amd64");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn inspect_image(&self, repository: &str, reference: &str) -> Result<ImageInspect> {
+        let key = format!("{}:{}", self.state.repository(repository), reference);
+        let storage = &self.state.storage;
+        let entry = storage
+            .get_manifest(&key)
+            .await?
+            .ok_or_else(|| RegistryError::ManifestNotFound(key.clone()))?;
+        let config_digest = inspect::config_digest(&key, &entry.content_type, &entry.data)?;
+        let config = storage
+            .get_blob(&config_digest)
+            .await?
+            .ok_or(RegistryError::BlobNotFound(config_digest))?;
+        inspect::inspect(
+            self.state.compute_digest(&entry.data),
+            &entry.content_type,
+            &entry.data,
+            &config,
+        )
+    }
+
+    /// Returns the annotations of a stored manifest or index and of the
+    /// descriptors it lists, without fetching any blob.
+    ///
+    /// The same view is served as JSON at
+    /// `GET /admin/repositories/<name>/manifests/<reference>/annotations`.
+    /// Fails with [`RegistryError::ManifestNotFound`] if nothing is stored
+    /// under the reference and [`RegistryError::InvalidManifest`] if the
+    /// manifest cannot be parsed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// client.push_image("app", "v1", &[b"layer".to_vec()]).await?;
+    /// let annotations = server.manifest_annotations("app", "v1").await?;
+    /// assert!(annotations.annotations.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn manifest_annotations(
+        &self,
+        repository: &str,
+        reference: &str,
+    ) -> Result<ManifestAnnotations> {
+        self.state.manifest_annotations(repository, reference).await
+    }
+
+    /// Returns the stored images whose label `key` is set to `value`, sorted
+    /// by repository and digest.
+    ///
+    /// Config labels and manifest annotations both count, so images labelled
+    /// by `docker build --label` and by `--annotation` are found alike. Every
+    /// stored manifest is considered, including index children and artifacts
+    /// stored only by digest; their [`LabeledImage::tags`] are empty. The
+    /// same search is served as JSON at
+    /// `GET /v2/_testkit/images?label=<key>&value=<value>`, where `value` may
+    /// be left out to match any value.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{ImageBuilder, RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// ImageBuilder::new()
+    ///     .label("org.opencontainers.image.vendor", "acme")
+    ///     .build()
+    ///     .push(&client, "app", "v1")
+    ///     .await?;
+    /// let images = server
+    ///     .find_images_with_label("org.opencontainers.image.vendor", "acme")
+    ///     .await?;
+    /// assert_eq!(images[0].tags, ["v1"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn find_images_with_label(
+        &self,
+        key: &str,
+        value: &str,
+    ) -> Result<Vec<LabeledImage>> {
+        self.state
+            .find_labeled_images(&LabelQuery {
+                label: key.to_string(),
+                value: Some(value.to_string()),
+                missing: false,
+            })
+            .await
+    }
+
+    /// Returns the stored images without the label `key`, sorted by
+    /// repository and digest, for policy checks such as "every image names
+    /// its source".
+    ///
+    /// Labels are found as in [`find_images_with_label`](Self::find_images_with_label);
+    /// the same search is served at
+    /// `GET /v2/_testkit/images?label=<key>&missing=true`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// client.push_image("app", "v1", &[b"layer".to_vec()]).await?;
+    /// let unlabelled = server
+    ///     .find_images_without_label("org.opencontainers.image.source")
+    ///     .await?;
+    /// assert_eq!(unlabelled.len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn find_images_without_label(&self, key: &str) -> Result<Vec<LabeledImage>> {
+        self.state
+            .find_labeled_images(&LabelQuery {
+                label: key.to_string(),
+                value: None,
+                missing: true,
+            })
+            .await
+    }
+
+    /// Returns the tags in `repository` that attach content to the manifest
+    /// `digest` through the referrers tag schema: its
+    /// [fallback tag](crate::referrers::fallback_tag) and cosign tags such
+    /// as `sha256-<hex>.sig`, sorted.
+    pub async fn fallback_tags(&self, repository: &str, digest: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}:", self.state.repository(repository));
+        let mut tags: Vec<String> = self
+            .state
+            .storage
+            .list_manifests()
+            .await?
+            .into_iter()
+            .filter(|key| is_tag_key(key))
+            .filter_map(|key| key.strip_prefix(&prefix).map(str::to_string))
+            .filter(|tag| is_fallback_tag_of(tag, digest))
+            .collect();
+        tags.sort();
+        Ok(tags)
+    }
+
+    /// Compares two stored images given as `(repository, reference)` pairs,
+    /// taking `base` as the starting point.
+    ///
+    /// Layers are compared by position: an incremental build that only
+    /// stacked a layer on top of `base` shows up as a single
+    /// [`added`](ImageDiff::added) layer. Fails like
+    /// [`inspect_image`](Self::inspect_image) for either image.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// client.push_image("app", "v1", &[b"base".to_vec()]).await?;
+    /// client.push_image("app", "v2", &[b"base".to_vec(), b"app".to_vec()]).await?;
+    /// let diff = server.diff_images(("app", "v1"), ("app", "v2")).await?;
+    /// assert_eq!(diff.added.len(), 1);
+    /// assert!(diff.changed.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn diff_images(&self, base: (&str, &str), other: (&str, &str)) -> Result<ImageDiff> {
+        let base = self.inspect_image(base.0, base.1).await?;
+        let other = self.inspect_image(other.0, other.1).await?;
+        Ok(base.diff(&other))
+    }
+
+    /// Checks that every manifest and blob the image `reference` (a tag or
+    /// digest) references is stored with the size and digest its
+    /// descriptor claims.
+    ///
+    /// Index children are walked too. Problems are collected rather than
+    /// returned as errors; only a missing or unparsable root manifest fails
+    /// the call.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// client.push_image("app", "v1", &[b"layer".to_vec()]).await?;
+    /// let report = server.verify_image("app", "v1").await?;
+    /// assert!(report.is_complete(), "{:?}", report.problems);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn verify_image(&self, repository: &str, reference: &str) -> Result<VerifyReport> {
+        let repository = self.state.repository(repository);
+        let synthetic = self.state.synthetic.read().await;
+        verify::verify(
+            self.state.storage.as_ref(),
+            &synthetic,
+            &repository,
+            reference,
+        )
+        .await
+    }
+
+    /// Checks the image `reference` points at, like
+    /// [`verify_image`](Self::verify_image). The registry part of
+    /// `reference` is ignored.
+    pub async fn verify_reference(&self, reference: &Reference) -> Result<VerifyReport> {
+        self.verify_image(reference.repository(), reference.reference())
+            .await
+    }
+
+    /// Writes the image `reference` (a tag or digest) of `repository` to
+    /// `writer` as an `oci-archive` tarball: `oci-layout`, an `index.json`
+    /// pointing at the image, and every manifest and blob under `blobs/`.
+    ///
+    /// Index children are included. A tag is recorded as the
+    /// `org.opencontainers.image.ref.name` annotation, so
+    /// `skopeo copy oci-archive:image.tar:<tag> ...` finds it. Fails with
+    /// [`RegistryError::ManifestNotFound`] or [`RegistryError::BlobNotFound`]
+    /// if the image is incomplete; see [`verify_image`](Self::verify_image).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// client.push_image("app", "v1", &[b"layer".to_vec()]).await?;
+    /// let file = tokio::fs::File::create("app.tar").await?;
+    /// server.export_image_tar("app", "v1", file).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn export_image_tar<W: AsyncWrite + Unpin>(
+        &self,
+        repository: &str,
+        reference: &str,
+        writer: W,
+    ) -> Result<()> {
+        let repository = self.state.repository(repository);
+        let synthetic = self.state.synthetic.read().await.clone();
+        archive::export(
+            self.state.storage.as_ref(),
+            &synthetic,
+            &repository,
+            reference,
+            writer,
+        )
+        .await
+    }
+
+    /// Writes every manifest and blob in the registry to `writer` as one
+    /// OCI image layout tarball, which
+    /// [`RegistryConfig::preload_from_tar`] loads into a new server.
+    ///
+    /// `index.json` lists each stored manifest with its repository and tag
+    /// (or digest) in the `io.containerd.image.name` annotation.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryConfig, RegistryServer};
+    /// # async fn example(server: RegistryServer) -> Result<(), Box<dyn std::error::Error>> {
+    /// let file = tokio::fs::File::create("fixtures.tar").await?;
+    /// server.export_registry_tar(file).await?;
+    /// let copy = RegistryServer::new(RegistryConfig::memory().preload_from_tar("fixtures.tar")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn export_registry_tar<W: AsyncWrite + Unpin>(&self, writer: W) -> Result<()> {
+        let synthetic = self.state.synthetic.read().await.clone();
+        archive::export_registry(self.state.storage.as_ref(), &synthetic, None, writer).await
+    }
+
+    /// Writes the manifests of `repository`, under their tags and digests,
+    /// and the blobs they reference to `writer` as an OCI image layout
+    /// tarball, which [`import_repository_tar`](Self::import_repository_tar)
+    /// loads into another server.
+    ///
+    /// The same archive is served at
+    /// `GET /admin/repositories/<name>/archive`, so harnesses in other
+    /// languages can move fixtures between registries too. Fails with
+    /// [`RegistryError::RepositoryNotFound`] if nothing is stored in
+    /// `repository`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryConfig, RegistryServer};
+    /// # async fn example(server: RegistryServer) -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut archive = Vec::new();
+    /// server.export_repository_tar("app", &mut archive).await?;
+    /// let other = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// other.import_repository_tar("app", archive.as_slice()).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn export_repository_tar<W: AsyncWrite + Unpin>(
+        &self,
+        repository: &str,
+        writer: W,
+    ) -> Result<()> {
+        self.state.export_repository(repository, writer).await
+    }
+
+    /// Loads an archive written by
+    /// [`export_repository_tar`](Self::export_repository_tar) or
+    /// [`export_registry_tar`](Self::export_registry_tar) into
+    /// `repository`, returning the number of manifests stored.
+    ///
+    /// Every manifest is stored in `repository` whatever repository it was
+    /// exported from, so fixtures can be imported under a new name. The
+    /// same import is available as `PUT /admin/repositories/<name>/archive`
+    /// with the archive as the body, answering
+    /// `{"manifests": <count>}`.
+    pub async fn import_repository_tar<R: AsyncRead + Unpin>(
+        &self,
+        repository: &str,
+        reader: R,
+    ) -> Result<usize> {
+        self.state.import_repository(repository, reader).await
+    }
+
+    /// Returns the manifests `tag` has pointed to, oldest first.
+    ///
+    /// A revision is recorded each time a push or [`retag`](Self::retag)
+    /// moves the tag to a different manifest; deleting the tag keeps its
+    /// history. The same list is served as JSON at
+    /// `GET /admin/repositories/<name>/tags/<tag>/history`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// let v1 = client.push_image("app", "latest", &[b"v1".to_vec()]).await?;
+    /// let v2 = client.push_image("app", "latest", &[b"v2".to_vec()]).await?;
+    /// let history = server.tag_history("app", "latest").await;
+    /// assert_eq!(history[0].digest, v1);
+    /// assert_eq!(history[1].digest, v2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn tag_history(&self, repository: &str, tag: &str) -> Vec<TagRevision> {
+        let key = format!("{}:{}", self.state.repository(repository), tag);
+        self.state
+            .tag_history
+            .read()
+            .await
+            .get(&key)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Deletes the manifest `reference` (a tag or digest) from `repository`.
+    ///
+    /// Deleting a tag leaves the manifest pullable by digest. With
+    /// [soft deletes](RegistryConfig::with_soft_delete) the manifest is kept
+    /// as a tombstone that [`undelete`](Self::undelete) restores. Fails with
+    /// [`RegistryError::ManifestNotFound`] if nothing is stored there.
+    pub async fn delete_manifest(&self, repository: &str, reference: &str) -> Result<()> {
+        let key = format!("{}:{}", self.state.repository(repository), reference);
+        info!("Deleting manifest {}", key);
+        if self.state.delete_manifest(&key).await? {
+            Ok(())
+        } else {
+            Err(RegistryError::ManifestNotFound(key))
+        }
+    }
+
+    /// Restores a soft-deleted manifest from its tombstone.
+    ///
+    /// Fails with [`RegistryError::ManifestNotFound`] if there is no
+    /// tombstone, either because soft deletes are off or because garbage
+    /// collection already purged it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let server = RegistryServer::new(RegistryConfig::memory().with_soft_delete(true)).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// client.push_image("app", "v1", &[b"layer".to_vec()]).await?;
+    /// server.delete_manifest("app", "v1").await?;
+    /// server.undelete("app", "v1").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn undelete(&self, repository: &str, reference: &str) -> Result<()> {
+        let key = format!("{}:{}", self.state.repository(repository), reference);
+        let entry = self
+            .state
+            .tombstones
+            .write()
+            .await
+            .remove(&key)
+            .ok_or_else(|| RegistryError::ManifestNotFound(key.clone()))?;
+
+        info!("Restoring manifest {}", key);
+        let digest = self.state.compute_digest(&entry.data);
+        self.state
+            .storage
+            .store_manifest(key.clone(), entry)
+            .await?;
+        self.state.record_tag(key, digest).await;
+        Ok(())
+    }
+
+    /// Moves every tag and manifest of repository `from` to `to`, along
+    /// with its tag history, metadata and the tombstones of its
+    /// soft-deleted manifests.
+    ///
+    /// Blobs are shared between repositories and stay where they are.
+    /// Fails with [`RegistryError::RepositoryNotFound`] if `from` holds no
+    /// manifests, and with [`RegistryError::Denied`] if `to` already holds
+    /// some. Names are compared once normalized by the profile, so renaming
+    /// a repository onto itself does nothing.
+    pub async fn rename_repo(&self, from: &str, to: &str) -> Result<()> {
+        let from = self.state.repository(from);
+        let to = self.state.repository(to);
+        let storage = &self.state.storage;
+
+        let prefix = format!("{}:", from);
+        let target = format!("{}:", to);
+        let manifests = storage.list_manifests().await?;
+        let keys: Vec<String> = manifests
+            .iter()
+            .filter(|key| key.starts_with(&prefix))
+            .cloned()
+            .collect();
+        if keys.is_empty() {
+            return Err(RegistryError::RepositoryNotFound(from));
+        }
+        if from == to {
+            return Ok(());
+        }
+        if manifests.iter().any(|key| key.starts_with(&target)) {
+            return Err(RegistryError::Denied(format!(
+                "repository {} already exists",
+                to
+            )));
+        }
+
+        info!("Renaming repository {} to {}", from, to);
+        for key in keys {
+            let new_key = format!("{}{}", target, &key[prefix.len()..]);
+            if let Some(entry) = storage.get_manifest(&key).await? {
+                storage.store_manifest(new_key.clone(), entry).await?;
+            }
+            storage.delete_manifest(&key).await?;
+
+            let mut tag_history = self.state.tag_history.write().await;
+            if let Some(revisions) = tag_history.remove(&key) {
+                tag_history.insert(new_key, revisions);
+            }
+        }
+
+        let mut tombstones = self.state.tombstones.write().await;
+        let moved: Vec<String> = tombstones
+            .keys()
+            .filter(|key| key.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for key in moved {
+            if let Some(entry) = tombstones.remove(&key) {
+                tombstones.insert(format!("{}{}", target, &key[prefix.len()..]), entry);
+            }
+        }
+
+        let mut metadata = self.state.metadata.write().await;
+        if let Some(entries) = metadata.remove(&from) {
+            metadata.insert(to, entries);
+        }
+        Ok(())
+    }
+
+    /// Returns what a garbage collection would remove, without removing it.
+    ///
+    /// Manifests are kept when a tag reaches them, directly, through an
+    /// index, or as referrers of a kept manifest; blobs are kept when a kept
+    /// manifest references them. Synthetic blobs are never collected.
+    /// Tombstones never keep content alive.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// client.push_blob("app", b"orphan".to_vec()).await?;
+    /// let preview = server.gc_preview().await?;
+    /// assert_eq!(preview.blobs.len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn gc_preview(&self) -> Result<GcReport> {
+        self.state.gc_plan().await
+    }
+
+    /// Deletes the tags that the configured [retention
+    /// policies](RegistryConfig::with_retention) no longer keep.
+    ///
+    /// Returns the deleted tags as `(repository, tag)` pairs. Tags pushed
+    /// before the server started count as pushed at startup. Emits
+    /// [`RegistryEvent::TagExpired`] for each deleted tag and a final
+    /// [`RegistryEvent::RetentionApplied`]. The manifests themselves remain
+    /// until garbage collected; see [`gc_preview`](Self::gc_preview).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryConfig, RegistryServer, RetentionPolicy};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = RegistryConfig::memory().with_retention("*", RetentionPolicy::new().keep_last(3));
+    /// let server = RegistryServer::new(config).await?;
+    /// // ... push tags ...
+    /// let deleted = server.apply_retention().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn apply_retention(&self) -> Result<Vec<(String, String)>> {
+        self.state.apply_retention().await
+    }
+
+    /// Deletes everything [`gc_preview`](Self::gc_preview) lists, purging
+    /// the tombstones of soft-deleted manifests first.
+    ///
+    /// Like distribution's garbage collector this does not coordinate with
+    /// in-flight pushes: a blob uploaded before its manifest is pushed is
+    /// collected if a run happens in between.
+    pub async fn collect_garbage(&self) -> Result<GcReport> {
+        self.state.collect_garbage().await
+    }
+
+    /// Verifies every stored blob against its digest and returns the digests
+    /// whose content no longer matches. Nothing is deleted.
+    pub async fn scrub(&self) -> Result<Vec<String>> {
+        self.state.scrub().await
+    }
+
+    /// Runs the configured [maintenance](RegistryConfig::with_maintenance)
+    /// tasks once, now, and emits the summary event.
+    ///
+    /// Does nothing if maintenance is not configured.
+    pub async fn run_maintenance(&self) -> Result<MaintenanceReport> {
+        match &self.state.config.maintenance {
+            Some(maintenance) => self.state.run_maintenance(maintenance).await,
+            None => Ok(MaintenanceReport::default()),
+        }
+    }
+
+    /// Returns the progress of [replication](RegistryConfig::with_replication).
+    ///
+    /// Always empty when replication is not configured.
+    pub fn replication_status(&self) -> ReplicationStatus {
+        self.state
+            .replicator
+            .as_ref()
+            .map(|replicator| replicator.status())
+            .unwrap_or_default()
+    }
+
+    /// Waits until every push so far has been copied to the replication
+    /// target or has failed to.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer, ReplicationConfig};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = RegistryConfig::memory()
+    ///     .with_replication(ReplicationConfig::new("http://staging:5000").with_credentials("ci", "secret"));
+    /// let server = RegistryServer::new(config).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// client.push_image("app", "v1", &[b"layer".to_vec()]).await?;
+    /// server.wait_for_replication().await;
+    /// assert!(server.replication_status().failures.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn wait_for_replication(&self) {
+        if let Some(replicator) = &self.state.replicator {
+            replicator.idle().await;
+        }
+    }
+
+    /// Returns the exchanges recorded so far when
+    /// [capture](RegistryConfig::with_capture) is enabled.
+    pub fn captured(&self) -> Vec<CapturedExchange> {
+        self.state
+            .recorder
+            .as_ref()
+            .map(|recorder| recorder.exchanges())
+            .unwrap_or_default()
+    }
+
+    /// Returns the requests to `repository` kept when
+    /// [received requests](RegistryConfig::with_received_requests) are
+    /// enabled, oldest first, with their headers as they arrived.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let server = RegistryServer::new(RegistryConfig::memory().with_received_requests(10)).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// client.push_image("app", "v1", &[b"layer".to_vec()]).await?;
+    /// let last = server.received_requests("app").pop().unwrap();
+    /// assert!(last.header("x-forwarded-for").is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn received_requests(&self, repository: &str) -> Vec<ReceivedRequest> {
+        let repository = self.state.repository(repository);
+        self.state
+            .received
+            .as_ref()
+            .map(|received| received.requests(&repository))
+            .unwrap_or_default()
+    }
+
+    /// Loads the content [`verify_sync`](crate::verify_sync) compares.
+    pub(crate) async fn snapshot(&self) -> Result<Snapshot> {
+        let synthetic: Vec<String> = self.state.synthetic.read().await.keys().cloned().collect();
+        Snapshot::load(self.state.storage.as_ref(), synthetic).await
+    }
+
+    /// Returns the captured requests to `endpoint`, grouped into one
+    /// series per path: the first attempt and the client's retries of it.
+    ///
+    /// Requires [capture](RegistryConfig::with_capture); without it there
+    /// are no series.
+    pub fn retries(&self, endpoint: Endpoint) -> Vec<RetrySeries> {
+        RetrySeries::collect(&self.captured(), endpoint)
+    }
+
+    /// Panics unless some path of `endpoint` was retried at least
+    /// `at_least` times, returning the most retried series for checks on
+    /// its delays. See [`retries`](crate::retries) for an example.
+    #[track_caller]
+    pub fn assert_retried(&self, endpoint: Endpoint, at_least: usize) -> RetrySeries {
+        assert!(
+            self.state.recorder.is_some(),
+            "retry assertions need RegistryConfig::with_capture"
+        );
+        let series = self.retries(endpoint);
+        let most = series.iter().max_by_key(|s| s.retries()).cloned();
+        match most {
+            Some(most) if most.retries() >= at_least => most,
+            _ => panic!(
+                "expected {:?} to be retried at least {} times, got {:?}",
+                endpoint,
+                at_least,
+                series
+                    .iter()
+                    .map(|s| (s.path.as_str(), s.retries()))
+                    .collect::<Vec<_>>()
+            ),
+        }
+    }
+
+    /// Returns the recorded exchanges as a HAR 1.2 log, ready to be written
+    /// to a file and opened in browser developer tools.
+    pub fn captured_har(&self) -> serde_json::Value {
+        har(&self.captured(), &self.public_url())
+    }
+
+    /// Discards the exchanges recorded so far.
+    pub fn clear_captured(&self) {
+        if let Some(recorder) = &self.state.recorder {
+            recorder.clear();
+        }
+    }
+
+    /// Returns how many upload sessions were started, finished, cancelled
+    /// and expired, and which are still open.
+    pub async fn upload_stats(&self) -> UploadStats {
+        let sessions: Vec<(String, UploadSession)> = self
+            .state
+            .uploads_started
+            .read()
+            .await
+            .iter()
+            .map(|(uuid, session)| (uuid.clone(), session.clone()))
+            .collect();
+        let mut open = Vec::with_capacity(sessions.len());
+        for (uuid, session) in sessions {
+            let size = self
+                .state
+                .storage
+                .upload_size(&uuid)
+                .await
+                .ok()
+                .flatten()
+                .unwrap_or_default();
+            open.push(OpenUpload {
+                uuid,
+                repository: session.repository,
+                age: session.started.elapsed(),
+                size,
+            });
+        }
+        open.sort_by_key(|upload| std::cmp::Reverse(upload.age));
+        self.state.upload_counters.stats(open)
+    }
+
+    /// Returns how many pushed blobs were already stored, for the whole
+    /// registry and per repository.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// client.push_blob("app", b"layer".to_vec()).await?;
+    /// client.push_blob("app", b"layer".to_vec()).await?;
+    /// let stats = server.dedup_stats().repository("app");
+    /// assert_eq!((stats.pushed, stats.duplicates), (2, 1));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dedup_stats(&self) -> DedupReport {
+        self.state.dedup.report()
+    }
+
+    /// Panics if any upload session is still open, listing them.
+    ///
+    /// Call it at the end of a test: a client that abandons an upload
+    /// without finishing it or cancelling it with `DELETE` leaves the
+    /// session behind, which a real registry keeps until it expires.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// client.push_image("app", "v1", &[b"layer".to_vec()]).await?;
+    /// server.assert_no_leaked_uploads().await;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn assert_no_leaked_uploads(&self) {
+        let stats = self.upload_stats().await;
+        assert!(
+            stats.open.is_empty(),
+            "{} of {} upload sessions were never finished or cancelled: {:#?}",
+            stats.open.len(),
+            stats.started,
+            stats.open
+        );
+    }
+
+    /// Returns per-route latencies, byte counts and connection reuse since
+    /// the server started or [`reset_metrics`](Self::reset_metrics) was last
+    /// called.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+    /// # let client = RegistryClient::new(server.url());
+    /// client.push_image("app", "v1", &[vec![0; 1 << 20]]).await?;
+    /// let metrics = server.metrics();
+    /// let puts = &metrics.routes["PUT /v2/{name}/manifests/{reference}"];
+    /// println!("p99 manifest push: {:?}", puts.latency.p99());
+    /// println!("received {:.0} B/s", metrics.receive_rate());
+    /// assert_eq!(metrics.connections.opened, 1, "client reconnected");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn metrics(&self) -> RegistryMetrics {
+        self.state.metrics.snapshot()
+    }
+
+    /// Discards the metrics collected so far.
+    pub fn reset_metrics(&self) {
+        self.state.metrics.reset();
+    }
+
+    /// Makes all pending writes visible when a [`Visibility`] lag is configured.
+    pub async fn flush(&self) {
+        if let Some(lagged) = &self.lagged {
+            lagged.flush().await;
+        }
+    }
+
+    async fn stop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+            handle.await.ok();
+            self.emit_stopped();
+        }
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+            self.emit_stopped();
+        }
+    }
+
+    fn emit_stopped(&self) {
+        self.state
+            .config
+            .lifecycle
+            .emit(LifecycleEvent::Stopped { addr: self.addr });
+    }
+}
+
+impl Drop for RegistryServer {
+    fn drop(&mut self) {
+        if let Some(maintenance) = &self.maintenance {
+            maintenance.abort();
+        }
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+            self.emit_stopped();
+        }
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+            self.emit_stopped();
+        }
+    }
+}
+
+/// Marks an upload session as being written until dropped.
+struct UploadWriter {
+    writers: Arc<std::sync::Mutex<HashSet<String>>>,
+    uuid: String,
+}
+
+impl Drop for UploadWriter {
+    fn drop(&mut self) {
+        self.writers.lock().unwrap().remove(&self.uuid);
+    }
+}
+
+/// Serves a registry created with [`RegistryServer::bind`] until it is
+/// stopped or dropped.
+pub type ServeFuture = BoxFuture<'static, ()>;
+
+/// Loops a registry runs, before they are spawned or handed to the caller.
+struct Tasks {
+    serve: BoxFuture<'static, ()>,
+    blob_server: Option<BoxFuture<'static, ()>>,
+    maintenance: Option<BoxFuture<'static, ()>>,
+}
+
+/// Runs maintenance every `maintenance.interval` until aborted.
+async fn maintain(state: AppState, maintenance: MaintenanceConfig) {
+    let mut interval = tokio::time::interval(maintenance.interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick completes immediately; wait a full interval instead.
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        if let Err(e) = state.run_maintenance(&maintenance).await {
+            warn!("Maintenance run failed: {}", e);
+        }
+    }
+}
+
+/// Builds the sub-registries serving configured namespaces.
+async fn namespaces(state: &AppState) -> Result<Vec<Namespace>> {
+    let mut namespaces = Vec::new();
+    for route in &state.config.namespaces {
+        let mut config = (*state.config).clone();
+        config.namespaces.clear();
+        config.replication = None;
+        let strip = match &route.target {
+            NamespaceTarget::Storage(backend) => {
+                config.storage = backend.clone();
+                config.upstreams.clear();
+                false
+            }
+            NamespaceTarget::Upstream(upstream) => {
+                config.storage = StorageBackend::Memory;
+                config.upstreams = vec![upstream.clone()];
+                true
+            }
+        };
+        let namespace_state = AppState {
+            storage: create_storage(&config.storage).await?,
+            upstreams: (!config.upstreams.is_empty())
+                .then(|| Arc::new(Upstreams::new(&config.upstreams))),
+            replicator: None,
+            config: Arc::new(config),
+            ..state.clone()
+        };
+        namespaces.push(Namespace {
+            prefix: route.prefix.clone(),
+            strip,
+            router: route_nested_names(routes().with_state(namespace_state)),
+        });
+    }
+    Ok(namespaces)
+}
+
+/// Registry API and admin routes, without middleware.
+fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/v2/", get(api_version))
+        .route("/v2/{name}/blobs/{digest}", head(check_blob))
+        .route("/v2/{name}/blobs/{digest}", get(get_blob))
+        .route("/v2/{name}/blobs/{digest}", delete(delete_blob))
+        .route("/v2/{name}/blobs/_exists", post(blobs_exist))
+        .route("/v2/{name}/blobs/uploads/", post(start_upload))
+        .route("/v2/{name}/blobs/uploads/{uuid}", patch(upload_chunk))
+        .route("/v2/{name}/blobs/uploads/{uuid}", put(finish_upload))
+        .route("/v2/{name}/blobs/uploads/{uuid}", get(upload_status))
+        .route("/v2/{name}/blobs/uploads/{uuid}", delete(cancel_upload))
+        .route("/v2/{name}/manifests/{reference}", put(put_manifest))
+        .route("/v2/{name}/manifests/{reference}", get(get_manifest))
+        .route("/v2/{name}/manifests/{reference}", head(check_manifest))
+        .route("/v2/{name}/manifests/{reference}", delete(delete_manifest))
+        .route("/v2/{name}/referrers/{digest}", get(get_referrers))
+        .route("/v2/{name}/tags/list", get(list_tags))
+        .route("/v2/_testkit/images", get(find_labeled_images))
+        .route(
+            "/admin/repositories/{name}/tags/{tag}/history",
+            get(get_tag_history),
+        )
+        .route(
+            "/admin/repositories/{name}/manifests/{reference}/annotations",
+            get(get_manifest_annotations),
+        )
+        .route(
+            "/admin/repositories/{name}/metadata",
+            get(get_metadata).put(put_metadata),
+        )
+        .route(
+            "/admin/repositories/{name}/archive",
+            get(export_repository).put(import_repository),
+        )
+        .route(
+            "/admin/repositories/{name}/requests",
+            get(get_received_requests),
+        )
+        .route(
+            "/admin/repositories/{name}/metadata/{key}",
+            delete(delete_metadata),
+        )
+        .route(
+            "/admin/maintenance-mode",
+            get(get_maintenance_mode)
+                .put(enter_maintenance_mode)
+                .delete(exit_maintenance_mode),
+        )
+}
+
+fn router(state: AppState, namespaces: Vec<Namespace>) -> Router {
+    let mut app = routes();
+
+    if !namespaces.is_empty() {
+        app = app.layer(middleware::from_fn_with_state(
+            Arc::new(namespaces),
+            route_namespace,
+        ));
+    }
+
+    if !state.config.endpoint_enabled(ApiEndpoint::Deletes) {
+        app = app.layer(middleware::from_fn(reject_deletes));
+    }
+
+    if !state.config.disabled_endpoints.is_empty() {
+        let disabled = Arc::new(state.config.disabled_endpoints.clone());
+        app = app.layer(middleware::from_fn_with_state(disabled, disable_endpoints));
+    }
+
+    if state.config.endpoint_enabled(ApiEndpoint::Catalog) {
+        app = app.route("/v2/_catalog", get(get_catalog));
+    } else {
+        app = app.route("/v2/_catalog", get(catalog_unsupported));
+    }
+
+    if let Some(limit) = &state.config.pull_rate_limit {
+        let limiter = Arc::new(PullRateLimiter::new(limit.clone()));
+        app = app.layer(middleware::from_fn_with_state(limiter, pull_rate_limit));
+    }
+
+    if let Some(authenticator) = &state.authenticator {
+        app = app
+            .layer(middleware::from_fn_with_state(
+                authenticator.clone(),
+                require_auth,
+            ))
+            .route_service(
+                "/token",
+                get(token_endpoint).with_state(authenticator.clone()),
+            )
+            .route_service(
+                "/token/introspect",
+                post(introspection_endpoint).with_state(authenticator.clone()),
+            );
+    }
+
+    if state.config.profile != RegistryProfile::Generic {
+        app = app.layer(middleware::from_fn_with_state(
+            state.config.profile,
+            profile_quirks,
+        ));
+    }
+
+    let locations = LocationRewrite {
+        style: state.config.location_style,
+        external_url: state.config.external_url.clone(),
+    };
+    if !locations.is_identity() {
+        app = app.layer(middleware::from_fn_with_state(
+            Arc::new(locations),
+            rewrite_locations,
+        ));
+    }
+
+    if !state.config.warnings.is_empty() {
+        let warnings = Arc::new(state.config.warnings.clone());
+        app = app.layer(middleware::from_fn_with_state(warnings, add_warnings));
+    }
+
+    if let Some(quota) = &state.config.quota {
+        let tracker = Arc::new(QuotaTracker::new(quota.clone()));
+        app = app.layer(middleware::from_fn_with_state(tracker, enforce_quota));
+    }
+
+    app = app.layer(middleware::from_fn_with_state(
+        state.maintenance_mode.clone(),
+        refuse_writes_in_maintenance,
+    ));
+
+    if let Some(recorder) = &state.recorder {
+        app = app.layer(middleware::from_fn_with_state(recorder.clone(), capture));
+    }
+
+    app = app.layer(middleware::from_fn_with_state(
+        state.metrics.clone(),
+        record_metrics,
+    ));
+
+    if !state.config.response_compression.is_empty() {
+        app = app
+            .layer(middleware::from_fn(mark_compressible))
+            .layer(compression(&state.config.response_compression));
+    }
+
+    if let Some(skew) = state.config.clock_skew {
+        app = app.layer(middleware::from_fn_with_state(skew, skew_date));
+    }
+
+    if state.config.protocol_log {
+        app = app.layer(middleware::from_fn(log_protocol));
+    }
+
+    if let Some(received) = &state.received {
+        app = app.layer(middleware::from_fn_with_state(
+            received.clone(),
+            record_received,
+        ));
+    }
+
+    if state.config.header_quirks.rewrites_headers() {
+        let quirks = Arc::new(state.config.header_quirks.clone());
+        app = app.layer(middleware::from_fn_with_state(quirks, apply_header_quirks));
+    }
+
+    app = app.layer(middleware::from_fn_with_state(
+        state.proxy.clone(),
+        simulate_proxy,
+    ));
+
+    let digest_policy = state.config.digest_policy.clone();
+    let app = app
+        .layer(middleware::from_fn(check_expectation))
+        .layer(
+            tower::ServiceBuilder::new()
+                .layer(axum::extract::DefaultBodyLimit::max(MAX_BODY_SIZE))
+                .layer(TraceLayer::new_for_http()),
+        )
+        .with_state(state);
+    let app = route_nested_names(app);
+
+    // Canonical digests are written into the URI, so they have to be in
+    // place before the request is routed.
+    match digest_policy {
+        Some(policy) => Router::new()
+            .fallback_service(app)
+            .layer(middleware::from_fn_with_state(
+                Arc::new(policy),
+                check_digests,
+            )),
+        None => app,
+    }
+}
+
+/// Reports a failure to bind `port` as [`RegistryError::AddrInUse`] if the
+/// port is taken.
+fn addr_in_use(error: io::Error, port: u16) -> RegistryError {
+    if error.kind() == io::ErrorKind::AddrInUse {
+        RegistryError::AddrInUse { port }
+    } else {
+        error.into()
+    }
+}
+
+/// Accepts connections until the task is aborted.
+///
+/// Connections live in a `JoinSet` owned by this future, so aborting the
+/// serving task tears down every open connection with it.
+pub(crate) async fn serve(listener: TcpListener, app: Router, casing: HeaderCasing) {
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                match accepted {
+                    Ok((stream, remote)) => {
+                        connections.spawn(serve_connection(stream, remote, app.clone(), casing));
+                    }
+                    Err(e) => warn!("Failed to accept connection: {}", e),
+                }
+            }
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+        }
+    }
+}
+
+pub(crate) async fn serve_connection<I>(
+    stream: I,
+    remote: SocketAddr,
+    app: Router,
+    casing: HeaderCasing,
+) where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let requests = ConnectionRequests::default();
+    let service = app.map_request(move |mut request: Request<Incoming>| {
+        request.extensions_mut().insert(ConnectInfo(remote));
+        request.extensions_mut().insert(requests.clone());
+        request
+    });
+
+    let mut builder = auto::Builder::new(TokioExecutor::new());
+    if casing == HeaderCasing::TitleCase {
+        builder.http1().title_case_headers(true);
+    }
+    let result = builder
+        .serve_connection_with_upgrades(TokioIo::new(stream), TowerToHyperService::new(service))
+        .await;
+
+    if let Err(e) = result {
+        debug!("Connection from {} closed with error: {}", remote, e);
+    }
+}
+
+async fn get_tag_history(
+    State(state): State<AppState>,
+    Path((name, tag)): Path<(String, String)>,
+) -> Response {
+    let key = format!("{}:{}", state.repository(&name), tag);
+    match state.tag_history.read().await.get(&key) {
+        Some(revisions) => {
+            Json(revisions.iter().map(RevisionBody::from).collect::<Vec<_>>()).into_response()
+        }
+        None => error_response(
+            StatusCode::NOT_FOUND,
+            "TAG_UNKNOWN",
+            "tag has no recorded history",
+        ),
+    }
+}
+
+async fn get_manifest_annotations(
+    State(state): State<AppState>,
+    Path((name, reference)): Path<(String, String)>,
+) -> Response {
+    match state.manifest_annotations(&name, &reference).await {
+        Ok(annotations) => Json(annotations).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn find_labeled_images(
+    State(state): State<AppState>,
+    query: std::result::Result<Query<LabelQuery>, QueryRejection>,
+) -> Response {
+    let query = match query {
+        Ok(Query(query)) => query,
+        Err(rejection) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "UNSUPPORTED",
+                &rejection.body_text(),
+            )
+        }
+    };
+    match state.find_labeled_images(&query).await {
+        Ok(images) => Json(LabeledImages { images }).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn get_referrers(
+    State(state): State<AppState>,
+    Path((name, digest)): Path<(String, String)>,
+    Query(query): Query<ReferrersQuery>,
+) -> Response {
+    let name = state.repository(&name);
+    let filter = query
+        .artifact_type
+        .as_deref()
+        .filter(|_| state.config.referrers_filtering);
+    let index = match state.referrers(&name, &digest, filter).await {
+        Ok(index) => index,
+        Err(e) => return e.into_response(),
+    };
+    let mut response = ([("Content-Type", OCI_INDEX_MEDIA_TYPE)], Json(index)).into_response();
+    if filter.is_some() {
+        response.headers_mut().insert(
+            FILTERS_APPLIED_HEADER,
+            HeaderValue::from_static("artifactType"),
+        );
+    }
+    response
+}
+
+async fn list_tags(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    pagination: std::result::Result<Query<Pagination>, QueryRejection>,
+) -> Response {
+    let pagination = match pagination {
+        Ok(Query(pagination)) => pagination,
+        Err(rejection) => return pagination_invalid(rejection),
+    };
+    let name = state.repository(&name);
+    let tags = match state.list_tags(&name).await {
+        Ok(tags) => tags,
+        Err(e) => return e.into_response(),
+    };
+    let (tags, next) = pagination.page(tags, false);
+    let path = format!("/v2/{}/tags/list", name);
+    with_next_link(Json(TagList { name, tags }).into_response(), &path, next)
+}
+
+fn pagination_invalid(rejection: QueryRejection) -> Response {
+    error_response(
+        StatusCode::BAD_REQUEST,
+        "PAGINATION_NUMBER_INVALID",
+        &rejection.body_text(),
+    )
+}
+
+/// Adds a `Link` header pointing at the next page, if there is one.
+fn with_next_link(mut response: Response, path: &str, next: Option<String>) -> Response {
+    if let Some(next) = next {
+        let link = format!("<{}?{}>; rel=\"next\"", path, next);
+        if let Ok(link) = HeaderValue::from_str(&link) {
+            response.headers_mut().insert("Link", link);
+        }
+    }
+    response
+}
+
+async fn export_repository(State(state): State<AppState>, Path(name): Path<String>) -> Response {
+    let mut archive = Vec::new();
+    match state.export_repository(&name, &mut archive).await {
+        Ok(()) => ([("Content-Type", "application/x-tar")], archive).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Serialize)]
+struct ImportedRepository {
+    manifests: usize,
+}
+
+async fn import_repository(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    body: Bytes,
+) -> Response {
+    match state.import_repository(&name, body.as_ref()).await {
+        Ok(manifests) => Json(ImportedRepository { manifests }).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn get_metadata(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Json<RepositoryMetadata> {
+    let name = state.repository(&name);
+    let metadata = state.metadata.read().await;
+    Json(metadata.get(&name).cloned().unwrap_or_default())
+}
+
+async fn get_received_requests(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Response {
+    let Some(received) = &state.received else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            "UNSUPPORTED",
+            "received requests are not kept",
+        );
+    };
+    let requests = received.requests(&state.repository(&name));
+    Json(
+        requests
+            .iter()
+            .map(ReceivedRequestBody::from)
+            .collect::<Vec<_>>(),
+    )
+    .into_response()
+}
+
+async fn put_metadata(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(entries): Json<RepositoryMetadata>,
+) -> Json<RepositoryMetadata> {
+    let name = state.repository(&name);
+    info!("Updating metadata of {}: {:?}", name, entries.keys());
+    let mut metadata = state.metadata.write().await;
+    let merged = metadata.entry(name).or_default();
+    merged.extend(entries);
+    Json(merged.clone())
+}
+
+async fn delete_metadata(
+    State(state): State<AppState>,
+    Path((name, key)): Path<(String, String)>,
+) -> Response {
+    let name = state.repository(&name);
+    match state.remove_metadata(&name, &key).await {
+        Some(_) => StatusCode::NO_CONTENT.into_response(),
+        None => error_response(
+            StatusCode::NOT_FOUND,
+            "METADATA_UNKNOWN",
+            "metadata key not set on repository",
+        ),
+    }
+}
+
+async fn api_version() -> Json<ApiVersion> {
+    Json(ApiVersion {
+        version: "registry/2.0".to_string(),
+    })
+}
+
+async fn get_catalog(
+    State(state): State<AppState>,
+    query: std::result::Result<Query<CatalogQuery>, QueryRejection>,
+    pagination: std::result::Result<Query<Pagination>, QueryRejection>,
+) -> Response {
+    let pagination = match pagination {
+        Ok(Query(pagination)) => pagination,
+        Err(rejection) => return pagination_invalid(rejection),
+    };
+    let mut repositories = match state.storage.list_repositories().await {
+        Ok(repositories) => repositories,
+        Err(e) => {
+            warn!("Failed to list repositories: {}", e);
+            return e.into_response();
+        }
+    };
+
+    let mut descending = false;
+    if state.config.catalog_extensions {
+        match query {
+            Ok(Query(query)) => {
+                descending = query.descending();
+                repositories = query.apply(repositories);
+            }
+            Err(rejection) => {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    "UNSUPPORTED",
+                    &rejection.body_text(),
+                )
+            }
+        }
+    }
+    let (repositories, next) = pagination.page(repositories, descending);
+    with_next_link(
+        Json(Catalog { repositories }).into_response(),
+        "/v2/_catalog",
+        next,
+    )
+}
+
+async fn catalog_unsupported() -> Response {
+    error_response(
+        StatusCode::METHOD_NOT_ALLOWED,
+        "UNSUPPORTED",
+        "The operation is unsupported.",
+    )
+}
+
+/// Answers manifest and blob deletes with `405` when deletes are disabled.
+/// Cancelling an upload session is not a delete and stays allowed, as in
+/// distribution.
+async fn reject_deletes(request: Request<Body>, next: Next) -> Response {
+    let content_delete = split_repository_path(request.uri().path()).is_some_and(|(_, rest)| {
+        rest.starts_with("manifests/")
+            || (rest.starts_with("blobs/") && !rest.starts_with("blobs/uploads/"))
+    });
+    if request.method() == Method::DELETE && content_delete {
+        return error_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "UNSUPPORTED",
+            "The operation is unsupported.",
+        );
+    }
+    next.run(request).await
+}
+
+/// Refuses registry API writes with 503 while in maintenance mode.
+///
+/// Batched existence checks are `POST`s but only read, so they pass.
+async fn refuse_writes_in_maintenance(
+    State(mode): State<Arc<std::sync::RwLock<Option<Duration>>>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    let write = !matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) && path.starts_with("/v2/")
+        && !path.ends_with("/blobs/_exists");
+    let retry_after = *mode.read().unwrap();
+    match retry_after {
+        Some(retry_after) if write => {
+            let mut response = error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "UNAVAILABLE",
+                "The registry is in maintenance mode and is not accepting writes.",
+            );
+            response
+                .headers_mut()
+                .insert("Retry-After", retry_after_secs(retry_after).into());
+            response
+        }
+        _ => next.run(request).await,
+    }
+}
+
+/// `Retry-After` seconds for a pause, rounded up so clients never retry
+/// before it ends.
+fn retry_after_secs(retry_after: Duration) -> u64 {
+    retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MaintenanceMode {
+    enabled: bool,
+    retry_after: u64,
+}
+
+impl MaintenanceMode {
+    fn of(state: &AppState) -> Self {
+        let mode = *state.maintenance_mode.read().unwrap();
+        Self {
+            enabled: mode.is_some(),
+            retry_after: mode.map_or(0, retry_after_secs),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MaintenanceRequest {
+    retry_after: Option<u64>,
+}
+
+async fn get_maintenance_mode(State(state): State<AppState>) -> Json<MaintenanceMode> {
+    Json(MaintenanceMode::of(&state))
+}
+
+/// `PUT /admin/maintenance-mode`; `Retry-After` defaults to a minute.
+async fn enter_maintenance_mode(State(state): State<AppState>, body: Bytes) -> Response {
+    let request = if body.is_empty() {
+        MaintenanceRequest { retry_after: None }
+    } else {
+        match serde_json::from_slice::<MaintenanceRequest>(&body) {
+            Ok(request) => request,
+            Err(e) => {
+                return error_response(StatusCode::BAD_REQUEST, "UNSUPPORTED", &e.to_string())
+            }
+        }
+    };
+    let retry_after = request.retry_after.unwrap_or(60);
+    warn!("Entering maintenance mode, Retry-After {}s", retry_after);
+    *state.maintenance_mode.write().unwrap() = Some(Duration::from_secs(retry_after));
+    Json(MaintenanceMode::of(&state)).into_response()
+}
+
+async fn exit_maintenance_mode(State(state): State<AppState>) -> Json<MaintenanceMode> {
+    info!("Leaving maintenance mode");
+    *state.maintenance_mode.write().unwrap() = None;
+    Json(MaintenanceMode::of(&state))
+}
+
+/// Checks many blobs at once: `POST /v2/<name>/blobs/_exists` with
+/// `{"digests": [...]}` answers `{"blobs": [{"digest", "exists"}, ...]}` in
+/// request order.
+///
+/// Like the upload endpoints it requires push access. Synthetic blobs count
+/// as present; upstreams are not consulted.
+async fn blobs_exist(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(request): Json<BlobsExistRequest>,
+) -> Response {
+    info!(
+        "Checking {} blobs in {}",
+        request.digests.len(),
+        strip_leading_slash(&name)
+    );
+    let mut digests = request.digests;
+    if let Some(policy) = &state.config.digest_policy {
+        for digest in &mut digests {
+            match policy.canonicalize(digest) {
+                Ok(canonical) => *digest = canonical,
+                Err(message) => return RegistryError::DigestInvalid(message).into_response(),
+            }
+        }
+    }
+
+    let stored = match state.storage.blobs_exist(&digests).await {
+        Ok(stored) => stored,
+        Err(e) => return RegistryError::StorageBackend(e.to_string()).into_response(),
+    };
+    let synthetic = state.synthetic.read().await;
+    let blobs = digests
+        .into_iter()
+        .zip(stored)
+        .map(|(digest, stored)| BlobExistence {
+            exists: stored || synthetic.contains_key(&digest),
+            digest,
+        })
+        .collect();
+    Json(BlobsExistBody { blobs }).into_response()
 }
 
 async fn check_blob(
     State(state): State<AppState>,
     Path((name, digest)): Path<(String, String)>,
-) -> impl IntoResponse {
+) -> Response {
     let name = strip_leading_slash(&name);
     info!("Checking blob: {}/{}", name, digest);
 
-    match state.storage.get_blob(&digest).await {
-        Ok(Some(blob)) => (StatusCode::OK, [("Content-Length", blob.len().to_string())]),
-        _ => (StatusCode::NOT_FOUND, [("Content-Length", "0".to_string())]),
+    if let Some(response) = state.foreign_layer_response(&digest).await {
+        return response;
+    }
+
+    if let Some(blob) = state.synthetic.read().await.get(&digest) {
+        return (StatusCode::OK, [("Content-Length", blob.size.to_string())]).into_response();
+    }
+
+    match state.find_blob(Some(name), &digest).await {
+        Ok(Some(blob)) => (
+            StatusCode::OK,
+            [
+                ("Content-Length", blob.len().to_string()),
+                ("Accept-Ranges", "bytes".to_string()),
+            ],
+        )
+            .into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, [("Content-Length", "0".to_string())]).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Deletes a blob, unless a manifest of another repository or a
+/// soft-deleted one still references it.
+async fn delete_blob(
+    State(state): State<AppState>,
+    Path((name, digest)): Path<(String, String)>,
+) -> Response {
+    let name = state.repository(&name);
+    info!("Deleting blob: {}/{}", name, digest);
+
+    match state.delete_blob(&name, &digest).await {
+        Ok(true) => StatusCode::ACCEPTED.into_response(),
+        Ok(false) => RegistryError::BlobNotFound(digest).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
 async fn get_blob(
     State(state): State<AppState>,
     Path((name, digest)): Path<(String, String)>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     let name = strip_leading_slash(&name);
     info!("Getting blob: {}/{}", name, digest);
 
-    match state.storage.get_blob(&digest).await {
-        Ok(Some(blob)) => (StatusCode::OK, blob),
-        _ => (StatusCode::NOT_FOUND, vec![]),
+    if let Some(response) = state.foreign_layer_response(&digest).await {
+        return response;
+    }
+
+    if let Some(redirector) = &state.redirector {
+        return (
+            StatusCode::TEMPORARY_REDIRECT,
+            [("Location", redirector.signed_url(&digest))],
+        )
+            .into_response();
+    }
+
+    blob_response(&state, Some(name), &digest, &headers).await
+}
+
+/// Parses the value of a `bytes=` range header against a blob of `len`
+/// bytes into an inclusive `(first, last)` pair, or `None` if the range
+/// cannot be satisfied.
+fn satisfiable_range(spec: &str, len: u64) -> Option<(u64, u64)> {
+    let (first, last) = spec.trim().split_once('-')?;
+    let (first, last) = match (first.parse::<u64>(), last.parse::<u64>()) {
+        (Ok(first), Ok(last)) => (first, last.min(len.checked_sub(1)?)),
+        (Ok(first), Err(_)) if last.is_empty() => (first, len.checked_sub(1)?),
+        (Err(_), Ok(suffix)) if first.is_empty() && suffix > 0 => {
+            (len.saturating_sub(suffix), len.checked_sub(1)?)
+        }
+        _ => return None,
+    };
+    (first <= last).then_some((first, last))
+}
+
+/// Serves `blob`, honoring a single-range `Range: bytes=` request header as
+/// lazy-pulling snapshotters (eStargz, zstd:chunked) send them. Multi-range
+/// requests are answered with the whole blob.
+fn ranged_blob_response(blob: Vec<u8>, headers: &HeaderMap) -> Response {
+    let spec = headers
+        .get("Range")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("bytes="))
+        .filter(|spec| !spec.contains(','));
+    let Some(spec) = spec else {
+        return (StatusCode::OK, [("Accept-Ranges", "bytes")], blob).into_response();
+    };
+
+    let len = blob.len() as u64;
+    match satisfiable_range(spec, len) {
+        Some((first, last)) => (
+            StatusCode::PARTIAL_CONTENT,
+            [
+                ("Accept-Ranges", "bytes".to_string()),
+                ("Content-Range", format!("bytes {}-{}/{}", first, last, len)),
+            ],
+            blob[first as usize..=last as usize].to_vec(),
+        )
+            .into_response(),
+        None => (
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [("Content-Range", format!("bytes */{}", len))],
+        )
+            .into_response(),
+    }
+}
+
+async fn blob_response(
+    state: &AppState,
+    name: Option<&str>,
+    digest: &str,
+    headers: &HeaderMap,
+) -> Response {
+    if let Some(blob) = state.synthetic.read().await.get(digest).copied() {
+        return (
+            StatusCode::OK,
+            [("Content-Length", blob.size.to_string())],
+            Body::from_stream(blob.stream()),
+        )
+            .into_response();
+    }
+
+    match state.find_blob(name, digest).await {
+        Ok(Some(blob)) => ranged_blob_response(blob, headers),
+        Ok(None) => RegistryError::BlobNotFound(digest.to_string()).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
+fn blob_server_router(state: AppState) -> Router {
+    Router::new()
+        .route("/blobs/{digest}", get(get_signed_blob))
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
+
+async fn get_signed_blob(
+    State(state): State<AppState>,
+    Path(digest): Path<String>,
+    Query(params): Query<SignedParams>,
+    headers: HeaderMap,
+) -> Response {
+    let valid = state
+        .redirector
+        .as_ref()
+        .is_some_and(|redirector| redirector.verify(&digest, &params));
+    if !valid {
+        warn!(
+            "Rejected blob URL with invalid or expired signature: {}",
+            digest
+        );
+        return RegistryError::Denied("blob URL signature is invalid or expired".to_string())
+            .into_response();
+    }
+
+    blob_response(&state, None, &digest, &headers).await
+}
+
 async fn start_upload(
     Path(name): Path<String>,
     State(state): State<AppState>,
-) -> impl IntoResponse {
+    Query(params): Query<MountParams>,
+) -> Response {
     let name = strip_leading_slash(&name);
+
+    // Blobs are shared by all repositories, so a mount succeeds whenever the
+    // blob is stored; otherwise a regular upload session is opened.
+    if let Some(digest) = params.mount {
+        match state
+            .storage
+            .blobs_exist(std::slice::from_ref(&digest))
+            .await
+        {
+            Ok(exists) if exists.first() == Some(&true) => {
+                state.dedup.record_mount(&state.repository(name));
+                info!(
+                    "Mounted blob {} into {} from {}",
+                    digest,
+                    name,
+                    params.from.as_deref().unwrap_or("-")
+                );
+                return (
+                    StatusCode::CREATED,
+                    [
+                        ("Location", format!("/v2/{}/blobs/{}", name, digest)),
+                        ("Docker-Content-Digest", digest),
+                    ],
+                )
+                    .into_response();
+            }
+            Ok(_) => {}
+            Err(e) => return e.into_response(),
+        }
+    }
+
     let uuid = uuid::Uuid::new_v4().to_string();
     info!("Starting upload: {} ({})", name, uuid);
 
     if let Err(e) = state.storage.create_upload(uuid.clone()).await {
         warn!("Failed to create upload: {}", e);
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            [("Location", String::new())],
-        );
+        return e.into_response();
     }
+    state.uploads_started.write().await.insert(
+        uuid.clone(),
+        UploadSession {
+            repository: state.repository(name),
+            started: Instant::now(),
+        },
+    );
+    state
+        .upload_counters
+        .started
+        .fetch_add(1, Ordering::Relaxed);
 
     (
         StatusCode::ACCEPTED,
         [("Location", format!("/v2/{}/blobs/uploads/{}", name, uuid))],
     )
+        .into_response()
 }
 
 async fn upload_chunk(
     State(state): State<AppState>,
     Path((name, uuid)): Path<(String, String)>,
-    body: Bytes,
-) -> impl IntoResponse {
+    request: Request<Body>,
+) -> Response {
     let name = strip_leading_slash(&name);
+
+    // Check the session before touching the body: reading it is what makes
+    // hyper send `100 Continue` to clients that asked for it.
+    if !matches!(state.storage.upload_size(&uuid).await, Ok(Some(_))) {
+        warn!("Upload not found: {}", uuid);
+        return upload_not_found();
+    }
+    let Some(_writer) = state.claim_upload(&uuid) else {
+        warn!("Concurrent write to upload {}", uuid);
+        return upload_conflict();
+    };
+    let content_range = request
+        .headers()
+        .get("Content-Range")
+        .and_then(|v| v.to_str().ok())
+        .map(parse_content_range);
+    let body = match Bytes::from_request(request, &state).await {
+        Ok(body) => body,
+        Err(rejection) => return rejection.into_response(),
+    };
     debug!("Uploading chunk: {}/{} ({} bytes)", name, uuid, body.len());
 
+    if let Some(range) = content_range {
+        let size = match state.storage.upload_size(&uuid).await {
+            Ok(Some(size)) => size,
+            _ => return upload_not_found(),
+        };
+        let in_order = range.is_some_and(|(start, end)| {
+            start == size && end.checked_add(1) == Some(start + body.len() as u64)
+        });
+        if !in_order && !body.is_empty() {
+            warn!(
+                "Chunk for upload {} does not continue at offset {}",
+                uuid, size
+            );
+            return upload_progress(StatusCode::RANGE_NOT_SATISFIABLE, name, uuid, size);
+        }
+    }
+
     match state.storage.append_upload(&uuid, &body).await {
-        Ok(_) => {
-            let end = body.len().saturating_sub(1);
-            (
-                StatusCode::ACCEPTED,
-                [
-                    ("Location", format!("/v2/{}/blobs/uploads/{}", name, uuid)),
-                    ("Range", format!("0-{}", end)),
-                    ("Docker-Upload-UUID", uuid),
-                ],
-            )
+        Ok(()) => {}
+        Err(RegistryError::UploadNotFound(_)) => {
+            warn!("Upload not found: {}", uuid);
+            return upload_not_found();
+        }
+        Err(e) => {
+            warn!("Failed to append to upload {}: {}", uuid, e);
+            return e.into_response();
         }
-        Err(_) => {
+    }
+    match state.storage.upload_size(&uuid).await {
+        Ok(Some(size)) => upload_progress(StatusCode::ACCEPTED, name, uuid, size),
+        _ => upload_not_found(),
+    }
+}
+
+async fn upload_status(
+    State(state): State<AppState>,
+    Path((name, uuid)): Path<(String, String)>,
+) -> Response {
+    let name = strip_leading_slash(&name);
+    match state.storage.upload_size(&uuid).await {
+        Ok(Some(size)) => upload_progress(StatusCode::NO_CONTENT, name, uuid, size),
+        _ => {
             warn!("Upload not found: {}", uuid);
-            (
-                StatusCode::NOT_FOUND,
-                [
-                    ("Location", String::new()),
-                    ("Range", String::new()),
-                    ("Docker-Upload-UUID", String::new()),
-                ],
-            )
+            upload_not_found()
         }
     }
 }
 
+/// Reports how much of an upload session has been received: `Range` covers
+/// every byte so far (`0-0` when empty, as distribution does) and
+/// `OCI-Upload-Offset` is where the next chunk starts.
+fn upload_progress(status: StatusCode, name: &str, uuid: String, size: u64) -> Response {
+    (
+        status,
+        [
+            ("Location", format!("/v2/{}/blobs/uploads/{}", name, uuid)),
+            ("Range", format!("0-{}", size.saturating_sub(1))),
+            ("OCI-Upload-Offset", size.to_string()),
+            ("Docker-Upload-UUID", uuid),
+        ],
+    )
+        .into_response()
+}
+
+/// Parses a chunk's `Content-Range`: `<start>-<end>`, inclusive, optionally
+/// with a `bytes ` prefix and `/<total>` suffix.
+fn parse_content_range(value: &str) -> Option<(u64, u64)> {
+    let value = value.trim();
+    let value = value.strip_prefix("bytes").map_or(value, str::trim_start);
+    let range = value.split('/').next()?;
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+    (start <= end).then_some((start, end))
+}
+
+/// Rejects a request that races another one on the same upload session.
+fn upload_conflict() -> Response {
+    error_response(
+        StatusCode::CONFLICT,
+        "BLOB_UPLOAD_INVALID",
+        "another request is writing to this upload",
+    )
+}
+
+fn upload_not_found() -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        "BLOB_UPLOAD_UNKNOWN",
+        "blob upload unknown to registry",
+    )
+}
+
 async fn finish_upload(
     State(state): State<AppState>,
     Path((name, uuid)): Path<(String, String)>,
     Query(params): Query<UploadParams>,
-    headers: HeaderMap,
-    body: Bytes,
-) -> impl IntoResponse {
+    request: Request<Body>,
+) -> Response {
     let name = strip_leading_slash(&name);
     debug!("Finishing upload: {}/{}", name, uuid);
 
+    if !matches!(state.storage.upload_size(&uuid).await, Ok(Some(_))) {
+        warn!("Upload not found: {}", uuid);
+        return upload_not_found();
+    }
+    let Some(_writer) = state.claim_upload(&uuid) else {
+        warn!("Upload {} finished while a chunk is being written", uuid);
+        return upload_conflict();
+    };
+    let (parts, body) = request.into_parts();
+    let (body, trailers) = match read_with_trailers(body).await {
+        Ok(read) => read,
+        Err(response) => return response,
+    };
+
+    // The digest may come as the query parameter, a header, or a trailer
+    // sent after a streamed body; any of them has to match the content.
+    let announced = [
+        params.digest,
+        upload_digest(&parts.headers),
+        trailers.as_ref().and_then(upload_digest),
+    ];
+    let mut announced = announced.into_iter().flatten();
+    let claimed = announced.next();
+    if let Some(other) = announced.find(|other| Some(other) != claimed.as_ref()) {
+        warn!("Upload {} announced conflicting digests", uuid);
+        return RegistryError::DigestInvalid(format!(
+            "digest {} conflicts with {}",
+            other,
+            claimed.unwrap_or_default()
+        ))
+        .into_response();
+    }
+
+    state.uploads_started.write().await.remove(&uuid);
     let upload_data = match state.storage.finish_upload(&uuid).await {
         Ok(Some(mut data)) => {
             data.extend_from_slice(&body);
@@ -238,47 +3514,44 @@ async fn finish_upload(
         }
         _ => {
             warn!("Upload not found: {}", uuid);
-            return (
-                StatusCode::NOT_FOUND,
-                [
-                    ("Location", String::new()),
-                    ("Docker-Content-Digest", String::new()),
-                ],
-            );
+            return upload_not_found();
         }
     };
 
-    let digest_str = params
-        .digest
-        .or_else(|| {
-            headers
-                .get("digest")
-                .or_else(|| headers.get("Docker-Content-Digest"))
-                .and_then(|v| v.to_str().ok())
-                .map(|s| s.to_string())
-        })
-        .unwrap_or_else(|| {
-            let mut hasher = Sha256::new();
-            hasher.update(&upload_data);
-            format!("sha256:{}", hex::encode(hasher.finalize()))
-        });
+    if let Some(claimed) = &claimed {
+        if let Err(message) = state.verify_digest(claimed, &upload_data) {
+            warn!("Rejected upload {}/{}: {}", name, uuid, message);
+            return RegistryError::DigestInvalid(message).into_response();
+        }
+    }
+    let digest_str = claimed.unwrap_or_else(|| state.compute_digest(&upload_data));
 
+    let duplicate = matches!(
+        state
+            .storage
+            .blobs_exist(std::slice::from_ref(&digest_str))
+            .await
+            .as_deref(),
+        Ok([true])
+    );
+    let size = upload_data.len() as u64;
     if let Err(e) = state
         .storage
         .store_blob(digest_str.clone(), upload_data)
         .await
     {
         warn!("Failed to store blob: {}", e);
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            [
-                ("Location", String::new()),
-                ("Docker-Content-Digest", String::new()),
-            ],
-        );
+        return RegistryError::StorageBackend(e.to_string()).into_response();
     }
 
     info!("Stored blob: {}", digest_str);
+    state
+        .upload_counters
+        .finished
+        .fetch_add(1, Ordering::Relaxed);
+    state
+        .dedup
+        .record_upload(&state.repository(name), duplicate, size);
 
     (
         StatusCode::CREATED,
@@ -287,56 +3560,393 @@ async fn finish_upload(
             ("Docker-Content-Digest", digest_str),
         ],
     )
+        .into_response()
+}
+
+/// Returns the digest a client announced in `headers` (or trailers) for
+/// the content of an upload.
+fn upload_digest(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("digest")
+        .or_else(|| headers.get("Docker-Content-Digest"))
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+}
+
+/// Reads a request body together with the trailers sent after it, if any.
+async fn read_with_trailers(
+    body: Body,
+) -> std::result::Result<(Bytes, Option<HeaderMap>), Response> {
+    match Limited::new(body, MAX_BODY_SIZE).collect().await {
+        Ok(collected) => {
+            let trailers = collected.trailers().cloned();
+            Ok((collected.to_bytes(), trailers))
+        }
+        Err(e) if e.is::<LengthLimitError>() => Err(RegistryError::TooLarge(format!(
+            "body exceeds {} bytes",
+            MAX_BODY_SIZE
+        ))
+        .into_response()),
+        Err(e) => Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "BLOB_UPLOAD_INVALID",
+            &e.to_string(),
+        )),
+    }
+}
+
+async fn cancel_upload(
+    State(state): State<AppState>,
+    Path((name, uuid)): Path<(String, String)>,
+) -> Response {
+    let name = strip_leading_slash(&name);
+    info!("Cancelling upload: {}/{}", name, uuid);
+
+    let Some(_writer) = state.claim_upload(&uuid) else {
+        warn!("Upload {} cancelled while a chunk is being written", uuid);
+        return upload_conflict();
+    };
+    // Storage decides: sessions outlive the server on disk.
+    let deleted = state.storage.delete_upload(&uuid).await;
+    state.uploads_started.write().await.remove(&uuid);
+    match deleted {
+        Ok(true) => {}
+        Ok(false) => return RegistryError::UploadNotFound(uuid).into_response(),
+        Err(e) => {
+            warn!("Failed to delete upload {}: {}", uuid, e);
+            return e.into_response();
+        }
+    }
+    state
+        .upload_counters
+        .cancelled
+        .fetch_add(1, Ordering::Relaxed);
+    StatusCode::NO_CONTENT.into_response()
+}
+
+fn manifest_too_large(limit: usize) -> RegistryError {
+    RegistryError::TooLarge(format!("manifest exceeds {} bytes", limit))
+}
+
+/// Whether the request announces a body over `limit` bytes.
+fn announces_too_large(headers: &HeaderMap, limit: usize) -> bool {
+    headers
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .is_some_and(|length| length > limit as u64)
+}
+
+/// Reads a whole manifest body, giving up as soon as it exceeds `limit`
+/// bytes.
+async fn read_manifest(headers: &HeaderMap, body: Body, limit: usize) -> Result<Bytes> {
+    if announces_too_large(headers, limit) {
+        return Err(manifest_too_large(limit));
+    }
+
+    let mut stream = body.into_data_stream();
+    let mut data = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| RegistryError::InvalidManifest(e.to_string()))?;
+        if data.len() + chunk.len() > limit {
+            return Err(manifest_too_large(limit));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(data))
+}
+
+/// Passes a manifest body on in chunks, giving up as soon as it exceeds
+/// `limit` bytes. Once it ends, the digests computed by `hashers` are put
+/// in `digests`, unless none matches a digest `reference`, in which case
+/// the stream ends with an error instead.
+fn stream_manifest(
+    body: Body,
+    limit: usize,
+    reference: &str,
+    hashers: Vec<(String, Box<dyn DigestHasher>)>,
+    digests: Arc<std::sync::Mutex<Vec<String>>>,
+) -> ManifestStream {
+    let expected = reference.contains(':').then(|| reference.to_string());
+    let state = Some((body.into_data_stream(), hashers, 0));
+    stream::unfold(state, move |state| {
+        let expected = expected.clone();
+        let digests = digests.clone();
+        async move {
+            let (mut body, mut hashers, received) = state?;
+            let chunk = match body.next().await {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(e)) => {
+                    return Some((Err(RegistryError::InvalidManifest(e.to_string())), None));
+                }
+                None => {
+                    let computed: Vec<String> = hashers
+                        .into_iter()
+                        .map(|(algorithm, hasher)| format!("{}:{}", algorithm, hasher.finish()))
+                        .collect();
+                    if let Some(expected) = expected.filter(|e| !computed.contains(e)) {
+                        let algorithm = expected.split(':').next().unwrap_or_default();
+                        let computed = computed
+                            .iter()
+                            .find(|digest| digest.split(':').next() == Some(algorithm))
+                            .cloned()
+                            .unwrap_or_default();
+                        let message = format!(
+                            "content digest {} does not match reference {}",
+                            computed, expected
+                        );
+                        return Some((Err(RegistryError::DigestInvalid(message)), None));
+                    }
+                    *digests.lock().unwrap() = computed;
+                    return None;
+                }
+            };
+            let received = received + chunk.len();
+            if received > limit {
+                return Some((Err(manifest_too_large(limit)), None));
+            }
+            for (_, hasher) in &mut hashers {
+                hasher.update(&chunk);
+            }
+            Some((Ok(chunk.to_vec()), Some((body, hashers, received))))
+        }
+    })
+    .boxed()
+}
+
+/// Streams `data` in chunks of `chunk_size` bytes, without a known length.
+fn chunked_body(data: Vec<u8>, chunk_size: usize) -> Body {
+    let data = Bytes::from(data);
+    let chunks: Vec<_> = (0..data.len())
+        .step_by(chunk_size)
+        .map(|start| {
+            let end = (start + chunk_size).min(data.len());
+            Ok::<_, std::convert::Infallible>(data.slice(start..end))
+        })
+        .collect();
+    Body::from_stream(stream::iter(chunks))
 }
 
 async fn put_manifest(
     State(state): State<AppState>,
     Path((name, reference)): Path<(String, String)>,
     headers: HeaderMap,
-    body: Bytes,
-) -> impl IntoResponse {
-    let name = strip_leading_slash(&name);
+    body: Body,
+) -> Response {
+    let name = state.repository(&name);
     info!("Putting manifest: {}/{}", name, reference);
 
-    let content_type = headers
-        .get("content-type")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("application/vnd.docker.distribution.manifest.v2+json")
-        .to_string();
+    if let Some(hashers) = state.manifest_hashers(&reference) {
+        return put_streamed_manifest(&state, name, reference, &headers, body, hashers).await;
+    }
+
+    let body = match read_manifest(&headers, body, state.config.max_manifest_size).await {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Rejected manifest {}/{}: {}", name, reference, e);
+            return e.into_response();
+        }
+    };
 
-    let mut hasher = Sha256::new();
-    hasher.update(&body);
-    let digest = format!("sha256:{}", hex::encode(hasher.finalize()));
+    let requested = headers.get("content-type").and_then(|v| v.to_str().ok());
+    let content_type = match state.config.manifest_content_type.resolve(requested, &body) {
+        Ok(content_type) => content_type,
+        Err(e) => {
+            warn!("Rejected manifest {}/{}: {}", name, reference, e);
+            return e.into_response();
+        }
+    };
+
+    let schema1 = state
+        .config
+        .schema1
+        .filter(|_| schema1::is_schema1(&content_type, &body));
+    if schema1 == Some(Schema1Mode::Reject) {
+        warn!("Rejected schema 1 manifest {}/{}", name, reference);
+        return RegistryError::InvalidManifest("schema 1 manifests are not accepted".to_string())
+            .into_response();
+    }
+    if schema1.is_none() {
+        if let Some(response) = state.manifest_rejection(&name, &content_type, &body).await {
+            return response;
+        }
+    }
+    if reference.contains(':') {
+        if let Err(message) = state.verify_digest(&reference, &body) {
+            warn!("Rejected manifest {}/{}: {}", name, reference, message);
+            return RegistryError::DigestInvalid(message).into_response();
+        }
+    }
+
+    let (content_type, body, reference) = if schema1 == Some(Schema1Mode::Convert) {
+        match state.convert_schema1(&name, &body).await {
+            Ok(manifest) => {
+                let reference = if reference.contains(':') {
+                    state.compute_digest(&manifest)
+                } else {
+                    reference
+                };
+                let content_type = DOCKER_MANIFEST_MEDIA_TYPE.to_string();
+                if let Some(response) = state
+                    .manifest_rejection(&name, &content_type, &manifest)
+                    .await
+                {
+                    return response;
+                }
+                (content_type, Bytes::from(manifest), reference)
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to convert schema 1 manifest {}/{}: {}",
+                    name, reference, e
+                );
+                return e.into_response();
+            }
+        }
+    } else {
+        (content_type, body, reference)
+    };
+
+    let digest = state.compute_digest(&body);
+    let subject = subject_digest(&body);
 
     let entry = ManifestEntry {
-        data: body.to_vec(),
+        data: Vec::from(body),
         content_type: content_type.clone(),
     };
 
     let key = format!("{}:{}", name, reference);
     let digest_key = format!("{}:{}", name, digest);
 
-    if let Err(e) = state.storage.store_manifest(key, entry.clone()).await {
+    if let Some(policy) = &state.config.signing_policy {
+        if let Err(e) = policy
+            .admit(
+                state.storage.as_ref(),
+                &name,
+                &reference,
+                &digest,
+                &entry.data,
+            )
+            .await
+        {
+            warn!("Rejected manifest {}/{}: {}", name, reference, e);
+            return e.into_response();
+        }
+    }
+
+    if state.config.immutable_tags && is_tag_key(&key) {
+        if let Ok(Some(existing)) = state.storage.get_manifest(&key).await {
+            if existing.data != entry.data {
+                warn!("Rejected overwrite of immutable tag {}/{}", name, reference);
+                let message = format!(
+                    "The image tag '{}' already exists in the '{}' repository and cannot be \
+                     overwritten because the repository is immutable.",
+                    reference, name
+                );
+                return error_response(StatusCode::BAD_REQUEST, "TAG_INVALID", &message);
+            }
+        }
+    }
+
+    if let Err(e) = state
+        .storage
+        .store_manifest(key.clone(), entry.clone())
+        .await
+    {
         warn!("Failed to store manifest: {}", e);
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            [
-                ("Location", String::new()),
-                ("Content-Type", String::new()),
-                ("Docker-Content-Digest", String::new()),
-            ],
-        );
+        return RegistryError::StorageBackend(e.to_string()).into_response();
     }
 
+    let foreign = foreign_layers(&entry.data);
+    if !foreign.is_empty() {
+        state.foreign_layers.write().await.extend(foreign);
+    }
+    if let Some(replicator) = &state.replicator {
+        replicator.enqueue(name.clone(), reference.clone(), entry.clone());
+    }
     if let Err(e) = state.storage.store_manifest(digest_key, entry).await {
         warn!("Failed to store manifest by digest: {}", e);
     }
+    state.record_tag(key, digest.clone()).await;
 
     info!(
         "Stored manifest with digest: {} (type: {})",
         digest, content_type
     );
 
+    let mut response = (
+        StatusCode::CREATED,
+        [
+            ("Location", format!("/v2/{}/manifests/{}", name, reference)),
+            ("Content-Type", content_type),
+            ("Docker-Content-Digest", digest),
+        ],
+    )
+        .into_response();
+    // Without the referrers API, clients must maintain the fallback index.
+    if state.config.endpoint_enabled(ApiEndpoint::Referrers) {
+        if let Some(subject) = subject.and_then(|subject| HeaderValue::from_str(&subject).ok()) {
+            response.headers_mut().insert(SUBJECT_HEADER, subject);
+        }
+    }
+    response
+}
+
+/// Stores a manifest as it arrives, for
+/// [`RegistryConfig::with_streamed_manifests`].
+async fn put_streamed_manifest(
+    state: &AppState,
+    name: String,
+    reference: String,
+    headers: &HeaderMap,
+    body: Body,
+    hashers: Vec<(String, Box<dyn DigestHasher>)>,
+) -> Response {
+    let limit = state.config.max_manifest_size;
+    if announces_too_large(headers, limit) {
+        warn!("Rejected manifest {}/{}: too large", name, reference);
+        return manifest_too_large(limit).into_response();
+    }
+    let requested = headers.get("content-type").and_then(|v| v.to_str().ok());
+    let content_type = match state.config.manifest_content_type.resolve(requested, &[]) {
+        Ok(content_type) => content_type,
+        Err(e) => return e.into_response(),
+    };
+
+    // The first digest is the sha256 one naming the manifest.
+    let digests = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let chunks = stream_manifest(body, limit, &reference, hashers, digests.clone());
+    let key = format!("{}:{}", name, reference);
+    if let Err(e) = state
+        .storage
+        .store_manifest_stream(key.clone(), content_type.clone(), chunks)
+        .await
+    {
+        warn!("Rejected manifest {}/{}: {}", name, reference, e);
+        return match e {
+            RegistryError::TooLarge(_)
+            | RegistryError::InvalidManifest(_)
+            | RegistryError::DigestInvalid(_) => e.into_response(),
+            e => RegistryError::StorageBackend(e.to_string()).into_response(),
+        };
+    }
+    let Some(digest) = digests.lock().unwrap().first().cloned() else {
+        let message = "storage did not read the manifest to the end".to_string();
+        return RegistryError::StorageBackend(message).into_response();
+    };
+
+    let digest_key = format!("{}:{}", name, digest);
+    if digest_key != key {
+        if let Err(e) = state.storage.copy_manifest(&key, digest_key).await {
+            warn!("Failed to store manifest by digest: {}", e);
+        }
+    }
+    state.record_tag(key, digest.clone()).await;
+
+    info!(
+        "Stored streamed manifest with digest: {} (type: {})",
+        digest, content_type
+    );
     (
         StatusCode::CREATED,
         [
@@ -345,28 +3955,54 @@ async fn put_manifest(
             ("Docker-Content-Digest", digest),
         ],
     )
+        .into_response()
 }
 
 async fn get_manifest(
     State(state): State<AppState>,
     Path((name, reference)): Path<(String, String)>,
-) -> impl IntoResponse {
-    let name = strip_leading_slash(&name);
+) -> Response {
+    let name = state.repository(&name);
     info!("Getting manifest: {}/{}", name, reference);
 
-    let key = format!("{}:{}", name, reference);
+    if let Some(chunk_size) = state.config.manifest_chunk_size {
+        let key = format!("{}:{}", name, reference);
+        if let Ok(Some((content_type, chunks))) =
+            state.storage.get_manifest_stream(&key, chunk_size).await
+        {
+            *state.pulls.write().await.entry(name).or_default() += 1;
+            let body = Body::from_stream(chunks.map(|chunk| chunk.map(Bytes::from)));
+            return (StatusCode::OK, [("Content-Type", content_type)], body).into_response();
+        }
+    }
 
-    match state.storage.get_manifest(&key).await {
-        Ok(Some(entry)) => (
-            StatusCode::OK,
-            [("Content-Type", entry.content_type)],
-            entry.data,
-        ),
-        _ => (
-            StatusCode::NOT_FOUND,
-            [("Content-Type", "text/plain".to_string())],
-            vec![],
-        ),
+    match state.find_manifest(&name, &reference).await {
+        Some(entry) => {
+            *state.pulls.write().await.entry(name).or_default() += 1;
+            let body = match state.config.manifest_chunk_size {
+                Some(chunk_size) => chunked_body(entry.data, chunk_size),
+                None => Body::from(entry.data),
+            };
+            (StatusCode::OK, [("Content-Type", entry.content_type)], body).into_response()
+        }
+        None => RegistryError::ManifestNotFound(format!("{}:{}", name, reference)).into_response(),
+    }
+}
+
+/// Deletes a manifest by digest, or a tag, leaving the manifest it
+/// pointed to pullable by digest.
+async fn delete_manifest(
+    State(state): State<AppState>,
+    Path((name, reference)): Path<(String, String)>,
+) -> Response {
+    let name = state.repository(&name);
+    info!("Deleting manifest: {}/{}", name, reference);
+
+    let key = format!("{}:{}", name, reference);
+    match state.delete_manifest(&key).await {
+        Ok(true) => StatusCode::ACCEPTED.into_response(),
+        Ok(false) => RegistryError::ManifestNotFound(key).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
@@ -374,29 +4010,27 @@ async fn check_manifest(
     State(state): State<AppState>,
     Path((name, reference)): Path<(String, String)>,
 ) -> impl IntoResponse {
-    let name = strip_leading_slash(&name);
+    let name = state.repository(&name);
     info!("Checking manifest: {}/{}", name, reference);
 
-    let key = format!("{}:{}", name, reference);
-
-    match state.storage.get_manifest(&key).await {
-        Ok(Some(entry)) => {
-            let mut hasher = Sha256::new();
-            hasher.update(&entry.data);
-            let digest = format!("sha256:{}", hex::encode(hasher.finalize()));
+    match state.find_manifest(&name, &reference).await {
+        Some(entry) => {
+            let digest = state.compute_digest(&entry.data);
 
             (
                 StatusCode::OK,
                 [
                     ("Content-Type", entry.content_type),
+                    ("Content-Length", entry.data.len().to_string()),
                     ("Docker-Content-Digest", digest),
                 ],
             )
         }
-        _ => (
+        None => (
             StatusCode::NOT_FOUND,
             [
                 ("Content-Type", "text/plain".to_string()),
+                ("Content-Length", "0".to_string()),
                 ("Docker-Content-Digest", String::new()),
             ],
         ),
@@ -0,0 +1,131 @@
+//! Admission policy requiring images to be signed before they are tagged.
+//!
+//! Pipelines that sign before they publish push an image by digest, attach
+//! a signature to it and only then tag it. With a [`SigningPolicy`] the
+//! registry enforces that order: a manifest pushed by tag is refused with
+//! `DENIED` unless a signature is already attached to its digest or it
+//! carries the required annotation. Pushes by digest are always accepted,
+//! since signing needs the digest to exist, and so are referrers and the
+//! tags of the referrers tag schema.
+
+use crate::error::{RegistryError, Result};
+use crate::oci::manifest::subject_digest;
+use crate::referrers::{self, fallback_tag, SIGNATURE_TAG_SUFFIX};
+use crate::storage::{is_tag_key, Storage};
+
+/// Artifact type of cosign signatures attached as referrers.
+pub const COSIGN_SIGNATURE_ARTIFACT_TYPE: &str = "application/vnd.dev.cosign.artifact.sig.v1+json";
+/// Artifact type of Notary Project signatures.
+pub const NOTARY_SIGNATURE_ARTIFACT_TYPE: &str = "application/vnd.cncf.notary.signature";
+
+/// What a manifest pushed by tag needs to be admitted.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::{RegistryConfig, SigningPolicy};
+///
+/// // Signed by cosign or Notation, or annotated by the release tooling.
+/// let config = RegistryConfig::memory().with_signing_policy(
+///     SigningPolicy::signature_referrer().with_annotation("com.example.release.signed"),
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningPolicy {
+    signature_types: Vec<String>,
+    annotation: Option<String>,
+}
+
+impl SigningPolicy {
+    /// Requires a signature attached to the manifest: a referrer of type
+    /// [`COSIGN_SIGNATURE_ARTIFACT_TYPE`] or
+    /// [`NOTARY_SIGNATURE_ARTIFACT_TYPE`], or a cosign `sha256-<hex>.sig`
+    /// tag.
+    pub fn signature_referrer() -> Self {
+        Self {
+            signature_types: vec![
+                COSIGN_SIGNATURE_ARTIFACT_TYPE.to_string(),
+                NOTARY_SIGNATURE_ARTIFACT_TYPE.to_string(),
+            ],
+            annotation: None,
+        }
+    }
+
+    /// Requires the manifest to carry the annotation `key`, with any value.
+    pub fn annotation(key: impl Into<String>) -> Self {
+        Self {
+            signature_types: Vec::new(),
+            annotation: Some(key.into()),
+        }
+    }
+
+    /// Also accepts referrers of `artifact_type` as signatures.
+    pub fn with_signature_type(mut self, artifact_type: impl Into<String>) -> Self {
+        self.signature_types.push(artifact_type.into());
+        self
+    }
+
+    /// Also accepts manifests carrying the annotation `key`.
+    pub fn with_annotation(mut self, key: impl Into<String>) -> Self {
+        self.annotation = Some(key.into());
+        self
+    }
+
+    /// Checks a manifest about to be stored under `repository:reference`,
+    /// failing with [`RegistryError::Denied`] if the policy refuses it.
+    pub(crate) async fn admit(
+        &self,
+        storage: &dyn Storage,
+        repository: &str,
+        reference: &str,
+        digest: &str,
+        data: &[u8],
+    ) -> Result<()> {
+        let key = format!("{}:{}", repository, reference);
+        // Fallback indexes and cosign tags, such as `sha256-<hex>.sig`.
+        let schema_tag = reference.starts_with("sha256-");
+        if !is_tag_key(&key) || schema_tag || subject_digest(data).is_some() {
+            return Ok(());
+        }
+        if let Some(annotation) = &self.annotation {
+            let manifest: serde_json::Value = serde_json::from_slice(data).unwrap_or_default();
+            if manifest["annotations"].get(annotation).is_some() {
+                return Ok(());
+            }
+        }
+        if !self.signature_types.is_empty() && self.signed(storage, repository, digest).await? {
+            return Ok(());
+        }
+        Err(RegistryError::Denied(format!(
+            "{} is not signed: {} has no attached signature",
+            key, digest
+        )))
+    }
+
+    /// Returns whether a signature of one of the accepted types is attached
+    /// to the manifest `digest`.
+    async fn signed(&self, storage: &dyn Storage, repository: &str, digest: &str) -> Result<bool> {
+        let cosign_tag = format!("{}{}", fallback_tag(digest), SIGNATURE_TAG_SUFFIX);
+        let cosign_key = format!("{}:{}", repository, cosign_tag);
+        if storage.get_manifest(&cosign_key).await?.is_some() {
+            return Ok(true);
+        }
+        for referrer in storage.list_referrers(repository, digest).await? {
+            let key = format!("{}:{}", repository, referrer);
+            let Some(entry) = storage.get_manifest(&key).await? else {
+                continue;
+            };
+            let Ok(descriptor) = referrers::referrer_descriptor(&entry.content_type, &entry.data)
+            else {
+                continue;
+            };
+            if descriptor
+                .artifact_type
+                .is_some_and(|artifact_type| self.signature_types.contains(&artifact_type))
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
@@ -0,0 +1,113 @@
+//! Options for the listening sockets.
+
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::{lookup_host, TcpListener, TcpSocket};
+
+/// Options applied to the sockets the server listens on.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::{RegistryConfig, SocketOptions};
+///
+/// let config = RegistryConfig::memory()
+///     .with_port(5000)
+///     .with_socket_options(SocketOptions::new().with_reuse_port(true).with_backlog(64));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketOptions {
+    /// `SO_REUSEADDR`: bind even while old connections to the port linger
+    /// in `TIME_WAIT`.
+    pub reuse_address: bool,
+    /// `SO_REUSEPORT`: let several sockets bind the same port. Ignored on
+    /// platforms without it.
+    pub reuse_port: bool,
+    /// Maximum number of pending connections.
+    pub backlog: u32,
+}
+
+impl SocketOptions {
+    /// `SO_REUSEADDR` on, `SO_REUSEPORT` off and a backlog of 1024, the
+    /// same as a plain tokio listener.
+    pub fn new() -> Self {
+        Self {
+            reuse_address: true,
+            reuse_port: false,
+            backlog: 1024,
+        }
+    }
+
+    /// Sets `SO_REUSEADDR`.
+    pub fn with_reuse_address(mut self, reuse: bool) -> Self {
+        self.reuse_address = reuse;
+        self
+    }
+
+    /// Sets `SO_REUSEPORT`.
+    pub fn with_reuse_port(mut self, reuse: bool) -> Self {
+        self.reuse_port = reuse;
+        self
+    }
+
+    /// Sets the maximum number of pending connections.
+    pub fn with_backlog(mut self, backlog: u32) -> Self {
+        self.backlog = backlog;
+        self
+    }
+
+    /// Binds a listener to the first address `addr` resolves to that
+    /// accepts it.
+    pub(crate) async fn bind(&self, addr: &str) -> io::Result<TcpListener> {
+        let mut last_error = None;
+        for addr in lookup_host(addr).await? {
+            match self.bind_addr(addr) {
+                Ok(listener) => return Ok(listener),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "address resolved to nothing")
+        }))
+    }
+
+    pub(crate) fn bind_addr(&self, addr: SocketAddr) -> io::Result<TcpListener> {
+        let socket = match addr {
+            SocketAddr::V4(_) => TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => TcpSocket::new_v6()?,
+        };
+        socket.set_reuseaddr(self.reuse_address)?;
+        #[cfg(all(unix, not(target_os = "solaris"), not(target_os = "illumos")))]
+        socket.set_reuseport(self.reuse_port)?;
+        socket.bind(addr)?;
+        socket.listen(self.backlog)
+    }
+
+    /// Binds `addr`, retrying while the port is still held by a previous
+    /// listener for up to `timeout`.
+    pub(crate) async fn rebind(
+        &self,
+        addr: SocketAddr,
+        timeout: Duration,
+    ) -> io::Result<TcpListener> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            match self.bind_addr(addr) {
+                Err(e)
+                    if e.kind() == io::ErrorKind::AddrInUse
+                        && tokio::time::Instant::now() < deadline =>
+                {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
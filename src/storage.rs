@@ -2,15 +2,20 @@
 
 use crate::config::StorageBackend;
 use crate::error::{RegistryError, Result};
+use crate::oci::manifest::subject_digest;
 use async_trait::async_trait;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::fs;
-use tokio::sync::RwLock;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{Mutex, RwLock};
 
 /// Container image manifest with metadata.
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct ManifestEntry {
     /// Raw manifest data.
     pub data: Vec<u8>,
@@ -18,6 +23,12 @@ pub struct ManifestEntry {
     pub content_type: String,
 }
 
+/// Content type assumed for manifests stored without one.
+const DEFAULT_MANIFEST_CONTENT_TYPE: &str = "application/vnd.docker.distribution.manifest.v2+json";
+
+/// Manifest content in chunks, as streamed to and from storage.
+pub type ManifestStream = BoxStream<'static, Result<Vec<u8>>>;
+
 /// Trait for storage backends handling registry data.
 #[async_trait]
 pub trait Storage: Send + Sync {
@@ -25,22 +36,183 @@ pub trait Storage: Send + Sync {
     async fn store_manifest(&self, key: String, entry: ManifestEntry) -> Result<()>;
     /// Retrieves a manifest by key.
     async fn get_manifest(&self, key: &str) -> Result<Option<ManifestEntry>>;
+    /// Removes a manifest, returning whether it existed.
+    async fn delete_manifest(&self, key: &str) -> Result<bool>;
+    /// Lists the keys of all stored manifests.
+    async fn list_manifests(&self) -> Result<Vec<String>>;
+    /// Stores a manifest arriving in `chunks` under `key`. Any previous
+    /// manifest is replaced once the chunks end without an error; if one
+    /// fails, nothing is stored.
+    ///
+    /// The default collects the chunks and calls
+    /// [`store_manifest`](Self::store_manifest); backends override it to
+    /// write them as they arrive.
+    async fn store_manifest_stream(
+        &self,
+        key: String,
+        content_type: String,
+        mut chunks: ManifestStream,
+    ) -> Result<()> {
+        let mut data = Vec::new();
+        while let Some(chunk) = chunks.next().await {
+            data.extend_from_slice(&chunk?);
+        }
+        self.store_manifest(key, ManifestEntry { data, content_type })
+            .await
+    }
+    /// Returns the content type of a manifest and its content in chunks of
+    /// at most `chunk_size` bytes.
+    ///
+    /// The default reads it whole with
+    /// [`get_manifest`](Self::get_manifest); backends override it to read
+    /// the chunks as they are sent.
+    async fn get_manifest_stream(
+        &self,
+        key: &str,
+        chunk_size: usize,
+    ) -> Result<Option<(String, ManifestStream)>> {
+        let Some(entry) = self.get_manifest(key).await? else {
+            return Ok(None);
+        };
+        let chunks: Vec<Result<Vec<u8>>> = entry
+            .data
+            .chunks(chunk_size)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect();
+        Ok(Some((entry.content_type, stream::iter(chunks).boxed())))
+    }
+    /// Stores the manifest under `from` under `to` as well, returning
+    /// whether `from` existed.
+    ///
+    /// The default reads and stores it; backends override it to copy it
+    /// without reading it.
+    async fn copy_manifest(&self, from: &str, to: String) -> Result<bool> {
+        let Some(entry) = self.get_manifest(from).await? else {
+            return Ok(false);
+        };
+        self.store_manifest(to, entry).await?;
+        Ok(true)
+    }
+    /// Lists the names of all repositories holding manifests, sorted
+    /// lexically.
+    ///
+    /// The default derives them from [`list_manifests`](Self::list_manifests).
+    async fn list_repositories(&self) -> Result<Vec<String>> {
+        let keys = self.list_manifests().await?;
+        Ok(repository_names(keys.iter().map(String::as_str)))
+    }
+    /// Lists the tags of `repository`, sorted lexically.
+    ///
+    /// The default filters [`list_manifests`](Self::list_manifests);
+    /// backends override it with a cheaper lookup.
+    async fn list_tags(&self, repository: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}:", repository);
+        let mut tags: Vec<String> = self
+            .list_manifests()
+            .await?
+            .iter()
+            .filter(|key| is_tag_key(key))
+            .filter_map(|key| key.strip_prefix(&prefix))
+            .map(str::to_string)
+            .collect();
+        tags.sort();
+        Ok(tags)
+    }
+    /// Lists the digests of the manifests in `repository` whose `subject`
+    /// is `subject`, sorted.
+    ///
+    /// The default reads every manifest of the repository stored by
+    /// digest; backends override it with an index.
+    async fn list_referrers(&self, repository: &str, subject: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}:", repository);
+        let mut referrers = Vec::new();
+        for key in self.list_manifests().await? {
+            let Some(digest) = key.strip_prefix(&prefix) else {
+                continue;
+            };
+            if is_tag_key(&key) {
+                continue;
+            }
+            let Some(entry) = self.get_manifest(&key).await? else {
+                continue;
+            };
+            if subject_digest(&entry.data).as_deref() == Some(subject) {
+                referrers.push(digest.to_string());
+            }
+        }
+        referrers.sort();
+        Ok(referrers)
+    }
     /// Stores a blob with the given digest.
     async fn store_blob(&self, digest: String, data: Vec<u8>) -> Result<()>;
     /// Retrieves a blob by digest.
     async fn get_blob(&self, digest: &str) -> Result<Option<Vec<u8>>>;
+    /// Removes a blob, returning whether it existed.
+    async fn delete_blob(&self, digest: &str) -> Result<bool>;
+    /// Lists the digests of all stored blobs.
+    async fn list_blobs(&self) -> Result<Vec<String>>;
+    /// Returns whether each blob is stored, in the order of `digests`.
+    ///
+    /// The default reads every blob; backends override it with a cheaper
+    /// lookup.
+    async fn blobs_exist(&self, digests: &[String]) -> Result<Vec<bool>> {
+        let mut exist = Vec::with_capacity(digests.len());
+        for digest in digests {
+            exist.push(self.get_blob(digest).await?.is_some());
+        }
+        Ok(exist)
+    }
     /// Creates a new upload session with the given UUID.
     async fn create_upload(&self, uuid: String) -> Result<()>;
     /// Appends data to an existing upload session.
     async fn append_upload(&self, uuid: &str, data: &[u8]) -> Result<()>;
+    /// Returns how many bytes an upload session holds, or `None` if the
+    /// session does not exist.
+    async fn upload_size(&self, uuid: &str) -> Result<Option<u64>>;
     /// Finalizes an upload session and returns the complete data.
     async fn finish_upload(&self, uuid: &str) -> Result<Option<Vec<u8>>>;
+    /// Discards an upload session, returning whether it existed.
+    async fn delete_upload(&self, uuid: &str) -> Result<bool>;
+    /// Discards state that a real registry process would lose on a crash.
+    ///
+    /// Persistent backends keep everything, which is the default.
+    async fn simulate_crash(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns whether a `name:reference` manifest key refers to a tag.
+pub(crate) fn is_tag_key(key: &str) -> bool {
+    key.split_once(':')
+        .is_some_and(|(_, reference)| !reference.contains(':'))
+}
+
+/// Returns the sorted, deduplicated repository names of manifest keys.
+fn repository_names<'a>(keys: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let names: BTreeSet<&str> = keys
+        .filter_map(|key| key.split_once(':'))
+        .map(|(repository, _)| repository)
+        .collect();
+    names.into_iter().map(str::to_string).collect()
+}
+
+/// Returns the `repository:subject` key a manifest stored under `key` is
+/// indexed as a referrer by, if it is stored by digest and has a subject.
+fn referrer_index_key(key: &str, data: &[u8]) -> Option<String> {
+    if is_tag_key(key) {
+        return None;
+    }
+    let (repository, _) = key.split_once(':')?;
+    let subject = subject_digest(data)?;
+    Some(format!("{}:{}", repository, subject))
 }
 
 /// In-memory storage implementation.
 #[derive(Default)]
 pub struct MemoryStorage {
     manifests: Arc<RwLock<HashMap<String, ManifestEntry>>>,
+    /// Referrer digests by `repository:subject`.
+    referrers: Arc<RwLock<HashMap<String, BTreeSet<String>>>>,
     blobs: Arc<RwLock<HashMap<String, Vec<u8>>>>,
     uploads: Arc<RwLock<HashMap<String, Vec<u8>>>>,
 }
@@ -55,7 +227,17 @@ impl MemoryStorage {
 #[async_trait::async_trait]
 impl Storage for MemoryStorage {
     async fn store_manifest(&self, key: String, entry: ManifestEntry) -> Result<()> {
-        self.manifests.write().await.insert(key, entry);
+        let mut manifests = self.manifests.write().await;
+        if let Some(index_key) = referrer_index_key(&key, &entry.data) {
+            let digest = key.split_once(':').map_or("", |(_, digest)| digest);
+            self.referrers
+                .write()
+                .await
+                .entry(index_key)
+                .or_default()
+                .insert(digest.to_string());
+        }
+        manifests.insert(key, entry);
         Ok(())
     }
 
@@ -63,6 +245,56 @@ impl Storage for MemoryStorage {
         Ok(self.manifests.read().await.get(key).cloned())
     }
 
+    async fn delete_manifest(&self, key: &str) -> Result<bool> {
+        let Some(entry) = self.manifests.write().await.remove(key) else {
+            return Ok(false);
+        };
+        if let Some(index_key) = referrer_index_key(key, &entry.data) {
+            let digest = key.split_once(':').map_or("", |(_, digest)| digest);
+            let mut referrers = self.referrers.write().await;
+            if let Some(digests) = referrers.get_mut(&index_key) {
+                digests.remove(digest);
+                if digests.is_empty() {
+                    referrers.remove(&index_key);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    async fn list_manifests(&self) -> Result<Vec<String>> {
+        Ok(self.manifests.read().await.keys().cloned().collect())
+    }
+
+    async fn list_repositories(&self) -> Result<Vec<String>> {
+        let manifests = self.manifests.read().await;
+        Ok(repository_names(manifests.keys().map(String::as_str)))
+    }
+
+    async fn list_tags(&self, repository: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}:", repository);
+        let manifests = self.manifests.read().await;
+        let mut tags: Vec<String> = manifests
+            .keys()
+            .filter(|key| is_tag_key(key))
+            .filter_map(|key| key.strip_prefix(&prefix))
+            .map(str::to_string)
+            .collect();
+        tags.sort();
+        Ok(tags)
+    }
+
+    async fn list_referrers(&self, repository: &str, subject: &str) -> Result<Vec<String>> {
+        let key = format!("{}:{}", repository, subject);
+        Ok(self
+            .referrers
+            .read()
+            .await
+            .get(&key)
+            .map(|digests| digests.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
     async fn store_blob(&self, digest: String, data: Vec<u8>) -> Result<()> {
         self.blobs.write().await.insert(digest, data);
         Ok(())
@@ -72,6 +304,19 @@ impl Storage for MemoryStorage {
         Ok(self.blobs.read().await.get(digest).cloned())
     }
 
+    async fn delete_blob(&self, digest: &str) -> Result<bool> {
+        Ok(self.blobs.write().await.remove(digest).is_some())
+    }
+
+    async fn list_blobs(&self) -> Result<Vec<String>> {
+        Ok(self.blobs.read().await.keys().cloned().collect())
+    }
+
+    async fn blobs_exist(&self, digests: &[String]) -> Result<Vec<bool>> {
+        let blobs = self.blobs.read().await;
+        Ok(digests.iter().map(|d| blobs.contains_key(d)).collect())
+    }
+
     async fn create_upload(&self, uuid: String) -> Result<()> {
         self.uploads.write().await.insert(uuid, Vec::new());
         Ok(())
@@ -86,15 +331,139 @@ impl Storage for MemoryStorage {
         }
     }
 
+    async fn upload_size(&self, uuid: &str) -> Result<Option<u64>> {
+        Ok(self
+            .uploads
+            .read()
+            .await
+            .get(uuid)
+            .map(|upload| upload.len() as u64))
+    }
+
     async fn finish_upload(&self, uuid: &str) -> Result<Option<Vec<u8>>> {
         Ok(self.uploads.write().await.remove(uuid))
     }
+
+    async fn delete_upload(&self, uuid: &str) -> Result<bool> {
+        Ok(self.uploads.write().await.remove(uuid).is_some())
+    }
+
+    async fn simulate_crash(&self) -> Result<()> {
+        self.uploads.write().await.clear();
+        Ok(())
+    }
+}
+
+/// Escapes a key into a single file name that [`decode_file_name`] reverses.
+fn encode_file_name(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for c in key.chars() {
+        match c {
+            '%' => encoded.push_str("%25"),
+            '/' => encoded.push_str("%2F"),
+            ':' => encoded.push_str("%3A"),
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+fn decode_file_name(name: &str) -> String {
+    name.replace("%2F", "/")
+        .replace("%3A", ":")
+        .replace("%25", "%")
+}
+
+/// Removes a file, returning whether it existed.
+async fn remove_if_exists(path: &std::path::Path) -> Result<bool> {
+    match fs::remove_file(path).await {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// A temporary directory that several servers in one process keep their
+/// storage under, each in its own subdirectory.
+///
+/// Blobs are content-addressed, so a blob stored by any of the servers is
+/// written once to a pool shared by all of them and hard-linked into each
+/// server's directory: a test matrix that pushes the same fixtures to
+/// dozens of servers holds one copy of them. Deleting a blob from one
+/// server leaves the others untouched.
+///
+/// The value is also the cleanup guard. A server's subdirectory is
+/// removed when its storage is dropped, and the whole directory once this
+/// value and every server using it are gone.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use registry_testkit::{RegistryConfig, RegistryServer, SharedTempDir};
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let shared = SharedTempDir::new()?;
+/// let a = RegistryServer::new(RegistryConfig::shared_temp_dir(&shared)).await?;
+/// let b = RegistryServer::new(RegistryConfig::shared_temp_dir(&shared)).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedTempDir {
+    dir: Arc<tempfile::TempDir>,
+}
+
+impl SharedTempDir {
+    /// Creates the directory under the system temporary directory.
+    pub fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            dir: Arc::new(tempfile::tempdir()?),
+        })
+    }
+
+    /// Creates the directory under `parent`, such as a tmpfs mount.
+    pub fn new_in(parent: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self {
+            dir: Arc::new(tempfile::tempdir_in(parent)?),
+        })
+    }
+
+    /// Returns the path of the directory.
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    fn pool_path(&self) -> PathBuf {
+        self.path().join("blobs")
+    }
+}
+
+/// Progress of a disk upload session, persisted next to its data after
+/// every chunk so the session survives a restart.
+#[derive(Serialize, Deserialize)]
+struct UploadState {
+    /// Bytes acknowledged so far.
+    offset: u64,
+    /// Digest of those bytes.
+    digest: String,
 }
 
+/// Running digest of an upload session, `None` until it is first loaded.
+type UploadHasher = Arc<Mutex<Option<Sha256>>>;
+
 /// Disk-based storage implementation.
+///
+/// Upload sessions are files under `uploads/`, each with a `.state` file
+/// recording the acknowledged offset and digest. A new `DiskStorage` over
+/// the same directory picks sessions up where they left off: bytes past the
+/// recorded offset, from a write interrupted by a crash, are discarded, and
+/// a session whose data no longer matches its digest is refused.
 pub struct DiskStorage {
     base_path: PathBuf,
     _temp_dir: Option<tempfile::TempDir>,
+    /// Keeps the shared parent alive; declared after `_temp_dir` so this
+    /// server's subdirectory is removed first.
+    shared: Option<SharedTempDir>,
+    hashers: std::sync::Mutex<HashMap<String, UploadHasher>>,
 }
 
 impl DiskStorage {
@@ -108,6 +477,8 @@ impl DiskStorage {
         Ok(Self {
             base_path: path,
             _temp_dir: None,
+            shared: None,
+            hashers: Default::default(),
         })
     }
 
@@ -123,31 +494,148 @@ impl DiskStorage {
         Ok(Self {
             base_path: path,
             _temp_dir: Some(temp_dir),
+            shared: None,
+            hashers: Default::default(),
         })
     }
 
+    /// Creates a temporary disk storage backend in its own subdirectory of
+    /// `shared`, sharing blob content with the other servers there.
+    pub async fn shared_temp(shared: &SharedTempDir) -> Result<Self> {
+        let instances = shared.path().join("instances");
+        fs::create_dir_all(&instances).await?;
+        fs::create_dir_all(shared.pool_path()).await?;
+        let temp_dir = tempfile::tempdir_in(&instances)?;
+        let mut storage = Self::new(temp_dir.path().to_path_buf()).await?;
+        storage._temp_dir = Some(temp_dir);
+        storage.shared = Some(shared.clone());
+        Ok(storage)
+    }
+
+    /// Stores a blob in the shared pool, if there is one, and links it
+    /// into this server's directory. Returns `false` if the blob has to be
+    /// written privately instead: without a pool, or if its content does
+    /// not match its digest and so must not be shared.
+    async fn link_pooled_blob(&self, digest: &str, data: &[u8]) -> Result<bool> {
+        let Some(shared) = &self.shared else {
+            return Ok(false);
+        };
+        if crate::digest::verify(digest, data).is_err() {
+            return Ok(false);
+        }
+        let pooled = shared.pool_path().join(encode_file_name(digest));
+        if !fs::try_exists(&pooled).await? {
+            // Written aside and renamed, so servers storing the same blob
+            // at once never see a partial file.
+            let partial = pooled.with_extension(format!("{}.partial", uuid::Uuid::new_v4()));
+            fs::write(&partial, data).await?;
+            fs::rename(&partial, &pooled).await?;
+        }
+        let blob_path = self.blob_path(digest);
+        remove_if_exists(&blob_path).await?;
+        if fs::hard_link(&pooled, &blob_path).await.is_err() {
+            fs::copy(&pooled, &blob_path).await?;
+        }
+        Ok(true)
+    }
+
     fn manifest_path(&self, key: &str) -> PathBuf {
-        let safe_key = key.replace(['/', ':'], "_");
         self.base_path
             .join("manifests")
-            .join(format!("{}.json", safe_key))
+            .join(format!("{}.json", encode_file_name(key)))
     }
 
     fn manifest_meta_path(&self, key: &str) -> PathBuf {
-        let safe_key = key.replace(['/', ':'], "_");
         self.base_path
             .join("manifests")
-            .join(format!("{}.meta", safe_key))
+            .join(format!("{}.meta", encode_file_name(key)))
+    }
+
+    /// A fresh path to write a manifest to before renaming it into place,
+    /// outside `manifests/` so listings never see it.
+    fn partial_manifest_path(&self) -> PathBuf {
+        self.base_path
+            .join("uploads")
+            .join(format!("{}.manifest.partial", uuid::Uuid::new_v4()))
     }
 
     fn blob_path(&self, digest: &str) -> PathBuf {
-        let safe_digest = digest.replace(['/', ':'], "_");
-        self.base_path.join("blobs").join(safe_digest)
+        self.base_path.join("blobs").join(encode_file_name(digest))
     }
 
     fn upload_path(&self, uuid: &str) -> PathBuf {
         self.base_path.join("uploads").join(uuid)
     }
+
+    fn upload_state_path(&self, uuid: &str) -> PathBuf {
+        self.base_path
+            .join("uploads")
+            .join(format!("{}.state", uuid))
+    }
+
+    fn upload_hasher(&self, uuid: &str) -> UploadHasher {
+        self.hashers
+            .lock()
+            .unwrap()
+            .entry(uuid.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Reads the persisted state of a session, or `None` if it has none.
+    async fn read_upload_state(&self, uuid: &str) -> Result<Option<UploadState>> {
+        match fs::read(self.upload_state_path(uuid)).await {
+            Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Replaces the state of a session in one rename, so a crash leaves
+    /// either the old or the new state behind.
+    async fn write_upload_state(&self, uuid: &str, state: &UploadState) -> Result<()> {
+        let path = self.upload_state_path(uuid);
+        let staging = path.with_extension("state.tmp");
+        fs::write(&staging, serde_json::to_vec(state)?).await?;
+        fs::rename(&staging, &path).await?;
+        Ok(())
+    }
+
+    /// Rebuilds the running digest of a session from disk after a restart,
+    /// dropping unacknowledged bytes and checking the rest.
+    async fn resume_upload(&self, uuid: &str) -> Result<Sha256> {
+        let path = self.upload_path(uuid);
+        let mut data = match fs::read(&path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(RegistryError::UploadNotFound(uuid.to_string()));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(state) = self.read_upload_state(uuid).await? {
+            if (data.len() as u64) < state.offset {
+                return Err(RegistryError::StorageBackend(format!(
+                    "upload {} lost data: {} of {} bytes on disk",
+                    uuid,
+                    data.len(),
+                    state.offset
+                )));
+            }
+            if data.len() as u64 > state.offset {
+                data.truncate(state.offset as usize);
+                fs::write(&path, &data).await?;
+            }
+            let hasher = Sha256::new_with_prefix(&data);
+            if format!("sha256:{}", hex::encode(hasher.clone().finalize())) != state.digest {
+                return Err(RegistryError::StorageBackend(format!(
+                    "upload {} does not match its recorded digest",
+                    uuid
+                )));
+            }
+            return Ok(hasher);
+        }
+        Ok(Sha256::new_with_prefix(&data))
+    }
 }
 
 #[async_trait]
@@ -173,12 +661,118 @@ impl Storage for DiskStorage {
         let data = fs::read(&manifest_path).await?;
         let content_type = fs::read_to_string(&meta_path)
             .await
-            .unwrap_or_else(|_| "application/vnd.docker.distribution.manifest.v2+json".to_string());
+            .unwrap_or_else(|_| DEFAULT_MANIFEST_CONTENT_TYPE.to_string());
 
         Ok(Some(ManifestEntry { data, content_type }))
     }
 
+    async fn delete_manifest(&self, key: &str) -> Result<bool> {
+        let existed = remove_if_exists(&self.manifest_path(key)).await?;
+        remove_if_exists(&self.manifest_meta_path(key)).await?;
+        Ok(existed)
+    }
+
+    async fn list_manifests(&self) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        let mut entries = fs::read_dir(self.base_path.join("manifests")).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            if let Some(key) = name.to_str().and_then(|n| n.strip_suffix(".json")) {
+                keys.push(decode_file_name(key));
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn store_manifest_stream(
+        &self,
+        key: String,
+        content_type: String,
+        mut chunks: ManifestStream,
+    ) -> Result<()> {
+        // Written aside and renamed, so a failed stream leaves any previous
+        // manifest in place and readers never see a partial one.
+        let partial = self.partial_manifest_path();
+        let written = async {
+            let mut file = fs::File::create(&partial).await?;
+            while let Some(chunk) = chunks.next().await {
+                file.write_all(&chunk?).await?;
+            }
+            file.flush().await?;
+            Ok::<_, RegistryError>(())
+        }
+        .await;
+        if let Err(e) = written {
+            remove_if_exists(&partial).await?;
+            return Err(e);
+        }
+        fs::write(self.manifest_meta_path(&key), &content_type).await?;
+        fs::rename(&partial, self.manifest_path(&key)).await?;
+        Ok(())
+    }
+
+    async fn get_manifest_stream(
+        &self,
+        key: &str,
+        chunk_size: usize,
+    ) -> Result<Option<(String, ManifestStream)>> {
+        let file = match fs::File::open(self.manifest_path(key)).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let content_type = fs::read_to_string(self.manifest_meta_path(key))
+            .await
+            .unwrap_or_else(|_| DEFAULT_MANIFEST_CONTENT_TYPE.to_string());
+        // The file is dropped after the last chunk or the first error.
+        let chunks = stream::unfold(Some(file), move |file| async move {
+            let mut file = file?;
+            let mut chunk = vec![0; chunk_size];
+            match file.read(&mut chunk).await {
+                Ok(0) => None,
+                Ok(read) => {
+                    chunk.truncate(read);
+                    Some((Ok(chunk), Some(file)))
+                }
+                Err(e) => Some((Err(e.into()), None)),
+            }
+        });
+        Ok(Some((content_type, chunks.boxed())))
+    }
+
+    async fn copy_manifest(&self, from: &str, to: String) -> Result<bool> {
+        let partial = self.partial_manifest_path();
+        match fs::copy(self.manifest_path(from), &partial).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+        let content_type = fs::read_to_string(self.manifest_meta_path(from))
+            .await
+            .unwrap_or_else(|_| DEFAULT_MANIFEST_CONTENT_TYPE.to_string());
+        fs::write(self.manifest_meta_path(&to), &content_type).await?;
+        fs::rename(&partial, self.manifest_path(&to)).await?;
+        Ok(true)
+    }
+
+    async fn list_repositories(&self) -> Result<Vec<String>> {
+        // Repository names hold no `:`, so the first encoded one ends them
+        // and only that part of each file name needs decoding.
+        let mut names = BTreeSet::new();
+        let mut entries = fs::read_dir(self.base_path.join("manifests")).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            if let Some((repository, _)) = name.to_str().and_then(|n| n.split_once("%3A")) {
+                names.insert(decode_file_name(repository));
+            }
+        }
+        Ok(names.into_iter().collect())
+    }
+
     async fn store_blob(&self, digest: String, data: Vec<u8>) -> Result<()> {
+        if self.link_pooled_blob(&digest, &data).await? {
+            return Ok(());
+        }
         let blob_path = self.blob_path(&digest);
         fs::write(&blob_path, &data).await?;
         Ok(())
@@ -195,26 +789,88 @@ impl Storage for DiskStorage {
         Ok(Some(data))
     }
 
+    async fn delete_blob(&self, digest: &str) -> Result<bool> {
+        remove_if_exists(&self.blob_path(digest)).await
+    }
+
+    async fn blobs_exist(&self, digests: &[String]) -> Result<Vec<bool>> {
+        let mut exist = Vec::with_capacity(digests.len());
+        for digest in digests {
+            exist.push(fs::try_exists(self.blob_path(digest)).await?);
+        }
+        Ok(exist)
+    }
+
+    async fn list_blobs(&self) -> Result<Vec<String>> {
+        let mut digests = Vec::new();
+        let mut entries = fs::read_dir(self.base_path.join("blobs")).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(name) = entry.file_name().to_str() {
+                digests.push(decode_file_name(name));
+            }
+        }
+        Ok(digests)
+    }
+
     async fn create_upload(&self, uuid: String) -> Result<()> {
         let upload_path = self.upload_path(&uuid);
         fs::write(&upload_path, &[]).await?;
+        let state = UploadState {
+            offset: 0,
+            digest: format!("sha256:{}", hex::encode(Sha256::digest(b""))),
+        };
+        self.write_upload_state(&uuid, &state).await?;
+        *self.upload_hasher(&uuid).lock().await = Some(Sha256::new());
         Ok(())
     }
 
     async fn append_upload(&self, uuid: &str, data: &[u8]) -> Result<()> {
-        let upload_path = self.upload_path(uuid);
-
-        if !upload_path.exists() {
-            return Err(RegistryError::UploadNotFound(uuid.to_string()));
-        }
-
-        let mut existing = fs::read(&upload_path).await?;
-        existing.extend_from_slice(data);
-        fs::write(&upload_path, &existing).await?;
+        // Chunks of one session are appended one at a time, each followed
+        // by its state, so the state always describes a prefix of the file.
+        let hasher = self.upload_hasher(uuid);
+        let mut hasher = hasher.lock().await;
+        let running = match hasher.take() {
+            Some(running) => running,
+            None => self.resume_upload(uuid).await?,
+        };
+
+        let mut file = match fs::OpenOptions::new()
+            .append(true)
+            .open(self.upload_path(uuid))
+            .await
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(RegistryError::UploadNotFound(uuid.to_string()));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        file.write_all(data).await?;
+        file.flush().await?;
+        let offset = file.metadata().await?.len();
+
+        let mut running = running;
+        running.update(data);
+        let state = UploadState {
+            offset,
+            digest: format!("sha256:{}", hex::encode(running.clone().finalize())),
+        };
+        self.write_upload_state(uuid, &state).await?;
+        *hasher = Some(running);
 
         Ok(())
     }
 
+    async fn upload_size(&self, uuid: &str) -> Result<Option<u64>> {
+        let size = match fs::metadata(self.upload_path(uuid)).await {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let state = self.read_upload_state(uuid).await?;
+        Ok(Some(state.map_or(size, |state| state.offset.min(size))))
+    }
+
     async fn finish_upload(&self, uuid: &str) -> Result<Option<Vec<u8>>> {
         let upload_path = self.upload_path(uuid);
 
@@ -222,11 +878,26 @@ impl Storage for DiskStorage {
             return Ok(None);
         }
 
-        let data = fs::read(&upload_path).await?;
-        fs::remove_file(&upload_path).await?;
+        let mut data = fs::read(&upload_path).await?;
+        if let Some(state) = self.read_upload_state(uuid).await? {
+            data.truncate(state.offset as usize);
+        }
+        self.delete_upload(uuid).await?;
 
         Ok(Some(data))
     }
+
+    async fn delete_upload(&self, uuid: &str) -> Result<bool> {
+        self.hashers.lock().unwrap().remove(uuid);
+        remove_if_exists(&self.upload_state_path(uuid)).await?;
+        remove_if_exists(&self.upload_path(uuid)).await
+    }
+
+    async fn simulate_crash(&self) -> Result<()> {
+        // Sessions stay on disk; only the running digests are lost.
+        self.hashers.lock().unwrap().clear();
+        Ok(())
+    }
 }
 
 /// Creates a storage backend from the given configuration.
@@ -235,5 +906,8 @@ pub async fn create_storage(backend: &StorageBackend) -> Result<Arc<dyn Storage>
         StorageBackend::Memory => Ok(Arc::new(MemoryStorage::new())),
         StorageBackend::TempDir => Ok(Arc::new(DiskStorage::temp().await?)),
         StorageBackend::Directory(path) => Ok(Arc::new(DiskStorage::new(path.clone()).await?)),
+        StorageBackend::SharedTempDir(shared) => {
+            Ok(Arc::new(DiskStorage::shared_temp(shared).await?))
+        }
     }
 }
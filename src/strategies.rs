@@ -0,0 +1,163 @@
+//! Proptest strategies for registry data, valid and subtly invalid.
+//!
+//! Available with the `proptest` feature. Downstream client crates can fuzz
+//! their parsers against the same shapes the testkit accepts and rejects.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use proptest::prelude::*;
+//! use registry_testkit::strategies;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn parses_any_digest(digest in strategies::digest()) {
+//!         assert!(my_client::parse_digest(&digest).is_ok());
+//!     }
+//! }
+//! ```
+
+use proptest::prelude::*;
+use serde_json::{json, Value};
+
+const COMPONENT: &str = "[a-z0-9]{1,8}((\\.|_|__|-{1,2})[a-z0-9]{1,8}){0,2}";
+
+/// Valid `sha256:` digests.
+pub fn digest() -> impl Strategy<Value = String> {
+    "[0-9a-f]{64}".prop_map(|hex| format!("sha256:{}", hex))
+}
+
+/// Digests that look plausible but violate the spec: uppercase hex, wrong
+/// length, missing or unknown algorithm, or stray whitespace.
+pub fn invalid_digest() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[0-9A-F]{64}".prop_map(|hex| format!("sha256:{}", hex)),
+        "[0-9a-f]{63}".prop_map(|hex| format!("sha256:{}", hex)),
+        "[0-9a-f]{65}".prop_map(|hex| format!("sha256:{}", hex)),
+        "[0-9a-f]{64}",
+        "[0-9a-f]{64}".prop_map(|hex| format!("sha257:{}", hex)),
+        "[0-9a-f]{64}".prop_map(|hex| format!("sha256: {}", hex)),
+    ]
+}
+
+/// Valid repository names, possibly with several path components.
+pub fn repository_name() -> impl Strategy<Value = String> {
+    proptest::collection::vec(COMPONENT, 1..4).prop_map(|parts| parts.join("/"))
+}
+
+/// Repository names that violate the spec: uppercase letters, leading or
+/// trailing separators, or empty path components.
+pub fn invalid_repository_name() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[A-Z][a-z0-9]{0,8}",
+        COMPONENT.prop_map(|name| format!("-{}", name)),
+        COMPONENT.prop_map(|name| format!("{}/", name)),
+        (COMPONENT, COMPONENT).prop_map(|(a, b)| format!("{}//{}", a, b)),
+        COMPONENT.prop_map(|name| format!("{}...x", name)),
+    ]
+}
+
+/// Valid tags.
+pub fn tag() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9_][a-zA-Z0-9._-]{0,40}"
+}
+
+/// Tags that violate the spec: leading separators, illegal characters, or
+/// more than 128 characters.
+pub fn invalid_tag() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[.-][a-z0-9]{1,8}",
+        "[a-z0-9]{1,8}[:@/ ][a-z0-9]{1,8}",
+        "[a-z]{129,140}",
+    ]
+}
+
+fn descriptor(media_type: &'static str) -> impl Strategy<Value = Value> {
+    (digest(), 1u64..1 << 32).prop_map(move |(digest, size)| {
+        json!({
+            "mediaType": media_type,
+            "digest": digest,
+            "size": size,
+        })
+    })
+}
+
+/// Valid OCI image manifests.
+pub fn image_manifest() -> impl Strategy<Value = Value> {
+    (
+        descriptor("application/vnd.oci.image.config.v1+json"),
+        proptest::collection::vec(
+            descriptor("application/vnd.oci.image.layer.v1.tar+gzip"),
+            0..6,
+        ),
+    )
+        .prop_map(|(config, layers)| {
+            json!({
+                "schemaVersion": 2,
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "config": config,
+                "layers": layers,
+            })
+        })
+}
+
+/// Image manifests with exactly one subtle defect.
+pub fn invalid_image_manifest() -> impl Strategy<Value = Value> {
+    (image_manifest(), invalid_digest(), 0..5u8).prop_map(|(mut manifest, bad_digest, defect)| {
+        match defect {
+            0 => manifest["schemaVersion"] = json!(1),
+            1 => {
+                manifest.as_object_mut().unwrap().remove("config");
+            }
+            2 => manifest["config"]["digest"] = json!(bad_digest),
+            3 => manifest["config"]["size"] = json!(-1),
+            _ => manifest["layers"] = json!({}),
+        }
+        manifest
+    })
+}
+
+/// Valid OCI image indexes pointing at one or more platform manifests.
+pub fn image_index() -> impl Strategy<Value = Value> {
+    let platform = prop_oneof![
+        Just(("linux", "amd64")),
+        Just(("linux", "arm64")),
+        Just(("linux", "s390x")),
+        Just(("windows", "amd64")),
+    ];
+    proptest::collection::vec(
+        (
+            descriptor("application/vnd.oci.image.manifest.v1+json"),
+            platform,
+        ),
+        1..5,
+    )
+    .prop_map(|entries| {
+        let manifests: Vec<Value> = entries
+            .into_iter()
+            .map(|(mut descriptor, (os, arch))| {
+                descriptor["platform"] = json!({ "os": os, "architecture": arch });
+                descriptor
+            })
+            .collect();
+        json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.index.v1+json",
+            "manifests": manifests,
+        })
+    })
+}
+
+/// Image indexes with exactly one subtle defect.
+pub fn invalid_image_index() -> impl Strategy<Value = Value> {
+    (image_index(), invalid_digest(), 0..3u8).prop_map(|(mut index, bad_digest, defect)| {
+        match defect {
+            0 => index["schemaVersion"] = json!(3),
+            1 => index["manifests"][0]["digest"] = json!(bad_digest),
+            _ => {
+                index.as_object_mut().unwrap().remove("manifests");
+            }
+        }
+        index
+    })
+}
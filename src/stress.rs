@@ -0,0 +1,311 @@
+//! Concurrent upload stress testing against any registry URL.
+//!
+//! Runs hundreds of chunked uploads at once, then pulls back every blob the
+//! registry accepted and checks it against its digest, so races in upload
+//! session handling show up as corrupted or missing blobs.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use registry_testkit::stress::{self, UploadStressConfig};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let config = UploadStressConfig::new()
+//!     .with_uploads(500)
+//!     .with_concurrency(64)
+//!     .with_interleaved_chunks(true);
+//! let report = stress::run_uploads("http://127.0.0.1:5000", config).await?;
+//! report.assert_consistent();
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::RegistryClient;
+use crate::digest::sha256_digest;
+use crate::error::Result;
+use crate::rng::SplitMix64;
+use futures_util::future;
+use reqwest::StatusCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Configuration for an upload stress run.
+#[derive(Debug, Clone)]
+pub struct UploadStressConfig {
+    /// Total number of uploads.
+    pub uploads: usize,
+    /// Number of uploads in flight at once.
+    pub concurrency: usize,
+    /// Number of `PATCH` requests per upload.
+    pub chunks: usize,
+    /// Size of each chunk in bytes.
+    pub chunk_size: usize,
+    /// Sends the chunks of one upload concurrently, each with its
+    /// `Content-Range`, instead of one after another.
+    pub interleaved_chunks: bool,
+    /// Repository uploads go to.
+    pub repository: String,
+    /// Seed for generated content.
+    pub seed: u64,
+}
+
+impl UploadStressConfig {
+    /// Creates a configuration for 200 uploads of four 16 KiB chunks, 32 at
+    /// a time, with chunks sent in order.
+    pub fn new() -> Self {
+        Self {
+            uploads: 200,
+            concurrency: 32,
+            chunks: 4,
+            chunk_size: 16 * 1024,
+            interleaved_chunks: false,
+            repository: "stress".to_string(),
+            seed: 0,
+        }
+    }
+
+    /// Sets the total number of uploads.
+    pub fn with_uploads(mut self, uploads: usize) -> Self {
+        self.uploads = uploads;
+        self
+    }
+
+    /// Sets how many uploads are in flight at once.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Sets the number of chunks per upload.
+    pub fn with_chunks(mut self, chunks: usize) -> Self {
+        self.chunks = chunks;
+        self
+    }
+
+    /// Sets the size of each chunk.
+    pub fn with_chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes;
+        self
+    }
+
+    /// Sets whether the chunks of one upload race each other.
+    pub fn with_interleaved_chunks(mut self, interleaved: bool) -> Self {
+        self.interleaved_chunks = interleaved;
+        self
+    }
+
+    /// Sets the repository uploads go to.
+    pub fn with_repository(mut self, repository: impl Into<String>) -> Self {
+        self.repository = repository.into();
+        self
+    }
+
+    /// Sets the seed for generated content.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+impl Default for UploadStressConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An upload the registry refused or that failed in transit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadFailure {
+    /// Index of the upload in the run.
+    pub upload: usize,
+    /// Request that failed: `start`, `patch` or `finish`.
+    pub stage: &'static str,
+    /// Status code, or `None` for transport errors.
+    pub status: Option<u16>,
+}
+
+/// Results of an upload stress run.
+#[derive(Debug, Clone, Default)]
+pub struct UploadStressReport {
+    /// Number of uploads attempted.
+    pub uploads: usize,
+    /// Digests of the uploads the registry accepted.
+    pub completed: Vec<String>,
+    /// Uploads that did not complete.
+    pub failed: Vec<UploadFailure>,
+    /// Accepted digests whose blob is missing or does not hash to the digest.
+    pub corrupted: Vec<String>,
+    /// Wall-clock duration of the uploads, excluding verification.
+    pub elapsed: Duration,
+}
+
+impl UploadStressReport {
+    /// Returns whether every accepted upload is stored intact.
+    ///
+    /// Failed uploads are allowed: a registry may refuse racing chunks, but
+    /// must not accept them and store the wrong content.
+    pub fn is_consistent(&self) -> bool {
+        self.corrupted.is_empty() && self.completed.len() + self.failed.len() == self.uploads
+    }
+
+    /// Returns whether every upload completed and is stored intact.
+    pub fn is_clean(&self) -> bool {
+        self.is_consistent() && self.failed.is_empty()
+    }
+
+    /// Panics with the corrupted digests unless the run
+    /// [is consistent](Self::is_consistent).
+    #[track_caller]
+    pub fn assert_consistent(&self) {
+        assert!(
+            self.is_consistent(),
+            "{} of {} accepted uploads are corrupted or missing: {:?}",
+            self.corrupted.len(),
+            self.completed.len(),
+            self.corrupted
+        );
+    }
+}
+
+/// Runs the configured uploads against the registry at `url`, then verifies
+/// every accepted blob.
+///
+/// Requests are sent without credentials. Individual upload failures are
+/// reported in [`UploadStressReport::failed`] rather than aborting the run.
+pub async fn run_uploads(url: &str, config: UploadStressConfig) -> Result<UploadStressReport> {
+    let started = Instant::now();
+    let permits = Arc::new(Semaphore::new(config.concurrency.max(1)));
+    let config = Arc::new(config);
+    let base_url = url.trim_end_matches('/').to_string();
+
+    let mut tasks = JoinSet::new();
+    for upload in 0..config.uploads {
+        let permits = permits.clone();
+        let config = config.clone();
+        let base_url = base_url.clone();
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            run_upload(&base_url, &config, upload).await
+        });
+    }
+
+    let mut report = UploadStressReport {
+        uploads: config.uploads,
+        ..Default::default()
+    };
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok(Ok(digest)) => report.completed.push(digest),
+            Ok(Err(failure)) => report.failed.push(failure),
+            Err(_) => {}
+        }
+    }
+    report.elapsed = started.elapsed();
+    report.failed.sort_by_key(|failure| failure.upload);
+
+    let client = RegistryClient::new(&base_url);
+    for digest in &report.completed {
+        let intact = match client.pull_blob(&config.repository, digest).await {
+            Ok(data) => sha256_digest(&data) == *digest,
+            Err(_) => false,
+        };
+        if !intact {
+            report.corrupted.push(digest.clone());
+        }
+    }
+    Ok(report)
+}
+
+/// Runs one upload, returning the digest it was finished with.
+async fn run_upload(
+    base_url: &str,
+    config: &UploadStressConfig,
+    upload: usize,
+) -> std::result::Result<String, UploadFailure> {
+    let failure = |stage, status: Option<StatusCode>| UploadFailure {
+        upload,
+        stage,
+        status: status.map(|s| s.as_u16()),
+    };
+
+    let mut rng = SplitMix64::new(config.seed ^ (upload as u64).wrapping_mul(0x9E37_79B9));
+    let chunks: Vec<Vec<u8>> = (0..config.chunks)
+        .map(|_| {
+            let mut chunk = vec![0u8; config.chunk_size];
+            rng.fill_bytes(&mut chunk);
+            chunk
+        })
+        .collect();
+    let digest = sha256_digest(&chunks.concat());
+
+    let http = reqwest::Client::new();
+    let response = http
+        .post(format!(
+            "{}/v2/{}/blobs/uploads/",
+            base_url, config.repository
+        ))
+        .send()
+        .await
+        .map_err(|e| failure("start", e.status()))?;
+    if response.status() != StatusCode::ACCEPTED {
+        return Err(failure("start", Some(response.status())));
+    }
+    let location = response
+        .headers()
+        .get("Location")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let upload_url = if location.starts_with("http") {
+        location.to_string()
+    } else {
+        format!("{}{}", base_url, location)
+    };
+
+    let patch = |index: usize, chunk: &[u8]| {
+        let start = index * config.chunk_size;
+        let end = start + chunk.len();
+        // Interleaved chunks each get their own connection so they really
+        // arrive concurrently.
+        let http = if config.interleaved_chunks {
+            reqwest::Client::new()
+        } else {
+            http.clone()
+        };
+        let request = http
+            .patch(&upload_url)
+            .header("Content-Type", "application/octet-stream")
+            .header("Content-Range", format!("{}-{}", start, end.max(1) - 1))
+            .body(chunk.to_vec());
+        async move {
+            match request.send().await {
+                Ok(response) if response.status() == StatusCode::ACCEPTED => Ok(()),
+                Ok(response) => Err(failure("patch", Some(response.status()))),
+                Err(e) => Err(failure("patch", e.status())),
+            }
+        }
+    };
+    if config.interleaved_chunks {
+        let patches = chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| patch(index, chunk));
+        future::try_join_all(patches).await?;
+    } else {
+        for (index, chunk) in chunks.iter().enumerate() {
+            patch(index, chunk).await?;
+        }
+    }
+
+    let separator = if upload_url.contains('?') { '&' } else { '?' };
+    let response = http
+        .put(format!("{}{}digest={}", upload_url, separator, digest))
+        .send()
+        .await
+        .map_err(|e| failure("finish", e.status()))?;
+    if response.status() != StatusCode::CREATED {
+        return Err(failure("finish", Some(response.status())));
+    }
+    Ok(digest)
+}
@@ -0,0 +1,240 @@
+//! Comparison of two registries, for testing mirroring and replication.
+
+use crate::digest::sha256_digest;
+use crate::error::Result;
+use crate::server::RegistryServer;
+use crate::storage::{is_tag_key, Storage};
+use crate::verify::descriptors;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// One of the two registries given to [`verify_sync`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// The first registry.
+    A,
+    /// The second registry.
+    B,
+}
+
+/// Something one registry has that the other lacks or holds differently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncDifference {
+    /// A repository with no manifests on one side. Its tags, manifests and
+    /// blobs are not reported separately.
+    MissingRepository {
+        repository: String,
+        missing_from: Side,
+    },
+    /// A tag present on one side only.
+    MissingTag {
+        repository: String,
+        tag: String,
+        missing_from: Side,
+    },
+    /// A tag pointing at different manifests.
+    TagMismatch {
+        repository: String,
+        tag: String,
+        a: String,
+        b: String,
+    },
+    /// A manifest, by digest, present on one side only.
+    MissingManifest {
+        repository: String,
+        digest: String,
+        missing_from: Side,
+    },
+    /// A blob referenced by a manifest of the repository, stored on one
+    /// side only.
+    MissingBlob {
+        repository: String,
+        digest: String,
+        missing_from: Side,
+    },
+}
+
+/// Outcome of comparing two registries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Repositories on either side.
+    pub repositories: usize,
+    /// Tags on either side.
+    pub tags: usize,
+    /// Manifests, by repository and digest, on either side.
+    pub manifests: usize,
+    /// Blobs referenced by those manifests.
+    pub blobs: usize,
+    /// Every difference found, ordered by repository.
+    pub differences: Vec<SyncDifference>,
+}
+
+impl SyncReport {
+    /// Returns whether both registries hold the same content.
+    pub fn is_in_sync(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+/// The content of one registry, by repository.
+#[derive(Default)]
+pub(crate) struct Snapshot {
+    /// Tag to manifest digest.
+    tags: BTreeMap<String, BTreeMap<String, String>>,
+    /// Manifest digest to the blobs it references.
+    manifests: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+    /// Digests of stored and synthetic blobs.
+    blobs: HashSet<String>,
+}
+
+impl Snapshot {
+    pub(crate) async fn load(
+        storage: &dyn Storage,
+        synthetic: impl IntoIterator<Item = String>,
+    ) -> Result<Self> {
+        let mut snapshot = Self::default();
+        for key in storage.list_manifests().await? {
+            let Some((repository, reference)) = key.split_once(':') else {
+                continue;
+            };
+            let Some(entry) = storage.get_manifest(&key).await? else {
+                continue;
+            };
+            let digest = sha256_digest(&entry.data);
+            if is_tag_key(&key) {
+                snapshot
+                    .tags
+                    .entry(repository.to_string())
+                    .or_default()
+                    .insert(reference.to_string(), digest.clone());
+            }
+            let blobs = serde_json::from_slice::<serde_json::Value>(&entry.data)
+                .map(|manifest| {
+                    ["config", "layers", "blobs"]
+                        .iter()
+                        .flat_map(|field| descriptors(&manifest, field))
+                        .map(|reference| reference.digest)
+                        .collect()
+                })
+                .unwrap_or_default();
+            snapshot
+                .manifests
+                .entry(repository.to_string())
+                .or_default()
+                .insert(digest, blobs);
+        }
+        snapshot.blobs = storage.list_blobs().await?.into_iter().collect();
+        snapshot.blobs.extend(synthetic);
+        Ok(snapshot)
+    }
+}
+
+/// Compares the repositories, tags, manifests and referenced blobs of two
+/// registries.
+///
+/// Manifests are compared by digest, so a mirror that re-serializes
+/// manifests shows up as [`TagMismatch`](SyncDifference::TagMismatch).
+/// Blobs nothing references, such as leftovers of abandoned uploads, are
+/// ignored.
+///
+/// # Examples
+///
+/// ```no_run
+/// use registry_testkit::{verify_sync, RegistryConfig, RegistryServer};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let source = RegistryServer::new(RegistryConfig::memory()).await?;
+/// let mirror = RegistryServer::new(RegistryConfig::memory()).await?;
+/// // ... run the mirroring tool from source.url() to mirror.url() ...
+/// let report = verify_sync(&source, &mirror).await?;
+/// assert!(report.is_in_sync(), "{:#?}", report.differences);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn verify_sync(a: &RegistryServer, b: &RegistryServer) -> Result<SyncReport> {
+    let a = a.snapshot().await?;
+    let b = b.snapshot().await?;
+    let mut report = SyncReport::default();
+
+    let repositories: BTreeSet<&String> = a.manifests.keys().chain(b.manifests.keys()).collect();
+    report.repositories = repositories.len();
+    for repository in repositories {
+        let (Some(a_manifests), Some(b_manifests)) =
+            (a.manifests.get(repository), b.manifests.get(repository))
+        else {
+            report.differences.push(SyncDifference::MissingRepository {
+                repository: repository.clone(),
+                missing_from: if a.manifests.contains_key(repository) {
+                    Side::B
+                } else {
+                    Side::A
+                },
+            });
+            continue;
+        };
+
+        let no_tags = BTreeMap::new();
+        let a_tags = a.tags.get(repository).unwrap_or(&no_tags);
+        let b_tags = b.tags.get(repository).unwrap_or(&no_tags);
+        let tags: BTreeSet<&String> = a_tags.keys().chain(b_tags.keys()).collect();
+        report.tags += tags.len();
+        for tag in tags {
+            let difference = match (a_tags.get(tag), b_tags.get(tag)) {
+                (Some(a_digest), Some(b_digest)) if a_digest == b_digest => continue,
+                (Some(a_digest), Some(b_digest)) => SyncDifference::TagMismatch {
+                    repository: repository.clone(),
+                    tag: tag.clone(),
+                    a: a_digest.clone(),
+                    b: b_digest.clone(),
+                },
+                (a_digest, _) => SyncDifference::MissingTag {
+                    repository: repository.clone(),
+                    tag: tag.clone(),
+                    missing_from: if a_digest.is_some() { Side::B } else { Side::A },
+                },
+            };
+            report.differences.push(difference);
+        }
+
+        let digests: BTreeSet<&String> = a_manifests.keys().chain(b_manifests.keys()).collect();
+        report.manifests += digests.len();
+        let mut blobs = BTreeSet::new();
+        for digest in digests {
+            let missing_from = match (a_manifests.get(digest), b_manifests.get(digest)) {
+                (Some(referenced), Some(_)) => {
+                    blobs.extend(referenced);
+                    continue;
+                }
+                (Some(referenced), None) => {
+                    blobs.extend(referenced);
+                    Side::B
+                }
+                (None, referenced) => {
+                    blobs.extend(referenced.into_iter().flatten());
+                    Side::A
+                }
+            };
+            report.differences.push(SyncDifference::MissingManifest {
+                repository: repository.clone(),
+                digest: digest.clone(),
+                missing_from,
+            });
+        }
+
+        report.blobs += blobs.len();
+        for digest in blobs {
+            let missing_from = match (a.blobs.contains(digest), b.blobs.contains(digest)) {
+                (true, false) => Side::B,
+                (false, true) => Side::A,
+                // Missing on both sides is an incomplete image, not a sync
+                // problem; `verify_image` reports it.
+                _ => continue,
+            };
+            report.differences.push(SyncDifference::MissingBlob {
+                repository: repository.clone(),
+                digest: digest.clone(),
+                missing_from,
+            });
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,62 @@
+//! Synthetic blobs whose content is generated on the fly from a seed.
+
+use crate::rng::SplitMix64;
+use axum::body::Bytes;
+use futures_util::stream::{self, Stream};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A blob of arbitrary size backed by a deterministic generator.
+///
+/// Nothing is allocated beyond a single chunk, so multi-gigabyte layers can
+/// be served from small CI machines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyntheticBlob {
+    /// Size of the blob in bytes.
+    pub size: u64,
+    /// Seed the content is generated from.
+    pub seed: u64,
+}
+
+impl SyntheticBlob {
+    /// Creates a synthetic blob of `size` bytes generated from `seed`.
+    pub fn new(size: u64, seed: u64) -> Self {
+        Self { size, seed }
+    }
+
+    fn chunk_iter(self) -> impl Iterator<Item = Bytes> {
+        let mut rng = SplitMix64::new(self.seed);
+        let mut remaining = self.size;
+        std::iter::from_fn(move || {
+            if remaining == 0 {
+                return None;
+            }
+            let len = remaining.min(CHUNK_SIZE as u64) as usize;
+            let mut chunk = vec![0u8; len];
+            rng.fill_bytes(&mut chunk);
+            remaining -= len as u64;
+            Some(Bytes::from(chunk))
+        })
+    }
+
+    /// Computes the `sha256:` digest by streaming the content once.
+    pub fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        for chunk in self.chunk_iter() {
+            hasher.update(&chunk);
+        }
+        format!("sha256:{}", hex::encode(hasher.finalize()))
+    }
+
+    /// Generates the full content in memory; only sensible for small blobs.
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.chunk_iter().flat_map(|chunk| chunk.to_vec()).collect()
+    }
+
+    /// Streams the content chunk by chunk.
+    pub fn stream(&self) -> impl Stream<Item = Result<Bytes, Infallible>> + Send + 'static {
+        stream::iter(self.chunk_iter().map(Ok))
+    }
+}
@@ -0,0 +1,10 @@
+//! Tag listing.
+
+use serde::Serialize;
+
+/// Body of `GET /v2/<name>/tags/list`.
+#[derive(Serialize)]
+pub(crate) struct TagList {
+    pub name: String,
+    pub tags: Vec<String>,
+}
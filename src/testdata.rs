@@ -0,0 +1,118 @@
+//! Reproducible layer content for benchmarks and cache tests.
+//!
+//! Layers are real tar archives whose files are generated from a seed, so
+//! the same settings give the same digest on every run and machine
+//! without fixtures checked into the repository. How well a layer
+//! compresses is set by the share of its content that repeats.
+
+use crate::archive::{tar_header, BLOCK};
+use crate::builder::Layer;
+use crate::gzip;
+use crate::rng::SplitMix64;
+
+/// Media type of uncompressed OCI layers.
+pub const OCI_LAYER_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar";
+
+/// Content is generated in runs of this many bytes, each either random or
+/// repeated.
+const RUN: usize = 256;
+
+/// Settings of a generated layer.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::testdata::LayerContent;
+///
+/// // 1 MiB over 4 files, three quarters of it repetitive.
+/// let content = LayerContent::new(7)
+///     .with_size(1 << 20)
+///     .with_files(4)
+///     .with_compressibility(0.75);
+/// let layer = content.layer();
+/// assert_eq!(layer.data, content.layer().data);
+/// assert!(layer.data.len() < 512 << 10);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerContent {
+    seed: u64,
+    size: u64,
+    files: usize,
+    compressibility: f64,
+}
+
+impl LayerContent {
+    /// One file of 1 MiB generated from `seed`, half of it repetitive.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            size: 1 << 20,
+            files: 1,
+            compressibility: 0.5,
+        }
+    }
+
+    /// Sets the total size of the files, in bytes. Tar headers and padding
+    /// come on top.
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Spreads the content over `files` files of equal size (at least one).
+    pub fn with_files(mut self, files: usize) -> Self {
+        self.files = files.max(1);
+        self
+    }
+
+    /// Sets the share of the content, from `0.0` (all random) to `1.0`
+    /// (all repeated), that compresses away.
+    pub fn with_compressibility(mut self, compressibility: f64) -> Self {
+        self.compressibility = compressibility.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Generates the uncompressed tar archive.
+    pub fn tar(&self) -> Vec<u8> {
+        let mut rng = SplitMix64::new(self.seed);
+        let mut motif = [0u8; RUN];
+        rng.fill_bytes(&mut motif);
+
+        let files = self.files as u64;
+        let mut tar = Vec::with_capacity(self.size as usize + (self.files + 2) * 2 * BLOCK);
+        for index in 0..files {
+            // The first files take the remainder.
+            let size = self.size / files + u64::from(index < self.size % files);
+            let path = format!("data/file-{:04}.bin", index);
+            tar.extend_from_slice(&tar_header(&path, size, b'0'));
+
+            let mut remaining = size as usize;
+            let mut run = [0u8; RUN];
+            while remaining > 0 {
+                let len = remaining.min(RUN);
+                if rng.next_f64() < self.compressibility {
+                    run[..len].copy_from_slice(&motif[..len]);
+                } else {
+                    rng.fill_bytes(&mut run[..len]);
+                }
+                tar.extend_from_slice(&run[..len]);
+                remaining -= len;
+            }
+            let padding = (BLOCK - size as usize % BLOCK) % BLOCK;
+            tar.resize(tar.len() + padding, 0);
+        }
+        tar.resize(tar.len() + 2 * BLOCK, 0);
+        tar
+    }
+
+    /// Generates a gzip-compressed tar layer.
+    pub fn layer(&self) -> Layer {
+        Layer::new(gzip::compress(&self.tar()))
+    }
+
+    /// Generates an uncompressed tar layer, of media type
+    /// [`OCI_LAYER_MEDIA_TYPE`].
+    pub fn uncompressed_layer(&self) -> Layer {
+        Layer::with_media_type(self.tar(), OCI_LAYER_MEDIA_TYPE)
+    }
+}
@@ -0,0 +1,147 @@
+//! The bearer token issuer as a server of its own.
+//!
+//! Production registries usually delegate token issuance to a separate
+//! service, which can fail while the registry stays up. A [`TokenServer`]
+//! serves the token endpoint on its own address, signing tokens with a key
+//! the registry is configured to trust, so tests can stop it on its own.
+
+use crate::audit::AuditLog;
+use crate::auth::{introspection_endpoint, token_endpoint, AuthConfig, Authenticator};
+use crate::error::Result;
+use crate::headers::HeaderCasing;
+use crate::profile::RegistryProfile;
+use crate::server::serve;
+use crate::socket::SocketOptions;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::info;
+
+/// A standalone token endpoint for registries using bearer auth.
+///
+/// The server issues tokens at `<url>/token` and introspects them at
+/// `<url>/token/introspect`, granting access by the users and rules of its
+/// [`AuthConfig`]. [`auth_config`](Self::auth_config) returns that
+/// configuration with the realm pointing here, for the registry.
+///
+/// # Examples
+///
+/// ```no_run
+/// use registry_testkit::{AuthConfig, RegistryConfig, RegistryServer, TokenServer};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut tokens = TokenServer::start(
+///     AuthConfig::bearer("registry.test").with_user("alice", "secret"),
+/// )
+/// .await?;
+/// let config = RegistryConfig::memory().with_auth(tokens.auth_config());
+/// let registry = RegistryServer::new(config).await?;
+///
+/// // The registry stays up; clients fail to get a token.
+/// tokens.stop().await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TokenServer {
+    addr: SocketAddr,
+    config: AuthConfig,
+    app: Router,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TokenServer {
+    /// Starts a token server on a free local port.
+    ///
+    /// `config` should come from [`AuthConfig::bearer`]; under basic auth
+    /// the token endpoint answers `404`. A signing key is generated unless
+    /// one is set.
+    pub async fn start(mut config: AuthConfig) -> Result<Self> {
+        if config.signing_key.is_none() {
+            config.signing_key = Some(uuid::Uuid::new_v4().to_string());
+        }
+
+        let listener = SocketOptions::new().bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let url = format!("http://{}", addr);
+        config = config.with_realm(format!("{}/token", url));
+
+        let audit = AuditLog::new(None).await?;
+        let authenticator = Arc::new(Authenticator::new(
+            config.clone(),
+            RegistryProfile::Generic,
+            &url,
+            None,
+            audit,
+        ));
+        let app = Router::new()
+            .route("/token", get(token_endpoint))
+            .route("/token/introspect", post(introspection_endpoint))
+            .with_state(authenticator);
+
+        info!("Token server listening on {}", addr);
+        let handle = tokio::spawn(serve(listener, app.clone(), HeaderCasing::default()));
+        Ok(Self {
+            addr,
+            config,
+            app,
+            handle: Some(handle),
+        })
+    }
+
+    /// Returns the socket address the server is bound to.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the base URL of the server.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Returns the token endpoint URL, the realm of bearer challenges.
+    pub fn realm(&self) -> String {
+        format!("{}/token", self.url())
+    }
+
+    /// Returns the auth configuration for a registry trusting this server:
+    /// the server's own, with its realm and signing key.
+    pub fn auth_config(&self) -> AuthConfig {
+        self.config.clone()
+    }
+
+    /// Stops serving, dropping open connections. Clients connecting now
+    /// are refused while the registry keeps running.
+    pub async fn stop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+            handle.await.ok();
+            info!("Token server on {} stopped", self.addr);
+        }
+    }
+
+    /// Starts serving again on the same address, stopping first if the
+    /// server is still running. Tokens issued before stay valid.
+    pub async fn restart(&mut self) -> Result<()> {
+        self.stop().await;
+        let listener = SocketOptions::new().bind_addr(self.addr)?;
+        info!("Token server restarted on {}", self.addr);
+        self.handle = Some(tokio::spawn(serve(
+            listener,
+            self.app.clone(),
+            HeaderCasing::default(),
+        )));
+        Ok(())
+    }
+}
+
+impl Drop for TokenServer {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! In-process transport that reaches the router without TCP.
+
+use crate::headers::HeaderCasing;
+use axum::Router;
+use hyper::rt::{Read, ReadBufCursor, Write};
+use hyper::Uri;
+use hyper_util::client::legacy::connect::{Connected, Connection};
+use hyper_util::rt::TokioIo;
+use std::future::{ready, Ready};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::DuplexStream;
+
+const DUPLEX_BUFFER: usize = 256 * 1024;
+
+/// Connector handing out in-memory duplex streams served by the registry.
+///
+/// Implements `tower::Service<Uri>`, so it plugs into
+/// `hyper_util::client::legacy::Client` (and anything else accepting a hyper
+/// connector). Every connection is served directly by the registry router;
+/// no sockets or ports are involved, and the host in request URIs is ignored.
+///
+/// # Examples
+///
+/// ```no_run
+/// use hyper_util::client::legacy::Client;
+/// use hyper_util::rt::TokioExecutor;
+/// # use registry_testkit::{RegistryServer, RegistryConfig};
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// # let server = RegistryServer::new(RegistryConfig::memory()).await?;
+/// let client: Client<_, String> =
+///     Client::builder(TokioExecutor::new()).build(server.connector());
+/// let response = client.get("http://registry.local/v2/".parse()?).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct InProcessConnector {
+    app: Router,
+    casing: HeaderCasing,
+}
+
+impl InProcessConnector {
+    pub(crate) fn new(app: Router, casing: HeaderCasing) -> Self {
+        Self { app, casing }
+    }
+}
+
+impl tower::Service<Uri> for InProcessConnector {
+    type Response = InProcessStream;
+    type Error = io::Error;
+    type Future = Ready<Result<InProcessStream, io::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _uri: Uri) -> Self::Future {
+        let (client, server) = tokio::io::duplex(DUPLEX_BUFFER);
+        let remote = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        tokio::spawn(crate::server::serve_connection(
+            server,
+            remote,
+            self.app.clone(),
+            self.casing,
+        ));
+        ready(Ok(InProcessStream {
+            io: TokioIo::new(client),
+        }))
+    }
+}
+
+/// Client half of an in-process connection.
+pub struct InProcessStream {
+    io: TokioIo<DuplexStream>,
+}
+
+impl Connection for InProcessStream {
+    fn connected(&self) -> Connected {
+        Connected::new()
+    }
+}
+
+impl Read for InProcessStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: ReadBufCursor<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl Write for InProcessStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
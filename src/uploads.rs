@@ -0,0 +1,64 @@
+//! Bookkeeping of blob upload sessions, for catching clients that leak them.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// An upload session that was opened and not yet finished or cancelled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenUpload {
+    /// Session ID, the last segment of its `Location`.
+    pub uuid: String,
+    /// Repository the session was opened in.
+    pub repository: String,
+    /// Time since the session was opened.
+    pub age: Duration,
+    /// Bytes received so far.
+    pub size: u64,
+}
+
+/// Upload sessions handled since the server started.
+///
+/// Sessions that are neither finished, cancelled nor expired are still
+/// [`open`](Self::open); at the end of a test, those are leaks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UploadStats {
+    /// Sessions opened with `POST /v2/<name>/blobs/uploads/`.
+    pub started: u64,
+    /// Sessions completed with a `PUT` that stored the blob.
+    pub finished: u64,
+    /// Sessions the client ended with `DELETE`.
+    pub cancelled: u64,
+    /// Sessions discarded by
+    /// [`MaintenanceConfig::with_upload_ttl`](crate::MaintenanceConfig::with_upload_ttl).
+    pub expired: u64,
+    /// Sessions still open, oldest first.
+    pub open: Vec<OpenUpload>,
+}
+
+/// An open session, as tracked by the server.
+#[derive(Debug, Clone)]
+pub(crate) struct UploadSession {
+    pub repository: String,
+    pub started: Instant,
+}
+
+/// How upload sessions ended.
+#[derive(Debug, Default)]
+pub(crate) struct UploadCounters {
+    pub started: AtomicU64,
+    pub finished: AtomicU64,
+    pub cancelled: AtomicU64,
+    pub expired: AtomicU64,
+}
+
+impl UploadCounters {
+    pub(crate) fn stats(&self, open: Vec<OpenUpload>) -> UploadStats {
+        UploadStats {
+            started: self.started.load(Ordering::Relaxed),
+            finished: self.finished.load(Ordering::Relaxed),
+            cancelled: self.cancelled.load(Ordering::Relaxed),
+            expired: self.expired.load(Ordering::Relaxed),
+            open,
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Read-through fallback to upstream registries.
+
+use crate::client::RegistryClient;
+use crate::copy::PlatformFilter;
+use crate::digest::sha256_digest;
+use crate::storage::ManifestEntry;
+use tracing::{debug, info};
+
+/// An upstream registry consulted when content is missing locally.
+///
+/// Upstreams are tried in the order they were added, like the mirror list of
+/// a containerd `hosts.toml`: the first one that has the content serves it,
+/// and errors (unreachable host, `401`, `404`, digest mismatch) fall
+/// through to the next.
+#[derive(Debug, Clone)]
+pub struct UpstreamConfig {
+    /// Base URL of the upstream registry.
+    pub url: String,
+    /// Username and password for the upstream registry.
+    pub credentials: Option<(String, String)>,
+    /// Store content fetched from this upstream locally, so later reads
+    /// are served without contacting it.
+    pub cache: bool,
+    /// Platforms kept in multi-platform images fetched by tag.
+    pub platforms: PlatformFilter,
+}
+
+impl UpstreamConfig {
+    /// Reads through to the registry at `url`, without credentials or
+    /// caching.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            credentials: None,
+            cache: false,
+            platforms: PlatformFilter::all(),
+        }
+    }
+
+    /// Logs in to the upstream with the given credentials.
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Caches content fetched from this upstream in local storage.
+    pub fn with_cache(mut self, cache: bool) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Narrows multi-platform images fetched by tag to `platforms`, so a
+    /// caching registry only stores what tests use.
+    ///
+    /// The index is rewritten without the other platforms and served and
+    /// cached under its new digest. Fetching the original index by digest
+    /// still returns it unchanged, since anything else would fail digest
+    /// verification.
+    pub fn with_platforms(mut self, platforms: PlatformFilter) -> Self {
+        self.platforms = platforms;
+        self
+    }
+}
+
+struct Upstream {
+    client: RegistryClient,
+    cache: bool,
+    platforms: PlatformFilter,
+}
+
+/// Content found upstream, and whether to cache it.
+pub(crate) struct Fetched<T> {
+    pub content: T,
+    pub cache: bool,
+}
+
+/// The configured fallback chain.
+pub(crate) struct Upstreams {
+    upstreams: Vec<Upstream>,
+}
+
+impl Upstreams {
+    pub(crate) fn new(configs: &[UpstreamConfig]) -> Self {
+        let upstreams = configs
+            .iter()
+            .map(|config| {
+                let mut client = RegistryClient::new(config.url.clone());
+                if let Some((username, password)) = &config.credentials {
+                    client = client.with_credentials(username.clone(), password.clone());
+                }
+                Upstream {
+                    client,
+                    cache: config.cache,
+                    platforms: config.platforms.clone(),
+                }
+            })
+            .collect();
+        Self { upstreams }
+    }
+
+    /// Fetches a manifest from the first upstream that serves it.
+    pub(crate) async fn manifest(
+        &self,
+        repository: &str,
+        reference: &str,
+    ) -> Option<Fetched<ManifestEntry>> {
+        for upstream in &self.upstreams {
+            let url = upstream.client.base_url();
+            match upstream.client.pull_manifest(repository, reference).await {
+                Ok(mut entry) if digest_matches(reference, &entry.data) => {
+                    info!("Fetched {}:{} from {}", repository, reference, url);
+                    if reference.contains(':') {
+                        // Pinned by digest; leave it as is.
+                    } else if let Some(filtered) = upstream.platforms.filter_index(&entry.data) {
+                        debug!(
+                            "Narrowed {}:{} to {}",
+                            repository, reference, upstream.platforms
+                        );
+                        entry.data = filtered;
+                    }
+                    return Some(Fetched {
+                        content: entry,
+                        cache: upstream.cache,
+                    });
+                }
+                Ok(_) => debug!("{} served a mismatched manifest {}", url, reference),
+                Err(e) => debug!("{} has no {}:{}: {}", url, repository, reference, e),
+            }
+        }
+        None
+    }
+
+    /// Fetches a blob from the first upstream that serves it.
+    pub(crate) async fn blob(&self, repository: &str, digest: &str) -> Option<Fetched<Vec<u8>>> {
+        for upstream in &self.upstreams {
+            let url = upstream.client.base_url();
+            match upstream.client.pull_blob(repository, digest).await {
+                Ok(data) if digest_matches(digest, &data) => {
+                    info!("Fetched blob {} from {}", digest, url);
+                    return Some(Fetched {
+                        content: data,
+                        cache: upstream.cache,
+                    });
+                }
+                Ok(_) => debug!("{} served a mismatched blob {}", url, digest),
+                Err(e) => debug!("{} has no blob {}: {}", url, digest, e),
+            }
+        }
+        None
+    }
+}
+
+/// Returns whether `data` matches `reference` when it is a sha256 digest.
+fn digest_matches(reference: &str, data: &[u8]) -> bool {
+    !reference.starts_with("sha256:") || sha256_digest(data) == reference
+}
@@ -0,0 +1,152 @@
+//! Post-hoc image completeness checks.
+
+use crate::digest::sha256_digest;
+use crate::error::{RegistryError, Result};
+use crate::storage::Storage;
+use crate::synthetic::SyntheticBlob;
+use std::collections::{HashMap, HashSet};
+
+/// Outcome of verifying an image and everything it references.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Digests of the manifests walked: the image itself and, for an index,
+    /// its children.
+    pub manifests: Vec<String>,
+    /// Number of distinct blobs checked.
+    pub blobs_checked: usize,
+    /// Everything found wrong, in walk order.
+    pub problems: Vec<VerifyProblem>,
+}
+
+impl VerifyReport {
+    /// Returns whether every referenced manifest and blob checked out.
+    pub fn is_complete(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// A reference that storage cannot satisfy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyProblem {
+    /// A child manifest of an index is not stored.
+    MissingManifest { digest: String },
+    /// A referenced blob is not stored.
+    MissingBlob { digest: String },
+    /// Stored content differs in size from its descriptor.
+    SizeMismatch {
+        digest: String,
+        expected: u64,
+        actual: u64,
+    },
+    /// Stored content does not hash to its digest.
+    DigestMismatch { digest: String, actual: String },
+}
+
+/// A descriptor: digest plus the size it claims.
+pub(crate) struct Reference {
+    pub digest: String,
+    pub size: Option<u64>,
+}
+
+/// Descriptors in `field`, which may hold one descriptor or an array.
+pub(crate) fn descriptors(manifest: &serde_json::Value, field: &str) -> Vec<Reference> {
+    manifest[field]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .chain(Some(&manifest[field]).filter(|d| d.is_object()))
+        .filter_map(|descriptor| {
+            Some(Reference {
+                digest: descriptor["digest"].as_str()?.to_string(),
+                size: descriptor["size"].as_u64(),
+            })
+        })
+        .collect()
+}
+
+/// Checks `data` against the digest and size a descriptor claims.
+fn check(reference: &Reference, data: &[u8], problems: &mut Vec<VerifyProblem>) {
+    let actual = data.len() as u64;
+    if let Some(expected) = reference.size.filter(|&size| size != actual) {
+        problems.push(VerifyProblem::SizeMismatch {
+            digest: reference.digest.clone(),
+            expected,
+            actual,
+        });
+    }
+    if reference.digest.starts_with("sha256:") {
+        let actual = sha256_digest(data);
+        if actual != reference.digest {
+            problems.push(VerifyProblem::DigestMismatch {
+                digest: reference.digest.clone(),
+                actual,
+            });
+        }
+    }
+}
+
+/// Walks the manifest stored under `repository:reference`.
+pub(crate) async fn verify(
+    storage: &dyn Storage,
+    synthetic: &HashMap<String, SyntheticBlob>,
+    repository: &str,
+    reference: &str,
+) -> Result<VerifyReport> {
+    let key = format!("{}:{}", repository, reference);
+    let root = storage
+        .get_manifest(&key)
+        .await?
+        .ok_or(RegistryError::ManifestNotFound(key))?;
+
+    let mut report = VerifyReport::default();
+    let mut seen_blobs = HashSet::new();
+    let mut queue = vec![(sha256_digest(&root.data), root.data)];
+    while let Some((digest, data)) = queue.pop() {
+        if report.manifests.contains(&digest) {
+            continue;
+        }
+        report.manifests.push(digest);
+        let manifest: serde_json::Value = serde_json::from_slice(&data)?;
+
+        for child in descriptors(&manifest, "manifests") {
+            let key = format!("{}:{}", repository, child.digest);
+            match storage.get_manifest(&key).await? {
+                Some(entry) => {
+                    check(&child, &entry.data, &mut report.problems);
+                    queue.push((child.digest, entry.data));
+                }
+                None => report.problems.push(VerifyProblem::MissingManifest {
+                    digest: child.digest,
+                }),
+            }
+        }
+
+        let blobs = ["config", "layers", "blobs"]
+            .iter()
+            .flat_map(|field| descriptors(&manifest, field));
+        for blob in blobs {
+            if !seen_blobs.insert(blob.digest.clone()) {
+                continue;
+            }
+            if let Some(generated) = synthetic.get(&blob.digest) {
+                // Generated content always matches; only the size can lie.
+                if let Some(expected) = blob.size.filter(|&size| size != generated.size) {
+                    report.problems.push(VerifyProblem::SizeMismatch {
+                        digest: blob.digest,
+                        expected,
+                        actual: generated.size,
+                    });
+                }
+                continue;
+            }
+            match storage.get_blob(&blob.digest).await? {
+                Some(data) => check(&blob, &data, &mut report.problems),
+                None => report.problems.push(VerifyProblem::MissingBlob {
+                    digest: blob.digest,
+                }),
+            }
+        }
+    }
+    report.blobs_checked = seen_blobs.len();
+    Ok(report)
+}
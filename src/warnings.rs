@@ -0,0 +1,139 @@
+//! `Warning` headers attached to registry responses.
+
+use crate::server::split_repository_path;
+use axum::{
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+/// Registry endpoints a warning can be limited to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningEndpoint {
+    /// `/v2/<name>/manifests/<reference>`.
+    Manifests,
+    /// `/v2/<name>/blobs/<digest>`.
+    Blobs,
+    /// `/v2/<name>/blobs/uploads/...`.
+    Uploads,
+    /// `/v2/<name>/tags/list`.
+    Tags,
+}
+
+impl WarningEndpoint {
+    /// Classifies the remainder of a repository path.
+    pub(crate) fn of(rest: &str) -> Option<Self> {
+        if rest.starts_with("blobs/uploads/") {
+            Some(Self::Uploads)
+        } else if rest.starts_with("blobs/") {
+            Some(Self::Blobs)
+        } else if rest.starts_with("manifests/") {
+            Some(Self::Manifests)
+        } else if rest.starts_with("tags/") {
+            Some(Self::Tags)
+        } else {
+            None
+        }
+    }
+}
+
+/// An RFC 7234 `Warning` header added to matching responses, like the
+/// deprecation notices Docker Hub sends for old manifest formats.
+///
+/// # Examples
+///
+/// ```
+/// use registry_testkit::warnings::{RegistryWarning, WarningEndpoint};
+///
+/// let warning = RegistryWarning::new("this repository is deprecated")
+///     .for_repository("legacy/*")
+///     .for_endpoint(WarningEndpoint::Manifests);
+/// assert_eq!(warning.header_value(), r#"299 - "this repository is deprecated""#);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryWarning {
+    /// Warning code; `299` ("miscellaneous persistent warning") by default.
+    pub code: u16,
+    /// Text of the warning.
+    pub text: String,
+    /// Repository the warning applies to; a trailing `*` matches a prefix.
+    /// All repositories if `None`.
+    pub repository: Option<String>,
+    /// Endpoint the warning applies to; all repository endpoints if `None`.
+    pub endpoint: Option<WarningEndpoint>,
+}
+
+impl RegistryWarning {
+    /// A `299` warning on every repository endpoint.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            code: 299,
+            text: text.into(),
+            repository: None,
+            endpoint: None,
+        }
+    }
+
+    /// Limits the warning to a repository, or to a prefix ending in `*`.
+    pub fn for_repository(mut self, repository: impl Into<String>) -> Self {
+        self.repository = Some(repository.into());
+        self
+    }
+
+    /// Limits the warning to one endpoint.
+    pub fn for_endpoint(mut self, endpoint: WarningEndpoint) -> Self {
+        self.endpoint = Some(endpoint);
+        self
+    }
+
+    /// Sets the warning code.
+    pub fn with_code(mut self, code: u16) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// Returns the header value: `<code> - "<text>"`.
+    pub fn header_value(&self) -> String {
+        let text = self.text.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("{} - \"{}\"", self.code, text)
+    }
+
+    fn matches(&self, repository: &str, endpoint: Option<WarningEndpoint>) -> bool {
+        let repository_matches = self
+            .repository
+            .as_deref()
+            .is_none_or(|pattern| matches_repository(pattern, repository));
+        repository_matches && self.endpoint.is_none_or(|e| Some(e) == endpoint)
+    }
+}
+
+/// Matches a repository against a name, or a prefix ending in `*`.
+pub(crate) fn matches_repository(pattern: &str, repository: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => repository.starts_with(prefix),
+        None => repository == pattern,
+    }
+}
+
+/// Appends the configured warnings that match the request path.
+pub(crate) async fn add_warnings(
+    State(warnings): State<Arc<Vec<RegistryWarning>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    let mut response = next.run(request).await;
+    let Some((repository, rest)) = split_repository_path(&path) else {
+        return response;
+    };
+
+    let endpoint = WarningEndpoint::of(rest);
+    for warning in warnings.iter().filter(|w| w.matches(repository, endpoint)) {
+        if let Ok(value) = HeaderValue::from_str(&warning.header_value()) {
+            response.headers_mut().append("Warning", value);
+        }
+    }
+    response
+}
@@ -0,0 +1,82 @@
+//! One-line logging of requests and responses for reading protocol flows.
+//!
+//! Unlike the [recorder](crate::capture), nothing is stored: each exchange
+//! becomes two `debug` events under the [`TARGET`] target, in a fixed
+//! format meant for grepping test output:
+//!
+//! ```text
+//! >> PUT /v2/app/blobs/uploads/1f2e?digest=sha256:ab.. content-length=5 content-type=application/octet-stream
+//! << 201 PUT /v2/app/blobs/uploads/1f2e?digest=sha256:ab.. docker-content-digest=sha256:ab.. location=/v2/app/blobs/sha256:ab.. (2ms)
+//! ```
+
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use std::fmt::Write;
+use std::time::Instant;
+use tracing::debug;
+
+/// Tracing target of the protocol log, for filters such as
+/// `RUST_LOG=registry_testkit::wire=debug`.
+pub const TARGET: &str = "registry_testkit::wire";
+
+/// Headers logged when present, in this order.
+const LOGGED_HEADERS: &[&str] = &[
+    "accept",
+    "authorization",
+    "content-type",
+    "content-length",
+    "content-range",
+    "range",
+    "content-encoding",
+    "location",
+    "docker-content-digest",
+    "docker-upload-uuid",
+    "docker-distribution-api-version",
+    "oci-subject",
+    "oci-filters-applied",
+    "www-authenticate",
+    "retry-after",
+    "link",
+];
+
+/// Appends ` name=value` for each logged header. Values containing spaces
+/// are quoted, and credentials are reduced to their scheme.
+fn headers(line: &mut String, headers: &HeaderMap) {
+    for name in LOGGED_HEADERS {
+        for value in headers.get_all(*name) {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            let value = if *name == header::AUTHORIZATION.as_str() {
+                value.split(' ').next().unwrap_or_default().to_string()
+            } else {
+                value.into_owned()
+            };
+            if value.contains(' ') {
+                let _ = write!(line, " {}={:?}", name, value);
+            } else {
+                let _ = write!(line, " {}={}", name, value);
+            }
+        }
+    }
+}
+
+/// Logs the request line and the status line of every exchange.
+pub(crate) async fn log_protocol(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let target = format!("{} {}", request.method(), request.uri());
+
+    let mut line = format!(">> {}", target);
+    headers(&mut line, request.headers());
+    debug!(target: TARGET, "{}", line);
+
+    let response = next.run(request).await;
+
+    let mut line = format!("<< {} {}", response.status().as_u16(), target);
+    headers(&mut line, response.headers());
+    let _ = write!(line, " ({}ms)", started.elapsed().as_millis());
+    debug!(target: TARGET, "{}", line);
+    response
+}
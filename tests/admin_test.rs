@@ -0,0 +1,256 @@
+use registry_testkit::{
+    AuthConfig, RegistryClient, RegistryConfig, RegistryError, RegistryProfile, RegistryServer,
+};
+
+#[tokio::test]
+async fn test_retag_promotes_manifest() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client
+        .push_image("app", "rc1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    server.retag("app", "rc1", "stable").await.unwrap();
+
+    assert_eq!(
+        client.pull_image("app", "stable").await.unwrap().digest,
+        digest
+    );
+    assert_eq!(
+        client.pull_image("app", "rc1").await.unwrap().digest,
+        digest
+    );
+
+    match server.retag("app", "missing", "stable").await {
+        Err(RegistryError::ManifestNotFound(key)) => assert_eq!(key, "app:missing"),
+        other => panic!("expected ManifestNotFound, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_rename_repo() {
+    for config in [RegistryConfig::memory(), RegistryConfig::temp_dir()] {
+        let server = RegistryServer::new(config).await.unwrap();
+        let client = RegistryClient::new(server.url());
+        let digest = client
+            .push_image("old", "v1", &[b"layer".to_vec()])
+            .await
+            .unwrap();
+
+        server.rename_repo("old", "new").await.unwrap();
+
+        assert_eq!(client.pull_image("new", "v1").await.unwrap().digest, digest);
+        assert_eq!(
+            client.pull_image("new", &digest).await.unwrap().digest,
+            digest
+        );
+        assert!(client.pull_manifest("old", "v1").await.is_err());
+
+        assert!(matches!(
+            server.rename_repo("old", "other").await,
+            Err(RegistryError::RepositoryNotFound(_))
+        ));
+    }
+}
+
+#[tokio::test]
+async fn test_rename_repo_onto_itself_keeps_it() {
+    let config = RegistryConfig::memory()
+        .with_auth(AuthConfig::bearer("registry.docker.io").with_user("alice", "secret"))
+        .emulate(RegistryProfile::DockerHub);
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url()).with_credentials("alice", "secret");
+    let digest = client
+        .push_image("library/nginx", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    server.rename_repo("nginx", "library/nginx").await.unwrap();
+
+    assert_eq!(
+        client
+            .pull_image("library/nginx", "v1")
+            .await
+            .unwrap()
+            .digest,
+        digest
+    );
+}
+
+#[tokio::test]
+async fn test_rename_repo_refuses_existing_target() {
+    let server = RegistryServer::new(RegistryConfig::memory().with_soft_delete(true))
+        .await
+        .unwrap();
+    let client = RegistryClient::new(server.url());
+    client
+        .push_image("old", "v1", &[b"old".to_vec()])
+        .await
+        .unwrap();
+    let digest = client
+        .push_image("new", "v1", &[b"new".to_vec()])
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        server.rename_repo("old", "new").await,
+        Err(RegistryError::Denied(_))
+    ));
+    assert_eq!(client.pull_image("new", "v1").await.unwrap().digest, digest);
+    assert!(client.pull_manifest("old", "v1").await.is_ok());
+
+    // Tombstones move with the repository.
+    server.delete_manifest("old", "v1").await.unwrap();
+    client
+        .push_image("old", "v2", &[b"old".to_vec()])
+        .await
+        .unwrap();
+    server.rename_repo("old", "moved").await.unwrap();
+    server.undelete("moved", "v1").await.unwrap();
+    assert!(client.pull_manifest("moved", "v1").await.is_ok());
+}
+
+#[tokio::test]
+async fn test_delete_manifest_without_soft_delete() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    server.delete_manifest("app", "v1").await.unwrap();
+
+    assert!(client.pull_image("app", "v1").await.is_err());
+    assert_eq!(
+        client.pull_image("app", &digest).await.unwrap().digest,
+        digest
+    );
+    assert!(matches!(
+        server.undelete("app", "v1").await,
+        Err(RegistryError::ManifestNotFound(_))
+    ));
+    assert!(matches!(
+        server.delete_manifest("app", "v1").await,
+        Err(RegistryError::ManifestNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_soft_delete_and_undelete() {
+    for config in [RegistryConfig::memory(), RegistryConfig::temp_dir()] {
+        let server = RegistryServer::new(config.with_soft_delete(true))
+            .await
+            .unwrap();
+        let client = RegistryClient::new(server.url());
+        let digest = client
+            .push_image("app", "v1", &[b"layer".to_vec()])
+            .await
+            .unwrap();
+
+        server.delete_manifest("app", "v1").await.unwrap();
+        assert!(client.pull_image("app", "v1").await.is_err());
+        assert_eq!(
+            client.pull_image("app", &digest).await.unwrap().digest,
+            digest
+        );
+
+        server.undelete("app", "v1").await.unwrap();
+        assert_eq!(client.pull_image("app", "v1").await.unwrap().digest, digest);
+    }
+}
+
+#[tokio::test]
+async fn test_gc_purges_tombstones() {
+    let server = RegistryServer::new(RegistryConfig::memory().with_soft_delete(true))
+        .await
+        .unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    server.delete_manifest("app", "v1").await.unwrap();
+    let preview = server.gc_preview().await.unwrap();
+    assert!(preview.tombstones.contains("app:v1"));
+    assert!(preview.manifests.contains(&format!("app@{}", digest)));
+
+    let report = server.collect_garbage().await.unwrap();
+    assert_eq!(report.tombstones, preview.tombstones);
+    assert!(matches!(
+        server.undelete("app", "v1").await,
+        Err(RegistryError::ManifestNotFound(_))
+    ));
+    assert!(client.pull_image("app", &digest).await.is_err());
+    assert!(server.gc_preview().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_maintenance_mode_refuses_writes_only() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let http = reqwest::Client::new();
+
+    server.enter_maintenance_mode(std::time::Duration::from_millis(1500));
+    assert_eq!(
+        server.maintenance_mode(),
+        Some(std::time::Duration::from_millis(1500))
+    );
+    assert_eq!(client.pull_image("app", "v1").await.unwrap().digest, digest);
+    let response = http
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(response.headers()["Retry-After"], "2");
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "UNAVAILABLE");
+    let status: serde_json::Value = http
+        .get(format!("{}/admin/maintenance-mode", server.url()))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["retryAfter"], 2);
+    assert!(client.push_blob("app", b"new".to_vec()).await.is_err());
+
+    server.exit_maintenance_mode();
+    assert!(client.push_blob("app", b"new".to_vec()).await.is_ok());
+
+    let admin = format!("{}/admin/maintenance-mode", server.url());
+    let status: serde_json::Value = http
+        .put(&admin)
+        .json(&serde_json::json!({ "retryAfter": 30 }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        status,
+        serde_json::json!({ "enabled": true, "retryAfter": 30 })
+    );
+    let response = http
+        .put(format!("{}/v2/app/manifests/v2", server.url()))
+        .body("{}")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(response.headers()["Retry-After"], "30");
+
+    http.delete(&admin).send().await.unwrap();
+    let status: serde_json::Value = http.get(&admin).send().await.unwrap().json().await.unwrap();
+    assert_eq!(status["enabled"], false);
+    assert_eq!(server.maintenance_mode(), None);
+}
@@ -0,0 +1,279 @@
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryError, RegistryServer};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Reads the regular files of a ustar archive.
+fn untar(archive: &[u8]) -> BTreeMap<String, Vec<u8>> {
+    assert_eq!(archive.len() % 512, 0);
+    let mut files = BTreeMap::new();
+    let mut offset = 0;
+    while archive[offset..offset + 512].iter().any(|&b| b != 0) {
+        let header = &archive[offset..offset + 512];
+        assert_eq!(&header[257..263], b"ustar\0");
+        let checksum: u64 = header[..148]
+            .iter()
+            .chain(&[b' '; 8])
+            .chain(&header[156..])
+            .map(|&b| u64::from(b))
+            .sum();
+        let field = |range: std::ops::Range<usize>| {
+            let text = std::str::from_utf8(&header[range]).unwrap();
+            u64::from_str_radix(text.trim_end_matches('\0').trim(), 8).unwrap()
+        };
+        assert_eq!(field(148..156), checksum);
+
+        let name = std::str::from_utf8(&header[..100])
+            .unwrap()
+            .trim_end_matches('\0')
+            .to_string();
+        let size = field(124..136) as usize;
+        let start = offset + 512;
+        files.insert(name, archive[start..start + size].to_vec());
+        offset = start + size.div_ceil(512) * 512;
+    }
+    files
+}
+
+#[tokio::test]
+async fn test_export_image_tar() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client
+        .push_image("app", "v1", &[vec![7u8; 1000], b"small".to_vec()])
+        .await
+        .unwrap();
+
+    let mut archive = Vec::new();
+    server
+        .export_image_tar("app", "v1", &mut archive)
+        .await
+        .unwrap();
+    let files = untar(&archive);
+
+    assert_eq!(files["oci-layout"], br#"{"imageLayoutVersion":"1.0.0"}"#);
+    let index: serde_json::Value = serde_json::from_slice(&files["index.json"]).unwrap();
+    let descriptor = &index["manifests"][0];
+    assert_eq!(descriptor["digest"], digest);
+    assert_eq!(
+        descriptor["annotations"]["org.opencontainers.image.ref.name"],
+        "v1"
+    );
+
+    // Manifest, config and two layers, each stored under its digest.
+    let blobs: Vec<_> = files.keys().filter(|k| k.starts_with("blobs/")).collect();
+    assert_eq!(blobs.len(), 4);
+    for path in blobs {
+        let hex = path.strip_prefix("blobs/sha256/").unwrap();
+        assert_eq!(hex::encode(Sha256::digest(&files[path])), hex);
+    }
+    let manifest_path = format!("blobs/sha256/{}", digest.strip_prefix("sha256:").unwrap());
+    assert_eq!(
+        files[&manifest_path].len() as u64,
+        descriptor["size"].as_u64().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_export_incomplete_image_fails() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let missing = format!("sha256:{}", "0".repeat(64));
+    let manifest = serde_json::json!({
+        "schemaVersion": 2,
+        "config": { "mediaType": "application/vnd.oci.image.config.v1+json", "size": 2, "digest": missing },
+        "layers": [],
+    });
+    client
+        .push_manifest(
+            "app",
+            "broken",
+            "application/vnd.oci.image.manifest.v1+json",
+            serde_json::to_vec(&manifest).unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let mut archive = Vec::new();
+    assert!(matches!(
+        server.export_image_tar("app", "broken", &mut archive).await,
+        Err(RegistryError::BlobNotFound(digest)) if digest == missing
+    ));
+    assert!(matches!(
+        server
+            .export_image_tar("app", "missing", &mut archive)
+            .await,
+        Err(RegistryError::ManifestNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_preload_from_registry_tar() {
+    let source = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(source.url());
+    let v1 = client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    client
+        .push_image("tools", "latest", &[b"lint".to_vec()])
+        .await
+        .unwrap();
+    let orphan = client.push_blob("app", b"orphan".to_vec()).await.unwrap();
+
+    // sha512 blob paths are too long for a plain ustar header.
+    let sha512 = {
+        use sha2::Sha512;
+        format!("sha512:{}", hex::encode(Sha512::digest(b"long")))
+    };
+    let http = reqwest::Client::new();
+    let response = http
+        .post(format!("{}/v2/app/blobs/uploads/", source.url()))
+        .send()
+        .await
+        .unwrap();
+    let location = response.headers()["Location"].to_str().unwrap();
+    let response = http
+        .put(format!("{}{}?digest={}", source.url(), location, sha512))
+        .body("long")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+
+    let mut archive = Vec::new();
+    source.export_registry_tar(&mut archive).await.unwrap();
+    let files = untar(&archive);
+    let index: serde_json::Value = serde_json::from_slice(&files["index.json"]).unwrap();
+    assert!(index["manifests"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|descriptor| descriptor["annotations"]["io.containerd.image.name"] == "app:v1"));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("registry.tar");
+    std::fs::write(&path, &archive).unwrap();
+    let copy = RegistryServer::new(RegistryConfig::memory().preload_from_tar(&path))
+        .await
+        .unwrap();
+    let client = RegistryClient::new(copy.url());
+    assert_eq!(client.pull_image("app", "v1").await.unwrap().digest, v1);
+    let manifest = client.pull_manifest("app", &v1).await.unwrap();
+    assert_eq!(
+        format!("sha256:{}", hex::encode(Sha256::digest(&manifest.data))),
+        v1
+    );
+    client.pull_image("tools", "latest").await.unwrap();
+    assert_eq!(client.pull_blob("app", &orphan).await.unwrap(), b"orphan");
+    assert_eq!(client.pull_blob("app", &sha512).await.unwrap(), b"long");
+}
+
+#[tokio::test]
+async fn test_preload_from_invalid_tar_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("registry.tar");
+    std::fs::write(&path, vec![0; 1024]).unwrap();
+    assert!(
+        RegistryServer::new(RegistryConfig::memory().preload_from_tar(&path))
+            .await
+            .is_err()
+    );
+    let missing = dir.path().join("missing.tar");
+    assert!(
+        RegistryServer::new(RegistryConfig::memory().preload_from_tar(missing))
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_repository_archive_over_admin_api() {
+    let source = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(source.url());
+    let digest = client
+        .push_image("app", "v1", &[b"app layer".to_vec()])
+        .await
+        .unwrap();
+    client
+        .push_image("base", "v1", &[b"base layer".to_vec()])
+        .await
+        .unwrap();
+
+    let http = reqwest::Client::new();
+    let response = http
+        .get(format!("{}/admin/repositories/app/archive", source.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "application/x-tar");
+    let archive = response.bytes().await.unwrap();
+    let files = untar(&archive);
+    assert!(!files.contains_key(&format!(
+        "blobs/sha256/{}",
+        hex::encode(Sha256::digest(b"base layer"))
+    )));
+
+    // Imported under another name into a second registry.
+    let target = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let response = http
+        .put(format!(
+            "{}/admin/repositories/fixtures/archive",
+            target.url()
+        ))
+        .body(archive)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["manifests"], 2);
+    let image = RegistryClient::new(target.url())
+        .pull_image("fixtures", "v1")
+        .await
+        .unwrap();
+    assert_eq!(image.digest, digest);
+    assert_eq!(image.layers, [b"app layer".to_vec()]);
+
+    let response = http
+        .get(format!(
+            "{}/admin/repositories/missing/archive",
+            source.url()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_repository_archive_round_trip() {
+    let source = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let digest = RegistryClient::new(source.url())
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let mut archive = Vec::new();
+    source
+        .export_repository_tar("app", &mut archive)
+        .await
+        .unwrap();
+
+    let target = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    assert_eq!(
+        target
+            .import_repository_tar("app", archive.as_slice())
+            .await
+            .unwrap(),
+        2
+    );
+    let image = RegistryClient::new(target.url())
+        .pull_image("app", &digest)
+        .await
+        .unwrap();
+    assert_eq!(image.layers, [b"layer".to_vec()]);
+    assert!(matches!(
+        source.export_repository_tar("missing", Vec::new()).await,
+        Err(RegistryError::RepositoryNotFound(_))
+    ));
+}
@@ -0,0 +1,155 @@
+use registry_testkit::{AuthConfig, RegistryClient, RegistryConfig, RegistryServer};
+use std::time::{Duration, SystemTime};
+
+fn bearer(ttl: Duration) -> RegistryConfig {
+    RegistryConfig::memory().with_auth(
+        AuthConfig::bearer("registry.test")
+            .with_user("ci", "secret")
+            .with_token_ttl(ttl),
+    )
+}
+
+async fn token(server: &RegistryServer, scopes: &[&str]) -> String {
+    let query: String = scopes
+        .iter()
+        .map(|scope| format!("&scope={}", scope))
+        .collect();
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/token?service=registry.test{}",
+            server.url(),
+            query
+        ))
+        .basic_auth("ci", Some("secret"))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = response.json().await.unwrap();
+    body["token"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_introspect_token() {
+    let server = RegistryServer::new(bearer(Duration::from_secs(300)))
+        .await
+        .unwrap();
+    let token = token(
+        &server,
+        &["repository:app:pull,push", "repository:base:pull"],
+    )
+    .await;
+
+    let introspection = server.introspect_token(&token).unwrap();
+    assert!(introspection.active);
+    assert_eq!(introspection.claims.sub, "ci");
+    assert_eq!(
+        introspection.claims.scopes(),
+        ["repository:app:pull,push", "repository:base:pull"]
+    );
+    assert!(introspection.expires_at() > SystemTime::now());
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/token/introspect", server.url()))
+        .form(&[("token", token.as_str())])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["active"], true);
+    assert_eq!(body["sub"], "ci");
+    assert_eq!(body["aud"], "registry.test");
+    assert_eq!(
+        body["scope"],
+        "repository:app:pull,push repository:base:pull"
+    );
+}
+
+#[tokio::test]
+async fn test_introspect_unknown_and_expired_tokens() {
+    let server = RegistryServer::new(bearer(Duration::ZERO)).await.unwrap();
+    let expired = token(&server, &["repository:app:pull"]).await;
+    let introspection = server.introspect_token(&expired).unwrap();
+    assert!(!introspection.active);
+    assert_eq!(introspection.claims.scopes(), ["repository:app:pull"]);
+
+    let other = RegistryServer::new(bearer(Duration::from_secs(300)))
+        .await
+        .unwrap();
+    let foreign = token(&other, &["repository:app:pull"]).await;
+    assert!(server.introspect_token(&foreign).is_none());
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/token/introspect", server.url()))
+        .form(&[("token", foreign.as_str())])
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body, serde_json::json!({ "active": false }));
+
+    let unauthenticated = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    assert!(unauthenticated.introspect_token(&expired).is_none());
+}
+
+#[tokio::test]
+async fn test_audit_log() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audit.jsonl");
+    let config = RegistryConfig::memory().with_auth(
+        AuthConfig::basic()
+            .with_user("ci", "secret")
+            .with_anonymous_pull(true)
+            .with_audit_file(&path),
+    );
+    let server = RegistryServer::new(config).await.unwrap();
+
+    let ci = RegistryClient::new(server.url()).with_credentials("ci", "secret");
+    ci.push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let anonymous = RegistryClient::new(server.url());
+    anonymous.pull_image("app", "v1").await.unwrap();
+    assert!(anonymous
+        .push_image("app", "v2", &[b"layer".to_vec()])
+        .await
+        .is_err());
+
+    let log = server.audit_log();
+    assert!(log.iter().any(|entry| entry.user.as_deref() == Some("ci")
+        && entry.action == "push"
+        && entry.path == "/v2/app/manifests/v1"
+        && entry.status == 201));
+    assert!(log
+        .iter()
+        .any(|entry| entry.user.is_none() && entry.action == "pull" && !entry.denied()));
+    let denied: Vec<_> = log.iter().filter(|entry| entry.denied()).collect();
+    assert!(!denied.is_empty());
+    assert!(denied
+        .iter()
+        .all(|entry| entry.user.is_none() && entry.action == "push"));
+    assert!(log.iter().all(|entry| entry.repository == "app"));
+
+    let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), log.len());
+    for (line, entry) in lines.iter().zip(&log) {
+        assert_eq!(line["user"].as_str(), entry.user.as_deref());
+        assert_eq!(line["path"], entry.path);
+        assert_eq!(line["status"], entry.status);
+    }
+}
+
+#[tokio::test]
+async fn test_no_audit_log_without_auth() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    assert!(server.audit_log().is_empty());
+}
@@ -0,0 +1,26 @@
+use registry_testkit::bench::{BenchSuite, Workload};
+
+#[tokio::test]
+async fn test_bench_suite_compares_default_backends() {
+    let results = BenchSuite::with_default_backends()
+        .await
+        .unwrap()
+        .with_workload(Workload::GetBlobs {
+            count: 4,
+            size: 1024,
+        })
+        .with_workload(Workload::Concurrent {
+            tasks: 4,
+            ops_per_task: 2,
+            size: 512,
+        })
+        .run()
+        .await
+        .unwrap();
+
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].backend, "memory");
+    assert_eq!(results[1].backend, "disk");
+    assert_eq!(results[2].operations, 16);
+    assert!(results.iter().all(|r| r.ops_per_sec() > 0.0));
+}
@@ -0,0 +1,31 @@
+use registry_testkit::{
+    CaptureConfig, DigestPolicy, RegistryClient, RegistryConfig, RegistryServer,
+};
+
+#[tokio::test]
+async fn test_blobs_exist_batch() {
+    for config in [RegistryConfig::memory(), RegistryConfig::temp_dir()] {
+        let config = config
+            .with_capture(CaptureConfig::new())
+            .with_digest_policy(DigestPolicy::new());
+        let server = RegistryServer::new(config).await.unwrap();
+        let client = RegistryClient::new(server.url());
+        let mut digests = Vec::new();
+        for layer in [&b"one"[..], b"two", b"three"] {
+            digests.push(client.push_blob("app", layer.to_vec()).await.unwrap());
+        }
+        let missing =
+            "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".to_string();
+        digests.insert(1, missing);
+        digests.push(digests[0].to_uppercase().replacen("SHA256", "sha256", 1));
+        server.clear_captured();
+
+        let exist = client.blobs_exist("app", &digests).await.unwrap();
+        assert_eq!(exist, [true, false, true, true, true]);
+
+        let captured = server.captured();
+        assert_eq!(captured.len(), 1);
+        assert_eq!(captured[0].request.method, "POST");
+        assert_eq!(captured[0].request.uri, "/v2/app/blobs/_exists");
+    }
+}
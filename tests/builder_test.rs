@@ -0,0 +1,214 @@
+use registry_testkit::builder::{
+    ESTARGZ_TOC_DIGEST_ANNOTATION, OCI_LAYER_GZIP_MEDIA_TYPE, OCI_LAYER_ZSTD_MEDIA_TYPE,
+    ZSTD_CHUNKED_MANIFEST_POSITION_ANNOTATION,
+};
+use registry_testkit::{ImageBuilder, Layer, RegistryClient, RegistryConfig, RegistryServer};
+
+#[tokio::test]
+async fn test_image_builder_lazy_pull_layers() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let toc = format!("sha256:{}", "a".repeat(64));
+    let image = ImageBuilder::new()
+        .platform("linux", "arm64")
+        .variant("v8")
+        .env("MODE=prod")
+        .entrypoint(["/app"])
+        .label("team", "web")
+        .layer(Layer::estargz(b"stargz".to_vec(), &toc, 4096))
+        .layer(Layer::zstd_chunked(
+            b"chunked".to_vec(),
+            "sha256:abc",
+            "100:20:40:1",
+        ))
+        .layer(Layer::zstd(b"plain".to_vec()))
+        .build();
+    let digest = image.push(&client, "app", "v1").await.unwrap();
+    assert_eq!(digest, image.digest);
+
+    let inspect = server.inspect_image("app", "v1").await.unwrap();
+    assert_eq!(inspect.platform.architecture, "arm64");
+    assert_eq!(inspect.platform.variant.as_deref(), Some("v8"));
+    assert_eq!(inspect.env, ["MODE=prod"]);
+    assert_eq!(inspect.entrypoint, ["/app"]);
+    assert_eq!(inspect.labels["team"], "web");
+
+    let media_types: Vec<_> = inspect
+        .layers
+        .iter()
+        .map(|l| l.media_type.as_str())
+        .collect();
+    assert_eq!(
+        media_types,
+        [
+            OCI_LAYER_GZIP_MEDIA_TYPE,
+            OCI_LAYER_ZSTD_MEDIA_TYPE,
+            OCI_LAYER_ZSTD_MEDIA_TYPE
+        ]
+    );
+    assert_eq!(
+        inspect.layers[0].annotations[ESTARGZ_TOC_DIGEST_ANNOTATION],
+        toc
+    );
+    assert_eq!(
+        inspect.layers[1].annotations[ZSTD_CHUNKED_MANIFEST_POSITION_ANNOTATION],
+        "100:20:40:1"
+    );
+    assert!(inspect.layers[2].annotations.is_empty());
+
+    let pulled = client.pull_image("app", "v1").await.unwrap();
+    assert_eq!(pulled.layers[1], b"chunked");
+    assert!(server
+        .verify_image("app", "v1")
+        .await
+        .unwrap()
+        .is_complete());
+}
+
+#[tokio::test]
+async fn test_blob_range_requests() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client
+        .push_blob("app", b"0123456789".to_vec())
+        .await
+        .unwrap();
+    let url = format!("{}/v2/app/blobs/{}", server.url(), digest);
+    let http = reqwest::Client::new();
+    let range = |value: &'static str| http.get(&url).header("Range", value).send();
+
+    let response = http.head(&url).send().await.unwrap();
+    assert_eq!(response.headers()["Accept-Ranges"], "bytes");
+
+    let response = range("bytes=2-5").await.unwrap();
+    assert_eq!(response.status(), 206);
+    assert_eq!(response.headers()["Content-Range"], "bytes 2-5/10");
+    assert_eq!(response.bytes().await.unwrap().as_ref(), b"2345");
+
+    // Footers (eStargz TOC, zstd:chunked manifest) are read as suffixes.
+    let response = range("bytes=-3").await.unwrap();
+    assert_eq!(response.headers()["Content-Range"], "bytes 7-9/10");
+    assert_eq!(response.bytes().await.unwrap().as_ref(), b"789");
+
+    let response = range("bytes=8-").await.unwrap();
+    assert_eq!(response.bytes().await.unwrap().as_ref(), b"89");
+
+    let response = range("bytes=4-100").await.unwrap();
+    assert_eq!(response.headers()["Content-Range"], "bytes 4-9/10");
+
+    let response = range("bytes=10-").await.unwrap();
+    assert_eq!(response.status(), 416);
+    assert_eq!(response.headers()["Content-Range"], "bytes */10");
+
+    let response = range("bytes=0-1,4-5").await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.bytes().await.unwrap().len(), 10);
+}
+
+#[tokio::test]
+async fn test_index_builder_multi_arch() {
+    use registry_testkit::oci::manifest::{Manifest, ValidationLevel, OCI_INDEX_MEDIA_TYPE};
+    use registry_testkit::IndexBuilder;
+
+    let config = RegistryConfig::memory().with_manifest_validation(ValidationLevel::Full);
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let index = IndexBuilder::new()
+        .image(ImageBuilder::new().layer(Layer::new(b"amd64".to_vec())))
+        .image(
+            ImageBuilder::new()
+                .platform("linux", "arm64")
+                .variant("v8")
+                .layer(Layer::new(b"arm64".to_vec())),
+        )
+        .image(
+            ImageBuilder::new()
+                .platform("windows", "amd64")
+                .os_version("10.0.20348.2113")
+                .layer(Layer::new(b"windows".to_vec())),
+        )
+        .build();
+    let digest = index.push(&client, "app", "v1").await.unwrap();
+    assert_eq!(digest, index.digest);
+
+    let pulled = client.pull_manifest("app", "v1").await.unwrap();
+    assert_eq!(pulled.content_type, OCI_INDEX_MEDIA_TYPE);
+    let Manifest::Index(parsed) = Manifest::parse(&pulled.content_type, &pulled.data).unwrap()
+    else {
+        panic!("expected an index");
+    };
+    let platforms: Vec<_> = parsed
+        .manifests
+        .iter()
+        .map(|m| {
+            let platform = m.platform.as_ref().unwrap();
+            (
+                platform.os.as_str(),
+                platform.architecture.as_str(),
+                platform.variant.as_deref(),
+                platform.os_version.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        platforms,
+        [
+            ("linux", "amd64", None, None),
+            ("linux", "arm64", Some("v8"), None),
+            ("windows", "amd64", None, Some("10.0.20348.2113")),
+        ]
+    );
+
+    let arm64 = index.image("linux", "arm64").unwrap();
+    let image = client.pull_image("app", &arm64.digest).await.unwrap();
+    assert_eq!(image.layers, [b"arm64".to_vec()]);
+    let inspect = server.inspect_image("app", &arm64.digest).await.unwrap();
+    assert_eq!(inspect.platform.variant.as_deref(), Some("v8"));
+}
+
+#[tokio::test]
+async fn test_artifact_builder_with_subject() {
+    use registry_testkit::builder::OCI_EMPTY_MEDIA_TYPE;
+    use registry_testkit::oci::manifest::Manifest;
+    use registry_testkit::ArtifactBuilder;
+
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let image = ImageBuilder::new()
+        .layer(Layer::new(b"app".to_vec()))
+        .build();
+    image.push(&client, "app", "v1").await.unwrap();
+
+    let artifact = ArtifactBuilder::new("application/vnd.example.sbom")
+        .blob(Layer::with_media_type(
+            b"{\"spdxVersion\":\"SPDX-2.3\"}".to_vec(),
+            "application/spdx+json",
+        ))
+        .annotation("org.example.tool", "scanner")
+        .subject(image.descriptor())
+        .build();
+    let digest = artifact
+        .push(&client, "app", &artifact.digest)
+        .await
+        .unwrap();
+
+    let pulled = client.pull_manifest("app", &digest).await.unwrap();
+    let Manifest::Image(manifest) = Manifest::parse(&pulled.content_type, &pulled.data).unwrap()
+    else {
+        panic!("expected an image manifest");
+    };
+    assert_eq!(
+        manifest.artifact_type.as_deref(),
+        Some("application/vnd.example.sbom")
+    );
+    assert_eq!(manifest.config.media_type, OCI_EMPTY_MEDIA_TYPE);
+    assert_eq!(manifest.layers[0].media_type, "application/spdx+json");
+    assert_eq!(manifest.annotations["org.example.tool"], "scanner");
+    let subject = manifest.subject.unwrap();
+    assert_eq!(subject.digest, image.digest);
+    assert_eq!(subject.size, image.manifest.len() as u64);
+    assert!(client
+        .blob_exists("app", &manifest.config.digest)
+        .await
+        .unwrap());
+}
@@ -0,0 +1,66 @@
+use registry_testkit::capture::{CapturedBody, REDACTED};
+use registry_testkit::warnings::WarningEndpoint;
+use registry_testkit::{AuthConfig, CaptureConfig, RegistryClient, RegistryConfig, RegistryServer};
+
+#[tokio::test]
+async fn test_capture_filters_and_redaction() {
+    let config = RegistryConfig::memory()
+        .with_auth(AuthConfig::basic().with_user("ci", "secret"))
+        .with_capture(
+            CaptureConfig::new()
+                .for_repository("app*")
+                .for_endpoint(WarningEndpoint::Manifests)
+                .for_endpoint(WarningEndpoint::Blobs)
+                .with_max_body_size(16),
+        );
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url()).with_credentials("ci", "secret");
+    let digest = client
+        .push_image("app", "v1", &[vec![7u8; 1024]])
+        .await
+        .unwrap();
+    client
+        .push_image("other", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&client.pull_manifest("app", &digest).await.unwrap().data).unwrap();
+    let layer = manifest["layers"][0]["digest"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    server.clear_captured();
+    assert!(server.captured().is_empty());
+
+    client.pull_manifest("app", "v1").await.unwrap();
+    client.pull_blob("app", &layer).await.unwrap();
+    client.pull_manifest("other", "v1").await.unwrap();
+
+    let captured = server.captured();
+    let uris: Vec<_> = captured.iter().map(|e| e.request.uri.as_str()).collect();
+    assert_eq!(uris.len(), 2, "{:?}", uris);
+    assert!(uris[0].starts_with("/v2/app/manifests/"));
+    assert!(uris[1].starts_with("/v2/app/blobs/"));
+
+    let authorization = captured[0]
+        .request
+        .headers
+        .iter()
+        .find(|(name, _)| name == "authorization")
+        .map(|(_, value)| value.as_str());
+    assert_eq!(authorization, Some(REDACTED));
+    assert_eq!(
+        captured[1].response.body,
+        CapturedBody::Omitted { size: Some(1024) }
+    );
+
+    let har = server.captured_har();
+    let entries = har["log"]["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["request"]["method"], "GET");
+    assert!(entries[0]["request"]["url"]
+        .as_str()
+        .unwrap()
+        .starts_with(&server.url()));
+    assert_eq!(entries[1]["response"]["bodySize"], 1024);
+}
@@ -0,0 +1,105 @@
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+
+async fn push_repositories(server: &RegistryServer) {
+    let client = RegistryClient::new(server.url());
+    for name in ["team-web", "team-api", "infra-db", "web-legacy"] {
+        client
+            .push_image(name, "v1", &[name.as_bytes().to_vec()])
+            .await
+            .unwrap();
+    }
+}
+
+async fn catalog(server: &RegistryServer, query: &str) -> (u16, serde_json::Value) {
+    let response = reqwest::get(format!("{}/v2/_catalog{}", server.url(), query))
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    (status, response.json().await.unwrap())
+}
+
+#[tokio::test]
+async fn test_catalog_lists_repositories() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    push_repositories(&server).await;
+
+    let (status, body) = catalog(&server, "?prefix=team&sort=desc").await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        body["repositories"],
+        serde_json::json!(["infra-db", "team-api", "team-web", "web-legacy"])
+    );
+}
+
+#[tokio::test]
+async fn test_catalog_extensions() {
+    let config = RegistryConfig::memory().with_catalog_extensions(true);
+    let server = RegistryServer::new(config).await.unwrap();
+    push_repositories(&server).await;
+
+    let (_, body) = catalog(&server, "?prefix=team-").await;
+    assert_eq!(
+        body["repositories"],
+        serde_json::json!(["team-api", "team-web"])
+    );
+
+    let (_, body) = catalog(&server, "?name=web").await;
+    assert_eq!(
+        body["repositories"],
+        serde_json::json!(["team-web", "web-legacy"])
+    );
+
+    let (_, body) = catalog(&server, "?name=web&sort=desc").await;
+    assert_eq!(
+        body["repositories"],
+        serde_json::json!(["web-legacy", "team-web"])
+    );
+
+    let (status, body) = catalog(&server, "?sort=sideways").await;
+    assert_eq!(status, 400);
+    assert_eq!(body["errors"][0]["code"], "UNSUPPORTED");
+}
+
+#[tokio::test]
+async fn test_catalog_pagination() {
+    for config in [RegistryConfig::memory(), RegistryConfig::temp_dir()] {
+        let server = RegistryServer::new(config).await.unwrap();
+        push_repositories(&server).await;
+
+        let response = reqwest::get(format!("{}/v2/_catalog?n=2", server.url()))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()["link"],
+            "</v2/_catalog?n=2&last=team-api>; rel=\"next\""
+        );
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(
+            body["repositories"],
+            serde_json::json!(["infra-db", "team-api"])
+        );
+
+        let (_, body) = catalog(&server, "?n=2&last=team-api").await;
+        assert_eq!(
+            body["repositories"],
+            serde_json::json!(["team-web", "web-legacy"])
+        );
+
+        let (status, body) = catalog(&server, "?n=-1").await;
+        assert_eq!(status, 400);
+        assert_eq!(body["errors"][0]["code"], "PAGINATION_NUMBER_INVALID");
+    }
+}
+
+#[tokio::test]
+async fn test_catalog_pagination_follows_sort_order() {
+    let config = RegistryConfig::memory().with_catalog_extensions(true);
+    let server = RegistryServer::new(config).await.unwrap();
+    push_repositories(&server).await;
+
+    let (_, body) = catalog(&server, "?sort=desc&n=2&last=team-web").await;
+    assert_eq!(
+        body["repositories"],
+        serde_json::json!(["team-api", "infra-db"])
+    );
+}
@@ -0,0 +1,77 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use registry_testkit::clock::http_date;
+use registry_testkit::{AuthConfig, ClockSkew, RegistryClient, RegistryConfig, RegistryServer};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[test]
+fn test_http_date() {
+    assert_eq!(
+        http_date(UNIX_EPOCH + Duration::from_secs(784_111_777)),
+        "Sun, 06 Nov 1994 08:49:37 GMT"
+    );
+    assert_eq!(http_date(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 GMT");
+}
+
+/// Whether `date` is within a second of `expected`.
+fn close_to(date: &str, expected: SystemTime) -> bool {
+    (0..=2).any(|offset| {
+        let candidate = expected - Duration::from_secs(1) + Duration::from_secs(offset);
+        http_date(candidate) == date
+    })
+}
+
+#[tokio::test]
+async fn test_skewed_date_header() {
+    let skew = ClockSkew::Ahead(Duration::from_secs(3600));
+    let server = RegistryServer::new(RegistryConfig::memory().with_clock_skew(skew))
+        .await
+        .unwrap();
+    let response = reqwest::get(format!("{}/v2/", server.url())).await.unwrap();
+    let date = response.headers()["Date"].to_str().unwrap();
+    assert!(close_to(date, skew.now()), "{}", date);
+    assert!(!close_to(date, SystemTime::now()), "{}", date);
+}
+
+#[tokio::test]
+async fn test_skewed_token_claims() {
+    let config = RegistryConfig::memory()
+        .with_auth(
+            AuthConfig::bearer("registry.test")
+                .with_user("ci", "secret")
+                .with_token_ttl(Duration::from_secs(300)),
+        )
+        .with_clock_skew(ClockSkew::Behind(Duration::from_secs(600)));
+    let server = RegistryServer::new(config).await.unwrap();
+
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/token?service=registry.test&scope=repository:app:pull,push",
+            server.url()
+        ))
+        .basic_auth("ci", Some("secret"))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = response.json().await.unwrap();
+    let token = body["token"].as_str().unwrap();
+    let payload = token.split('.').nth(1).unwrap();
+    let claims: serde_json::Value =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap();
+
+    // Already expired by the client's clock...
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let exp = claims["exp"].as_u64().unwrap();
+    assert!(exp < now, "exp {} is not before {}", exp, now);
+    assert_eq!(exp - claims["iat"].as_u64().unwrap(), 300);
+
+    // ...but still accepted by the registry, whose clock agrees with it.
+    let client = RegistryClient::new(server.url()).with_credentials("ci", "secret");
+    client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+}
@@ -0,0 +1,108 @@
+use registry_testkit::{
+    AuthConfig, ContentEncoding, RegistryClient, RegistryConfig, RegistryServer,
+};
+
+async fn get(url: &str, accept_encoding: Option<&str>) -> reqwest::Response {
+    let mut request = reqwest::Client::new().get(url);
+    if let Some(accept_encoding) = accept_encoding {
+        request = request.header("Accept-Encoding", accept_encoding);
+    }
+    request.send().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_manifest_responses_are_compressed() {
+    let config = RegistryConfig::memory()
+        .with_response_compression([ContentEncoding::Zstd, ContentEncoding::Gzip]);
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let manifest = client.pull_manifest("app", "v1").await.unwrap().data;
+    let url = format!("{}/v2/app/manifests/v1", server.url());
+
+    for (accept, encoding) in [
+        ("gzip, deflate", Some(ContentEncoding::Gzip)),
+        ("gzip;q=0.5, zstd", Some(ContentEncoding::Zstd)),
+        ("zstd;q=0, gzip", Some(ContentEncoding::Gzip)),
+        ("gzip, zstd", Some(ContentEncoding::Zstd)),
+        ("br", None),
+        ("identity", None),
+    ] {
+        let response = get(&url, Some(accept)).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["Vary"], "accept-encoding");
+        let content_encoding = response
+            .headers()
+            .get("Content-Encoding")
+            .map(|v| v.to_str().unwrap().to_string());
+        let body = response.bytes().await.unwrap();
+        match encoding {
+            Some(encoding) => {
+                assert_eq!(
+                    content_encoding.as_deref(),
+                    Some(encoding.as_str()),
+                    "{}",
+                    accept
+                );
+                assert_eq!(encoding.decode(&body).unwrap(), manifest);
+            }
+            None => {
+                assert_eq!(content_encoding, None, "{}", accept);
+                assert_eq!(body, manifest);
+            }
+        }
+    }
+
+    // Without Accept-Encoding, and for blobs and HEAD, nothing changes.
+    let response = get(&url, None).await;
+    assert!(response.headers().get("Content-Encoding").is_none());
+    assert_eq!(response.bytes().await.unwrap(), manifest);
+    let blob = client.push_blob("app", b"blob".to_vec()).await.unwrap();
+    let response = get(
+        &format!("{}/v2/app/blobs/{}", server.url(), blob),
+        Some("gzip"),
+    )
+    .await;
+    assert!(response.headers().get("Content-Encoding").is_none());
+    let response = reqwest::Client::new()
+        .head(&url)
+        .header("Accept-Encoding", "gzip")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.headers().get("Content-Encoding").is_none());
+    assert_eq!(
+        response.headers()["Content-Length"],
+        manifest.len().to_string().as_str()
+    );
+    for encoding in [ContentEncoding::Gzip, ContentEncoding::Zstd] {
+        let data = manifest.repeat(16);
+        let encoded = encoding.encode(&data);
+        assert!(encoded.len() < data.len() / 4, "{}", encoding.as_str());
+        assert_eq!(encoding.decode(&encoded).unwrap(), data);
+    }
+}
+
+#[tokio::test]
+async fn test_token_responses_are_compressed() {
+    let config = RegistryConfig::memory()
+        .with_auth(AuthConfig::bearer("registry.test").with_anonymous_pull(true))
+        .with_response_compression([ContentEncoding::Gzip]);
+    let server = RegistryServer::new(config).await.unwrap();
+
+    let url = format!(
+        "{}/token?service=registry.test&scope=repository:app:pull",
+        server.url()
+    );
+    let response = get(&url, Some("gzip")).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["Content-Encoding"], "gzip");
+    let body = ContentEncoding::Gzip
+        .decode(&response.bytes().await.unwrap())
+        .unwrap();
+    let token: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(token["token"].is_string());
+}
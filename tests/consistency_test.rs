@@ -0,0 +1,43 @@
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer, Visibility};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_manual_visibility_requires_flush() {
+    let config = RegistryConfig::memory().with_visibility(Visibility::Manual);
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let digest = client.push_blob("app", b"lagging".to_vec()).await.unwrap();
+    assert!(!client.blob_exists("app", &digest).await.unwrap());
+
+    server.flush().await;
+    assert!(client.blob_exists("app", &digest).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_delayed_visibility_serves_previous_manifest() {
+    let config =
+        RegistryConfig::memory().with_visibility(Visibility::Delayed(Duration::from_millis(300)));
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let first = client
+        .push_manifest("app", "latest", "application/json", b"{\"v\":1}".to_vec())
+        .await
+        .unwrap();
+    assert!(client.pull_manifest("app", "latest").await.is_err());
+
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    client
+        .push_manifest("app", "latest", "application/json", b"{\"v\":2}".to_vec())
+        .await
+        .unwrap();
+
+    let served = client.pull_manifest("app", "latest").await.unwrap();
+    assert_eq!(served.data, b"{\"v\":1}");
+    assert!(client.pull_manifest("app", &first).await.is_ok());
+
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    let served = client.pull_manifest("app", "latest").await.unwrap();
+    assert_eq!(served.data, b"{\"v\":2}");
+}
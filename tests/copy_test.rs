@@ -0,0 +1,137 @@
+use registry_testkit::builder::BuiltIndex;
+use registry_testkit::copy::{copy_image, PlatformFilter};
+use registry_testkit::{
+    ImageBuilder, IndexBuilder, Layer, RegistryClient, RegistryConfig, RegistryServer,
+    UpstreamConfig,
+};
+
+fn multi_platform_index() -> BuiltIndex {
+    IndexBuilder::new()
+        .image(ImageBuilder::new().layer(Layer::new(b"amd64".to_vec())))
+        .image(
+            ImageBuilder::new()
+                .platform("linux", "arm64")
+                .variant("v8")
+                .layer(Layer::new(b"arm64".to_vec())),
+        )
+        .image(
+            ImageBuilder::new()
+                .platform("windows", "amd64")
+                .layer(Layer::new(b"windows".to_vec())),
+        )
+        .build()
+}
+
+fn platforms(manifest: &[u8]) -> Vec<String> {
+    let index: serde_json::Value = serde_json::from_slice(manifest).unwrap();
+    index["manifests"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            format!(
+                "{}/{}",
+                entry["platform"]["os"].as_str().unwrap(),
+                entry["platform"]["architecture"].as_str().unwrap()
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_platform_filter_parsing() {
+    assert!(PlatformFilter::all().is_all());
+    let filter = PlatformFilter::only(["linux/amd64", "linux/arm64/v8"]).unwrap();
+    assert_eq!(filter.to_string(), "linux/amd64,linux/arm64/v8");
+    assert!(PlatformFilter::only(["linux/arm/v7/extra"]).is_err());
+    assert!(PlatformFilter::only([""]).is_err());
+}
+
+#[tokio::test]
+async fn test_copy_keeps_selected_platforms() {
+    let source = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let target = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let source_client = RegistryClient::new(source.url());
+    let target_client = RegistryClient::new(target.url());
+    let index = multi_platform_index();
+    index.push(&source_client, "app", "v1").await.unwrap();
+    let reference = source.reference("app:v1").unwrap();
+
+    // Everything is copied as is without a filter.
+    let digest = copy_image(
+        &source_client,
+        &reference,
+        &target_client,
+        &reference.clone().with_tag("all"),
+        &PlatformFilter::all(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(digest, index.digest);
+    assert!(target
+        .verify_image("app", "all")
+        .await
+        .unwrap()
+        .is_complete());
+
+    let target = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let target_client = RegistryClient::new(target.url());
+    let filter = PlatformFilter::only(["linux"]).unwrap();
+    let digest = copy_image(
+        &source_client,
+        &reference,
+        &target_client,
+        &reference,
+        &filter,
+    )
+    .await
+    .unwrap();
+    assert_ne!(digest, index.digest);
+
+    let copied = target_client.pull_manifest("app", "v1").await.unwrap();
+    assert_eq!(platforms(&copied.data), ["linux/amd64", "linux/arm64"]);
+    assert!(target
+        .verify_image("app", "v1")
+        .await
+        .unwrap()
+        .is_complete());
+    let windows = &index.images[2];
+    assert!(target_client
+        .pull_manifest("app", &windows.digest)
+        .await
+        .is_err());
+    assert!(!target_client
+        .blob_exists("app", &windows.layers[0].descriptor().digest)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_upstream_narrows_indexes_fetched_by_tag() {
+    let origin = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let index = multi_platform_index();
+    index
+        .push(&RegistryClient::new(origin.url()), "app", "v1")
+        .await
+        .unwrap();
+
+    let upstream = UpstreamConfig::new(origin.url())
+        .with_cache(true)
+        .with_platforms(PlatformFilter::only(["linux/amd64"]).unwrap());
+    let server = RegistryServer::new(RegistryConfig::memory().with_upstream(upstream))
+        .await
+        .unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let narrowed = client.pull_manifest("app", "v1").await.unwrap();
+    assert_eq!(platforms(&narrowed.data), ["linux/amd64"]);
+    let image = client
+        .pull_image("app", &index.images[0].digest)
+        .await
+        .unwrap();
+    assert_eq!(image.layers, [b"amd64".to_vec()]);
+
+    // The original index stays reachable by its digest.
+    let original = client.pull_manifest("app", &index.digest).await.unwrap();
+    assert_eq!(original.data, index.manifest);
+}
@@ -0,0 +1,173 @@
+use registry_testkit::{RegistryConfig, RegistryServer};
+
+async fn start_upload(client: &reqwest::Client, url: &str) -> String {
+    let response = client
+        .post(format!("{}/v2/app/blobs/uploads/", url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    let location = response.headers()["Location"].to_str().unwrap();
+    format!("{}{}", url, location)
+}
+
+#[tokio::test]
+async fn test_disk_upload_resumes_after_restart() {
+    let mut server = RegistryServer::new(RegistryConfig::temp_dir())
+        .await
+        .unwrap();
+    let client = reqwest::Client::new();
+    let upload_url = start_upload(&client, &server.url()).await;
+
+    let response = client
+        .patch(&upload_url)
+        .body("hello ")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+
+    server.simulate_crash().await.unwrap();
+    assert!(!server.is_running());
+    assert!(client
+        .get(format!("{}/v2/", server.url()))
+        .send()
+        .await
+        .is_err());
+
+    server.restart().await.unwrap();
+    assert!(server.is_running());
+
+    let digest = "sha256:b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+    let response = client
+        .put(format!("{}?digest={}", upload_url, digest))
+        .body("world")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+
+    let blob = client
+        .get(format!("{}/v2/app/blobs/{}", server.url(), digest))
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    assert_eq!(&blob[..], b"hello world");
+}
+
+#[tokio::test]
+async fn test_memory_upload_sessions_lost_on_crash() {
+    let mut server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = reqwest::Client::new();
+    let upload_url = start_upload(&client, &server.url()).await;
+
+    server.simulate_crash().await.unwrap();
+    server.restart().await.unwrap();
+
+    let response = client.patch(&upload_url).body("data").send().await.unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_disk_upload_resumes_in_new_process() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = RegistryConfig::directory(dir.path().to_path_buf());
+    let client = reqwest::Client::new();
+
+    let server = RegistryServer::new(config.clone()).await.unwrap();
+    let upload_url = start_upload(&client, &server.url()).await;
+    let path = upload_url.split_once("/v2/").unwrap().1.to_string();
+    let uuid = path.rsplit('/').next().unwrap().to_string();
+    let response = client
+        .patch(&upload_url)
+        .header("Content-Range", "0-5")
+        .body("hello ")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    drop(server);
+
+    // A chunk that was being written when the process died.
+    let data = dir.path().join("uploads").join(&uuid);
+    let mut torn = std::fs::read(&data).unwrap();
+    torn.extend_from_slice(b"wor");
+    std::fs::write(&data, torn).unwrap();
+
+    let server = RegistryServer::new(config.clone()).await.unwrap();
+    let upload_url = format!("{}/v2/{}", server.url(), path);
+    let response = client.get(&upload_url).send().await.unwrap();
+    assert_eq!(response.status(), 204);
+    assert_eq!(response.headers()["Range"], "0-5");
+    let response = client
+        .patch(&upload_url)
+        .header("Content-Range", "6-10")
+        .body("world")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+
+    let digest = "sha256:b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+    let response = client
+        .put(format!("{}?digest={}", upload_url, digest))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    assert!(!data.exists());
+}
+
+#[tokio::test]
+async fn test_disk_upload_refuses_corrupted_session() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = RegistryConfig::directory(dir.path().to_path_buf());
+    let client = reqwest::Client::new();
+
+    let server = RegistryServer::new(config.clone()).await.unwrap();
+    let upload_url = start_upload(&client, &server.url()).await;
+    let uuid = upload_url.rsplit('/').next().unwrap().to_string();
+    client
+        .patch(&upload_url)
+        .body("hello ")
+        .send()
+        .await
+        .unwrap();
+    drop(server);
+
+    std::fs::write(dir.path().join("uploads").join(&uuid), "HELLO ").unwrap();
+
+    let server = RegistryServer::new(config).await.unwrap();
+    let upload_url = format!("{}/v2/app/blobs/uploads/{}", server.url(), uuid);
+    let response = client
+        .patch(&upload_url)
+        .body("world")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 500);
+}
+
+#[tokio::test]
+async fn test_disk_upload_cancelled_in_new_process() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = RegistryConfig::directory(dir.path().to_path_buf());
+    let client = reqwest::Client::new();
+
+    let server = RegistryServer::new(config.clone()).await.unwrap();
+    let upload_url = start_upload(&client, &server.url()).await;
+    let path = upload_url.split_once("/v2/").unwrap().1.to_string();
+    drop(server);
+
+    let server = RegistryServer::new(config).await.unwrap();
+    let upload_url = format!("{}/v2/{}", server.url(), path);
+    let response = client.delete(&upload_url).send().await.unwrap();
+    assert_eq!(response.status(), 204);
+    assert_eq!(server.upload_stats().await.cancelled, 1);
+    let response = client.delete(&upload_url).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+    assert_eq!(server.upload_stats().await.cancelled, 1);
+}
@@ -0,0 +1,37 @@
+use registry_testkit::{DedupStats, RegistryClient, RegistryConfig, RegistryServer};
+
+#[tokio::test]
+async fn test_dedup_stats() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    client.push_blob("app", b"base".to_vec()).await.unwrap();
+    client.push_blob("app", b"base".to_vec()).await.unwrap();
+    client.push_blob("app", b"change".to_vec()).await.unwrap();
+    client.push_blob("tools", b"base".to_vec()).await.unwrap();
+    let digest = client.push_blob("app", b"shared".to_vec()).await.unwrap();
+    assert!(client.mount_blob("tools", &digest, "app").await.unwrap());
+
+    let report = server.dedup_stats();
+    assert_eq!(
+        report.repository("app"),
+        DedupStats {
+            pushed: 4,
+            duplicates: 1,
+            duplicate_bytes: 4,
+        }
+    );
+    assert_eq!(
+        report.repository("tools"),
+        DedupStats {
+            pushed: 2,
+            duplicates: 2,
+            duplicate_bytes: 4,
+        }
+    );
+    assert_eq!(report.total.pushed, 6);
+    assert_eq!(report.total.duplicates, 3);
+    assert_eq!(report.total.hit_ratio(), 0.5);
+    assert_eq!(report.repository("other"), DedupStats::default());
+    assert_eq!(DedupStats::default().hit_ratio(), 0.0);
+}
@@ -0,0 +1,166 @@
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+
+#[tokio::test]
+async fn test_delete_manifests_and_blobs() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let layer = client.push_blob("app", b"orphan".to_vec()).await.unwrap();
+    let http = reqwest::Client::new();
+    let url = |path: String| format!("{}{}", server.url(), path);
+
+    // Deleting the tag keeps the manifest pullable by digest.
+    let response = http
+        .delete(url("/v2/app/manifests/v1".to_string()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    assert!(client.pull_image("app", "v1").await.is_err());
+    client.pull_image("app", &digest).await.unwrap();
+
+    let response = http
+        .delete(url(format!("/v2/app/manifests/{}", digest)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    let response = http
+        .get(url(format!("/v2/app/manifests/{}", digest)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let response = http
+        .delete(url(format!("/v2/app/manifests/{}", digest)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "MANIFEST_UNKNOWN");
+
+    let response = http
+        .delete(url(format!("/v2/app/blobs/{}", layer)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    assert!(!client.blob_exists("app", &layer).await.unwrap());
+    let response = http
+        .delete(url(format!("/v2/app/blobs/{}", layer)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "BLOB_UNKNOWN");
+}
+
+#[tokio::test]
+async fn test_deletes_disabled() {
+    let server = RegistryServer::new(RegistryConfig::memory().with_deletes_enabled(false))
+        .await
+        .unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    let layer = client.push_blob("app", b"orphan".to_vec()).await.unwrap();
+    let http = reqwest::Client::new();
+
+    let response = http
+        .delete(format!("{}/v2/app/manifests/{}", server.url(), digest))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 405);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "UNSUPPORTED");
+    client.pull_image("app", &digest).await.unwrap();
+
+    let response = http
+        .delete(format!("{}/v2/app/blobs/{}", server.url(), layer))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 405);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "UNSUPPORTED");
+    assert!(client.blob_exists("app", &layer).await.unwrap());
+
+    // Cancelling an upload is not a delete of registry content.
+    let response = http
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    let location = response.headers()["location"].to_str().unwrap().to_string();
+    let response = http
+        .delete(format!("{}{}", server.url(), location))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+    server.assert_no_leaked_uploads().await;
+}
+
+#[tokio::test]
+async fn test_blob_delete_refused_while_referenced_elsewhere() {
+    let server = RegistryServer::new(RegistryConfig::memory().with_soft_delete(true))
+        .await
+        .unwrap();
+    let client = RegistryClient::new(server.url());
+    client
+        .push_image("app", "v1", &[b"shared".to_vec()])
+        .await
+        .unwrap();
+    let other = client
+        .push_image("other", "v1", &[b"shared".to_vec()])
+        .await
+        .unwrap();
+    let layer = client.push_blob("app", b"shared".to_vec()).await.unwrap();
+    let http = reqwest::Client::new();
+    let delete = |name: &str| {
+        http.delete(format!("{}/v2/{}/blobs/{}", server.url(), name, layer))
+            .send()
+    };
+
+    // Blobs are shared, so `other` would lose its layer.
+    let response = delete("app").await.unwrap();
+    assert_eq!(response.status(), 403);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "DENIED");
+
+    // A soft-deleted manifest still holds it, for undelete.
+    server.delete_manifest("other", "v1").await.unwrap();
+    server.delete_manifest("other", &other).await.unwrap();
+    let response = delete("app").await.unwrap();
+    assert_eq!(response.status(), 403);
+
+    // Manifests of the repository the delete goes through do not.
+    server.collect_garbage().await.unwrap();
+    let response = delete("app").await.unwrap();
+    assert_eq!(response.status(), 202);
+    assert!(!client.blob_exists("app", &layer).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_delete_synthetic_blob() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = server.register_synthetic_blob(1024, 3).await.unwrap();
+
+    let response = reqwest::Client::new()
+        .delete(format!("{}/v2/app/blobs/{}", server.url(), digest))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    assert!(!client.blob_exists("app", &digest).await.unwrap());
+}
@@ -0,0 +1,231 @@
+use registry_testkit::{DigestPolicy, RegistryClient, RegistryConfig, RegistryServer};
+
+const DATA: &[u8] = b"layer";
+
+fn digest() -> String {
+    use sha2::{Digest, Sha256};
+    format!("sha256:{}", hex::encode(Sha256::digest(DATA)))
+}
+
+async fn finish_upload(server: &RegistryServer, digest: &str) -> reqwest::Response {
+    let http = reqwest::Client::new();
+    let response = http
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    let location = response.headers()["Location"].to_str().unwrap().to_string();
+    http.put(format!("{}{}", server.url(), location))
+        .query(&[("digest", digest)])
+        .body(DATA)
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn error_code(response: reqwest::Response) -> String {
+    let body: serde_json::Value = response.json().await.unwrap();
+    body["errors"][0]["code"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_denormalized_digests_are_canonicalized() {
+    let config = RegistryConfig::memory().with_digest_policy(DigestPolicy::new());
+    let server = RegistryServer::new(config).await.unwrap();
+
+    let response = finish_upload(&server, &format!(" {} ", digest().to_uppercase())).await;
+    assert_eq!(response.status(), 201);
+    assert_eq!(
+        response.headers()["Docker-Content-Digest"],
+        digest().as_str()
+    );
+
+    let client = RegistryClient::new(server.url());
+    assert!(client.blob_exists("app", &digest()).await.unwrap());
+    assert!(client
+        .blob_exists("app", &digest().to_uppercase())
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_strict_policy_rejects_denormalized_digests() {
+    let config = RegistryConfig::memory().with_digest_policy(DigestPolicy::new().strict(true));
+    let server = RegistryServer::new(config).await.unwrap();
+
+    let response = finish_upload(&server, &digest().to_uppercase()).await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(error_code(response).await, "DIGEST_INVALID");
+
+    let response = finish_upload(&server, "sha256:1234").await;
+    assert_eq!(error_code(response).await, "DIGEST_INVALID");
+
+    assert_eq!(finish_upload(&server, &digest()).await.status(), 201);
+}
+
+#[tokio::test]
+async fn test_algorithm_allowlist() {
+    let config = RegistryConfig::memory()
+        .with_digest_policy(DigestPolicy::new().with_algorithms(["sha256"]));
+    let server = RegistryServer::new(config).await.unwrap();
+
+    let response = reqwest::get(format!(
+        "{}/v2/app/manifests/sha512:{}",
+        server.url(),
+        "a".repeat(128)
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 400);
+    assert_eq!(error_code(response).await, "DIGEST_INVALID");
+
+    let response = reqwest::get(format!("{}/v2/app/manifests/latest", server.url()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_manifest_put_by_digest_is_verified() {
+    use sha2::{Digest, Sha256, Sha512};
+
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let manifest = br#"{"schemaVersion":2,"config":{},"layers":[]}"#.to_vec();
+    let put = |reference: String| {
+        reqwest::Client::new()
+            .put(format!("{}/v2/app/manifests/{}", server.url(), reference))
+            .header("Content-Type", "application/vnd.oci.image.manifest.v1+json")
+            .body(manifest.clone())
+            .send()
+    };
+
+    let wrong = format!("sha256:{}", "0".repeat(64));
+    let response = put(wrong.clone()).await.unwrap();
+    assert_eq!(response.status(), 400);
+    assert_eq!(error_code(response).await, "DIGEST_INVALID");
+    let response = reqwest::get(format!("{}/v2/app/manifests/{}", server.url(), wrong))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    let sha256 = format!("sha256:{}", hex::encode(Sha256::digest(&manifest)));
+    assert_eq!(put(sha256).await.unwrap().status(), 201);
+    let sha512 = format!("sha512:{}", hex::encode(Sha512::digest(&manifest)));
+    assert_eq!(put(sha512).await.unwrap().status(), 201);
+    assert_eq!(put("v1".to_string()).await.unwrap().status(), 201);
+}
+
+/// A toy algorithm: the length of the content.
+#[derive(Debug)]
+struct Length;
+
+impl registry_testkit::Digester for Length {
+    fn algorithm(&self) -> &str {
+        "length"
+    }
+
+    fn encode(&self, data: &[u8]) -> String {
+        data.len().to_string()
+    }
+}
+
+/// Hashes everything to zeros under the sha256 name.
+#[derive(Debug)]
+struct Zeros;
+
+impl registry_testkit::Digester for Zeros {
+    fn algorithm(&self) -> &str {
+        "sha256"
+    }
+
+    fn encode(&self, _data: &[u8]) -> String {
+        "0".repeat(64)
+    }
+
+    fn encoded_length(&self) -> Option<usize> {
+        Some(64)
+    }
+}
+
+#[tokio::test]
+async fn test_plugged_in_digesters() {
+    let length = format!("length:{}", DATA.len());
+
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let response = finish_upload(&server, &length).await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(error_code(response).await, "DIGEST_INVALID");
+
+    let config =
+        RegistryConfig::memory().with_digest_policy(DigestPolicy::new().with_digester(Length));
+    let server = RegistryServer::new(config).await.unwrap();
+    assert_eq!(finish_upload(&server, &length).await.status(), 201);
+    assert!(RegistryClient::new(server.url())
+        .blob_exists("app", &length)
+        .await
+        .unwrap());
+    assert_eq!(finish_upload(&server, &digest()).await.status(), 201);
+
+    let config =
+        RegistryConfig::memory().with_digest_policy(DigestPolicy::new().with_digester(Zeros));
+    let server = RegistryServer::new(config).await.unwrap();
+    let response = finish_upload(&server, &digest()).await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(error_code(response).await, "DIGEST_INVALID");
+    let zeros = format!("sha256:{}", "0".repeat(64));
+    assert_eq!(finish_upload(&server, &zeros).await.status(), 201);
+}
+
+#[tokio::test]
+async fn test_manifest_digests_use_the_policy() {
+    let config =
+        RegistryConfig::memory().with_digest_policy(DigestPolicy::new().with_digester(Zeros));
+    let server = RegistryServer::new(config).await.unwrap();
+    let zeros = format!("sha256:{}", "0".repeat(64));
+    let index = serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": "application/vnd.oci.image.index.v1+json",
+        "manifests": [],
+    });
+    let http = reqwest::Client::new();
+
+    let response = http
+        .put(format!("{}/v2/app/manifests/v1", server.url()))
+        .header("Content-Type", "application/vnd.oci.image.index.v1+json")
+        .body(serde_json::to_vec(&index).unwrap())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    assert_eq!(response.headers()["Docker-Content-Digest"], zeros.as_str());
+
+    let response = http
+        .head(format!("{}/v2/app/manifests/v1", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["Docker-Content-Digest"], zeros.as_str());
+    let response = http
+        .get(format!("{}/v2/app/manifests/{}", server.url(), zeros))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // Uploads completed without a digest are stored under the policy's.
+    let response = http
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    let location = response.headers()["Location"].to_str().unwrap().to_string();
+    let response = http
+        .put(format!("{}{}", server.url(), location))
+        .body(DATA)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    assert_eq!(response.headers()["Docker-Content-Digest"], zeros.as_str());
+}
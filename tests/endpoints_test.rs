@@ -0,0 +1,80 @@
+use registry_testkit::{ApiEndpoint, RegistryClient, RegistryConfig, RegistryServer};
+
+#[tokio::test]
+async fn test_disabled_endpoints_answer_like_minimal_registries() {
+    let config = RegistryConfig::memory()
+        .with_endpoint_disabled(ApiEndpoint::Catalog)
+        .with_endpoint_disabled(ApiEndpoint::TagList)
+        .with_endpoint_disabled(ApiEndpoint::Referrers)
+        .with_endpoint_disabled(ApiEndpoint::Deletes)
+        .with_endpoint_disabled(ApiEndpoint::BlobMount);
+    assert!(!config.endpoint_enabled(ApiEndpoint::Deletes));
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let http = reqwest::Client::new();
+    let url = |path: &str| format!("{}{}", server.url(), path);
+
+    let response = http.get(url("/v2/_catalog")).send().await.unwrap();
+    assert_eq!(response.status(), 405);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "UNSUPPORTED");
+
+    let response = http.get(url("/v2/app/tags/list")).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+    let response = http
+        .get(url(&format!("/v2/app/referrers/{}", digest)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    let response = http
+        .delete(url(&format!("/v2/app/manifests/{}", digest)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 405);
+
+    let layer = client.push_blob("app", b"shared".to_vec()).await.unwrap();
+    let response = http
+        .post(url(&format!(
+            "/v2/other/blobs/uploads/?mount={}&from=app",
+            layer
+        )))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    let location = response.headers()["Location"].to_str().unwrap();
+    assert!(location.starts_with("/v2/other/blobs/uploads/"));
+
+    // Everything else keeps working.
+    assert_eq!(client.pull_image("app", "v1").await.unwrap().digest, digest);
+}
+
+#[tokio::test]
+async fn test_endpoints_enabled_by_default() {
+    let config = RegistryConfig::memory();
+    for endpoint in [
+        ApiEndpoint::Catalog,
+        ApiEndpoint::TagList,
+        ApiEndpoint::Referrers,
+        ApiEndpoint::Deletes,
+        ApiEndpoint::BlobMount,
+    ] {
+        assert!(config.endpoint_enabled(endpoint), "{:?}", endpoint);
+    }
+    assert!(!config
+        .with_deletes_enabled(false)
+        .endpoint_enabled(ApiEndpoint::Deletes));
+
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let response = reqwest::get(format!("{}/v2/_catalog", server.url()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+}
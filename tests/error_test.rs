@@ -0,0 +1,82 @@
+use axum::response::IntoResponse;
+use registry_testkit::RegistryError;
+
+#[test]
+fn test_error_codes_and_statuses() {
+    let cases = [
+        (RegistryError::BlobNotFound("b".into()), 404, "BLOB_UNKNOWN"),
+        (
+            RegistryError::ManifestNotFound("m".into()),
+            404,
+            "MANIFEST_UNKNOWN",
+        ),
+        (
+            RegistryError::UploadNotFound("u".into()),
+            404,
+            "BLOB_UPLOAD_UNKNOWN",
+        ),
+        (
+            RegistryError::DigestInvalid("d".into()),
+            400,
+            "DIGEST_INVALID",
+        ),
+        (RegistryError::NameInvalid("n".into()), 400, "NAME_INVALID"),
+        (RegistryError::Denied("x".into()), 403, "DENIED"),
+        (RegistryError::Unauthorized("x".into()), 401, "UNAUTHORIZED"),
+        (RegistryError::TooLarge("x".into()), 413, "SIZE_INVALID"),
+        (RegistryError::StorageBackend("x".into()), 500, "UNKNOWN"),
+    ];
+    for (error, status, code) in cases {
+        assert_eq!(error.status_code().as_u16(), status, "{}", error);
+        assert_eq!(error.code(), code);
+    }
+}
+
+#[tokio::test]
+async fn test_error_into_response() {
+    use http_body_util::BodyExt;
+
+    let response = RegistryError::NameInvalid("Bad/Name".into()).into_response();
+    assert_eq!(response.status(), 400);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["errors"][0]["code"], "NAME_INVALID");
+    assert_eq!(
+        body["errors"][0]["message"],
+        "Invalid repository name: Bad/Name"
+    );
+}
+
+#[tokio::test]
+async fn test_handlers_answer_with_error_bodies() {
+    use registry_testkit::{ErrorBody, RegistryConfig, RegistryServer};
+
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let http = reqwest::Client::new();
+    let digest = format!("sha256:{}", "0".repeat(64));
+    let cases = [
+        ("/v2/app/manifests/v1".to_string(), 404, "MANIFEST_UNKNOWN"),
+        (format!("/v2/app/blobs/{}", digest), 404, "BLOB_UNKNOWN"),
+        (
+            "/v2/app/blobs/uploads/missing".to_string(),
+            404,
+            "BLOB_UPLOAD_UNKNOWN",
+        ),
+        ("/v2/app/tags/list".to_string(), 404, "NAME_UNKNOWN"),
+    ];
+    for (path, status, code) in cases {
+        let response = http
+            .get(format!("{}{}", server.url(), path))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), status, "{}", path);
+        assert!(response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("application/json"));
+        let body: ErrorBody = response.json().await.unwrap();
+        assert_eq!(body.errors[0].code, code, "{}", path);
+        assert!(!body.errors[0].message.is_empty());
+    }
+}
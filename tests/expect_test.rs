@@ -0,0 +1,113 @@
+use registry_testkit::{AuthConfig, RegistryConfig, RegistryServer};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Sends request headers announcing a body and returns the connection with
+/// the first response head the server sends before any body is written.
+async fn send_headers(
+    addr: SocketAddr,
+    method: &str,
+    path: &str,
+    extra: &str,
+) -> (TcpStream, String) {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let head = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: 4\r\n{}\r\n",
+        method, path, addr, extra
+    );
+    stream.write_all(head.as_bytes()).await.unwrap();
+    let response = read_head(&mut stream).await;
+    (stream, response)
+}
+
+async fn read_head(stream: &mut TcpStream) -> String {
+    let mut buf = Vec::new();
+    let mut byte = [0u8; 1];
+    while !buf.ends_with(b"\r\n\r\n") {
+        tokio::time::timeout(Duration::from_secs(5), stream.read_exact(&mut byte))
+            .await
+            .expect("server did not answer before the body was sent")
+            .unwrap();
+        buf.push(byte[0]);
+    }
+    String::from_utf8(buf).unwrap()
+}
+
+async fn start_upload(server: &RegistryServer) -> String {
+    let response = reqwest::Client::new()
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    response.headers()["Location"].to_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_continue_then_accept_upload() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let location = start_upload(&server).await;
+
+    let (mut stream, head) = send_headers(
+        server.addr(),
+        "PATCH",
+        &location,
+        "Expect: 100-continue\r\n",
+    )
+    .await;
+    assert!(head.starts_with("HTTP/1.1 100 Continue"), "{}", head);
+
+    stream.write_all(b"data").await.unwrap();
+    let head = read_head(&mut stream).await;
+    assert!(head.starts_with("HTTP/1.1 202"), "{}", head);
+}
+
+#[tokio::test]
+async fn test_unknown_upload_rejected_before_body() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+
+    let (_, head) = send_headers(
+        server.addr(),
+        "PATCH",
+        "/v2/app/blobs/uploads/missing",
+        "Expect: 100-continue\r\n",
+    )
+    .await;
+    assert!(head.starts_with("HTTP/1.1 404"), "{}", head);
+}
+
+#[tokio::test]
+async fn test_unauthenticated_push_rejected_before_body() {
+    let config = RegistryConfig::memory().with_auth(AuthConfig::basic().with_user("u", "p"));
+    let server = RegistryServer::new(config).await.unwrap();
+
+    let (_, head) = send_headers(
+        server.addr(),
+        "PUT",
+        "/v2/app/manifests/latest",
+        "Expect: 100-continue\r\n",
+    )
+    .await;
+    assert!(head.starts_with("HTTP/1.1 401"), "{}", head);
+}
+
+#[tokio::test]
+async fn test_oversized_and_unknown_expectations() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let location = start_upload(&server).await;
+
+    let mut stream = TcpStream::connect(server.addr()).await.unwrap();
+    let head = format!(
+        "PATCH {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nExpect: 100-continue\r\n\r\n",
+        location,
+        server.addr(),
+        1u64 << 40
+    );
+    stream.write_all(head.as_bytes()).await.unwrap();
+    let head = read_head(&mut stream).await;
+    assert!(head.starts_with("HTTP/1.1 413"), "{}", head);
+
+    let (_, head) = send_headers(server.addr(), "PATCH", &location, "Expect: teapot\r\n").await;
+    assert!(head.starts_with("HTTP/1.1 417"), "{}", head);
+}
@@ -0,0 +1,152 @@
+use registry_testkit::storage::{MemoryStorage, Storage};
+use registry_testkit::{
+    FaultConfig, FlakyStorage, RegistryClient, RegistryConfig, RegistryError, RegistryServer,
+    StorageFault, StorageMethod,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn test_stale_reads_serve_previous_tag() {
+    let faults = FaultConfig::new().with_stale_reads(Duration::from_millis(300));
+    let server = RegistryServer::new(RegistryConfig::memory().with_faults(faults))
+        .await
+        .unwrap();
+    let client = RegistryClient::new(server.url());
+
+    client
+        .push_manifest("app", "prod", "application/json", b"{\"v\":1}".to_vec())
+        .await
+        .unwrap();
+    let second = client
+        .push_manifest("app", "prod", "application/json", b"{\"v\":2}".to_vec())
+        .await
+        .unwrap();
+
+    let by_tag = client.pull_manifest("app", "prod").await.unwrap();
+    assert_eq!(by_tag.data, b"{\"v\":1}");
+    let by_digest = client.pull_manifest("app", &second).await.unwrap();
+    assert_eq!(by_digest.data, b"{\"v\":2}");
+
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    let by_tag = client.pull_manifest("app", "prod").await.unwrap();
+    assert_eq!(by_tag.data, b"{\"v\":2}");
+}
+
+#[tokio::test]
+async fn test_storage_faults_surface_as_server_errors() {
+    let faults = FaultConfig::new()
+        .with_storage_fault(StorageFault::fail(StorageMethod::GetBlob).after(1).times(2))
+        .with_storage_fault(
+            StorageFault::fail(StorageMethod::StoreManifest).with_message("disk full"),
+        )
+        .with_storage_fault(StorageFault::delay(
+            StorageMethod::CreateUpload,
+            Duration::from_millis(200),
+        ));
+    let server = RegistryServer::new(RegistryConfig::memory().with_faults(faults))
+        .await
+        .unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let started = Instant::now();
+    let digest = client.push_blob("app", b"layer".to_vec()).await.unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(200));
+
+    let url = format!("{}/v2/app/blobs/{}", server.url(), digest);
+    let http = reqwest::Client::new();
+    let mut statuses = Vec::new();
+    for _ in 0..4 {
+        statuses.push(http.get(&url).send().await.unwrap().status().as_u16());
+    }
+    assert_eq!(statuses, [200, 500, 500, 200]);
+
+    let response = http
+        .put(format!("{}/v2/app/manifests/v1", server.url()))
+        .header("Content-Type", "application/json")
+        .body("{}")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 500);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "UNKNOWN");
+    assert!(body["errors"][0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("disk full"));
+}
+
+#[tokio::test]
+async fn test_failed_upload_start_answers_with_error_body() {
+    let faults = FaultConfig::new().with_storage_fault(
+        StorageFault::fail(StorageMethod::CreateUpload)
+            .with_message("disk full")
+            .times(1),
+    );
+    let server = RegistryServer::new(RegistryConfig::memory().with_faults(faults))
+        .await
+        .unwrap();
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 500);
+    assert!(response.headers().get("location").is_none());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "UNKNOWN");
+    assert!(body["errors"][0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("disk full"));
+    assert_eq!(server.upload_stats().await.started, 0);
+}
+
+#[tokio::test]
+async fn test_flaky_storage_counts_calls() {
+    let inner: Arc<dyn Storage> = Arc::new(MemoryStorage::new());
+    let storage = FlakyStorage::new(
+        inner,
+        vec![StorageFault::fail(StorageMethod::GetBlob).times(1)],
+    );
+    storage
+        .store_blob("sha256:a".to_string(), b"a".to_vec())
+        .await
+        .unwrap();
+    assert!(matches!(
+        storage.get_blob("sha256:a").await,
+        Err(RegistryError::StorageBackend(_))
+    ));
+    assert_eq!(storage.get_blob("sha256:a").await.unwrap().unwrap(), b"a");
+    assert_eq!(storage.calls(StorageMethod::GetBlob), 2);
+    assert_eq!(storage.calls(StorageMethod::StoreBlob), 1);
+}
+
+#[tokio::test]
+async fn test_corrupted_uploads_fail_verification_on_pull() {
+    let faults = FaultConfig::new()
+        .with_storage_fault(StorageFault::corrupt(StorageMethod::StoreBlob).times(1));
+    let server = RegistryServer::new(RegistryConfig::memory().with_faults(faults))
+        .await
+        .unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let corrupted = client.push_blob("app", b"layer".to_vec()).await.unwrap();
+    let intact = client.push_blob("app", b"other".to_vec()).await.unwrap();
+    assert!(client.blob_exists("app", &corrupted).await.unwrap());
+
+    let error = client.pull_blob("app", &corrupted).await.unwrap_err();
+    assert!(
+        matches!(error, RegistryError::DigestInvalid(_)),
+        "{}",
+        error
+    );
+    assert_eq!(client.pull_blob("app", &intact).await.unwrap(), b"other");
+    let unverified = RegistryClient::new(server.url()).with_digest_verification(false);
+    assert_ne!(
+        unverified.pull_blob("app", &corrupted).await.unwrap(),
+        b"layer"
+    );
+}
@@ -0,0 +1,73 @@
+use registry_testkit::{
+    NamespaceTarget, RegistryClient, RegistryConfig, RegistryServer, StorageBackend, UpstreamConfig,
+};
+
+#[tokio::test]
+async fn test_namespace_storage_is_separate() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = RegistryConfig::memory().with_namespace(
+        "internal-",
+        NamespaceTarget::Storage(StorageBackend::Directory(dir.path().to_path_buf())),
+    );
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let digest = client
+        .push_image("internal-app", "v1", &[b"secret".to_vec()])
+        .await
+        .unwrap();
+    client
+        .push_image("public", "v1", &[b"public".to_vec()])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        client
+            .pull_image("internal-app", "v1")
+            .await
+            .unwrap()
+            .digest,
+        digest
+    );
+    // The main storage only holds the default namespace.
+    assert!(server.inspect_image("internal-app", "v1").await.is_err());
+    assert!(server.inspect_image("public", "v1").await.is_ok());
+    assert!(std::fs::read_dir(dir.path()).unwrap().next().is_some());
+}
+
+#[tokio::test]
+async fn test_namespace_proxies_upstream() {
+    let hub = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let digest = RegistryClient::new(hub.url())
+        .push_image("alpine", "3", &[b"alpine".to_vec()])
+        .await
+        .unwrap();
+
+    let config = RegistryConfig::memory().with_namespace(
+        "hub-",
+        NamespaceTarget::Upstream(UpstreamConfig::new(hub.url()).with_cache(true)),
+    );
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let image = client.pull_image("hub-alpine", "3").await.unwrap();
+    assert_eq!(image.digest, digest);
+    assert_eq!(image.layers, [b"alpine".to_vec()]);
+    client.pull_image("hub-alpine", "3").await.unwrap();
+    assert_eq!(hub.pull_count("alpine").await, 1);
+
+    // Repositories outside the namespace never reach the upstream.
+    assert!(client.pull_image("alpine", "3").await.is_err());
+
+    // Pushes into the namespace land in its own storage, through the
+    // rewritten upload locations.
+    let pushed = client
+        .push_image("hub-local", "v1", &[b"local".to_vec()])
+        .await
+        .unwrap();
+    assert_eq!(
+        client.pull_image("hub-local", "v1").await.unwrap().digest,
+        pushed
+    );
+    assert!(server.inspect_image("hub-local", "v1").await.is_err());
+}
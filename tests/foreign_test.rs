@@ -0,0 +1,69 @@
+use registry_testkit::{
+    ForeignLayerPolicy, ImageBuilder, Layer, RegistryClient, RegistryConfig, RegistryServer,
+};
+
+const BASE_URL: &str = "https://mcr.microsoft.com/v2/windows/servercore/blobs/base";
+
+/// Pushes a Windows-style image whose base layer is foreign and returns the
+/// foreign layer's blob URL.
+async fn push_windows_image(server: &RegistryServer) -> String {
+    let client = RegistryClient::new(server.url());
+    let image = ImageBuilder::new()
+        .platform("windows", "amd64")
+        .layer(Layer::foreign(b"windows base".to_vec(), [BASE_URL]))
+        .layer(Layer::new(b"app".to_vec()))
+        .build();
+    image.push(&client, "app", "v1").await.unwrap();
+
+    let inspect = server.inspect_image("app", "v1").await.unwrap();
+    format!("{}/v2/app/blobs/{}", server.url(), inspect.layers[0].digest)
+}
+
+fn no_redirects() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_foreign_layers_served_by_default() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let url = push_windows_image(&server).await;
+
+    let response = no_redirects().get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.bytes().await.unwrap().as_ref(), b"windows base");
+}
+
+#[tokio::test]
+async fn test_foreign_layers_not_found() {
+    let config = RegistryConfig::memory().with_foreign_layers(ForeignLayerPolicy::NotFound);
+    let server = RegistryServer::new(config).await.unwrap();
+    let url = push_windows_image(&server).await;
+
+    let response = no_redirects().head(&url).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+    let response = no_redirects().get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "BLOB_UNKNOWN");
+
+    // Regular layers are unaffected.
+    let client = RegistryClient::new(server.url());
+    let manifest = client.pull_manifest("app", "v1").await.unwrap();
+    let manifest: serde_json::Value = serde_json::from_slice(&manifest.data).unwrap();
+    let app_layer = manifest["layers"][1]["digest"].as_str().unwrap();
+    assert_eq!(client.pull_blob("app", app_layer).await.unwrap(), b"app");
+}
+
+#[tokio::test]
+async fn test_foreign_layers_redirect() {
+    let config = RegistryConfig::memory().with_foreign_layers(ForeignLayerPolicy::Redirect);
+    let server = RegistryServer::new(config).await.unwrap();
+    let url = push_windows_image(&server).await;
+
+    let response = no_redirects().get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 307);
+    assert_eq!(response.headers()["Location"], BASE_URL);
+}
@@ -0,0 +1,106 @@
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+
+const INDEX: &str = "application/vnd.oci.image.index.v1+json";
+const MANIFEST: &str = "application/vnd.oci.image.manifest.v1+json";
+
+#[tokio::test]
+async fn test_gc_preview_lists_overwritten_image_without_deleting() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let old = client
+        .push_image("app", "latest", &[b"old layer".to_vec()])
+        .await
+        .unwrap();
+    assert!(server.gc_preview().await.unwrap().is_empty());
+
+    let old_manifest = client.pull_manifest("app", "latest").await.unwrap();
+    client
+        .push_image("app", "latest", &[b"new layer".to_vec()])
+        .await
+        .unwrap();
+    let orphan = client.push_blob("app", b"orphan".to_vec()).await.unwrap();
+
+    let preview = server.gc_preview().await.unwrap();
+    assert_eq!(
+        preview.manifests.iter().collect::<Vec<_>>(),
+        vec![&format!("app@{}", old)]
+    );
+    assert_eq!(preview.blobs.len(), 2);
+    assert!(preview.blobs.contains(&orphan));
+    assert_eq!(
+        preview.bytes_reclaimed,
+        (old_manifest.data.len() + b"old layer".len() + b"orphan".len()) as u64
+    );
+
+    // Nothing was removed.
+    assert!(client.pull_image("app", &old).await.is_ok());
+    assert!(client.blob_exists("app", &orphan).await.unwrap());
+}
+
+fn image_manifest(config: &str, subject: Option<(&str, usize)>) -> Vec<u8> {
+    let mut manifest = serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": MANIFEST,
+        "config": {
+            "mediaType": "application/vnd.oci.image.config.v1+json",
+            "digest": config,
+            "size": 2,
+        },
+        "layers": [],
+    });
+    if let Some((digest, size)) = subject {
+        manifest["subject"] = serde_json::json!({
+            "mediaType": MANIFEST,
+            "digest": digest,
+            "size": size,
+        });
+    }
+    serde_json::to_vec(&manifest).unwrap()
+}
+
+async fn push_by_digest(client: &RegistryClient, content_type: &str, data: Vec<u8>) -> String {
+    let digest = format!("sha256:{}", sha256_hex(&data));
+    client
+        .push_manifest("app", &digest, content_type, data)
+        .await
+        .unwrap()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(Sha256::digest(data))
+}
+
+#[tokio::test]
+async fn test_gc_preview_keeps_index_children_and_referrers() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let config = client.push_blob("app", b"{}".to_vec()).await.unwrap();
+
+    let child = image_manifest(&config, None);
+    let child_len = child.len();
+    let child_digest = push_by_digest(&client, MANIFEST, child).await;
+    let signature = image_manifest(&config, Some((&child_digest, child_len)));
+    push_by_digest(&client, MANIFEST, signature).await;
+
+    let index = serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": INDEX,
+        "manifests": [{ "mediaType": MANIFEST, "digest": child_digest, "size": child_len }],
+    });
+    client
+        .push_manifest("app", "multi", INDEX, serde_json::to_vec(&index).unwrap())
+        .await
+        .unwrap();
+
+    let unrelated = image_manifest("sha256:unrelated", None);
+    let unrelated_digest = push_by_digest(&client, MANIFEST, unrelated).await;
+
+    let preview = server.gc_preview().await.unwrap();
+    assert!(preview.blobs.is_empty(), "{:?}", preview);
+    assert_eq!(
+        preview.manifests.into_iter().collect::<Vec<_>>(),
+        vec![format!("app@{}", unrelated_digest)]
+    );
+}
@@ -0,0 +1,69 @@
+use registry_testkit::{
+    HeaderCasing, HeaderQuirks, RegistryClient, RegistryConfig, RegistryServer, RegistryWarning,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Sends a raw HTTP/1.1 GET and returns the response head as received.
+async fn raw_head(server: &RegistryServer, path: &str) -> String {
+    let mut stream = tokio::net::TcpStream::connect(server.addr()).await.unwrap();
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path,
+        server.addr()
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.unwrap();
+    let response = String::from_utf8_lossy(&response).into_owned();
+    response.split("\r\n\r\n").next().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_title_case_headers() {
+    let config = RegistryConfig::memory()
+        .with_header_quirks(HeaderQuirks::new().with_casing(HeaderCasing::TitleCase));
+    let server = RegistryServer::new(config).await.unwrap();
+    RegistryClient::new(server.url())
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let head = raw_head(&server, "/v2/app/manifests/v1").await;
+    assert!(head.contains("\r\nContent-Type: "), "{}", head);
+
+    let plain = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let head = raw_head(&plain, "/v2/app/manifests/v1").await;
+    assert!(head.contains("\r\ncontent-type: "), "{}", head);
+}
+
+#[tokio::test]
+async fn test_duplicated_and_joined_headers() {
+    let config = RegistryConfig::memory()
+        .with_warning(RegistryWarning::new("first"))
+        .with_warning(RegistryWarning::new("second"))
+        .with_header_quirks(
+            HeaderQuirks::new()
+                .duplicate("docker-content-digest")
+                .join("warning"),
+        );
+    let server = RegistryServer::new(config).await.unwrap();
+    let digest = RegistryClient::new(server.url())
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    let response = reqwest::Client::new()
+        .head(format!("{}/v2/app/manifests/v1", server.url()))
+        .send()
+        .await
+        .unwrap();
+    let digests: Vec<_> = response
+        .headers()
+        .get_all("docker-content-digest")
+        .iter()
+        .collect();
+    assert_eq!(digests, [digest.as_str(), digest.as_str()]);
+    let warnings: Vec<_> = response.headers().get_all("warning").iter().collect();
+    assert_eq!(warnings.len(), 1);
+    let warning = warnings[0].to_str().unwrap();
+    assert!(warning.contains("first") && warning.contains("second"));
+}
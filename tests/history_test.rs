@@ -0,0 +1,68 @@
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+
+#[tokio::test]
+async fn test_tag_history_records_each_push() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let v1 = client
+        .push_image("app", "latest", &[b"v1".to_vec()])
+        .await
+        .unwrap();
+    let v2 = client
+        .push_image("app", "latest", &[b"v2".to_vec()])
+        .await
+        .unwrap();
+    // Re-pushing the same manifest doesn't add a revision.
+    client
+        .push_image("app", "latest", &[b"v2".to_vec()])
+        .await
+        .unwrap();
+    server.retag("app", "latest", "stable").await.unwrap();
+
+    let history = server.tag_history("app", "latest").await;
+    let digests: Vec<_> = history.iter().map(|r| r.digest.clone()).collect();
+    assert_eq!(digests, vec![v1.clone(), v2.clone()]);
+    assert!(history[0].pushed_at <= history[1].pushed_at);
+
+    let stable = server.tag_history("app", "stable").await;
+    assert_eq!(stable.len(), 1);
+    assert_eq!(stable[0].digest, v2);
+    assert!(server.tag_history("app", "missing").await.is_empty());
+
+    let response = reqwest::get(format!(
+        "{}/admin/repositories/app/tags/latest/history",
+        server.url()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let revisions = body.as_array().unwrap();
+    assert_eq!(revisions.len(), 2);
+    assert_eq!(revisions[0]["digest"], v1);
+    assert_eq!(revisions[1]["digest"], v2);
+    assert!(revisions[0]["pushedAt"].as_str().unwrap().ends_with('Z'));
+
+    let response = reqwest::get(format!(
+        "{}/admin/repositories/app/tags/missing/history",
+        server.url()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_tag_history_follows_rename() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client
+        .push_image("old", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    server.rename_repo("old", "new").await.unwrap();
+
+    assert!(server.tag_history("old", "v1").await.is_empty());
+    assert_eq!(server.tag_history("new", "v1").await[0].digest, digest);
+}
@@ -0,0 +1,250 @@
+use registry_testkit::client::{
+    OCI_CONFIG_MEDIA_TYPE, OCI_LAYER_MEDIA_TYPE, OCI_MANIFEST_MEDIA_TYPE,
+};
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryError, RegistryServer};
+
+/// Pushes an image with the given config and layers, returning its digest.
+async fn push_with_config(
+    client: &RegistryClient,
+    repo: &str,
+    tag: &str,
+    config: serde_json::Value,
+    layers: &[&[u8]],
+) -> String {
+    let config = serde_json::to_vec(&config).unwrap();
+    let config_size = config.len();
+    let config_digest = client.push_blob(repo, config).await.unwrap();
+    let mut descriptors = Vec::new();
+    for layer in layers {
+        let digest = client.push_blob(repo, layer.to_vec()).await.unwrap();
+        descriptors.push(serde_json::json!({
+            "mediaType": OCI_LAYER_MEDIA_TYPE,
+            "size": layer.len(),
+            "digest": digest,
+        }));
+    }
+    let manifest = serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": OCI_MANIFEST_MEDIA_TYPE,
+        "config": {
+            "mediaType": OCI_CONFIG_MEDIA_TYPE,
+            "size": config_size,
+            "digest": config_digest,
+        },
+        "layers": descriptors,
+    });
+    client
+        .push_manifest(
+            repo,
+            tag,
+            OCI_MANIFEST_MEDIA_TYPE,
+            serde_json::to_vec(&manifest).unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_inspect_image() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let config = serde_json::json!({
+        "architecture": "arm64",
+        "os": "linux",
+        "variant": "v8",
+        "config": {
+            "Env": ["PATH=/usr/bin", "MODE=prod"],
+            "Entrypoint": ["/app"],
+            "Cmd": null,
+            "Labels": { "org.opencontainers.image.version": "1.2.3" },
+        },
+        "rootfs": { "type": "layers", "diff_ids": [] },
+    });
+    let digest = push_with_config(&client, "app", "v1", config, &[b"base", b"app"]).await;
+
+    let image = server.inspect_image("app", "v1").await.unwrap();
+    assert_eq!(image.digest, digest);
+    assert_eq!(image.media_type, OCI_MANIFEST_MEDIA_TYPE);
+    assert_eq!(image.platform.os, "linux");
+    assert_eq!(image.platform.architecture, "arm64");
+    assert_eq!(image.platform.variant.as_deref(), Some("v8"));
+    assert_eq!(image.env, ["PATH=/usr/bin", "MODE=prod"]);
+    assert_eq!(image.entrypoint, ["/app"]);
+    assert!(image.cmd.is_empty());
+    assert_eq!(image.labels["org.opencontainers.image.version"], "1.2.3");
+    assert_eq!(image.layers.len(), 2);
+    assert_eq!(image.layers[0].size, 4);
+    assert_eq!(image.layers[1].media_type, OCI_LAYER_MEDIA_TYPE);
+    assert_eq!(server.inspect_image("app", &digest).await.unwrap(), image);
+}
+
+#[tokio::test]
+async fn test_inspect_image_errors() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    assert!(matches!(
+        server.inspect_image("app", "missing").await,
+        Err(RegistryError::ManifestNotFound(_))
+    ));
+
+    let child = client
+        .push_image("app", "child", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let index = serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": "application/vnd.oci.image.index.v1+json",
+        "manifests": [{
+            "mediaType": OCI_MANIFEST_MEDIA_TYPE,
+            "size": 1,
+            "digest": child,
+        }],
+    });
+    client
+        .push_manifest(
+            "app",
+            "multi",
+            "application/vnd.oci.image.index.v1+json",
+            serde_json::to_vec(&index).unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        server.inspect_image("app", "multi").await,
+        Err(RegistryError::InvalidManifest(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_diff_images() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let config = |version: &str, env: &[&str]| {
+        serde_json::json!({
+            "architecture": "amd64",
+            "os": "linux",
+            "config": {
+                "Env": env,
+                "Cmd": ["serve"],
+                "Labels": { "version": version },
+            },
+        })
+    };
+    push_with_config(
+        &client,
+        "app",
+        "v1",
+        config("1", &["MODE=dev", "OLD=1"]),
+        &[b"base", b"deps"],
+    )
+    .await;
+    push_with_config(
+        &client,
+        "app",
+        "v2",
+        config("2", &["MODE=prod"]),
+        &[b"base", b"deps", b"app"],
+    )
+    .await;
+    push_with_config(
+        &client,
+        "other",
+        "v1",
+        config("1", &["MODE=dev", "OLD=1"]),
+        &[b"new-base", b"deps"],
+    )
+    .await;
+
+    let diff = server
+        .diff_images(("app", "v1"), ("app", "v2"))
+        .await
+        .unwrap();
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.added[0].size, 3);
+    assert!(diff.removed.is_empty());
+    assert!(diff.changed.is_empty());
+    let fields: Vec<_> = diff
+        .config
+        .iter()
+        .map(|c| (c.field.as_str(), c.from.as_deref(), c.to.as_deref()))
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            ("env.MODE", Some("dev"), Some("prod")),
+            ("env.OLD", Some("1"), None),
+            ("labels.version", Some("1"), Some("2")),
+        ]
+    );
+
+    let reverse = server
+        .diff_images(("app", "v2"), ("app", "v1"))
+        .await
+        .unwrap();
+    assert_eq!(reverse.removed, diff.added);
+
+    let rebased = server
+        .diff_images(("app", "v1"), ("other", "v1"))
+        .await
+        .unwrap();
+    assert_eq!(rebased.changed.len(), 1);
+    assert_eq!(rebased.changed[0].index, 0);
+    assert!(rebased.config.is_empty());
+
+    assert!(server
+        .diff_images(("app", "v1"), ("app", "v1"))
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_manifest_annotations() {
+    use registry_testkit::{ImageBuilder, IndexBuilder, Layer};
+
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let image = ImageBuilder::new()
+        .annotation("org.opencontainers.image.source", "https://example.com/app")
+        .layer(Layer::new(b"layer".to_vec()).with_annotation("layer.role", "base"))
+        .build();
+    let digest = image.push(&client, "app", "v1").await.unwrap();
+
+    let annotations = server.manifest_annotations("app", "v1").await.unwrap();
+    assert_eq!(annotations.digest, digest);
+    assert_eq!(annotations.media_type, OCI_MANIFEST_MEDIA_TYPE);
+    assert_eq!(
+        annotations.annotations["org.opencontainers.image.source"],
+        "https://example.com/app"
+    );
+    assert_eq!(annotations.descriptors.len(), 2);
+    assert!(annotations.descriptors[0].annotations.is_empty());
+    assert_eq!(annotations.descriptors[1].annotations["layer.role"], "base");
+
+    let index = IndexBuilder::new()
+        .built_image(image)
+        .annotation("org.opencontainers.image.version", "1.0")
+        .build();
+    index.push(&client, "app", "multi").await.unwrap();
+    let url = format!(
+        "{}/admin/repositories/app/manifests/multi/annotations",
+        server.url()
+    );
+    let body: serde_json::Value = reqwest::get(&url).await.unwrap().json().await.unwrap();
+    assert_eq!(body["digest"], index.digest);
+    assert_eq!(
+        body["annotations"]["org.opencontainers.image.version"],
+        "1.0"
+    );
+    assert_eq!(body["descriptors"][0]["digest"], digest);
+
+    let missing = server.manifest_annotations("app", "v2").await;
+    assert!(matches!(missing, Err(RegistryError::ManifestNotFound(_))));
+    let url = format!(
+        "{}/admin/repositories/app/manifests/v2/annotations",
+        server.url()
+    );
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), 404);
+}
@@ -0,0 +1,129 @@
+use registry_testkit::{ImageBuilder, Layer, RegistryClient, RegistryConfig, RegistryServer};
+
+const SOURCE: &str = "org.opencontainers.image.source";
+
+async fn labelled_registry() -> (RegistryServer, String) {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let app = ImageBuilder::new()
+        .label(SOURCE, "https://example.com/app")
+        .label("org.opencontainers.image.vendor", "acme")
+        .layer(Layer::new(b"app".to_vec()))
+        .build();
+    app.push(&client, "app", "v1").await.unwrap();
+    app.push(&client, "app", "stable").await.unwrap();
+    // Labelled by annotation rather than config label.
+    ImageBuilder::new()
+        .annotation(SOURCE, "https://example.com/base")
+        .layer(Layer::new(b"base".to_vec()))
+        .build()
+        .push(&client, "base", "latest")
+        .await
+        .unwrap();
+    let unlabelled = client
+        .push_image("app", "v2", &[b"unlabelled".to_vec()])
+        .await
+        .unwrap();
+    (server, unlabelled)
+}
+
+#[tokio::test]
+async fn test_find_images_by_label() {
+    let (server, unlabelled) = labelled_registry().await;
+
+    let images = server
+        .find_images_with_label(SOURCE, "https://example.com/app")
+        .await
+        .unwrap();
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].repository, "app");
+    assert_eq!(images[0].tags, ["stable", "v1"]);
+    assert_eq!(
+        images[0].label("org.opencontainers.image.vendor"),
+        Some("acme")
+    );
+
+    let images = server
+        .find_images_with_label(SOURCE, "https://example.com/base")
+        .await
+        .unwrap();
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].repository, "base");
+
+    let missing = server.find_images_without_label(SOURCE).await.unwrap();
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0].digest, unlabelled);
+    assert_eq!(missing[0].tags, ["v2"]);
+}
+
+#[tokio::test]
+async fn test_annotation_overrides_config_label() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    ImageBuilder::new()
+        .label(SOURCE, "from-label")
+        .annotation(SOURCE, "from-annotation")
+        .build()
+        .push(&client, "app", "v1")
+        .await
+        .unwrap();
+    assert!(server
+        .find_images_with_label(SOURCE, "from-label")
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        server
+            .find_images_with_label(SOURCE, "from-annotation")
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_label_search_endpoint() {
+    let (server, unlabelled) = labelled_registry().await;
+    let http = reqwest::Client::new();
+    let search = |query: &str| {
+        http.get(format!("{}/v2/_testkit/images?{}", server.url(), query))
+            .send()
+    };
+
+    let response = search(&format!("label={}", SOURCE)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let repositories: Vec<_> = body["images"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|image| image["repository"].as_str().unwrap())
+        .collect();
+    assert_eq!(repositories, ["app", "base"]);
+
+    let body: serde_json::Value =
+        search(&format!("label={}&value=https://example.com/base", SOURCE))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+    assert_eq!(body["images"][0]["tags"], serde_json::json!(["latest"]));
+    assert_eq!(
+        body["images"][0]["labels"][SOURCE],
+        "https://example.com/base"
+    );
+
+    let body: serde_json::Value = search(&format!("label={}&missing=true", SOURCE))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["images"].as_array().unwrap().len(), 1);
+    assert_eq!(body["images"][0]["digest"], unlabelled);
+
+    let response = search("value=acme").await.unwrap();
+    assert_eq!(response.status(), 400);
+}
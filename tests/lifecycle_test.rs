@@ -0,0 +1,50 @@
+use registry_testkit::{LifecycleEvent, RegistryConfig, RegistryServer};
+use std::sync::{Arc, Mutex};
+
+fn recording(config: RegistryConfig) -> (RegistryConfig, Arc<Mutex<Vec<String>>>) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+    let config = config.on_event(move |event| {
+        let name = match event {
+            LifecycleEvent::StorageInitialized { .. } => "storage".to_string(),
+            LifecycleEvent::Started { url, .. } => format!("started {}", url),
+            LifecycleEvent::Stopped { .. } => "stopped".to_string(),
+            LifecycleEvent::FatalError { .. } => "fatal".to_string(),
+            _ => "other".to_string(),
+        };
+        recorded.lock().unwrap().push(name);
+    });
+    (config, events)
+}
+
+#[tokio::test]
+async fn test_lifecycle_callbacks() {
+    let (config, events) = recording(RegistryConfig::memory());
+    let mut server = RegistryServer::new(config).await.unwrap();
+    let started = format!("started {}", server.url());
+    assert_eq!(*events.lock().unwrap(), ["storage", started.as_str()]);
+
+    server.simulate_crash().await.unwrap();
+    server.restart().await.unwrap();
+    drop(server);
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            "storage",
+            started.as_str(),
+            "stopped",
+            started.as_str(),
+            "stopped"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_lifecycle_fatal_error() {
+    let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = taken.local_addr().unwrap().port();
+    let (config, events) = recording(RegistryConfig::memory().with_port(port));
+
+    assert!(RegistryServer::new(config).await.is_err());
+    assert_eq!(*events.lock().unwrap(), ["storage", "fatal"]);
+}
@@ -0,0 +1,90 @@
+use registry_testkit::loadgen::{self, LoadConfig, SizeDistribution};
+use registry_testkit::{
+    FaultConfig, RegistryClient, RegistryConfig, RegistryServer, StorageFault, StorageMethod,
+    TransferDirection, TransferProgress,
+};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn test_client_push_pull_roundtrip() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let digest = client
+        .push_image("app", "v1", &[b"layer-one".to_vec(), b"layer-two".to_vec()])
+        .await
+        .unwrap();
+
+    let image = client.pull_image("app", "v1").await.unwrap();
+    assert_eq!(image.digest, digest);
+    assert_eq!(
+        image.layers,
+        vec![b"layer-one".to_vec(), b"layer-two".to_vec()]
+    );
+}
+
+#[tokio::test]
+async fn test_client_transfers_layers_concurrently() {
+    // Each blob write takes 200ms, so four layers and a config take a
+    // second in sequence.
+    let faults = FaultConfig::new().with_storage_fault(StorageFault::delay(
+        StorageMethod::StoreBlob,
+        Duration::from_millis(200),
+    ));
+    let server = RegistryServer::new(RegistryConfig::memory().with_faults(faults))
+        .await
+        .unwrap();
+    let progress: Arc<Mutex<Vec<TransferProgress>>> = Arc::default();
+    let recorded = progress.clone();
+    let client = RegistryClient::new(server.url())
+        .with_concurrency(5)
+        .with_progress(move |p| recorded.lock().unwrap().push(p.clone()));
+
+    let layers: Vec<Vec<u8>> = (0..4)
+        .map(|i| format!("layer-{}", i).into_bytes())
+        .collect();
+    let started = Instant::now();
+    client.push_image("app", "v1", &layers).await.unwrap();
+    assert!(started.elapsed() < Duration::from_millis(800));
+
+    let image = client.pull_image("app", "v1").await.unwrap();
+    assert_eq!(image.layers, layers);
+
+    let progress = progress.lock().unwrap();
+    assert_eq!(progress.len(), 10);
+    let (pushes, pulls): (Vec<_>, Vec<_>) = progress
+        .iter()
+        .partition(|p| p.direction == TransferDirection::Push);
+    for transfers in [pushes, pulls] {
+        assert_eq!(
+            transfers.iter().map(|p| p.completed).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5]
+        );
+        assert!(transfers.iter().all(|p| p.total == 5));
+        assert_eq!(
+            transfers.iter().map(|p| p.bytes).sum::<u64>(),
+            image.config.len() as u64 + layers.iter().map(|l| l.len() as u64).sum::<u64>()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_loadgen_reports_latencies() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+
+    let config = LoadConfig::new()
+        .with_clients(4)
+        .with_duration(Duration::from_millis(500))
+        .with_sizes(SizeDistribution::Uniform {
+            min: 512,
+            max: 4096,
+        });
+    let report = loadgen::run(&server.url(), config).await.unwrap();
+
+    assert_eq!(report.errors, 0);
+    assert!(report.pushes.count() > 0);
+    assert!(report.pushes.p50() <= report.pushes.p99());
+    assert!(report.pushes.p99() <= report.pushes.max());
+    assert!(report.bytes_pushed > 0);
+}
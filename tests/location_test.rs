@@ -0,0 +1,80 @@
+use registry_testkit::{
+    AuthConfig, BlobRedirectConfig, LocationStyle, RegistryClient, RegistryConfig, RegistryServer,
+};
+
+async fn upload_location(server: &RegistryServer) -> String {
+    let response = reqwest::Client::new()
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    response.headers()["Location"].to_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_relative_locations_by_default() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    assert!(upload_location(&server)
+        .await
+        .starts_with("/v2/app/blobs/uploads/"));
+}
+
+#[tokio::test]
+async fn test_absolute_locations_use_request_host() {
+    let config = RegistryConfig::memory().with_location_style(LocationStyle::Absolute);
+    let server = RegistryServer::new(config).await.unwrap();
+
+    let location = upload_location(&server).await;
+    assert!(location.starts_with(&format!("{}/v2/app/blobs/uploads/", server.url())));
+
+    let client = RegistryClient::new(server.url());
+    let digest = client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let image = client.pull_image("app", &digest).await.unwrap();
+    assert_eq!(image.layers, vec![b"layer".to_vec()]);
+}
+
+#[tokio::test]
+async fn test_external_url() {
+    let config = RegistryConfig::memory()
+        .with_location_style(LocationStyle::Absolute)
+        .with_external_url("https://registry.test.local/");
+    let server = RegistryServer::new(config).await.unwrap();
+    assert!(upload_location(&server)
+        .await
+        .starts_with("https://registry.test.local/v2/app/blobs/uploads/"));
+
+    let config = RegistryConfig::memory().with_external_url("https://proxy.test.local/registry");
+    let server = RegistryServer::new(config).await.unwrap();
+    assert!(upload_location(&server)
+        .await
+        .starts_with("/registry/v2/app/blobs/uploads/"));
+}
+
+#[tokio::test]
+async fn test_external_url_in_token_realm_and_redirects() {
+    let config = RegistryConfig::memory()
+        .with_external_url("https://registry.test.local")
+        .with_auth(AuthConfig::bearer("registry.test.local").with_anonymous_pull(true))
+        .with_blob_redirects(
+            BlobRedirectConfig::new().with_external_url("https://blobs.test.local/"),
+        );
+    let server = RegistryServer::new(config).await.unwrap();
+    assert_eq!(server.public_url(), "https://registry.test.local");
+    assert_eq!(
+        server.blob_server_url().as_deref(),
+        Some("https://blobs.test.local")
+    );
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+    let challenge = response.headers()["WWW-Authenticate"].to_str().unwrap();
+    assert!(challenge.contains("realm=\"https://registry.test.local/token\""));
+}
@@ -0,0 +1,133 @@
+use registry_testkit::{
+    MaintenanceConfig, RegistryClient, RegistryConfig, RegistryError, RegistryEvent,
+    RegistryServer, RetentionPolicy,
+};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_scheduled_maintenance_cleans_up_and_reports() {
+    let config = RegistryConfig::memory()
+        .with_retention("app", RetentionPolicy::new().keep_last(1))
+        .with_maintenance(
+            MaintenanceConfig::new(Duration::from_millis(200))
+                .with_retention(true)
+                .with_gc(true)
+                .with_upload_ttl(Duration::from_millis(50)),
+        );
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let mut events = server.subscribe();
+
+    let orphan = client.push_blob("app", b"orphan".to_vec()).await.unwrap();
+    client
+        .push_manifest("app", "a", "application/json", b"{\"a\":1}".to_vec())
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    client
+        .push_manifest("app", "b", "application/json", b"{\"b\":1}".to_vec())
+        .await
+        .unwrap();
+    reqwest::Client::new()
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+
+    let report = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let RegistryEvent::MaintenanceCompleted(report) = events.recv().await.unwrap() {
+                return report;
+            }
+        }
+    })
+    .await
+    .expect("no maintenance run");
+
+    assert_eq!(
+        report.tags_deleted,
+        vec![("app".to_string(), "a".to_string())]
+    );
+    assert_eq!(report.uploads_expired, 1);
+    let garbage = report.garbage.unwrap();
+    assert!(garbage.blobs.contains(&orphan));
+    assert_eq!(garbage.manifests.len(), 1);
+
+    assert!(!client.blob_exists("app", &orphan).await.unwrap());
+    assert!(client.pull_manifest("app", "b").await.is_ok());
+    assert!(server.gc_preview().await.unwrap().is_empty());
+}
+
+/// Pushes `data` as a blob addressed by its sha512 digest.
+async fn push_sha512_blob(server: &RegistryServer, data: &'static [u8]) -> String {
+    use sha2::{Digest, Sha512};
+    let digest = format!("sha512:{}", hex::encode(Sha512::digest(data)));
+    let http = reqwest::Client::new();
+    let response = http
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    let location = response.headers()["Location"].to_str().unwrap().to_string();
+    let response = http
+        .put(format!("{}{}", server.url(), location))
+        .query(&[("digest", &digest)])
+        .body(data)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    digest
+}
+
+#[tokio::test]
+async fn test_scrub_reports_corrupt_blobs() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = RegistryServer::new(RegistryConfig::directory(dir.path().to_path_buf()))
+        .await
+        .unwrap();
+    let client = RegistryClient::new(server.url());
+    let good = client.push_blob("app", b"good".to_vec()).await.unwrap();
+    let bad = client.push_blob("app", b"bad".to_vec()).await.unwrap();
+    let bad512 = push_sha512_blob(&server, b"bad").await;
+    assert!(server.scrub().await.unwrap().is_empty());
+
+    for digest in [&bad, &bad512] {
+        let path = dir.path().join("blobs").join(digest.replace(':', "%3A"));
+        std::fs::write(path, b"bit rot").unwrap();
+    }
+
+    assert_eq!(server.scrub().await.unwrap(), vec![bad, bad512]);
+    assert!(client.blob_exists("app", &good).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_client_detects_corrupt_blobs() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = RegistryServer::new(RegistryConfig::directory(dir.path().to_path_buf()))
+        .await
+        .unwrap();
+    let client = RegistryClient::new(server.url());
+    client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let layer = client.push_blob("app", b"layer".to_vec()).await.unwrap();
+
+    let path = dir.path().join("blobs").join(layer.replace(':', "%3A"));
+    std::fs::write(path, b"bit rot").unwrap();
+
+    let err = client.pull_blob("app", &layer).await.unwrap_err();
+    assert!(
+        matches!(err, RegistryError::DigestInvalid(ref message) if message.contains(&layer)),
+        "{}",
+        err
+    );
+    assert!(client.pull_image("app", "v1").await.is_err());
+
+    // A naive client takes whatever the registry serves.
+    let naive = RegistryClient::new(server.url()).with_digest_verification(false);
+    assert_eq!(naive.pull_blob("app", &layer).await.unwrap(), b"bit rot");
+    let image = naive.pull_image("app", "v1").await.unwrap();
+    assert_eq!(image.layers, vec![b"bit rot".to_vec()]);
+}
@@ -0,0 +1,360 @@
+use registry_testkit::client::{OCI_CONFIG_MEDIA_TYPE, OCI_MANIFEST_MEDIA_TYPE};
+use registry_testkit::oci::manifest::{Manifest, ValidationLevel, OCI_INDEX_MEDIA_TYPE};
+use registry_testkit::{DigestPolicy, RegistryClient, RegistryConfig, RegistryServer};
+
+async fn server(level: ValidationLevel) -> (RegistryServer, RegistryClient) {
+    let config = RegistryConfig::memory().with_manifest_validation(level);
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    (server, client)
+}
+
+fn manifest(config_digest: &str) -> Vec<u8> {
+    serde_json::to_vec(&serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": OCI_MANIFEST_MEDIA_TYPE,
+        "config": {
+            "mediaType": OCI_CONFIG_MEDIA_TYPE,
+            "size": 2,
+            "digest": config_digest,
+        },
+        "layers": [],
+    }))
+    .unwrap()
+}
+
+async fn put(server: &RegistryServer, content_type: &str, body: Vec<u8>) -> (u16, Option<String>) {
+    let response = reqwest::Client::new()
+        .put(format!("{}/v2/app/manifests/v1", server.url()))
+        .header("Content-Type", content_type)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    (
+        status,
+        body["errors"][0]["code"].as_str().map(str::to_string),
+    )
+}
+
+#[tokio::test]
+async fn test_no_validation_stores_any_bytes() {
+    let (server, _) = server(ValidationLevel::None).await;
+    let (status, _) = put(&server, OCI_MANIFEST_MEDIA_TYPE, b"not json".to_vec()).await;
+    assert_eq!(status, 201);
+}
+
+#[tokio::test]
+async fn test_schema_validation() {
+    let (server, _) = server(ValidationLevel::Schema).await;
+
+    let (status, code) = put(&server, OCI_MANIFEST_MEDIA_TYPE, b"not json".to_vec()).await;
+    assert_eq!((status, code.as_deref()), (400, Some("MANIFEST_INVALID")));
+
+    let (_, code) = put(
+        &server,
+        OCI_INDEX_MEDIA_TYPE,
+        manifest(&format!("sha256:{}", "a".repeat(64))),
+    )
+    .await;
+    assert_eq!(code.as_deref(), Some("MANIFEST_INVALID"));
+
+    let (_, code) = put(&server, OCI_MANIFEST_MEDIA_TYPE, manifest("sha256:short")).await;
+    assert_eq!(code.as_deref(), Some("MANIFEST_INVALID"));
+
+    let (status, _) = put(
+        &server,
+        OCI_MANIFEST_MEDIA_TYPE,
+        manifest(&format!("sha256:{}", "a".repeat(64))),
+    )
+    .await;
+    assert_eq!(status, 201);
+}
+
+#[tokio::test]
+async fn test_full_validation_requires_referenced_content() {
+    let (server, client) = server(ValidationLevel::Full).await;
+
+    let missing = format!("sha256:{}", "a".repeat(64));
+    let (status, code) = put(&server, OCI_MANIFEST_MEDIA_TYPE, manifest(&missing)).await;
+    assert_eq!(
+        (status, code.as_deref()),
+        (400, Some("MANIFEST_BLOB_UNKNOWN"))
+    );
+
+    let config = client.push_blob("app", b"{}".to_vec()).await.unwrap();
+    let (status, _) = put(&server, OCI_MANIFEST_MEDIA_TYPE, manifest(&config)).await;
+    assert_eq!(status, 201);
+
+    let digest = client
+        .push_image("app", "v2", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let index = serde_json::to_vec(&serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": OCI_INDEX_MEDIA_TYPE,
+        "manifests": [
+            { "mediaType": OCI_MANIFEST_MEDIA_TYPE, "size": 1, "digest": digest },
+            { "mediaType": OCI_MANIFEST_MEDIA_TYPE, "size": 1, "digest": missing },
+        ],
+    }))
+    .unwrap();
+    let (_, code) = put(&server, OCI_INDEX_MEDIA_TYPE, index).await;
+    assert_eq!(code.as_deref(), Some("MANIFEST_BLOB_UNKNOWN"));
+}
+
+#[test]
+fn test_parse_manifest_kinds() {
+    let digest = format!("sha256:{}", "a".repeat(64));
+    let Manifest::Image(image) = Manifest::parse("", &manifest(&digest)).unwrap() else {
+        panic!("expected an image manifest");
+    };
+    assert_eq!(image.config.digest, digest);
+
+    let index = br#"{"schemaVersion":2,"manifests":[]}"#;
+    assert!(matches!(
+        Manifest::parse("application/json", index).unwrap(),
+        Manifest::Index(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_manifest_content_type_detection() {
+    use registry_testkit::oci::manifest::{
+        ContentTypePolicy, DOCKER_CONFIG_MEDIA_TYPE, DOCKER_MANIFEST_MEDIA_TYPE,
+    };
+
+    async fn push(
+        server: &RegistryServer,
+        tag: &str,
+        content_type: Option<&str>,
+        body: &str,
+    ) -> u16 {
+        let mut request = reqwest::Client::new()
+            .put(format!("{}/v2/app/manifests/{}", server.url(), tag))
+            .body(body.to_string());
+        if let Some(content_type) = content_type {
+            request = request.header("Content-Type", content_type);
+        }
+        request.send().await.unwrap().status().as_u16()
+    }
+
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let oci = r#"{"schemaVersion":2,"config":{"mediaType":"application/vnd.oci.image.config.v1+json"},"layers":[]}"#;
+    let docker = format!(
+        r#"{{"schemaVersion":2,"config":{{"mediaType":"{}"}},"layers":[]}}"#,
+        DOCKER_CONFIG_MEDIA_TYPE
+    );
+    let index = r#"{"schemaVersion":2,"manifests":[]}"#;
+    let declared = format!(
+        r#"{{"schemaVersion":2,"mediaType":"{}","config":{{}},"layers":[]}}"#,
+        OCI_MANIFEST_MEDIA_TYPE
+    );
+
+    assert_eq!(push(&server, "oci", None, oci).await, 201);
+    assert_eq!(
+        push(&server, "docker", Some("application/json"), &docker).await,
+        201
+    );
+    assert_eq!(push(&server, "index", None, index).await, 201);
+    assert_eq!(push(&server, "declared", None, &declared).await, 201);
+    for (tag, expected) in [
+        ("oci", OCI_MANIFEST_MEDIA_TYPE),
+        ("docker", DOCKER_MANIFEST_MEDIA_TYPE),
+        ("index", OCI_INDEX_MEDIA_TYPE),
+        ("declared", OCI_MANIFEST_MEDIA_TYPE),
+    ] {
+        let pulled = client.pull_manifest("app", tag).await.unwrap();
+        assert_eq!(pulled.content_type, expected, "{}", tag);
+    }
+
+    assert_eq!(
+        push(
+            &server,
+            "mismatch",
+            Some(DOCKER_MANIFEST_MEDIA_TYPE),
+            &declared
+        )
+        .await,
+        400
+    );
+
+    let config = RegistryConfig::memory().with_manifest_content_type(ContentTypePolicy::Fallback(
+        DOCKER_MANIFEST_MEDIA_TYPE.to_string(),
+    ));
+    let legacy = RegistryServer::new(config).await.unwrap();
+    assert_eq!(push(&legacy, "oci", None, oci).await, 201);
+    let pulled = RegistryClient::new(legacy.url())
+        .pull_manifest("app", "oci")
+        .await
+        .unwrap();
+    assert_eq!(pulled.content_type, DOCKER_MANIFEST_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn test_head_manifest_reports_length() {
+    let (server, client) = server(ValidationLevel::None).await;
+    let digest = client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let pulled = client.pull_manifest("app", "v1").await.unwrap();
+
+    let http = reqwest::Client::new();
+    let response = http
+        .head(format!("{}/v2/app/manifests/v1", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let headers = response.headers();
+    assert_eq!(
+        headers["Content-Length"],
+        pulled.data.len().to_string().as_str()
+    );
+    assert_eq!(headers["Content-Type"], pulled.content_type.as_str());
+    assert_eq!(headers["Docker-Content-Digest"], digest.as_str());
+
+    let response = http
+        .head(format!("{}/v2/app/manifests/missing", server.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    assert_eq!(response.headers()["Content-Length"], "0");
+}
+
+#[tokio::test]
+async fn test_manifest_size_limit() {
+    let config = RegistryConfig::memory().with_max_manifest_size(1024);
+    let server = RegistryServer::new(config).await.unwrap();
+
+    let (status, code) = put(&server, OCI_MANIFEST_MEDIA_TYPE, vec![b' '; 2048]).await;
+    assert_eq!(status, 413);
+    assert_eq!(code.as_deref(), Some("SIZE_INVALID"));
+
+    // Without a Content-Length, the body is cut off once it passes the limit.
+    let chunks = (0..3).map(|_| {
+        Ok::<_, std::io::Error>(hyper::body::Frame::data(axum::body::Bytes::from(vec![
+            b' ';
+            1000
+        ])))
+    });
+    let body = http_body_util::StreamBody::new(futures_util::stream::iter(chunks));
+    let response = reqwest::Client::new()
+        .put(format!("{}/v2/app/manifests/v1", server.url()))
+        .header("Content-Type", OCI_MANIFEST_MEDIA_TYPE)
+        .body(reqwest::Body::wrap(body))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 413);
+
+    let (status, _) = put(&server, OCI_MANIFEST_MEDIA_TYPE, manifest("sha256:00")).await;
+    assert_eq!(status, 201);
+}
+
+#[tokio::test]
+async fn test_chunked_manifest_responses() {
+    let config = RegistryConfig::memory().with_chunked_manifests(16);
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    let response = reqwest::get(format!("{}/v2/app/manifests/v1", server.url()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().get("Content-Length").is_none());
+    assert_eq!(response.headers()["Transfer-Encoding"], "chunked");
+    let data = response.bytes().await.unwrap();
+    assert!(data.len() > 16);
+
+    let pulled = client.pull_manifest("app", &digest).await.unwrap();
+    assert_eq!(pulled.data, data);
+}
+
+fn streamed_body(chunks: usize, byte: u8) -> reqwest::Body {
+    let chunks = (0..chunks).map(move |_| {
+        Ok::<_, std::io::Error>(hyper::body::Frame::data(axum::body::Bytes::from(vec![
+            byte;
+            1000
+        ])))
+    });
+    reqwest::Body::wrap(http_body_util::StreamBody::new(futures_util::stream::iter(
+        chunks,
+    )))
+}
+
+#[tokio::test]
+async fn test_streamed_manifests() {
+    let config = RegistryConfig::temp_dir()
+        .with_max_manifest_size(4096)
+        .with_chunked_manifests(256)
+        .with_streamed_manifests(true);
+    let server = RegistryServer::new(config).await.unwrap();
+    let http = reqwest::Client::new();
+    let url = |reference: &str| format!("{}/v2/app/manifests/{}", server.url(), reference);
+
+    let response = http
+        .put(url("v1"))
+        .header("Content-Type", OCI_MANIFEST_MEDIA_TYPE)
+        .body(streamed_body(3, b' '))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let digest = DigestPolicy::new()
+        .compute("sha256", &vec![b' '; 3000])
+        .unwrap();
+    assert_eq!(response.headers()["Docker-Content-Digest"], digest.as_str());
+
+    for reference in ["v1", digest.as_str()] {
+        let response = http.get(url(reference)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["Transfer-Encoding"], "chunked");
+        assert_eq!(response.headers()["Content-Type"], OCI_MANIFEST_MEDIA_TYPE);
+        assert_eq!(response.bytes().await.unwrap(), vec![b' '; 3000]);
+    }
+
+    // The digest is only known once the body has been stored aside.
+    let wrong = DigestPolicy::new().compute("sha256", b"other").unwrap();
+    let response = http
+        .put(url(&wrong))
+        .header("Content-Type", OCI_MANIFEST_MEDIA_TYPE)
+        .body(streamed_body(2, b'x'))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let response = http.get(url(&wrong)).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+
+    // Going over the limit partway through keeps the tag as it was.
+    let response = http
+        .put(url("v1"))
+        .header("Content-Type", OCI_MANIFEST_MEDIA_TYPE)
+        .body(streamed_body(5, b'y'))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 413);
+    let response = http.get(url("v1")).send().await.unwrap();
+    assert_eq!(response.bytes().await.unwrap(), vec![b' '; 3000]);
+
+    let tags: serde_json::Value = http
+        .get(format!("{}/v2/app/tags/list", server.url()))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(tags["tags"], serde_json::json!(["v1"]));
+}
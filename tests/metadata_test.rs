@@ -0,0 +1,61 @@
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+
+#[tokio::test]
+async fn test_repository_metadata_api() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    server
+        .set_repository_metadata("app", "owner", "team-web")
+        .await;
+
+    let http = reqwest::Client::new();
+    let url = format!("{}/admin/repositories/app/metadata", server.url());
+    let body: serde_json::Value = http.get(&url).send().await.unwrap().json().await.unwrap();
+    assert_eq!(body, serde_json::json!({ "owner": "team-web" }));
+
+    let response = http
+        .put(&url)
+        .json(&serde_json::json!({ "tier": "gold", "owner": "team-api" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let metadata = server.repository_metadata("app").await;
+    assert_eq!(metadata["owner"], "team-api");
+    assert_eq!(metadata["tier"], "gold");
+
+    let response = http.delete(format!("{}/tier", url)).send().await.unwrap();
+    assert_eq!(response.status(), 204);
+    let response = http.delete(format!("{}/tier", url)).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+    assert_eq!(
+        server.remove_repository_metadata("app", "owner").await,
+        Some("team-api".to_string())
+    );
+    assert!(server.repository_metadata("app").await.is_empty());
+
+    let response = http
+        .put(&url)
+        .json(&serde_json::json!({ "replicas": 3 }))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_client_error());
+}
+
+#[tokio::test]
+async fn test_repository_metadata_follows_rename() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    client
+        .push_image("old", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    server
+        .set_repository_metadata("old", "owner", "team-web")
+        .await;
+
+    server.rename_repo("old", "new").await.unwrap();
+
+    assert!(server.repository_metadata("old").await.is_empty());
+    assert_eq!(server.repository_metadata("new").await["owner"], "team-web");
+}
@@ -0,0 +1,83 @@
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+
+#[tokio::test]
+async fn test_per_route_metrics() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let layer = vec![1u8; 100_000];
+    let digest = client
+        .push_image("app", "v1", std::slice::from_ref(&layer))
+        .await
+        .unwrap();
+    client.pull_image("app", &digest).await.unwrap();
+
+    let metrics = server.metrics();
+    let finish = &metrics.routes["PUT /v2/{name}/blobs/uploads/{uuid}"];
+    assert_eq!(finish.latency.count(), 2);
+    assert!(finish.bytes_received >= layer.len() as u64);
+    assert!(finish.latency.p99() >= finish.latency.p50());
+
+    let pulls = &metrics.routes["GET /v2/{name}/blobs/{digest}"];
+    assert_eq!(pulls.latency.count(), 2);
+    assert!(pulls.bytes_sent >= layer.len() as u64);
+    assert_eq!(
+        metrics.routes["PUT /v2/{name}/manifests/{reference}"]
+            .latency
+            .count(),
+        1
+    );
+    assert!(metrics.bytes_received() >= layer.len() as u64);
+    assert!(metrics.send_rate() > 0.0);
+
+    let response = reqwest::get(format!("{}/v2/app/manifests/v1", server.url()))
+        .await
+        .unwrap();
+    assert!(response.content_length().is_some());
+
+    server.reset_metrics();
+    assert!(server.metrics().routes.is_empty());
+}
+
+#[tokio::test]
+async fn test_connection_stats() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let layers = [b"one".to_vec(), b"two".to_vec(), b"three".to_vec()];
+    client.push_image("app", "v1", &layers).await.unwrap();
+    client.pull_image("app", "v1").await.unwrap();
+
+    let connections = server.metrics().connections;
+    assert_eq!(connections.opened, 1);
+    assert_eq!(connections.reused, connections.requests - 1);
+    assert!(connections.requests_per_connection() > 1.0);
+
+    // A fresh client per request never reuses a connection.
+    server.reset_metrics();
+    for _ in 0..3 {
+        reqwest::get(format!("{}/v2/", server.url())).await.unwrap();
+    }
+    let connections = server.metrics().connections;
+    assert_eq!(connections.opened, 3);
+    assert_eq!(connections.reused, 0);
+}
+
+#[tokio::test]
+async fn test_connection_stats_after_reset_mid_connection() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let http = reqwest::Client::new();
+    let url = format!("{}/v2/", server.url());
+    for _ in 0..3 {
+        http.get(&url).send().await.unwrap();
+    }
+    assert_eq!(server.metrics().connections.opened, 1);
+
+    server.reset_metrics();
+    for _ in 0..2 {
+        http.get(&url).send().await.unwrap();
+    }
+    let connections = server.metrics().connections;
+    assert_eq!(connections.requests, 2);
+    assert_eq!(connections.opened, 1);
+    assert_eq!(connections.reused, 1);
+    assert_eq!(connections.requests_per_connection(), 2.0);
+}
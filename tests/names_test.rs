@@ -0,0 +1,117 @@
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+
+#[tokio::test]
+async fn test_multi_segment_repository_names() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let name = "myorg/myteam/app";
+    let digest = client
+        .push_image(name, "latest", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    assert_eq!(
+        client.pull_image(name, "latest").await.unwrap().digest,
+        digest
+    );
+    assert_eq!(
+        client.pull_image(name, &digest).await.unwrap().digest,
+        digest
+    );
+    let http = reqwest::Client::new();
+    let url = |path: &str| format!("{}{}", server.url(), path);
+
+    let response = http
+        .post(url("/v2/library/nginx/blobs/uploads/"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    let location = response.headers()["location"].to_str().unwrap().to_string();
+    assert!(
+        location.starts_with("/v2/library/nginx/blobs/uploads/"),
+        "{}",
+        location
+    );
+    let response = http.get(url(&location)).send().await.unwrap();
+    assert_eq!(response.status(), 204);
+
+    let response = http
+        .get(url("/v2/myorg/myteam/app/tags/list"))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["name"], name);
+    assert_eq!(body["tags"], serde_json::json!(["latest"]));
+
+    let response = http.get(url("/v2/_catalog")).send().await.unwrap();
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["repositories"], serde_json::json!([name]));
+
+    let response = http
+        .get(url("/v2/myorg/app/manifests/latest"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_admin_routes_take_multi_segment_names() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let name = "myorg/metadata/app";
+    let digest = client
+        .push_image(name, "latest", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let http = reqwest::Client::new();
+    let url = |path: &str| format!("{}/admin/repositories/{}/{}", server.url(), name, path);
+
+    let response = http.get(url("tags/latest/history")).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body[0]["digest"], digest);
+
+    let response = http
+        .put(url("metadata"))
+        .json(&serde_json::json!({ "owner": "team-web" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(server.repository_metadata(name).await["owner"], "team-web");
+    let response = http.delete(url("metadata/owner")).send().await.unwrap();
+    assert_eq!(response.status(), 204);
+    assert!(server.repository_metadata(name).await.is_empty());
+
+    let response = http.get(url("archive")).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn test_names_with_endpoint_segments() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let http = reqwest::Client::new();
+
+    for name in ["acme/tags/app", "foo/manifests", "blobs/uploads"] {
+        let digest = client
+            .push_image(name, "latest", &[name.as_bytes().to_vec()])
+            .await
+            .unwrap();
+        assert_eq!(
+            client.pull_image(name, "latest").await.unwrap().digest,
+            digest
+        );
+
+        let response = http
+            .get(format!("{}/v2/{}/tags/list", server.url(), name))
+            .send()
+            .await
+            .unwrap();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["name"], name);
+        assert_eq!(body["tags"], serde_json::json!(["latest"]));
+    }
+}
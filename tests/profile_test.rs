@@ -0,0 +1,408 @@
+use registry_testkit::{
+    AuthConfig, RegistryClient, RegistryConfig, RegistryProfile, RegistryServer,
+};
+use std::time::Duration;
+
+fn docker_hub() -> RegistryConfig {
+    RegistryConfig::memory()
+        .with_auth(
+            AuthConfig::bearer("registry.docker.io")
+                .with_anonymous_pull(true)
+                .with_user("alice", "secret"),
+        )
+        .emulate(RegistryProfile::DockerHub)
+}
+
+#[tokio::test]
+async fn test_docker_hub_requires_token_and_allows_anonymous_pull() {
+    let server = RegistryServer::new(docker_hub()).await.unwrap();
+
+    let response = reqwest::get(format!("{}/v2/", server.url())).await.unwrap();
+    assert_eq!(response.status(), 401);
+    let challenge = response.headers()["WWW-Authenticate"].to_str().unwrap();
+    assert!(challenge.starts_with("Bearer realm="));
+    assert!(challenge.contains("service=\"registry.docker.io\""));
+
+    let anonymous = RegistryClient::new(server.url());
+    assert!(anonymous
+        .push_image("busybox", "latest", &[b"layer".to_vec()])
+        .await
+        .is_err());
+
+    let alice = RegistryClient::new(server.url()).with_credentials("alice", "secret");
+    let digest = alice
+        .push_image("busybox", "latest", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    let image = anonymous.pull_image("busybox", "latest").await.unwrap();
+    assert_eq!(image.digest, digest);
+}
+
+#[tokio::test]
+async fn test_docker_hub_library_namespace_and_pull_counts() {
+    let server = RegistryServer::new(docker_hub()).await.unwrap();
+    let alice = RegistryClient::new(server.url()).with_credentials("alice", "secret");
+    alice
+        .push_manifest("nginx", "latest", "application/json", b"{}".to_vec())
+        .await
+        .unwrap();
+
+    alice.pull_manifest("nginx", "latest").await.unwrap();
+    alice.pull_manifest("nginx", "latest").await.unwrap();
+    assert_eq!(server.pull_count("nginx").await, 2);
+    assert_eq!(server.pull_count("library/nginx").await, 2);
+}
+
+#[tokio::test]
+async fn test_docker_hub_upload_stats_use_library_namespace() {
+    let server = RegistryServer::new(docker_hub()).await.unwrap();
+    let http = reqwest::Client::new();
+    let token: serde_json::Value = http
+        .get(format!(
+            "{}/token?service=registry.docker.io&scope=repository:library/nginx:pull,push",
+            server.url()
+        ))
+        .basic_auth("alice", Some("secret"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let response = http
+        .post(format!("{}/v2/nginx/blobs/uploads/", server.url()))
+        .bearer_auth(token["token"].as_str().unwrap())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    let stats = server.upload_stats().await;
+    assert_eq!(stats.open[0].repository, "library/nginx");
+}
+
+#[tokio::test]
+async fn test_docker_hub_rate_limit_headers() {
+    let server = RegistryServer::new(docker_hub()).await.unwrap();
+    let alice = RegistryClient::new(server.url()).with_credentials("alice", "secret");
+    alice
+        .push_manifest("alpine", "3", "application/json", b"{}".to_vec())
+        .await
+        .unwrap();
+
+    let token: serde_json::Value = reqwest::get(format!(
+        "{}/token?service=registry.docker.io&scope=repository:library/alpine:pull",
+        server.url()
+    ))
+    .await
+    .unwrap()
+    .json()
+    .await
+    .unwrap();
+    let token = token["token"].as_str().unwrap();
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/v2/alpine/manifests/3", server.url());
+    let head = client.head(&url).bearer_auth(token).send().await.unwrap();
+    assert_eq!(head.headers()["ratelimit-remaining"], "100;w=21600");
+
+    let get = client.get(&url).bearer_auth(token).send().await.unwrap();
+    assert_eq!(get.status(), 200);
+    assert_eq!(get.headers()["ratelimit-limit"], "100;w=21600");
+    assert_eq!(get.headers()["ratelimit-remaining"], "99;w=21600");
+    assert!(get.headers().contains_key("docker-ratelimit-source"));
+}
+
+#[tokio::test]
+async fn test_ecr_authorization_token_login() {
+    let server = RegistryServer::new(RegistryConfig::memory().emulate(RegistryProfile::Ecr))
+        .await
+        .unwrap();
+
+    let response = reqwest::get(format!("{}/v2/app/manifests/latest", server.url()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "DENIED");
+
+    let token = server.authorization_token().unwrap();
+    let remaining = token
+        .expires_at
+        .duration_since(std::time::SystemTime::now())
+        .unwrap();
+    assert!(remaining > Duration::from_secs(11 * 60 * 60));
+    assert!(remaining <= Duration::from_secs(12 * 60 * 60));
+
+    let (user, password) = token.credentials().unwrap();
+    assert_eq!(user, "AWS");
+    let client = RegistryClient::new(server.url()).with_credentials(user, password);
+    let digest = client
+        .push_image("app", "latest", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    assert_eq!(
+        client.pull_image("app", "latest").await.unwrap().digest,
+        digest
+    );
+}
+
+#[tokio::test]
+async fn test_ecr_rejects_expired_token() {
+    let config = RegistryConfig::memory()
+        .with_auth(AuthConfig::basic().with_token_ttl(Duration::ZERO))
+        .emulate(RegistryProfile::Ecr);
+    let server = RegistryServer::new(config).await.unwrap();
+    let (user, password) = server.authorization_token().unwrap().credentials().unwrap();
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/v2/app/manifests/latest", server.url()))
+        .basic_auth(user, Some(password))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "DENIED");
+    assert!(body["errors"][0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("expired"));
+}
+
+#[tokio::test]
+async fn test_ecr_error_payloads_and_no_catalog() {
+    let config = RegistryConfig::memory()
+        .with_auth(AuthConfig::basic().with_user("ci", "pw"))
+        .emulate(RegistryProfile::Ecr);
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = reqwest::Client::new();
+
+    let missing = client
+        .get(format!("{}/v2/app/manifests/nope", server.url()))
+        .basic_auth("ci", Some("pw"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+    let body: serde_json::Value = missing.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "MANIFEST_UNKNOWN");
+    assert_eq!(body["errors"][0]["message"], "Requested image not found");
+
+    let catalog = client
+        .get(format!("{}/v2/_catalog", server.url()))
+        .basic_auth("ci", Some("pw"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(catalog.status(), 405);
+    let body: serde_json::Value = catalog.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "UNSUPPORTED");
+}
+
+#[tokio::test]
+async fn test_immutable_tags() {
+    let server = RegistryServer::new(RegistryConfig::memory().with_immutable_tags(true))
+        .await
+        .unwrap();
+    let client = RegistryClient::new(server.url());
+    client
+        .push_manifest("app", "v1", "application/json", b"{}".to_vec())
+        .await
+        .unwrap();
+    client
+        .push_manifest("app", "v1", "application/json", b"{}".to_vec())
+        .await
+        .unwrap();
+
+    let response = reqwest::Client::new()
+        .put(format!("{}/v2/app/manifests/v1", server.url()))
+        .header("Content-Type", "application/json")
+        .body(r#"{"changed":true}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "TAG_INVALID");
+
+    let digest = client
+        .push_manifest(
+            "app",
+            "v2",
+            "application/json",
+            br#"{"changed":true}"#.to_vec(),
+        )
+        .await
+        .unwrap();
+    client
+        .push_manifest(
+            "app",
+            &digest,
+            "application/json",
+            br#"{"changed":true}"#.to_vec(),
+        )
+        .await
+        .unwrap();
+}
+
+fn ghcr() -> RegistryConfig {
+    RegistryConfig::memory()
+        .with_auth(
+            AuthConfig::bearer("ghcr.io")
+                .with_anonymous_pull(true)
+                .with_user("alice", "ghp_alice")
+                .with_user("bob", "ghp_bob")
+                .with_private_repository("bob"),
+        )
+        .emulate(RegistryProfile::Ghcr)
+}
+
+#[tokio::test]
+async fn test_ghcr_namespaces_and_public_packages() {
+    let server = RegistryServer::new(ghcr()).await.unwrap();
+    let alice = RegistryClient::new(server.url()).with_credentials("alice", "ghp_alice");
+    let bob = RegistryClient::new(server.url()).with_credentials("bob", "ghp_bob");
+
+    let digest = alice
+        .push_image("alice", "latest", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    assert!(bob
+        .push_manifest("alice", "latest", "application/json", b"{}".to_vec())
+        .await
+        .is_err());
+
+    let anonymous = RegistryClient::new(server.url());
+    let image = anonymous.pull_image("alice", "latest").await.unwrap();
+    assert_eq!(image.digest, digest);
+
+    let token: serde_json::Value = reqwest::Client::new()
+        .get(format!(
+            "{}/token?service=ghcr.io&scope=repository:alice:pull,push",
+            server.url()
+        ))
+        .basic_auth("alice", Some("ghp_alice"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(token["token"].is_string());
+    assert!(token.get("access_token").is_none());
+}
+
+#[tokio::test]
+async fn test_ghcr_private_packages_are_hidden() {
+    let server = RegistryServer::new(ghcr()).await.unwrap();
+    let bob = RegistryClient::new(server.url()).with_credentials("bob", "ghp_bob");
+    bob.push_manifest("bob", "v1", "application/json", b"{}".to_vec())
+        .await
+        .unwrap();
+
+    let url = format!("{}/v2/bob/manifests/v1", server.url());
+    let client = reqwest::Client::new();
+
+    let with_pat = client
+        .get(&url)
+        .basic_auth("bob", Some("ghp_bob"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(with_pat.status(), 200);
+
+    let as_alice = client
+        .get(&url)
+        .basic_auth("alice", Some("ghp_alice"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(as_alice.status(), 404);
+    let body: serde_json::Value = as_alice.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "NAME_UNKNOWN");
+
+    let anonymous = RegistryClient::new(server.url());
+    match anonymous.pull_manifest("bob", "v1").await {
+        Err(registry_testkit::RegistryError::UnexpectedStatus { status, .. }) => {
+            assert_eq!(status, 404)
+        }
+        other => panic!("expected 404, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_quay_signatures_header_and_robot_accounts() {
+    let config = RegistryConfig::memory()
+        .with_auth(AuthConfig::bearer("quay.io").with_user("acme+ci", "robot-token"))
+        .emulate(RegistryProfile::Quay);
+    let server = RegistryServer::new(config).await.unwrap();
+
+    let response = reqwest::get(format!("{}/v2/", server.url())).await.unwrap();
+    assert_eq!(response.headers()["X-Registry-Supports-Signatures"], "1");
+
+    let robot = RegistryClient::new(server.url()).with_credentials("acme+ci", "robot-token");
+    robot
+        .push_manifest("acme", "v1", "application/json", b"{}".to_vec())
+        .await
+        .unwrap();
+    assert!(robot
+        .push_manifest("other", "v1", "application/json", b"{}".to_vec())
+        .await
+        .is_err());
+
+    let missing = reqwest::get(format!("{}/v2/acme/manifests/nope", server.url()))
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 401);
+    let body: serde_json::Value = missing.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "UNAUTHORIZED");
+    assert!(body["errors"][0]["detail"].is_object());
+}
+
+#[tokio::test]
+async fn test_harbor_error_codes_and_robot_accounts() {
+    let config = RegistryConfig::memory()
+        .with_auth(
+            AuthConfig::bearer("harbor-registry")
+                .with_anonymous_pull(true)
+                .with_user("robot$library+ci", "robot-secret"),
+        )
+        .emulate(RegistryProfile::Harbor);
+    let server = RegistryServer::new(config).await.unwrap();
+
+    let robot =
+        RegistryClient::new(server.url()).with_credentials("robot$library+ci", "robot-secret");
+    robot
+        .push_manifest("library", "v1", "application/json", b"{}".to_vec())
+        .await
+        .unwrap();
+    assert!(robot
+        .push_manifest("other", "v1", "application/json", b"{}".to_vec())
+        .await
+        .is_err());
+
+    let token: serde_json::Value = reqwest::get(format!(
+        "{}/token?service=harbor-registry&scope=repository:library:pull",
+        server.url()
+    ))
+    .await
+    .unwrap()
+    .json()
+    .await
+    .unwrap();
+    let missing = reqwest::Client::new()
+        .get(format!("{}/v2/library/manifests/v2", server.url()))
+        .bearer_auth(token["token"].as_str().unwrap())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+    let body: serde_json::Value = missing.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "NOT_FOUND");
+    assert_eq!(
+        body["errors"][0]["message"],
+        "artifact library:v2 not found"
+    );
+}
@@ -0,0 +1,79 @@
+use registry_testkit::{
+    LocationStyle, ProxySimulation, RegistryClient, RegistryConfig, RegistryServer,
+};
+
+#[tokio::test]
+async fn test_proxy_rewrites_request_headers() {
+    let config = RegistryConfig::memory()
+        .with_received_requests(10)
+        .with_location_style(LocationStyle::Absolute);
+    let server = RegistryServer::new(config).await.unwrap();
+    RegistryClient::new(server.url())
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    server.set_proxy_simulation(Some(
+        ProxySimulation::new()
+            .with_host("registry.internal:5000")
+            .strip_header("x-trace-id"),
+    ));
+
+    let http = reqwest::Client::new();
+    http.get(format!("{}/v2/app/manifests/v1", server.url()))
+        .header("X-Forwarded-For", "10.0.0.1")
+        .header("X-Trace-Id", "abc")
+        .send()
+        .await
+        .unwrap();
+    let received = server.received_requests("app").pop().unwrap();
+    assert_eq!(received.header("x-forwarded-for"), ["10.0.0.1, 127.0.0.1"]);
+    assert_eq!(
+        received.header("x-forwarded-host"),
+        [server.addr().to_string()]
+    );
+    assert_eq!(received.header("x-forwarded-proto"), ["https"]);
+    assert_eq!(received.header("host"), ["registry.internal:5000"]);
+    assert!(received.header("x-trace-id").is_empty());
+
+    // Absolute locations are built from the rewritten host, which clients
+    // outside the proxy cannot reach.
+    let response = http
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    let location = response.headers()["location"].to_str().unwrap();
+    assert!(location.starts_with("http://registry.internal:5000/v2/app/blobs/uploads/"));
+}
+
+#[tokio::test]
+async fn test_proxy_http10_closes_connections() {
+    let server = RegistryServer::new(
+        RegistryConfig::memory().with_proxy_simulation(ProxySimulation::new().with_http10(true)),
+    )
+    .await
+    .unwrap();
+    let client = RegistryClient::new(server.url());
+    let layers = [vec![7u8; 100_000], b"small".to_vec()];
+    let digest = client.push_image("app", "v1", &layers).await.unwrap();
+    let image = client.pull_image("app", "v1").await.unwrap();
+    assert_eq!(image.digest, digest);
+    assert_eq!(image.layers, layers);
+
+    let response = reqwest::get(format!("{}/v2/app/manifests/v1", server.url()))
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["connection"], "close");
+    assert!(response.headers().get("transfer-encoding").is_none());
+    assert!(response.content_length().is_some());
+
+    let connections = server.metrics().connections;
+    assert_eq!(connections.reused, 0);
+    assert_eq!(connections.opened, connections.requests);
+
+    // Switched off, the same client keeps its connection again.
+    server.set_proxy_simulation(None);
+    server.reset_metrics();
+    client.pull_image("app", "v1").await.unwrap();
+    assert!(server.metrics().connections.reused > 0);
+}
@@ -0,0 +1,46 @@
+use registry_testkit::{QuotaConfig, RegistryConfig, RegistryServer};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_quota_returns_429_when_exceeded() {
+    let quota = QuotaConfig::new(2, Duration::from_secs(60));
+    let server = RegistryServer::new(RegistryConfig::memory().with_quota(quota))
+        .await
+        .unwrap();
+    let client = reqwest::Client::new();
+    let url = format!("{}/v2/", server.url());
+
+    assert_eq!(client.get(&url).send().await.unwrap().status(), 200);
+    assert_eq!(client.get(&url).send().await.unwrap().status(), 200);
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 429);
+    assert!(response.headers().contains_key("Retry-After"));
+}
+
+#[tokio::test]
+async fn test_quota_keyed_by_forwarded_header() {
+    let quota = QuotaConfig::new(1, Duration::from_secs(60))
+        .behind_proxy()
+        .with_client_limit("10.0.0.2", 3);
+    let server = RegistryServer::new(RegistryConfig::memory().with_quota(quota))
+        .await
+        .unwrap();
+    let client = reqwest::Client::new();
+    let url = format!("{}/v2/", server.url());
+
+    let send = |ip: &'static str| {
+        client
+            .get(&url)
+            .header("X-Forwarded-For", format!("{}, 192.168.0.1", ip))
+            .send()
+    };
+
+    assert_eq!(send("10.0.0.1").await.unwrap().status(), 200);
+    assert_eq!(send("10.0.0.1").await.unwrap().status(), 429);
+
+    for _ in 0..3 {
+        assert_eq!(send("10.0.0.2").await.unwrap().status(), 200);
+    }
+    assert_eq!(send("10.0.0.2").await.unwrap().status(), 429);
+}
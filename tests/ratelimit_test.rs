@@ -0,0 +1,50 @@
+use registry_testkit::{PullRateLimit, RegistryClient, RegistryConfig, RegistryServer};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_pull_budget_decrements_and_resets() {
+    let config = RegistryConfig::memory().with_pull_rate_limit(
+        PullRateLimit::new(2, Duration::from_secs(1)).with_source("test-source"),
+    );
+    let server = RegistryServer::new(config).await.unwrap();
+    RegistryClient::new(server.url())
+        .push_manifest("app", "latest", "application/json", b"{}".to_vec())
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/v2/app/manifests/latest", server.url());
+
+    let head = client.head(&url).send().await.unwrap();
+    assert_eq!(head.headers()["ratelimit-remaining"], "2;w=1");
+
+    let first = client.get(&url).send().await.unwrap();
+    assert_eq!(first.status(), 200);
+    assert_eq!(first.headers()["ratelimit-limit"], "2;w=1");
+    assert_eq!(first.headers()["ratelimit-remaining"], "1;w=1");
+    assert_eq!(first.headers()["docker-ratelimit-source"], "test-source");
+
+    let second = client.get(&url).send().await.unwrap();
+    assert_eq!(second.headers()["ratelimit-remaining"], "0;w=1");
+
+    let limited = client.get(&url).send().await.unwrap();
+    assert_eq!(limited.status(), 429);
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let reset = client.get(&url).send().await.unwrap();
+    assert_eq!(reset.status(), 200);
+    assert_eq!(reset.headers()["ratelimit-remaining"], "1;w=1");
+}
+
+#[tokio::test]
+async fn test_blob_requests_do_not_consume_budget() {
+    let config = RegistryConfig::memory()
+        .with_pull_rate_limit(PullRateLimit::new(1, Duration::from_secs(60)));
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client.push_blob("app", b"data".to_vec()).await.unwrap();
+
+    for _ in 0..3 {
+        client.pull_blob("app", &digest).await.unwrap();
+    }
+}
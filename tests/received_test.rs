@@ -0,0 +1,67 @@
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+
+#[tokio::test]
+async fn test_received_headers() {
+    let config = RegistryConfig::memory().with_received_requests(2);
+    let server = RegistryServer::new(config).await.unwrap();
+    let http = reqwest::Client::new();
+    for tag in ["v1", "v2", "v3"] {
+        http.get(format!("{}/v2/app/manifests/{}", server.url(), tag))
+            .header("X-Forwarded-For", "10.0.0.1")
+            .header("Authorization", "Bearer secret-token")
+            .send()
+            .await
+            .unwrap();
+    }
+
+    let requests = server.received_requests("app");
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].uri, "/v2/app/manifests/v2");
+    assert_eq!(requests[1].uri, "/v2/app/manifests/v3");
+    assert_eq!(requests[1].method, "GET");
+    assert_eq!(requests[1].header("x-forwarded-for"), ["10.0.0.1"]);
+    let authorization = requests[1].header("Authorization");
+    assert_eq!(authorization.len(), 1);
+    assert!(!authorization[0].contains("secret-token"));
+
+    let body: serde_json::Value = http
+        .get(format!("{}/admin/repositories/app/requests", server.url()))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let requests = body.as_array().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1]["uri"], "/v2/app/manifests/v3");
+    assert!(requests[1]["headers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|header| header["name"] == "x-forwarded-for" && header["value"] == "10.0.0.1"));
+}
+
+#[tokio::test]
+async fn test_received_per_repository() {
+    let config = RegistryConfig::memory().with_received_requests(10);
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    client.push_blob("app", b"layer".to_vec()).await.unwrap();
+
+    assert!(!server.received_requests("app").is_empty());
+    assert!(server.received_requests("other").is_empty());
+}
+
+#[tokio::test]
+async fn test_received_off_by_default() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    client.push_blob("app", b"layer".to_vec()).await.unwrap();
+    assert!(server.received_requests("app").is_empty());
+
+    let response = reqwest::get(format!("{}/admin/repositories/app/requests", server.url()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
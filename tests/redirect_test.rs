@@ -0,0 +1,28 @@
+use registry_testkit::{BlobRedirectConfig, RegistryClient, RegistryConfig, RegistryServer};
+
+#[tokio::test]
+async fn test_blob_get_redirects_to_signed_url() {
+    let config = RegistryConfig::memory().with_blob_redirects(BlobRedirectConfig::new());
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client.push_blob("app", b"layer".to_vec()).await.unwrap();
+
+    let no_redirects = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let response = no_redirects
+        .get(format!("{}/v2/app/blobs/{}", server.url(), digest))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 307);
+    let location = response.headers()["Location"].to_str().unwrap().to_string();
+    assert!(location.starts_with(&server.blob_server_url().unwrap()));
+
+    let tampered = location.replace("signature=", "signature=0");
+    let response = no_redirects.get(tampered).send().await.unwrap();
+    assert_eq!(response.status(), 403);
+
+    assert_eq!(client.pull_blob("app", &digest).await.unwrap(), b"layer");
+}
@@ -0,0 +1,117 @@
+use registry_testkit::{Reference, RegistryClient, RegistryConfig, RegistryError, RegistryServer};
+
+const DIGEST: &str = "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+#[test]
+fn test_reference_parsing_and_normalization() {
+    for (input, registry, repository, tag, digest, full, familiar) in [
+        (
+            "busybox",
+            "docker.io",
+            "library/busybox",
+            Some("latest"),
+            None,
+            "docker.io/library/busybox:latest",
+            "busybox:latest",
+        ),
+        (
+            "index.docker.io/library/busybox:1.36",
+            "docker.io",
+            "library/busybox",
+            Some("1.36"),
+            None,
+            "docker.io/library/busybox:1.36",
+            "busybox:1.36",
+        ),
+        (
+            "grafana/loki",
+            "docker.io",
+            "grafana/loki",
+            Some("latest"),
+            None,
+            "docker.io/grafana/loki:latest",
+            "grafana/loki:latest",
+        ),
+        (
+            "localhost/app",
+            "localhost",
+            "app",
+            Some("latest"),
+            None,
+            "localhost/app:latest",
+            "localhost/app:latest",
+        ),
+        (
+            "127.0.0.1:5000/team/app:v1",
+            "127.0.0.1:5000",
+            "team/app",
+            Some("v1"),
+            None,
+            "127.0.0.1:5000/team/app:v1",
+            "127.0.0.1:5000/team/app:v1",
+        ),
+    ] {
+        let reference = Reference::parse(input).unwrap();
+        assert_eq!(reference.registry(), registry, "{}", input);
+        assert_eq!(reference.repository(), repository, "{}", input);
+        assert_eq!(reference.tag(), tag, "{}", input);
+        assert_eq!(reference.digest(), digest, "{}", input);
+        assert_eq!(reference.to_string(), full, "{}", input);
+        assert_eq!(reference.familiar(), familiar, "{}", input);
+        assert_eq!(Reference::parse(familiar).unwrap(), reference);
+    }
+
+    let pinned: Reference = format!("ghcr.io/org/app:v1@{}", DIGEST).parse().unwrap();
+    assert_eq!(pinned.tag(), Some("v1"));
+    assert_eq!(pinned.digest(), Some(DIGEST));
+    assert_eq!(pinned.reference(), DIGEST);
+    let by_digest = Reference::parse(&format!("ghcr.io/org/app@{}", DIGEST)).unwrap();
+    assert_eq!(by_digest.tag(), None);
+    assert_eq!(by_digest.to_string(), format!("ghcr.io/org/app@{}", DIGEST));
+
+    let moved = pinned.with_registry("http://127.0.0.1:5000/");
+    assert_eq!(
+        moved.to_string(),
+        format!("127.0.0.1:5000/org/app:v1@{}", DIGEST)
+    );
+
+    for invalid in ["App", "app:", "app:-v1", "a//b", "app_-x", "-app", "app/"] {
+        assert!(
+            matches!(
+                Reference::parse(invalid),
+                Err(RegistryError::NameInvalid(_))
+            ),
+            "{}",
+            invalid
+        );
+    }
+    assert!(matches!(
+        Reference::parse("app@sha256:ABC"),
+        Err(RegistryError::DigestInvalid(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_references_drive_client_and_server_helpers() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let reference = server.reference("app:v1").unwrap();
+    assert_eq!(reference.registry(), format!("127.0.0.1:{}", server.port()));
+    assert_eq!(server.reference("docker.io/app:v1").unwrap(), reference);
+
+    let digest = client
+        .push_reference(&reference, &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    assert!(server
+        .verify_reference(&reference)
+        .await
+        .unwrap()
+        .is_complete());
+
+    let pinned = reference.with_digest(digest.clone());
+    let image = client.pull_reference(&pinned).await.unwrap();
+    assert_eq!(image.digest, digest);
+    assert_eq!(image.layers, vec![b"layer".to_vec()]);
+}
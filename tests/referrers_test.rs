@@ -0,0 +1,293 @@
+use registry_testkit::builder::{ArtifactBuilder, BuiltImage, ImageBuilder, Layer};
+use registry_testkit::oci::manifest::OCI_INDEX_MEDIA_TYPE;
+use registry_testkit::referrers::{
+    fallback_tag, is_fallback_tag_of, SIGNATURE_TAG_SUFFIX, SUBJECT_HEADER,
+};
+use registry_testkit::{
+    ApiEndpoint, ReferrerDeletePolicy, RegistryClient, RegistryConfig, RegistryServer,
+};
+
+#[test]
+fn test_fallback_tags() {
+    let digest = "sha256:0123abcd";
+    assert_eq!(fallback_tag(digest), "sha256-0123abcd");
+    assert!(is_fallback_tag_of("sha256-0123abcd", digest));
+    assert!(is_fallback_tag_of("sha256-0123abcd.sig", digest));
+    assert!(is_fallback_tag_of("sha256-0123abcd.att", digest));
+    assert!(!is_fallback_tag_of("sha256-0123abcdef", digest));
+    assert!(!is_fallback_tag_of("v1", digest));
+}
+
+#[tokio::test]
+async fn test_referrers_through_fallback_tag_schema() {
+    let config = RegistryConfig::memory().with_endpoint_disabled(ApiEndpoint::Referrers);
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let image = ImageBuilder::new()
+        .layer(Layer::new(b"app".to_vec()))
+        .build();
+    image.push(&client, "app", "v1").await.unwrap();
+    assert!(client
+        .referrers("app", &image.digest)
+        .await
+        .unwrap()
+        .manifests
+        .is_empty());
+
+    let signature = ArtifactBuilder::new("application/vnd.dev.cosign.artifact.sig.v1+json")
+        .blob(Layer::with_media_type(
+            b"sig".to_vec(),
+            "application/octet-stream",
+        ))
+        .subject(image.descriptor())
+        .build();
+    let sbom = ArtifactBuilder::new("application/spdx+json")
+        .blob(Layer::with_media_type(
+            b"{}".to_vec(),
+            "application/spdx+json",
+        ))
+        .annotation("org.opencontainers.image.created", "2024-01-01T00:00:00Z")
+        .subject(image.descriptor())
+        .build();
+    signature.attach(&client, "app").await.unwrap();
+    sbom.attach(&client, "app").await.unwrap();
+    // Attaching again leaves a single entry.
+    sbom.attach(&client, "app").await.unwrap();
+
+    let index = client.referrers("app", &image.digest).await.unwrap();
+    assert_eq!(index.media_type.as_deref(), Some(OCI_INDEX_MEDIA_TYPE));
+    let digests: Vec<_> = index.manifests.iter().map(|d| d.digest.as_str()).collect();
+    assert_eq!(digests, [signature.digest.as_str(), sbom.digest.as_str()]);
+    assert_eq!(
+        index.manifests[1].artifact_type.as_deref(),
+        Some("application/spdx+json")
+    );
+    assert_eq!(
+        index.manifests[1].annotations["org.opencontainers.image.created"],
+        "2024-01-01T00:00:00Z"
+    );
+
+    let cosign_tag = format!("{}{}", fallback_tag(&image.digest), SIGNATURE_TAG_SUFFIX);
+    signature.push(&client, "app", &cosign_tag).await.unwrap();
+    assert_eq!(
+        server.fallback_tags("app", &image.digest).await.unwrap(),
+        [fallback_tag(&image.digest), cosign_tag]
+    );
+
+    let response = reqwest::get(format!(
+        "{}/v2/app/referrers/{}",
+        server.url(),
+        image.digest
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+/// Pushes an image to `app` as `v1` and attaches an SBOM to it, a
+/// signature to the SBOM, and a cosign signature tag to the image.
+async fn referrer_graph(client: &RegistryClient) -> (BuiltImage, BuiltImage, BuiltImage, String) {
+    let image = ImageBuilder::new()
+        .layer(Layer::new(b"app".to_vec()))
+        .build();
+    image.push(client, "app", "v1").await.unwrap();
+    let sbom = ArtifactBuilder::new("application/spdx+json")
+        .subject(image.descriptor())
+        .build();
+    sbom.attach(client, "app").await.unwrap();
+    let signature = ArtifactBuilder::new("application/vnd.dev.cosign.artifact.sig.v1+json")
+        .subject(sbom.descriptor())
+        .build();
+    signature.attach(client, "app").await.unwrap();
+    let cosign = ArtifactBuilder::new("application/vnd.dev.cosign.simplesigning.v1+json")
+        .annotation("dev.cosignproject.cosign/signature", "MEUCIQ")
+        .build();
+    let cosign_tag = format!("{}{}", fallback_tag(&image.digest), SIGNATURE_TAG_SUFFIX);
+    cosign.push(client, "app", &cosign_tag).await.unwrap();
+    (image, sbom, signature, cosign.digest)
+}
+
+#[tokio::test]
+async fn test_deleting_referrers_updates_fallback_index() {
+    // Without the referrers API, clients keep fallback indexes to update.
+    let config = RegistryConfig::memory().with_endpoint_disabled(ApiEndpoint::Referrers);
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let (image, sbom, signature, _) = referrer_graph(&client).await;
+    let extra = ArtifactBuilder::new("application/vnd.example+json")
+        .subject(image.descriptor())
+        .build();
+    extra.attach(&client, "app").await.unwrap();
+
+    server.delete_manifest("app", &extra.digest).await.unwrap();
+    let index = client.referrers("app", &image.digest).await.unwrap();
+    let digests: Vec<_> = index.manifests.iter().map(|d| d.digest.as_str()).collect();
+    assert_eq!(digests, [sbom.digest.as_str()]);
+
+    // The last referrer takes the fallback index with it.
+    server
+        .delete_manifest("app", &signature.digest)
+        .await
+        .unwrap();
+    assert!(client
+        .referrers("app", &sbom.digest)
+        .await
+        .unwrap()
+        .manifests
+        .is_empty());
+    assert!(server
+        .fallback_tags("app", &sbom.digest)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Orphaned by default: the SBOM outlives the image.
+    server.delete_manifest("app", "v1").await.unwrap();
+    server.delete_manifest("app", &image.digest).await.unwrap();
+    assert!(client.pull_manifest("app", &sbom.digest).await.is_ok());
+    assert_eq!(
+        server
+            .fallback_tags("app", &image.digest)
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn test_cascading_referrer_deletes() {
+    let config = RegistryConfig::memory().with_referrer_deletes(ReferrerDeletePolicy::Cascade);
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let (image, sbom, signature, cosign) = referrer_graph(&client).await;
+
+    // Deleting the tag leaves the manifest and its referrers.
+    server.delete_manifest("app", "v1").await.unwrap();
+    assert!(client.pull_manifest("app", &sbom.digest).await.is_ok());
+
+    server.delete_manifest("app", &image.digest).await.unwrap();
+    for digest in [&sbom.digest, &signature.digest, &cosign] {
+        assert!(
+            client.pull_manifest("app", digest).await.is_err(),
+            "{} survived",
+            digest
+        );
+    }
+    assert!(server
+        .fallback_tags("app", &image.digest)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(server
+        .fallback_tags("app", &sbom.digest)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_referrers_api_filters_by_artifact_type() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let (image, sbom, _, _) = referrer_graph(&client).await;
+    let signature = ArtifactBuilder::new("application/vnd.dev.cosign.artifact.sig.v1+json")
+        .subject(image.descriptor())
+        .build();
+    signature.attach(&client, "app").await.unwrap();
+
+    let url = format!("{}/v2/app/referrers/{}", server.url(), image.digest);
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().get("OCI-Filters-Applied").is_none());
+    let index: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(index["mediaType"], OCI_INDEX_MEDIA_TYPE);
+    assert_eq!(index["manifests"].as_array().unwrap().len(), 2);
+
+    let response = reqwest::get(format!("{}?artifactType=application/spdx%2Bjson", url))
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["OCI-Filters-Applied"], "artifactType");
+    let index: serde_json::Value = response.json().await.unwrap();
+    let digests: Vec<_> = index["manifests"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["digest"].as_str().unwrap())
+        .collect();
+    assert_eq!(digests, [sbom.digest.as_str()]);
+}
+
+#[tokio::test]
+async fn test_referrers_filtering_disabled() {
+    let config = RegistryConfig::memory().with_referrers_filtering(false);
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let (image, _, _, _) = referrer_graph(&client).await;
+    let signature = ArtifactBuilder::new("application/vnd.dev.cosign.artifact.sig.v1+json")
+        .subject(image.descriptor())
+        .build();
+    signature.attach(&client, "app").await.unwrap();
+
+    let response = reqwest::get(format!(
+        "{}/v2/app/referrers/{}?artifactType=application/spdx%2Bjson",
+        server.url(),
+        image.digest
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().get("OCI-Filters-Applied").is_none());
+    let index: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(index["manifests"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_subject_acknowledged_and_indexed() {
+    for config in [RegistryConfig::memory(), RegistryConfig::temp_dir()] {
+        let server = RegistryServer::new(config).await.unwrap();
+        let client = RegistryClient::new(server.url());
+        let image = ImageBuilder::new().build();
+        image.push(&client, "app", "v1").await.unwrap();
+        let sbom = ArtifactBuilder::new("application/spdx+json")
+            .subject(image.descriptor())
+            .build();
+
+        let response = reqwest::Client::new()
+            .put(format!("{}/v2/app/manifests/{}", server.url(), sbom.digest))
+            .header("Content-Type", &sbom.media_type)
+            .body(sbom.manifest.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 201);
+        assert_eq!(response.headers()[SUBJECT_HEADER], image.digest.as_str());
+
+        // The registry indexes the referrer, so no fallback tag is needed.
+        sbom.attach(&client, "app").await.unwrap();
+        assert!(server
+            .fallback_tags("app", &image.digest)
+            .await
+            .unwrap()
+            .is_empty());
+        let storage = server.storage();
+        assert_eq!(
+            storage.list_referrers("app", &image.digest).await.unwrap(),
+            [sbom.digest.as_str()]
+        );
+        assert!(storage
+            .list_referrers("other", &image.digest)
+            .await
+            .unwrap()
+            .is_empty());
+
+        server.delete_manifest("app", &sbom.digest).await.unwrap();
+        assert!(client
+            .referrers("app", &image.digest)
+            .await
+            .unwrap()
+            .manifests
+            .is_empty());
+    }
+}
@@ -0,0 +1,76 @@
+use registry_testkit::{
+    AuthConfig, RegistryClient, RegistryConfig, RegistryEvent, RegistryServer, ReplicationConfig,
+};
+
+#[tokio::test]
+async fn test_pushes_are_replicated() {
+    let remote_config =
+        RegistryConfig::memory().with_auth(AuthConfig::bearer("staging").with_user("ci", "secret"));
+    let remote = RegistryServer::new(remote_config).await.unwrap();
+    let replication = ReplicationConfig::new(remote.url()).with_credentials("ci", "secret");
+    let server = RegistryServer::new(RegistryConfig::memory().with_replication(replication))
+        .await
+        .unwrap();
+    let mut events = server.subscribe();
+
+    let client = RegistryClient::new(server.url());
+    client
+        .push_image("app", "v1", &[b"old".to_vec()])
+        .await
+        .unwrap();
+    let digest = client
+        .push_image("app", "v1", &[b"new".to_vec(), b"layer".to_vec()])
+        .await
+        .unwrap();
+    server.wait_for_replication().await;
+
+    let status = server.replication_status();
+    assert_eq!(status.pending, 0);
+    assert_eq!(status.replicated, 2);
+    assert!(status.failures.is_empty());
+    assert_eq!(
+        events.recv().await.unwrap(),
+        RegistryEvent::Replicated {
+            repository: "app".to_string(),
+            reference: "v1".to_string(),
+        }
+    );
+
+    let remote_client = RegistryClient::new(remote.url()).with_credentials("ci", "secret");
+    let image = remote_client.pull_image("app", "v1").await.unwrap();
+    assert_eq!(image.digest, digest);
+    assert_eq!(image.layers, [b"new".to_vec(), b"layer".to_vec()]);
+}
+
+#[tokio::test]
+async fn test_replication_failures_are_reported() {
+    let remote_config =
+        RegistryConfig::memory().with_auth(AuthConfig::basic().with_user("ci", "secret"));
+    let remote = RegistryServer::new(remote_config).await.unwrap();
+    let replication = ReplicationConfig::new(remote.url()).with_credentials("ci", "wrong");
+    let server = RegistryServer::new(RegistryConfig::memory().with_replication(replication))
+        .await
+        .unwrap();
+    let mut events = server.subscribe();
+
+    let client = RegistryClient::new(server.url());
+    client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    server.wait_for_replication().await;
+
+    let status = server.replication_status();
+    assert_eq!(status.replicated, 0);
+    assert_eq!(status.failures.len(), 1);
+    assert_eq!(status.failures[0].repository, "app");
+    assert_eq!(status.failures[0].reference, "v1");
+    assert!(status.failures[0].error.contains("401"));
+    assert!(matches!(
+        events.recv().await.unwrap(),
+        RegistryEvent::ReplicationFailed { .. }
+    ));
+
+    // The local push itself is unaffected.
+    assert!(client.pull_image("app", "v1").await.is_ok());
+}
@@ -0,0 +1,64 @@
+use registry_testkit::{
+    RegistryClient, RegistryConfig, RegistryEvent, RegistryServer, RetentionPolicy,
+};
+use std::time::Duration;
+
+async fn push_tags(client: &RegistryClient, repository: &str, tags: &[&str]) {
+    for tag in tags {
+        client
+            .push_manifest(repository, tag, "application/json", tag.as_bytes().to_vec())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_keep_last_deletes_oldest_tags_and_emits_events() {
+    let config =
+        RegistryConfig::memory().with_retention("app", RetentionPolicy::new().keep_last(2));
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    push_tags(&client, "app", &["a", "b", "c"]).await;
+    push_tags(&client, "other", &["a", "b", "c"]).await;
+
+    let mut events = server.subscribe();
+    let deleted = server.apply_retention().await.unwrap();
+    assert_eq!(deleted, vec![("app".to_string(), "a".to_string())]);
+
+    assert_eq!(
+        events.recv().await.unwrap(),
+        RegistryEvent::TagExpired {
+            repository: "app".to_string(),
+            tag: "a".to_string(),
+        }
+    );
+    assert_eq!(
+        events.recv().await.unwrap(),
+        RegistryEvent::RetentionApplied { tags_deleted: 1 }
+    );
+
+    assert!(client.pull_manifest("app", "a").await.is_err());
+    assert!(client.pull_manifest("app", "c").await.is_ok());
+    assert!(client.pull_manifest("other", "a").await.is_ok());
+}
+
+#[tokio::test]
+async fn test_max_age_applies_to_every_repository() {
+    let config = RegistryConfig::memory().with_retention(
+        "*",
+        RetentionPolicy::new().max_age(Duration::from_millis(200)),
+    );
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    push_tags(&client, "app", &["old"]).await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    push_tags(&client, "app", &["new"]).await;
+
+    let deleted = server.apply_retention().await.unwrap();
+    assert_eq!(deleted, vec![("app".to_string(), "old".to_string())]);
+    assert!(client.pull_manifest("app", "new").await.is_ok());
+
+    // The untagged manifest is left for garbage collection.
+    assert_eq!(server.gc_preview().await.unwrap().manifests.len(), 1);
+}
@@ -0,0 +1,99 @@
+use registry_testkit::retries::Endpoint;
+use registry_testkit::{
+    CaptureConfig, FaultConfig, RegistryConfig, RegistryServer, StorageFault, StorageMethod,
+};
+use std::time::Duration;
+
+/// Pushes a manifest the way a client with exponential backoff would.
+async fn push_with_backoff(url: &str, mut delay: Duration, multiplier: u32) -> u16 {
+    let http = reqwest::Client::new();
+    for _ in 0..6 {
+        let status = http
+            .put(url)
+            .header("Content-Type", "application/json")
+            .body("{}")
+            .send()
+            .await
+            .unwrap()
+            .status();
+        if status.is_success() {
+            return status.as_u16();
+        }
+        tokio::time::sleep(delay).await;
+        delay *= multiplier;
+    }
+    500
+}
+
+async fn flaky_server() -> (RegistryServer, String) {
+    let faults = FaultConfig::new()
+        .with_storage_fault(StorageFault::fail(StorageMethod::StoreManifest).times(3));
+    let config = RegistryConfig::memory()
+        .with_faults(faults)
+        .with_capture(CaptureConfig::new());
+    let server = RegistryServer::new(config).await.unwrap();
+    let url = format!("{}/v2/app/manifests/v1", server.url());
+    (server, url)
+}
+
+#[tokio::test]
+async fn test_assert_retried_with_backoff() {
+    let (server, url) = flaky_server().await;
+    assert_eq!(
+        push_with_backoff(&url, Duration::from_millis(40), 2).await,
+        201
+    );
+
+    let series = server.assert_retried(Endpoint::PutManifest, 3);
+    assert_eq!(series.retries(), 3);
+    assert_eq!(
+        series.attempts.iter().map(|a| a.status).collect::<Vec<_>>(),
+        [500, 500, 500, 201]
+    );
+    assert_eq!(series.delays().len(), 3);
+    series.assert_min_delay(Duration::from_millis(40));
+    series.assert_backoff(1.5);
+    series.assert_succeeded();
+    assert_eq!(series.path, "/v2/app/manifests/v1");
+    assert!(server.retries(Endpoint::GetBlob).is_empty());
+}
+
+#[tokio::test]
+#[should_panic(expected = "do not grow")]
+async fn test_assert_backoff_rejects_constant_delays() {
+    let (server, url) = flaky_server().await;
+    push_with_backoff(&url, Duration::from_millis(30), 1).await;
+    server
+        .assert_retried(Endpoint::PutManifest, 3)
+        .assert_backoff(1.5);
+}
+
+#[tokio::test]
+#[should_panic(expected = "at least 5 times")]
+async fn test_assert_retried_counts_attempts() {
+    let (server, url) = flaky_server().await;
+    push_with_backoff(&url, Duration::from_millis(1), 2).await;
+    server.assert_retried(Endpoint::PutManifest, 5);
+}
+
+#[test]
+fn test_endpoint_classification() {
+    assert_eq!(Endpoint::of("GET", "/v2/"), Some(Endpoint::ApiVersion));
+    assert_eq!(
+        Endpoint::of("POST", "/v2/org/app/blobs/uploads/"),
+        Some(Endpoint::StartUpload)
+    );
+    assert_eq!(
+        Endpoint::of("PATCH", "/v2/app/blobs/uploads/1234"),
+        Some(Endpoint::PatchUpload)
+    );
+    assert_eq!(
+        Endpoint::of("HEAD", "/v2/app/manifests/v1"),
+        Some(Endpoint::HeadManifest)
+    );
+    assert_eq!(
+        Endpoint::of("GET", "/v2/app/blobs/sha256:abc"),
+        Some(Endpoint::GetBlob)
+    );
+    assert_eq!(Endpoint::of("GET", "/admin/maintenance-mode"), None);
+}
@@ -0,0 +1,54 @@
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+
+#[test]
+fn test_bind_on_current_thread_runtime() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let local = tokio::task::LocalSet::new();
+    local.block_on(&runtime, async {
+        let (server, serve) = RegistryServer::bind(RegistryConfig::memory())
+            .await
+            .unwrap();
+        let serving = tokio::task::spawn_local(serve);
+        assert!(server.is_running());
+
+        let client = RegistryClient::new(server.url());
+        let digest = client
+            .push_image("app", "v1", &[b"layer".to_vec()])
+            .await
+            .unwrap();
+        client.pull_manifest("app", &digest).await.unwrap();
+
+        drop(server);
+        serving.await.unwrap();
+    });
+}
+
+#[test]
+fn test_bind_stops_serving_on_crash() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let (mut server, serve) = RegistryServer::bind(RegistryConfig::memory())
+            .await
+            .unwrap();
+        let client = RegistryClient::new(server.url());
+        let layers = [b"layer".to_vec()];
+        tokio::select! {
+            _ = serve => panic!("server stopped early"),
+            pushed = client.push_image("app", "v1", &layers) => {
+                pushed.unwrap();
+            }
+        }
+
+        server.simulate_crash().await.unwrap();
+        assert!(!server.is_running());
+        server.restart().await.unwrap();
+        let client = RegistryClient::new(server.url());
+        client.pull_manifest("app", "v1").await.unwrap();
+    });
+}
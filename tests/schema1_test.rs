@@ -0,0 +1,163 @@
+use registry_testkit::oci::manifest::{ValidationLevel, DOCKER_MANIFEST_MEDIA_TYPE};
+use registry_testkit::oci::schema1::{
+    Schema1Mode, DOCKER_LAYER_MEDIA_TYPE, DOCKER_LAYER_TAR_MEDIA_TYPE, DOCKER_SCHEMA1_MEDIA_TYPE,
+};
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+use sha2::{Digest, Sha256};
+
+/// Two gzip members: the `line` text below with dynamic Huffman codes, then
+/// `tail` in a stored block.
+const GZIP_LAYER: &str = concat!(
+    "1f8b08000000000002039dd6c711c2400c40d13b55a8049448dd101630182f184caa9e810ef867cd3fe9cdaedaa62b32",
+    "5ec86d5fe43234eba3acfafae8645b9f72184ee7abd47be97fe376f97ec9a6ee46edb751d018681c34019a04cd043453",
+    "d0cc4033273b45108804251494585082418906251c9478500242890823220cbd0d448411114644181161448411114644",
+    "1811e1448413118ebe0b22c2890827229c887022c2890827228288082222888840170411114444101141440411114444",
+    "121149442411914444a2a3928848222289882422f24f111fa94d10c3620c00001f8b0800000000000403010400fbff74",
+    "61696c5db4377c04000000",
+);
+
+fn sha256(data: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(data)))
+}
+
+fn schema1_manifest(layers: &[(&str, serde_json::Value)]) -> String {
+    let fs_layers: Vec<_> = layers
+        .iter()
+        .map(|(digest, _)| serde_json::json!({ "blobSum": digest }))
+        .collect();
+    let history: Vec<_> = layers
+        .iter()
+        .map(|(_, v1)| serde_json::json!({ "v1Compatibility": v1.to_string() }))
+        .collect();
+    serde_json::json!({
+        "schemaVersion": 1,
+        "name": "app",
+        "tag": "v1",
+        "architecture": "amd64",
+        "fsLayers": fs_layers,
+        "history": history,
+    })
+    .to_string()
+}
+
+async fn put(server: &RegistryServer, reference: &str, body: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .put(format!("{}/v2/app/manifests/{}", server.url(), reference))
+        .body(body.to_string())
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_schema1_reject_and_accept() {
+    let manifest = schema1_manifest(&[(&sha256(b"layer"), serde_json::json!({ "id": "a" }))]);
+
+    let config = RegistryConfig::memory().with_schema1(Schema1Mode::Reject);
+    let server = RegistryServer::new(config).await.unwrap();
+    let response = put(&server, "v1", &manifest).await;
+    assert_eq!(response.status(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "MANIFEST_INVALID");
+
+    let config = RegistryConfig::memory()
+        .with_manifest_validation(ValidationLevel::Schema)
+        .with_schema1(Schema1Mode::Accept);
+    let server = RegistryServer::new(config).await.unwrap();
+    assert_eq!(put(&server, "v1", &manifest).await.status(), 201);
+    let pulled = RegistryClient::new(server.url())
+        .pull_manifest("app", "v1")
+        .await
+        .unwrap();
+    assert_eq!(pulled.content_type, DOCKER_SCHEMA1_MEDIA_TYPE);
+    assert_eq!(pulled.data, manifest.as_bytes());
+}
+
+#[tokio::test]
+async fn test_schema1_conversion() {
+    let config = RegistryConfig::memory()
+        .with_manifest_validation(ValidationLevel::Full)
+        .with_schema1(Schema1Mode::Convert);
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let base = b"base layer tar".to_vec();
+    let base_digest = client.push_blob("app", base.clone()).await.unwrap();
+    let top = hex::decode(GZIP_LAYER).unwrap();
+    let top_digest = client.push_blob("app", top.clone()).await.unwrap();
+    let mut top_tar: Vec<u8> = (0..60)
+        .flat_map(|i| {
+            format!("line {}: the quick brown fox jumps over the lazy dog\n", i).into_bytes()
+        })
+        .collect();
+    top_tar.extend_from_slice(b"tail");
+
+    let manifest = schema1_manifest(&[
+        (
+            &top_digest,
+            serde_json::json!({
+                "id": "c",
+                "parent": "b",
+                "architecture": "amd64",
+                "os": "linux",
+                "config": { "Cmd": ["/app"] },
+                "container_config": { "Cmd": ["/bin/sh", "-c", "COPY app /app"] },
+            }),
+        ),
+        (
+            &sha256(b"never pushed"),
+            serde_json::json!({
+                "id": "b",
+                "parent": "a",
+                "throwaway": true,
+                "container_config": { "Cmd": ["/bin/sh", "-c", "ENV A=1"] },
+            }),
+        ),
+        (&base_digest, serde_json::json!({ "id": "a" })),
+    ]);
+    let response = put(&server, "v1", &manifest).await;
+    assert_eq!(response.status(), 201);
+    let digest = response.headers()["Docker-Content-Digest"]
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let pulled = client.pull_manifest("app", "v1").await.unwrap();
+    assert_eq!(pulled.content_type, DOCKER_MANIFEST_MEDIA_TYPE);
+    assert_eq!(sha256(&pulled.data), digest);
+    let converted: serde_json::Value = serde_json::from_slice(&pulled.data).unwrap();
+    assert_eq!(converted["schemaVersion"], 2);
+    let layers = converted["layers"].as_array().unwrap();
+    assert_eq!(layers.len(), 2);
+    assert_eq!(layers[0]["digest"], base_digest.as_str());
+    assert_eq!(layers[0]["mediaType"], DOCKER_LAYER_TAR_MEDIA_TYPE);
+    assert_eq!(layers[1]["digest"], top_digest.as_str());
+    assert_eq!(layers[1]["mediaType"], DOCKER_LAYER_MEDIA_TYPE);
+    assert_eq!(layers[1]["size"], top.len());
+
+    let config_digest = converted["config"]["digest"].as_str().unwrap();
+    let config: serde_json::Value =
+        serde_json::from_slice(&client.pull_blob("app", config_digest).await.unwrap()).unwrap();
+    assert_eq!(
+        config["rootfs"]["diff_ids"],
+        serde_json::json!([sha256(&base), sha256(&top_tar)])
+    );
+    assert_eq!(config["history"].as_array().unwrap().len(), 3);
+    assert_eq!(config["history"][1]["empty_layer"], true);
+    assert_eq!(
+        config["history"][2]["created_by"],
+        "/bin/sh -c COPY app /app"
+    );
+    assert_eq!(config["config"]["Cmd"], serde_json::json!(["/app"]));
+    assert!(config.get("id").is_none() && config.get("parent").is_none());
+
+    let inspect = server.inspect_image("app", "v1").await.unwrap();
+    assert_eq!(inspect.layers.len(), 2);
+    assert_eq!(inspect.cmd, ["/app"]);
+
+    let missing = schema1_manifest(&[(&sha256(b"missing"), serde_json::json!({ "id": "x" }))]);
+    let response = put(&server, "v2", &missing).await;
+    assert_eq!(response.status(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "MANIFEST_BLOB_UNKNOWN");
+}
@@ -0,0 +1,87 @@
+use registry_testkit::builder::{ArtifactBuilder, ImageBuilder, Layer};
+use registry_testkit::referrers::{fallback_tag, SIGNATURE_TAG_SUFFIX};
+use registry_testkit::signing::COSIGN_SIGNATURE_ARTIFACT_TYPE;
+use registry_testkit::{
+    RegistryClient, RegistryConfig, RegistryError, RegistryServer, SigningPolicy,
+};
+
+#[tokio::test]
+async fn test_unsigned_images_are_denied() {
+    let config = RegistryConfig::memory().with_signing_policy(SigningPolicy::signature_referrer());
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let result = client.push_image("app", "v1", &[b"layer".to_vec()]).await;
+    assert!(matches!(
+        result,
+        Err(RegistryError::UnexpectedStatus { status: 403, .. })
+    ));
+
+    let image = ImageBuilder::new()
+        .layer(Layer::new(b"app".to_vec()))
+        .build();
+    image.push(&client, "app", &image.digest).await.unwrap();
+    let response = reqwest::Client::new()
+        .put(format!("{}/v2/app/manifests/v1", server.url()))
+        .header("Content-Type", &image.media_type)
+        .body(image.manifest.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "DENIED");
+
+    // Signed by digest first, the image can be tagged.
+    ArtifactBuilder::new(COSIGN_SIGNATURE_ARTIFACT_TYPE)
+        .subject(image.descriptor())
+        .build()
+        .attach(&client, "app")
+        .await
+        .unwrap();
+    image.push(&client, "app", "v1").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_signing_policy_accepts_cosign_tags_and_annotations() {
+    let config = RegistryConfig::memory().with_signing_policy(
+        SigningPolicy::signature_referrer().with_annotation("com.example.signed"),
+    );
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let image = ImageBuilder::new()
+        .layer(Layer::new(b"app".to_vec()))
+        .build();
+    image.push(&client, "app", &image.digest).await.unwrap();
+    let cosign_tag = format!("{}{}", fallback_tag(&image.digest), SIGNATURE_TAG_SUFFIX);
+    ArtifactBuilder::new("application/vnd.dev.cosign.simplesigning.v1+json")
+        .build()
+        .push(&client, "app", &cosign_tag)
+        .await
+        .unwrap();
+    image.push(&client, "app", "v1").await.unwrap();
+
+    ImageBuilder::new()
+        .annotation("com.example.signed", "true")
+        .build()
+        .push(&client, "app", "v2")
+        .await
+        .unwrap();
+
+    // Another artifact type does not count as a signature.
+    let unsigned = ImageBuilder::new()
+        .layer(Layer::new(b"unsigned".to_vec()))
+        .build();
+    unsigned
+        .push(&client, "app", &unsigned.digest)
+        .await
+        .unwrap();
+    ArtifactBuilder::new("application/spdx+json")
+        .subject(unsigned.descriptor())
+        .build()
+        .attach(&client, "app")
+        .await
+        .unwrap();
+    assert!(unsigned.push(&client, "app", "v3").await.is_err());
+}
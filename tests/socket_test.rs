@@ -0,0 +1,45 @@
+use registry_testkit::{
+    RegistryClient, RegistryConfig, RegistryError, RegistryServer, SocketOptions,
+};
+
+#[tokio::test]
+async fn test_rebind_same_port_cycles() {
+    let config = RegistryConfig::temp_dir()
+        .with_socket_options(SocketOptions::new().with_reuse_port(true).with_backlog(16));
+    let mut server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let url = server.url();
+    client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    for _ in 0..5 {
+        client.pull_manifest("app", "v1").await.unwrap();
+        server.rebind_same_port().await.unwrap();
+        assert_eq!(server.url(), url);
+    }
+    client.pull_manifest("app", "v1").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_port_conflicts() {
+    let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = taken.local_addr().unwrap().port();
+
+    let result = RegistryServer::new(RegistryConfig::memory().with_port(port)).await;
+    assert!(matches!(result, Err(RegistryError::AddrInUse { port: p }) if p == port));
+
+    let server = RegistryServer::new(
+        RegistryConfig::memory()
+            .with_port(port)
+            .with_port_fallback(true),
+    )
+    .await
+    .unwrap();
+    assert_ne!(server.addr().port(), port);
+    RegistryClient::new(server.url())
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+}
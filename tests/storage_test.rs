@@ -0,0 +1,95 @@
+use registry_testkit::storage::{DiskStorage, Storage};
+use registry_testkit::{
+    RegistryClient, RegistryConfig, RegistryError, RegistryServer, SharedTempDir,
+};
+
+fn digest(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    format!("sha256:{}", hex::encode(Sha256::digest(data)))
+}
+
+fn count_files(path: &std::path::Path) -> usize {
+    std::fs::read_dir(path).map_or(0, |entries| entries.count())
+}
+
+#[tokio::test]
+async fn test_shared_temp_dir_pools_blobs() {
+    let shared = SharedTempDir::new().unwrap();
+    let a = DiskStorage::shared_temp(&shared).await.unwrap();
+    let b = DiskStorage::shared_temp(&shared).await.unwrap();
+    assert_eq!(count_files(&shared.path().join("instances")), 2);
+
+    let layer = b"fixture layer".to_vec();
+    a.store_blob(digest(&layer), layer.clone()).await.unwrap();
+    b.store_blob(digest(&layer), layer.clone()).await.unwrap();
+    assert_eq!(count_files(&shared.path().join("blobs")), 1);
+
+    // Deleting from one server leaves the other's copy alone.
+    assert!(a.delete_blob(&digest(&layer)).await.unwrap());
+    assert_eq!(a.get_blob(&digest(&layer)).await.unwrap(), None);
+    assert_eq!(b.get_blob(&digest(&layer)).await.unwrap(), Some(layer));
+
+    // Content that does not match its digest is kept private.
+    let wrong = digest(b"something else");
+    a.store_blob(wrong.clone(), b"mismatch".to_vec())
+        .await
+        .unwrap();
+    assert_eq!(count_files(&shared.path().join("blobs")), 1);
+    assert_eq!(b.get_blob(&wrong).await.unwrap(), None);
+
+    drop(a);
+    assert_eq!(count_files(&shared.path().join("instances")), 1);
+    let path = shared.path().to_path_buf();
+    drop(shared);
+    assert!(path.exists(), "removed while a server still uses it");
+    drop(b);
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn test_servers_in_shared_temp_dir() {
+    let shared = SharedTempDir::new().unwrap();
+    let a = RegistryServer::new(RegistryConfig::shared_temp_dir(&shared))
+        .await
+        .unwrap();
+    let b = RegistryServer::new(RegistryConfig::shared_temp_dir(&shared))
+        .await
+        .unwrap();
+
+    let layers = [b"base".to_vec(), b"app".to_vec()];
+    let pushed_a = RegistryClient::new(a.url())
+        .push_image("app", "v1", &layers)
+        .await
+        .unwrap();
+    let pushed_b = RegistryClient::new(b.url())
+        .push_image("app", "v1", &layers)
+        .await
+        .unwrap();
+    assert_eq!(pushed_a, pushed_b);
+
+    // Two layers and a config, stored once for both servers.
+    assert_eq!(count_files(&shared.path().join("blobs")), 3);
+    let image = RegistryClient::new(b.url())
+        .pull_image("app", "v1")
+        .await
+        .unwrap();
+    assert_eq!(image.digest, pushed_b);
+}
+
+#[tokio::test]
+async fn test_server_exposes_its_storage() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client.push_blob("app", b"layer".to_vec()).await.unwrap();
+
+    let storage = server.storage();
+    assert_eq!(storage.get_blob(&digest).await.unwrap().unwrap(), b"layer");
+    storage
+        .store_blob(digest.clone(), b"tampered".to_vec())
+        .await
+        .unwrap();
+    assert!(matches!(
+        client.pull_blob("app", &digest).await,
+        Err(RegistryError::DigestInvalid(_))
+    ));
+}
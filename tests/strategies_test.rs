@@ -0,0 +1,41 @@
+#![cfg(feature = "proptest")]
+
+use proptest::prelude::*;
+use registry_testkit::strategies;
+
+fn is_valid_digest(digest: &str) -> bool {
+    digest.strip_prefix("sha256:").is_some_and(|hex| {
+        hex.len() == 64 && hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    })
+}
+
+proptest! {
+    #[test]
+    fn digests_are_valid(digest in strategies::digest()) {
+        prop_assert!(is_valid_digest(&digest));
+    }
+
+    #[test]
+    fn invalid_digests_are_invalid(digest in strategies::invalid_digest()) {
+        prop_assert!(!is_valid_digest(&digest));
+    }
+
+    #[test]
+    fn repository_names_are_lowercase(name in strategies::repository_name()) {
+        prop_assert!(!name.starts_with('/') && !name.ends_with('/'));
+        prop_assert_eq!(name.to_lowercase(), name);
+    }
+
+    #[test]
+    fn manifests_reference_valid_digests(manifest in strategies::image_manifest()) {
+        prop_assert_eq!(manifest["schemaVersion"].as_u64(), Some(2));
+        prop_assert!(is_valid_digest(manifest["config"]["digest"].as_str().unwrap()));
+    }
+
+    #[test]
+    fn indexes_have_platforms(index in strategies::image_index()) {
+        for entry in index["manifests"].as_array().unwrap() {
+            prop_assert!(entry["platform"]["os"].is_string());
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use registry_testkit::sync::Side;
+use registry_testkit::{
+    verify_sync, RegistryClient, RegistryConfig, RegistryServer, SyncDifference,
+};
+
+#[tokio::test]
+async fn test_verify_sync_reports_differences() {
+    let a = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let b = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let (client_a, client_b) = (RegistryClient::new(a.url()), RegistryClient::new(b.url()));
+
+    for client in [&client_a, &client_b] {
+        client
+            .push_image("app", "v1", &[b"layer".to_vec()])
+            .await
+            .unwrap();
+    }
+    let report = verify_sync(&a, &b).await.unwrap();
+    assert!(report.is_in_sync(), "{:?}", report.differences);
+    assert_eq!(
+        (
+            report.repositories,
+            report.tags,
+            report.manifests,
+            report.blobs
+        ),
+        (1, 1, 1, 2)
+    );
+
+    let v2 = client_a
+        .push_image("app", "v2", &[b"layer 2".to_vec()])
+        .await
+        .unwrap();
+    let latest_a = client_a
+        .push_image("app", "latest", &[b"a".to_vec()])
+        .await
+        .unwrap();
+    let latest_b = client_b
+        .push_image("app", "latest", &[b"b".to_vec()])
+        .await
+        .unwrap();
+    client_b
+        .push_image("tools", "v1", &[b"tool".to_vec()])
+        .await
+        .unwrap();
+    // The same manifest on both sides, but one layer never made it to b.
+    let layer = client_a
+        .push_blob("lib", b"only on a".to_vec())
+        .await
+        .unwrap();
+    let manifest = serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": "application/vnd.oci.image.manifest.v1+json",
+        "config": { "mediaType": "application/vnd.oci.empty.v1+json", "size": 2, "digest": "sha256:44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a" },
+        "layers": [{ "mediaType": "application/vnd.oci.image.layer.v1.tar", "size": 9, "digest": layer }],
+    })
+    .to_string();
+    for client in [&client_a, &client_b] {
+        client.push_blob("lib", b"{}".to_vec()).await.unwrap();
+        client
+            .push_manifest(
+                "lib",
+                "v1",
+                "application/vnd.oci.image.manifest.v1+json",
+                manifest.clone().into_bytes(),
+            )
+            .await
+            .unwrap();
+    }
+
+    let report = verify_sync(&a, &b).await.unwrap();
+    assert!(!report.is_in_sync());
+    let differences = report.differences;
+    assert!(differences.contains(&SyncDifference::MissingTag {
+        repository: "app".to_string(),
+        tag: "v2".to_string(),
+        missing_from: Side::B,
+    }));
+    assert!(differences.contains(&SyncDifference::MissingManifest {
+        repository: "app".to_string(),
+        digest: v2,
+        missing_from: Side::B,
+    }));
+    assert!(differences.contains(&SyncDifference::TagMismatch {
+        repository: "app".to_string(),
+        tag: "latest".to_string(),
+        a: latest_a,
+        b: latest_b,
+    }));
+    assert!(differences.contains(&SyncDifference::MissingRepository {
+        repository: "tools".to_string(),
+        missing_from: Side::A,
+    }));
+    assert!(differences.contains(&SyncDifference::MissingBlob {
+        repository: "lib".to_string(),
+        digest: layer,
+        missing_from: Side::B,
+    }));
+    assert!(differences
+        .iter()
+        .all(|d| !matches!(d, SyncDifference::MissingTag { tag, .. } if tag == "v1")));
+}
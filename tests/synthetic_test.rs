@@ -0,0 +1,28 @@
+use registry_testkit::synthetic::SyntheticBlob;
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+use sha2::{Digest, Sha256};
+
+#[tokio::test]
+async fn test_synthetic_blob_served_with_precomputed_digest() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let size = 3 * 64 * 1024 + 17;
+    let digest = server.register_synthetic_blob(size, 7).await.unwrap();
+
+    let client = RegistryClient::new(server.url());
+    assert!(client.blob_exists("other", &digest).await.unwrap());
+
+    let data = client.pull_blob("any", &digest).await.unwrap();
+    assert_eq!(data.len() as u64, size);
+    assert_eq!(
+        format!("sha256:{}", hex::encode(Sha256::digest(&data))),
+        digest
+    );
+    assert_eq!(data, SyntheticBlob::new(size, 7).to_vec());
+}
+
+#[test]
+fn test_synthetic_blob_is_deterministic() {
+    let a = SyntheticBlob::new(1000, 1);
+    assert_eq!(a.digest(), SyntheticBlob::new(1000, 1).digest());
+    assert_ne!(a.digest(), SyntheticBlob::new(1000, 2).digest());
+}
@@ -0,0 +1,108 @@
+use registry_testkit::builder::ImageBuilder;
+use registry_testkit::{LocationStyle, RegistryClient, RegistryConfig, RegistryServer};
+
+#[tokio::test]
+async fn test_tags_list_paginates_with_link_headers() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let image = ImageBuilder::new().build();
+    for tag in ["v3", "v1", "latest", "v2"] {
+        image.push(&client, "app", tag).await.unwrap();
+    }
+    image.push(&client, "other", "v9").await.unwrap();
+    let http = reqwest::Client::new();
+    let url = |path: &str| format!("{}{}", server.url(), path);
+
+    let response = http.get(url("/v2/app/tags/list")).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().get("link").is_none());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["name"], "app");
+    assert_eq!(
+        body["tags"],
+        serde_json::json!(["latest", "v1", "v2", "v3"])
+    );
+
+    let response = http.get(url("/v2/app/tags/list?n=2")).send().await.unwrap();
+    assert_eq!(
+        response.headers()["link"],
+        "</v2/app/tags/list?n=2&last=v1>; rel=\"next\""
+    );
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["tags"], serde_json::json!(["latest", "v1"]));
+
+    let response = http
+        .get(url("/v2/app/tags/list?n=2&last=v1"))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.headers().get("link").is_none());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["tags"], serde_json::json!(["v2", "v3"]));
+}
+
+#[tokio::test]
+async fn test_tags_list_links_follow_external_url() {
+    for (style, link) in [
+        (
+            LocationStyle::Relative,
+            "</prefix/v2/app/tags/list?n=1&last=v1>; rel=\"next\"",
+        ),
+        (
+            LocationStyle::Absolute,
+            "<https://host/prefix/v2/app/tags/list?n=1&last=v1>; rel=\"next\"",
+        ),
+    ] {
+        let config = RegistryConfig::memory()
+            .with_external_url("https://host/prefix")
+            .with_location_style(style);
+        let server = RegistryServer::new(config).await.unwrap();
+        // Manifest pushes do not follow the prefixed `Location` a proxy
+        // would serve.
+        let client = RegistryClient::new(server.url());
+        for tag in ["v1", "v2"] {
+            client
+                .push_manifest("app", tag, "application/json", b"{}".to_vec())
+                .await
+                .unwrap();
+        }
+
+        let response = reqwest::get(format!("{}/v2/app/tags/list?n=1", server.url()))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["link"], link, "{:?}", style);
+    }
+}
+
+#[tokio::test]
+async fn test_tags_list_errors() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let image = ImageBuilder::new().build();
+    image
+        .push(&client, "untagged", &image.digest)
+        .await
+        .unwrap();
+    let http = reqwest::Client::new();
+    let url = |path: &str| format!("{}{}", server.url(), path);
+
+    let response = http.get(url("/v2/missing/tags/list")).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "NAME_UNKNOWN");
+
+    let response = http
+        .get(url("/v2/untagged/tags/list"))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["tags"], serde_json::json!([]));
+
+    let response = http
+        .get(url("/v2/untagged/tags/list?n=many"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+}
@@ -0,0 +1,61 @@
+use registry_testkit::builder::ImageBuilder;
+use registry_testkit::testdata::OCI_LAYER_MEDIA_TYPE;
+use registry_testkit::{
+    ContentEncoding, LayerContent, RegistryClient, RegistryConfig, RegistryServer,
+};
+
+#[test]
+fn test_generated_layers_are_reproducible() {
+    let content = LayerContent::new(42).with_size(100_000).with_files(3);
+    assert_eq!(content.layer(), content.layer());
+    assert_ne!(
+        content.tar(),
+        LayerContent::new(43).with_size(100_000).with_files(3).tar()
+    );
+
+    let tar = content.tar();
+    // A header and 66 blocks of content per file, then two end blocks.
+    assert_eq!(tar.len(), 3 * (512 + 66 * 512) + 1024);
+    assert!(tar.starts_with(b"data/file-0000.bin\0"));
+    assert_eq!(&tar[257..262], b"ustar");
+
+    let layer = content.uncompressed_layer();
+    assert_eq!(layer.media_type, OCI_LAYER_MEDIA_TYPE);
+    assert_eq!(layer.data, tar);
+    let decoded = ContentEncoding::Gzip.decode(&content.layer().data).unwrap();
+    assert_eq!(decoded, tar);
+}
+
+#[test]
+fn test_compressibility_sets_compressed_size() {
+    let size = |compressibility: f64| {
+        LayerContent::new(1)
+            .with_size(256 << 10)
+            .with_compressibility(compressibility)
+            .layer()
+            .data
+            .len()
+    };
+    let random = size(0.0);
+    let half = size(0.5);
+    let repeated = size(1.0);
+    assert!(random > 256 << 10, "{}", random);
+    assert!(half < random * 3 / 4, "{} {}", half, random);
+    assert!(repeated < 16 << 10, "{}", repeated);
+}
+
+#[tokio::test]
+async fn test_generated_layers_push_with_stable_digests() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let build = || {
+        ImageBuilder::new()
+            .layer(LayerContent::new(5).with_size(200_000).layer())
+            .build()
+    };
+    let image = build();
+    image.push(&client, "app", "v1").await.unwrap();
+    assert_eq!(build().digest, image.digest);
+    let pulled = client.pull_image("app", "v1").await.unwrap();
+    assert_eq!(pulled.digest, image.digest);
+}
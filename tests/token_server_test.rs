@@ -0,0 +1,53 @@
+use registry_testkit::{AuthConfig, RegistryClient, RegistryConfig, RegistryServer, TokenServer};
+
+#[tokio::test]
+async fn test_registry_trusts_standalone_token_server() {
+    let mut tokens =
+        TokenServer::start(AuthConfig::bearer("registry.test").with_user("ci", "secret"))
+            .await
+            .unwrap();
+    let registry = RegistryServer::new(RegistryConfig::memory().with_auth(tokens.auth_config()))
+        .await
+        .unwrap();
+    let client = || RegistryClient::new(registry.url()).with_credentials("ci", "secret");
+    client()
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    let response = reqwest::get(format!("{}/v2/", registry.url()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+    let challenge = response.headers()["www-authenticate"].to_str().unwrap();
+    assert!(challenge.contains(&format!("realm=\"{}\"", tokens.realm())));
+
+    // Tokens from the token server are accepted by the registry.
+    let token = reqwest::Client::new()
+        .get(format!(
+            "{}?service=registry.test&scope=repository:app:pull",
+            tokens.realm()
+        ))
+        .basic_auth("ci", Some("secret"))
+        .send()
+        .await
+        .unwrap();
+    let token: serde_json::Value = token.json().await.unwrap();
+    let response = reqwest::Client::new()
+        .get(format!("{}/v2/app/manifests/v1", registry.url()))
+        .bearer_auth(token["token"].as_str().unwrap())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    tokens.stop().await;
+    assert!(client().pull_image("app", "v1").await.is_err());
+    let response = reqwest::get(format!("{}/v2/", registry.url()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+
+    tokens.restart().await.unwrap();
+    client().pull_image("app", "v1").await.unwrap();
+}
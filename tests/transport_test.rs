@@ -0,0 +1,35 @@
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::Request;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use registry_testkit::{RegistryConfig, RegistryServer};
+
+#[tokio::test]
+async fn test_in_process_connector_round_trip() {
+    let mut server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    server.simulate_crash().await.unwrap();
+
+    let client: Client<_, Full<Bytes>> =
+        Client::builder(TokioExecutor::new()).build(server.connector());
+
+    let manifest = Bytes::from_static(b"{\"schemaVersion\":2}");
+    let request = Request::put("http://registry.local/v2/app/manifests/latest")
+        .header("Content-Type", "application/vnd.oci.image.manifest.v1+json")
+        .body(Full::new(manifest.clone()))
+        .unwrap();
+    let response = client.request(request).await.unwrap();
+    assert_eq!(response.status(), 201);
+
+    let response = client
+        .get(
+            "http://registry.local/v2/app/manifests/latest"
+                .parse()
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body, manifest);
+}
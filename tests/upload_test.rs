@@ -0,0 +1,327 @@
+use registry_testkit::{ApiEndpoint, MountStats, RegistryClient, RegistryConfig, RegistryServer};
+
+fn header(response: &reqwest::Response, name: &str) -> String {
+    response.headers()[name].to_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_chunked_upload_reports_cumulative_range() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    let upload_url = format!("{}{}", server.url(), header(&response, "Location"));
+
+    let response = client.get(&upload_url).send().await.unwrap();
+    assert_eq!(response.status(), 204);
+    assert_eq!(header(&response, "Range"), "0-0");
+    assert_eq!(header(&response, "OCI-Upload-Offset"), "0");
+
+    for (chunk, range, offset) in [("hello ", "0-5", "6"), ("world", "0-10", "11")] {
+        let response = client.patch(&upload_url).body(chunk).send().await.unwrap();
+        assert_eq!(response.status(), 202);
+        assert_eq!(header(&response, "Range"), range);
+        assert_eq!(header(&response, "OCI-Upload-Offset"), offset);
+    }
+
+    let response = client.get(&upload_url).send().await.unwrap();
+    assert_eq!(response.status(), 204);
+    assert_eq!(header(&response, "Range"), "0-10");
+    assert!(header(&response, "Location").ends_with(upload_url.rsplit('/').next().unwrap()));
+
+    let missing = format!("{}/v2/app/blobs/uploads/unknown", server.url());
+    assert_eq!(client.get(missing).send().await.unwrap().status(), 404);
+}
+
+#[tokio::test]
+async fn test_upload_stress_sequential_chunks() {
+    use registry_testkit::stress::{self, UploadStressConfig};
+
+    for config in [RegistryConfig::memory(), RegistryConfig::temp_dir()] {
+        let server = RegistryServer::new(config).await.unwrap();
+        let config = UploadStressConfig::new()
+            .with_uploads(100)
+            .with_concurrency(16)
+            .with_chunk_size(4096);
+        let report = stress::run_uploads(&server.url(), config).await.unwrap();
+        report.assert_consistent();
+        assert!(report.is_clean(), "{:?}", report.failed);
+        assert_eq!(report.completed.len(), 100);
+    }
+}
+
+#[tokio::test]
+async fn test_upload_stress_interleaved_chunks_stay_consistent() {
+    use registry_testkit::stress::{self, UploadStressConfig};
+
+    for config in [RegistryConfig::memory(), RegistryConfig::temp_dir()] {
+        let server = RegistryServer::new(config).await.unwrap();
+        let config = UploadStressConfig::new()
+            .with_uploads(50)
+            .with_chunk_size(1024)
+            .with_interleaved_chunks(true);
+        let report = stress::run_uploads(&server.url(), config).await.unwrap();
+        report.assert_consistent();
+        for failure in &report.failed {
+            assert_eq!(failure.stage, "patch");
+            assert!(matches!(failure.status, Some(409 | 416)), "{:?}", failure);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_out_of_order_chunk_is_rejected() {
+    let server = RegistryServer::new(RegistryConfig::temp_dir())
+        .await
+        .unwrap();
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    let location = format!("{}{}", server.url(), header(&response, "Location"));
+
+    let response = client
+        .patch(&location)
+        .header("Content-Range", "4-7")
+        .body(b"5678".to_vec())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 416);
+    assert_eq!(header(&response, "Range"), "0-0");
+    assert_eq!(header(&response, "OCI-Upload-Offset"), "0");
+
+    for (range, chunk) in [("0-3", b"1234"), ("4-7", b"5678")] {
+        let response = client
+            .patch(&location)
+            .header("Content-Range", range)
+            .body(chunk.to_vec())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 202);
+    }
+    let response = client
+        .patch(&location)
+        .header("Content-Range", "4-7")
+        .body(b"5678".to_vec())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 416);
+    assert_eq!(header(&response, "Range"), "0-7");
+
+    let response = client
+        .patch(&location)
+        .header("Content-Range", format!("8-{}", u64::MAX))
+        .body(b"9".to_vec())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 416);
+}
+
+#[tokio::test]
+async fn test_blob_mount_and_fallback() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client.push_blob("app", b"shared".to_vec()).await.unwrap();
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/v2/other/blobs/uploads/?mount={}&from=app",
+            server.url(),
+            digest
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    assert_eq!(
+        header(&response, "Location"),
+        format!("/v2/other/blobs/{}", digest)
+    );
+    assert_eq!(header(&response, "Docker-Content-Digest"), digest);
+
+    assert!(client.mount_blob("other", &digest, "app").await.unwrap());
+    assert!(!client
+        .mount_blob("other", "sha256:0000", "app")
+        .await
+        .unwrap());
+    let pushed = client
+        .push_blob_from("third", "app", b"shared".to_vec())
+        .await
+        .unwrap();
+    assert_eq!(pushed, digest);
+    // An unknown blob falls back to an upload, which then succeeds.
+    let fresh = client
+        .push_blob_from("third", "app", b"fresh".to_vec())
+        .await
+        .unwrap();
+    assert_eq!(client.pull_blob("third", &fresh).await.unwrap(), b"fresh");
+    assert_eq!(
+        client.mount_stats(),
+        MountStats {
+            attempted: 4,
+            mounted: 2,
+            fell_back: 1,
+        }
+    );
+
+    // A registry without mount support makes every push fall back.
+    let config = RegistryConfig::memory().with_endpoint_disabled(ApiEndpoint::BlobMount);
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    client.push_blob("app", b"shared".to_vec()).await.unwrap();
+    client
+        .push_blob_from("other", "app", b"shared".to_vec())
+        .await
+        .unwrap();
+    assert_eq!(client.mount_stats().mounted, 0);
+    assert_eq!(client.mount_stats().fell_back, 1);
+}
+
+#[tokio::test]
+async fn test_leaked_upload_sessions_are_reported() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    server.assert_no_leaked_uploads().await;
+
+    let http = reqwest::Client::new();
+    let start = || async {
+        let response = http
+            .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+            .send()
+            .await
+            .unwrap();
+        format!("{}{}", server.url(), header(&response, "Location"))
+    };
+    let abandoned = start().await;
+    http.patch(&abandoned)
+        .body(b"partial".to_vec())
+        .send()
+        .await
+        .unwrap();
+    let cancelled = start().await;
+    let response = http.delete(&cancelled).send().await.unwrap();
+    assert_eq!(response.status(), 204);
+    let response = http.delete(&cancelled).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+
+    let stats = server.upload_stats().await;
+    assert_eq!(stats.started, 4);
+    assert_eq!(stats.finished, 2);
+    assert_eq!(stats.cancelled, 1);
+    assert_eq!(stats.expired, 0);
+    assert_eq!(stats.open.len(), 1);
+    assert_eq!(stats.open[0].repository, "app");
+    assert_eq!(stats.open[0].size, 7);
+    assert!(abandoned.ends_with(&stats.open[0].uuid));
+
+    let leaked = tokio::spawn(async move { server.assert_no_leaked_uploads().await })
+        .await
+        .unwrap_err();
+    let message = leaked.into_panic();
+    let message = message.downcast_ref::<String>().unwrap();
+    assert!(message.contains("1 of 4 upload sessions"), "{}", message);
+}
+
+fn sha256(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    format!("sha256:{}", hex::encode(Sha256::digest(data)))
+}
+
+/// Finishes an upload of `content` with a chunked body whose trailer
+/// carries `trailer_digest`, returning the raw response. reqwest does not
+/// send request trailers, so the request is written by hand.
+async fn finish_with_trailer(
+    server: &RegistryServer,
+    query: &str,
+    content: &[u8],
+    trailer_digest: &str,
+) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    let location = header(&response, "Location");
+
+    let mut request = format!(
+        "PUT {}{} HTTP/1.1\r\nHost: registry\r\nConnection: close\r\n\
+         Transfer-Encoding: chunked\r\nTrailer: Docker-Content-Digest\r\n\r\n\
+         {:x}\r\n",
+        location,
+        query,
+        content.len()
+    )
+    .into_bytes();
+    request.extend_from_slice(content);
+    request.extend_from_slice(
+        format!("\r\n0\r\nDocker-Content-Digest: {}\r\n\r\n", trailer_digest).as_bytes(),
+    );
+
+    let address = server.url().trim_start_matches("http://").to_string();
+    let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+    stream.write_all(&request).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn test_upload_digest_trailer_is_verified() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = sha256(b"layer");
+
+    let response = finish_with_trailer(&server, "", b"layer", &digest).await;
+    assert!(response.starts_with("HTTP/1.1 201"), "{}", response);
+    assert_eq!(client.pull_blob("app", &digest).await.unwrap(), b"layer");
+
+    let wrong = sha256(b"other");
+    let response = finish_with_trailer(&server, "", b"tampered", &wrong).await;
+    assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+    assert!(response.contains("DIGEST_INVALID"), "{}", response);
+    assert!(client.pull_blob("app", &wrong).await.is_err());
+    assert!(client.pull_blob("app", &sha256(b"tampered")).await.is_err());
+
+    // A trailer disagreeing with the query parameter is refused too.
+    let query = format!("?digest={}", digest);
+    let response = finish_with_trailer(&server, &query, b"layer", &wrong).await;
+    assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+}
+#[tokio::test]
+async fn test_upload_digest_query_is_verified() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("{}/v2/app/blobs/uploads/", server.url()))
+        .send()
+        .await
+        .unwrap();
+    let wrong = sha256(b"other");
+    let response = client
+        .put(format!(
+            "{}{}?digest={}",
+            server.url(),
+            header(&response, "Location"),
+            wrong
+        ))
+        .body("layer")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+}
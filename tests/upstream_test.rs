@@ -0,0 +1,95 @@
+use registry_testkit::{
+    AuthConfig, RegistryClient, RegistryConfig, RegistryServer, UpstreamConfig,
+};
+
+/// URL of a port nothing listens on.
+async fn dead_url() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
+
+#[tokio::test]
+async fn test_falls_through_to_next_upstream() {
+    let mirror = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let origin = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let digest = RegistryClient::new(origin.url())
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    let config = RegistryConfig::memory()
+        .with_upstream(UpstreamConfig::new(dead_url().await))
+        .with_upstream(UpstreamConfig::new(mirror.url()))
+        .with_upstream(UpstreamConfig::new(origin.url()));
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    let image = client.pull_image("app", "v1").await.unwrap();
+    assert_eq!(image.digest, digest);
+    assert_eq!(image.layers, [b"layer".to_vec()]);
+    assert_eq!(origin.pull_count("app").await, 1);
+
+    // Without caching every read goes upstream again.
+    client.pull_image("app", "v1").await.unwrap();
+    assert_eq!(origin.pull_count("app").await, 2);
+
+    assert!(client.pull_image("app", "missing").await.is_err());
+}
+
+#[tokio::test]
+async fn test_caching_upstream() {
+    let origin = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let digest = RegistryClient::new(origin.url())
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    let config =
+        RegistryConfig::memory().with_upstream(UpstreamConfig::new(origin.url()).with_cache(true));
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+
+    client.pull_image("app", "v1").await.unwrap();
+    let image = client.pull_image("app", "v1").await.unwrap();
+    assert_eq!(image.digest, digest);
+    assert_eq!(origin.pull_count("app").await, 1);
+    assert!(server
+        .verify_image("app", "v1")
+        .await
+        .unwrap()
+        .is_complete());
+}
+
+#[tokio::test]
+async fn test_upstream_credentials() {
+    let auth = AuthConfig::basic().with_user("reader", "secret");
+    let origin = RegistryServer::new(RegistryConfig::memory().with_auth(auth))
+        .await
+        .unwrap();
+    RegistryClient::new(origin.url())
+        .with_credentials("reader", "secret")
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    let anonymous = RegistryServer::new(
+        RegistryConfig::memory().with_upstream(UpstreamConfig::new(origin.url())),
+    )
+    .await
+    .unwrap();
+    assert!(RegistryClient::new(anonymous.url())
+        .pull_image("app", "v1")
+        .await
+        .is_err());
+
+    let authenticated = RegistryServer::new(
+        RegistryConfig::memory()
+            .with_upstream(UpstreamConfig::new(origin.url()).with_credentials("reader", "secret")),
+    )
+    .await
+    .unwrap();
+    assert!(RegistryClient::new(authenticated.url())
+        .pull_image("app", "v1")
+        .await
+        .is_ok());
+}
@@ -0,0 +1,112 @@
+use registry_testkit::client::{OCI_LAYER_MEDIA_TYPE, OCI_MANIFEST_MEDIA_TYPE};
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer, VerifyProblem};
+
+const INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+
+async fn push_json(
+    client: &RegistryClient,
+    tag: &str,
+    media_type: &str,
+    manifest: serde_json::Value,
+) -> String {
+    client
+        .push_manifest(
+            "app",
+            tag,
+            media_type,
+            serde_json::to_vec(&manifest).unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_verify_complete_image() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client
+        .push_image("app", "v1", &[b"one".to_vec(), b"two".to_vec()])
+        .await
+        .unwrap();
+
+    let report = server.verify_image("app", "v1").await.unwrap();
+    assert!(report.is_complete(), "{:?}", report.problems);
+    assert_eq!(report.manifests, [digest]);
+    assert_eq!(report.blobs_checked, 3);
+}
+
+#[tokio::test]
+async fn test_verify_reports_missing_and_mismatched_blobs() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let config = client.push_blob("app", b"{}".to_vec()).await.unwrap();
+    let layer = client.push_blob("app", b"layer".to_vec()).await.unwrap();
+    let missing = format!("sha256:{}", "0".repeat(64));
+    push_json(
+        &client,
+        "broken",
+        OCI_MANIFEST_MEDIA_TYPE,
+        serde_json::json!({
+            "schemaVersion": 2,
+            "config": { "mediaType": "application/vnd.oci.image.config.v1+json", "size": 2, "digest": config },
+            "layers": [
+                { "mediaType": OCI_LAYER_MEDIA_TYPE, "size": 99, "digest": layer },
+                { "mediaType": OCI_LAYER_MEDIA_TYPE, "size": 1, "digest": missing },
+            ],
+        }),
+    )
+    .await;
+
+    let report = server.verify_image("app", "broken").await.unwrap();
+    assert!(!report.is_complete());
+    assert_eq!(
+        report.problems,
+        [
+            VerifyProblem::SizeMismatch {
+                digest: layer,
+                expected: 99,
+                actual: 5,
+            },
+            VerifyProblem::MissingBlob { digest: missing },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_verify_walks_index_children() {
+    let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let child = client
+        .push_image("app", "amd64", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    let child_size = client
+        .pull_manifest("app", &child)
+        .await
+        .unwrap()
+        .data
+        .len();
+    let missing = format!("sha256:{}", "1".repeat(64));
+    push_json(
+        &client,
+        "multi",
+        INDEX_MEDIA_TYPE,
+        serde_json::json!({
+            "schemaVersion": 2,
+            "manifests": [
+                { "mediaType": OCI_MANIFEST_MEDIA_TYPE, "size": child_size, "digest": child },
+                { "mediaType": OCI_MANIFEST_MEDIA_TYPE, "size": 10, "digest": missing },
+            ],
+        }),
+    )
+    .await;
+
+    let report = server.verify_image("app", "multi").await.unwrap();
+    assert_eq!(report.manifests.len(), 2);
+    assert_eq!(report.manifests[1], child);
+    assert_eq!(report.blobs_checked, 2);
+    assert_eq!(
+        report.problems,
+        [VerifyProblem::MissingManifest { digest: missing }]
+    );
+}
@@ -0,0 +1,66 @@
+use registry_testkit::warnings::WarningEndpoint;
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer, RegistryWarning};
+
+fn warnings(response: &reqwest::Response) -> Vec<String> {
+    response
+        .headers()
+        .get_all("Warning")
+        .iter()
+        .map(|v| v.to_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_warning_headers() {
+    let config = RegistryConfig::memory()
+        .with_warning(RegistryWarning::new("registry maintenance on Friday"))
+        .with_warning(
+            RegistryWarning::new("schema 1 manifests are \"deprecated\"")
+                .for_repository("legacy*")
+                .for_endpoint(WarningEndpoint::Manifests),
+        );
+    let server = RegistryServer::new(config).await.unwrap();
+    let client = RegistryClient::new(server.url());
+    let digest = client
+        .push_image("legacy-app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+    client
+        .push_image("app", "v1", &[b"layer".to_vec()])
+        .await
+        .unwrap();
+
+    let http = reqwest::Client::new();
+    let get = |path: String| http.get(format!("{}{}", server.url(), path)).send();
+
+    let response = get("/v2/legacy-app/manifests/v1".to_string())
+        .await
+        .unwrap();
+    assert_eq!(
+        warnings(&response),
+        [
+            r#"299 - "registry maintenance on Friday""#,
+            r#"299 - "schema 1 manifests are \"deprecated\"""#,
+        ]
+    );
+
+    let response = get("/v2/app/manifests/v1".to_string()).await.unwrap();
+    assert_eq!(
+        warnings(&response),
+        [r#"299 - "registry maintenance on Friday""#]
+    );
+
+    let config_digest = server
+        .inspect_image("legacy-app", &digest)
+        .await
+        .unwrap()
+        .config_digest;
+    let response = get(format!("/v2/legacy-app/blobs/{}", config_digest))
+        .await
+        .unwrap();
+    assert_eq!(warnings(&response).len(), 1);
+
+    // Only repository endpoints carry warnings.
+    let response = get("/v2/".to_string()).await.unwrap();
+    assert!(warnings(&response).is_empty());
+}
@@ -0,0 +1,94 @@
+use registry_testkit::{RegistryClient, RegistryConfig, RegistryServer};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs `f` with debug events of `target` written to a buffer, returning
+/// the lines. The test runtime is single-threaded, so the server's tasks
+/// log through the same thread-local subscriber.
+async fn logged<F: std::future::Future<Output = ()>>(target: &str, f: F) -> Vec<String> {
+    let buffer = Buffer::default();
+    let writer = buffer.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(format!("{}=debug", target))
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .without_time()
+        .finish();
+    let guard = tracing::subscriber::set_default(subscriber);
+    f.await;
+    drop(guard);
+    let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    output.lines().map(str::to_string).collect()
+}
+
+#[tokio::test]
+async fn test_protocol_log_lines() {
+    let lines = logged(registry_testkit::wirelog::TARGET, async {
+        let config = RegistryConfig::memory().with_protocol_logging(true);
+        let server = RegistryServer::new(config).await.unwrap();
+        let client = RegistryClient::new(server.url());
+        let digest = client.push_blob("app", b"layer".to_vec()).await.unwrap();
+        let response = reqwest::Client::new()
+            .get(format!("{}/v2/app/manifests/missing", server.url()))
+            .header("Authorization", "Bearer secret-token")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+        assert!(!digest.is_empty());
+    })
+    .await;
+
+    let request = lines
+        .iter()
+        .find(|line| line.contains(">> GET /v2/app/manifests/missing"))
+        .unwrap_or_else(|| panic!("{:#?}", lines));
+    assert!(request.contains("authorization=Bearer"), "{}", request);
+    assert!(!request.contains("secret-token"), "{}", request);
+
+    let response = lines
+        .iter()
+        .find(|line| line.contains("<< 404 GET /v2/app/manifests/missing"))
+        .unwrap_or_else(|| panic!("{:#?}", lines));
+    assert!(response.contains("content-type="), "{}", response);
+
+    let stored = lines
+        .iter()
+        .find(|line| line.contains("<< 201 PUT /v2/app/blobs/uploads/"))
+        .unwrap_or_else(|| panic!("{:#?}", lines));
+    assert!(
+        stored.contains("docker-content-digest=sha256:"),
+        "{}",
+        stored
+    );
+    assert!(
+        stored.contains("location=/v2/app/blobs/sha256:"),
+        "{}",
+        stored
+    );
+}
+
+#[tokio::test]
+async fn test_protocol_log_off_by_default() {
+    let lines = logged(registry_testkit::wirelog::TARGET, async {
+        let server = RegistryServer::new(RegistryConfig::memory()).await.unwrap();
+        let client = RegistryClient::new(server.url());
+        client.push_blob("app", b"layer".to_vec()).await.unwrap();
+    })
+    .await;
+    assert!(lines.is_empty(), "{:#?}", lines);
+}